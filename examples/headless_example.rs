@@ -0,0 +1,65 @@
+use glium::backend::Facade;
+use glium::IndexBuffer;
+use glium::VertexBuffer;
+use opengl_renderer::renderer::Renderer;
+use opengl_renderer::shaders::skybox::Skybox;
+use opengl_renderer::system_loop::HeadlessSystem;
+use opengl_renderer::utils::camera::Camera;
+use opengl_renderer::utils::camera::OrbitController;
+use opengl_renderer::utils::model::Model;
+use opengl_renderer::utils::model::ModelLoad;
+use opengl_renderer::utils::pbr_skybox::PBRSkybox;
+use opengl_renderer::utils::texture_saver::TextureSaver;
+
+/// Renders a model to `thumbnail.png` without opening a window
+fn main() {
+    let system = HeadlessSystem::new(512, 512).unwrap();
+    let facade = system.get_facade().get_context().clone();
+
+    let pbr_skybox =
+        PBRSkybox::from_equirect_hdr(&facade, "resources/textures/newport_loft.hdr", 512).unwrap();
+
+    let skybox = Model::new(
+        VertexBuffer::new(&facade, &opengl_renderer::utils::shapes::get_cube()).unwrap(),
+        IndexBuffer::new(
+            &facade,
+            glium::index::PrimitiveType::TrianglesList,
+            &(0..36).collect::<Vec<_>>(),
+        )
+        .unwrap(),
+        Skybox::load_from_fs(&facade),
+    );
+
+    let model = Model::load_from_fs(&facade, "resources/objects/sphere.glb").unwrap();
+
+    let (width, height) = system.get_size();
+    let mut camera = Camera::new();
+    camera.update_aspect_from_dimensions(width, height);
+    OrbitController::new([0.0; 3].into(), 3.0).update_camera(&mut camera);
+
+    let mut renderer = Renderer::new();
+
+    let image = system
+        .render_once(|surface| {
+            let mut scene = renderer.begin_scene();
+            scene.scene_data.projection = camera.get_projection_matrix().into();
+            scene.scene_data.camera = camera.clone();
+            scene.scene_data.set_scene_object(pbr_skybox.clone());
+            scene
+                .scene_data
+                .get_lights_mut()
+                .add_light([10.0, 10.0, 3.0], [1500.0; 3]);
+
+            model.publish(&mut scene);
+            skybox.publish(&mut scene);
+
+            scene.finish(surface);
+        })
+        .unwrap();
+
+    // Wait for the file to be written before exiting
+    TextureSaver::image_to_fs_in_background(image, "thumbnail.png", false)
+        .join()
+        .unwrap()
+        .unwrap();
+}
@@ -1,26 +1,42 @@
 use egui::style::Margin;
 use glium::IndexBuffer;
+use glium::PolygonMode;
 use glium::VertexBuffer;
-use nalgebra::Perspective3;
-use opengl_renderer::shaders::brdf::BRDF;
-use opengl_renderer::shaders::equi_rect_to_cubemap::EquiRectCubemap;
-use opengl_renderer::shaders::irradiance_convolution::IrradianceConvolution;
-use opengl_renderer::shaders::prefilter::Prefilter;
+use nalgebra::Matrix4;
+use opengl_renderer::shaders::fxaa::Fxaa;
+use opengl_renderer::shaders::normal_debug::NormalDebug;
+use opengl_renderer::shaders::outline::Outline;
+use opengl_renderer::shaders::phong::Phong;
 use opengl_renderer::shaders::skybox::Skybox;
+use opengl_renderer::shaders::ssao::MAX_SAMPLES;
+use opengl_renderer::utils::indices::remove_indices;
+use opengl_renderer::utils::instanced_model::InstancedModel;
+use opengl_renderer::utils::model::LoadOptions;
 use opengl_renderer::utils::model::ModelLoad;
 use opengl_renderer::utils::pbr_skybox::PBRSkybox;
-use opengl_renderer::utils::texture_loader::TextureLoader;
+use opengl_renderer::utils::render_surface::RenderSurface;
+use opengl_renderer::utils::scene_description::SceneDescription;
+use opengl_renderer::utils::texture_load_queue::TextureLoadQueue;
+use opengl_renderer::utils::texture_registry::TextureRegistry;
+use opengl_renderer::utils::texture_saver::TextureSaver;
+use opengl_renderer::utils::vertex_ao::bake_vertex_ao;
+use opengl_renderer::vertex::Instance;
+use std::cell::RefCell;
 use std::rc::Rc;
 
 use glium::backend::Facade;
-use glium::framebuffer::SimpleFrameBuffer;
 use glium::glutin;
-use glium::texture::DepthTexture2d;
-use glium::texture::SrgbTexture2d;
-use glium::Surface;
+use glium::texture::SrgbFormat;
+use opengl_renderer::renderer::Background;
+use opengl_renderer::renderer::Fog;
 use opengl_renderer::renderer::Renderable;
 use opengl_renderer::renderer::Renderer;
+use opengl_renderer::renderer::WireframeOverlay;
+use opengl_renderer::shader::Shader;
 use opengl_renderer::utils::camera::Camera;
+use opengl_renderer::utils::camera::CameraDescription;
+use opengl_renderer::utils::camera::FlyController;
+use opengl_renderer::utils::camera::OrbitController;
 use opengl_renderer::{system_loop::SystemLoop, window::Window};
 
 use opengl_renderer::utils::model::Model;
@@ -32,32 +48,19 @@ fn main() {
     let mut debug_open = true;
 
     let mut render_texture = RenderSurface::new(&facade, 100, 100).unwrap();
+    let mut msaa_surface =
+        RenderSurface::with_format(&facade, 100, 100, SrgbFormat::U8U8U8U8, 4, true).unwrap();
+    let mut msaa_enabled = true;
     let egui_texture: egui::TextureId = event_loop
         .get_egui_glium_mut()
         .painter
         .register_native_texture(render_texture.texture.clone(), Default::default());
 
     let mut renderer = Renderer::new();
+    renderer.set_clear_color([0.0, 0.0, 0.0, 1.0]);
 
-    let pbr_skybox = {
-        let skybox_cubemap = EquiRectCubemap::load_from_fs(&facade).compute(
-            &facade,
-            &TextureLoader::from_fs_hdr(&facade, "resources/textures/newport_loft.hdr").unwrap(),
-            512,
-        );
-
-        let irradiance =
-            IrradianceConvolution::load_from_fs(&facade).calculate(&facade, &skybox_cubemap);
-
-        let prefilter = Prefilter::load_from_fs(&facade).compute(&facade, &skybox_cubemap);
-
-        PBRSkybox::new(
-            skybox_cubemap.into(),
-            irradiance.into(),
-            prefilter.into(),
-            BRDF::load_from_fs(&facade).compute(&facade).into(),
-        )
-    };
+    let mut pbr_skybox =
+        PBRSkybox::from_equirect_hdr(&facade, "resources/textures/newport_loft.hdr", 512).unwrap();
 
     let skybox = Model::new(
         VertexBuffer::new(&facade, &opengl_renderer::utils::shapes::get_cube()).unwrap(),
@@ -71,29 +74,130 @@ fn main() {
     );
 
     let mut camera = Camera::new();
-    camera.position = [0.0, 0.0, 3.0].into();
+    let mut orbit = OrbitController::new([0.0; 3].into(), 3.0);
+    orbit.update_camera(&mut camera);
+
+    // Shared with the event handler, which feeds it the keyboard and mouse input
+    let fly = Rc::new(RefCell::new(FlyController::new(3.0)));
+    let mut fly_enabled = false;
+    {
+        let fly = fly.clone();
+        event_loop.subscribe_events(move |event| fly.borrow_mut().handle_event(event));
+    }
 
     let mut models = vec![Model::load_from_fs(&facade, "resources/objects/sphere.glb").unwrap()];
+    let mut selected: Option<usize> = None;
+    // Textures of opened models, keyed by the index of the model
+    let mut texture_queue = TextureLoadQueue::new();
+    let mut exposure = 1.0;
+    let mut reflection_lod_bias = 0.0;
+    let mut ibl_intensity = 1.0;
+    let mut fog = Fog::new([0.6, 0.65, 0.7], 0.05);
+    let mut fog_enabled = false;
+    let mut background = Background::Skybox;
+    let mut wireframe_overlay = WireframeOverlay::new([0.0; 3], 1.0);
+    let mut wireframe_overlay_enabled = false;
+    let fxaa = Fxaa::new(&facade);
+    let mut fxaa_enabled = true;
+    let mut take_screenshot = false;
+    let mut normal_debug = NormalDebug::load_from_fs(&facade);
+    let mut debug_view = false;
+    let mut phong = Phong::load_from_fs(&facade);
+    let mut phong_view = false;
+    let outline = Outline::load_from_fs(&facade);
+    let mut instanced_models: Vec<InstancedModel> = Vec::new();
 
     event_loop.subscribe_render(move |render_info| {
-        render_info.target.clear_color(0.0, 0.0, 0.0, 1.0);
+        renderer.advance_time(*render_info.delta);
+
+        for ((index, target), texture) in texture_queue.poll(&facade) {
+            match (models.get_mut(index), texture) {
+                (Some(model), Ok(texture)) => model.set_queued_texture(target, texture),
+                (Some(model), Err(err)) => model.set_queued_error(&facade, target, err),
+                (None, Err(err)) => eprintln!("Failed to load {:?}: {}", target.path, err),
+                (None, Ok(_)) => {}
+            }
+        }
+
+        if fly_enabled {
+            let mut fly = fly.borrow_mut();
+            fly.set_keyboard_blocked(render_info.egui_glium.egui_ctx.wants_keyboard_input());
+            fly.update_camera(&mut camera, *render_info.delta);
+        }
 
         egui::TopBottomPanel::top("topbar").show(&render_info.egui_glium.egui_ctx, |ui| {
             ui.horizontal(|ui| {
                 if ui.button("show debug").clicked() {
                     debug_open = true;
                 }
+                // Draw a grid of the first model with a single draw call per sub model
+                if ui.button("instance grid").clicked() {
+                    if let Some(model) = models.first() {
+                        let instances = (0..400)
+                            .map(|i| {
+                                let (x, z) = ((i % 20) as f32 - 9.5, (i / 20) as f32 - 9.5);
+                                let albedo = [(x + 10.0) / 20.0, 0.5, (z + 10.0) / 20.0];
+
+                                Instance::with_albedo(
+                                    Matrix4::new_translation(&[x * 2.5, -3.0, z * 2.5].into()),
+                                    albedo,
+                                )
+                            })
+                            .collect::<Vec<_>>();
+
+                        instanced_models
+                            .push(InstancedModel::from_model(&facade, model, &instances).unwrap());
+                    }
+                }
                 // Open model
                 if ui.button("open").clicked() {
                     if let Some(files) = rfd::FileDialog::new().pick_files() {
                         for path in files {
-                            if let Ok(model) = Model::load_from_fs(&facade, path) {
+                            let loaded = Model::load_from_fs_queued(
+                                &facade,
+                                &path,
+                                LoadOptions::default(),
+                                &mut texture_queue,
+                                models.len(),
+                            );
+                            match loaded {
                                 // Move the model off of the camera so you can actually see it
-                                models.push(model);
+                                Ok(model) => models.push(model),
+                                Err(err) => eprintln!("Failed to load {:?}: {}", path, err),
+                            }
+                        }
+                    }
+                }
+                if ui.button("save scene").clicked() {
+                    if let Some(path) = rfd::FileDialog::new().save_file() {
+                        let mut description = SceneDescription::from_models(&models);
+                        description.camera = Some(CameraDescription::from_camera(&camera));
+                        if let Err(err) = description.save(&path) {
+                            eprintln!("Failed to save {:?}: {}", path, err);
+                        }
+                    }
+                }
+                if ui.button("load scene").clicked() {
+                    if let Some(path) = rfd::FileDialog::new().pick_file() {
+                        let loaded = SceneDescription::open(&path).and_then(|description| {
+                            Ok((description.to_models(&facade)?, description.camera))
+                        });
+                        match loaded {
+                            Ok((loaded, saved_camera)) => {
+                                models = loaded;
+                                texture_queue.clear();
+                                selected = None;
+                                if let Some(saved_camera) = saved_camera {
+                                    camera = saved_camera.to_camera();
+                                }
                             }
+                            Err(err) => eprintln!("Failed to load {:?}: {}", path, err),
                         }
                     }
                 }
+                if ui.button("screenshot").clicked() {
+                    take_screenshot = true;
+                }
             });
         });
 
@@ -112,11 +216,182 @@ fn main() {
                     1.0 / render_info.delta.as_secs_f32()
                 ));
 
-                ui.label(&format!(
-                    "res: {}x{}",
-                    render_texture.width(),
-                    render_texture.height()
+                let (width, height) = match msaa_enabled {
+                    true => msaa_surface.size(),
+                    false => render_texture.size(),
+                };
+                ui.label(&format!("res: {}x{}", width, height));
+
+                let stats = renderer.get_stats();
+                ui.label(format!("draw calls: {}", stats.draw_calls));
+                ui.label(format!("triangles: {}", stats.triangles));
+                ui.label(format!("material batches: {}", stats.material_batches));
+                for (type_id, triangles) in renderer.get_triangles_by_material() {
+                    let name = renderer.get_material_name(type_id).unwrap_or("unknown");
+                    ui.label(format!("    {}: {} triangles", name, triangles));
+                }
+
+                let textures = TextureRegistry::get_stats();
+                ui.label(format!(
+                    "textures: {} ({:.1} MB), cubemaps: {} ({:.1} MB)",
+                    textures.textures,
+                    textures.texture_bytes as f32 / 1_000_000.0,
+                    textures.cubemaps,
+                    textures.cubemap_bytes as f32 / 1_000_000.0,
                 ));
+                if let Some(free) = facade.get_context().get_free_video_memory() {
+                    ui.label(format!("free video memory: {} MB", free / 1_000_000));
+                }
+
+                ui.add(egui::Slider::new(&mut exposure, 0.0..=5.0).text("exposure"));
+                ui.add(
+                    egui::Slider::new(&mut reflection_lod_bias, -2.0..=2.0)
+                        .text("reflection lod bias"),
+                );
+                ui.add(egui::Slider::new(&mut ibl_intensity, 0.0..=3.0).text("ibl intensity"));
+
+                ui.checkbox(&mut fog_enabled, "fog");
+                if fog_enabled {
+                    ui.add(egui::Slider::new(&mut fog.density, 0.0..=0.5).text("fog density"));
+                    ui.horizontal(|ui| {
+                        egui::widgets::color_picker::color_edit_button_rgb(ui, &mut fog.color);
+                        ui.label("fog color");
+                    });
+                }
+
+                let mut skybox_rotation = pbr_skybox.get_rotation_rad().to_degrees();
+                if ui
+                    .add(egui::Slider::new(&mut skybox_rotation, 0.0..=360.0).text("skybox rotation"))
+                    .changed()
+                {
+                    pbr_skybox.set_rotation_rad(skybox_rotation.to_radians());
+                }
+                ui.horizontal(|ui| {
+                    let mut tint = pbr_skybox.get_tint();
+                    if egui::widgets::color_picker::color_edit_button_rgb(ui, &mut tint).changed() {
+                        pbr_skybox.set_tint(tint);
+                    }
+                    ui.label("skybox tint");
+                });
+                ui.horizontal(|ui| {
+                    // Radio buttons compare the colors too, so only the variant is checked
+                    let is_skybox = matches!(background, Background::Skybox);
+                    if ui.radio(is_skybox, "skybox").clicked() {
+                        background = Background::Skybox;
+                    }
+                    let is_solid = matches!(background, Background::SolidColor(_));
+                    if ui.radio(is_solid, "solid").clicked() && !is_solid {
+                        background = Background::SolidColor([0.2; 3]);
+                    }
+                    let is_gradient = matches!(background, Background::Gradient { .. });
+                    if ui.radio(is_gradient, "gradient").clicked() && !is_gradient {
+                        background = Background::Gradient {
+                            top: [0.35, 0.45, 0.6],
+                            bottom: [0.05; 3],
+                        };
+                    }
+                });
+                match &mut background {
+                    Background::Skybox => {}
+                    Background::SolidColor(color) => {
+                        egui::widgets::color_picker::color_edit_button_rgb(ui, color);
+                    }
+                    Background::Gradient { top, bottom } => {
+                        ui.horizontal(|ui| {
+                            egui::widgets::color_picker::color_edit_button_rgb(ui, top);
+                            egui::widgets::color_picker::color_edit_button_rgb(ui, bottom);
+                            ui.label("background top and bottom");
+                        });
+                    }
+                }
+
+                let mut wireframe = renderer.get_polygon_mode() == PolygonMode::Line;
+                if ui.checkbox(&mut wireframe, "wireframe").changed() {
+                    renderer.set_polygon_mode(match wireframe {
+                        true => PolygonMode::Line,
+                        false => PolygonMode::Fill,
+                    });
+                }
+                ui.checkbox(&mut wireframe_overlay_enabled, "wireframe overlay");
+                if wireframe_overlay_enabled {
+                    ui.add(
+                        egui::Slider::new(&mut wireframe_overlay.thickness, 0.5..=5.0)
+                            .text("wireframe thickness"),
+                    );
+                    ui.horizontal(|ui| {
+                        egui::widgets::color_picker::color_edit_button_rgb(
+                            ui,
+                            &mut wireframe_overlay.color,
+                        );
+                        ui.label("wireframe color");
+                    });
+                }
+                ui.checkbox(&mut fxaa_enabled, "fxaa");
+                ui.checkbox(
+                    &mut msaa_enabled,
+                    format!("msaa ({}x)", msaa_surface.get_samples()),
+                );
+
+                let mut depth_prepass = renderer.is_depth_prepass_enabled();
+                if ui.checkbox(&mut depth_prepass, "depth pre-pass").changed() {
+                    match depth_prepass {
+                        true => renderer.enable_depth_prepass(&facade),
+                        false => renderer.disable_depth_prepass(),
+                    }
+                }
+
+                let mut sort_opaque = renderer.get_sort_opaque();
+                if ui.checkbox(&mut sort_opaque, "sort opaque").changed() {
+                    renderer.set_sort_opaque(sort_opaque);
+                }
+
+                let mut ssao_enabled = renderer.is_ssao_enabled();
+                if ui.checkbox(&mut ssao_enabled, "ssao").changed() {
+                    match ssao_enabled {
+                        true => renderer.enable_ssao(&facade),
+                        false => renderer.disable_ssao(),
+                    }
+                }
+                if let Some(ssao) = renderer.get_ssao_mut() {
+                    let mut radius = ssao.get_radius();
+                    if ui
+                        .add(egui::Slider::new(&mut radius, 0.05..=2.0).text("ssao radius"))
+                        .changed()
+                    {
+                        ssao.set_radius(radius);
+                    }
+                    let mut bias = ssao.get_bias();
+                    if ui
+                        .add(egui::Slider::new(&mut bias, 0.0..=0.1).text("ssao bias"))
+                        .changed()
+                    {
+                        ssao.set_bias(bias);
+                    }
+                    let mut samples = ssao.get_sample_count();
+                    if ui
+                        .add(egui::Slider::new(&mut samples, 1..=MAX_SAMPLES).text("ssao samples"))
+                        .changed()
+                    {
+                        ssao.set_sample_count(samples);
+                    }
+                }
+
+                let mut debug_draw = renderer.is_debug_draw_enabled();
+                if ui.checkbox(&mut debug_draw, "bounding boxes").changed() {
+                    match debug_draw {
+                        true => renderer.enable_debug_draw(&facade),
+                        false => renderer.disable_debug_draw(),
+                    }
+                }
+
+                ui.checkbox(&mut debug_view, "geometry debug view");
+                if debug_view {
+                    normal_debug.debug_ui(ui);
+                }
+                ui.checkbox(&mut phong_view, "phong shading");
+                if phong_view {
+                    phong.debug_ui(ui);
+                }
             });
 
         egui::SidePanel::new(egui::panel::Side::Left, "model")
@@ -167,15 +442,100 @@ fn main() {
                             camera.set_yaw_rad(yaw.to_radians());
                         }
                     });
+
+                    if let Some(model) = selected.and_then(|i| models.get(i)) {
+                        if ui.button("look at selected model").clicked() {
+                            let center = match model.get_world_aabb() {
+                                Some(aabb) => aabb.get_center(),
+                                None => *model.get_position(),
+                            };
+                            camera.set_target(Some(center));
+                        }
+                    }
+                    if let Some(model) = selected.and_then(|i| models.get_mut(i)) {
+                        if ui.button("center on origin").clicked() {
+                            model.center_on_origin();
+                        }
+                        let mergeable = model.count_mergeable();
+                        let merge_button =
+                            egui::Button::new(format!("merge {} sub models", mergeable));
+                        if ui.add_enabled(mergeable > 0, merge_button).clicked() {
+                            if let Err(err) = model.merge_sub_models(&facade) {
+                                eprintln!("Failed to merge sub models: {}", err);
+                            }
+                        }
+                        if ui.button("bake vertex ao").clicked() {
+                            if let Err(err) = bake_vertex_ao(&facade, model, 64) {
+                                eprintln!("Failed to bake ao: {}", err);
+                            }
+                        }
+                    }
+                    if camera.get_target().is_some() && ui.button("stop looking at model").clicked()
+                    {
+                        camera.set_target(None);
+                    }
+
+                    ui.checkbox(&mut fly_enabled, "fly camera").on_hover_text(
+                        "hold the right mouse button over the scene to look around, move with WASD, Q and E",
+                    );
+                    if fly_enabled {
+                        ui.add(
+                            egui::Slider::new(&mut fly.borrow_mut().speed_multiplier, 0.1..=10.0)
+                                .logarithmic(true)
+                                .text("speed"),
+                        );
+                    }
+
+                    ui.label("clipping planes");
+                    ui.horizontal(|ui| {
+                        let mut near = camera.get_near();
+                        let mut far = camera.get_far();
+                        if ui
+                            .add(
+                                egui::DragValue::new(&mut near)
+                                    .prefix("near: ")
+                                    .speed(0.01)
+                                    .clamp_range(0.001..=far),
+                            )
+                            .changed()
+                        {
+                            camera.set_near(near);
+                        }
+                        if ui
+                            .add(
+                                egui::DragValue::new(&mut far)
+                                    .prefix("far: ")
+                                    .speed(1)
+                                    .clamp_range(near..=f32::MAX),
+                            )
+                            .changed()
+                        {
+                            camera.set_far(far);
+                        }
+                    });
                     ui.separator();
 
+                    let mut removed = Vec::new();
                     for (i, model) in models.iter_mut().enumerate() {
                         ui.push_id(i, |ui| {
-                            ui.label(format!("Model {}", i));
+                            ui.horizontal(|ui| {
+                                if selected == Some(i) {
+                                    ui.label(format!("Model {} (selected)", i));
+                                } else {
+                                    ui.label(format!("Model {}", i));
+                                }
+                                if ui.button("remove").clicked() {
+                                    removed.push(i);
+                                }
+                            });
                             model.debug_ui(ui);
                             ui.separator();
                         });
                     }
+                    if !removed.is_empty() {
+                        remove_indices(&mut models, &removed);
+                        selected = None;
+                    }
                 })
             });
 
@@ -189,53 +549,161 @@ fn main() {
                 size_px.x *= render_info.egui_glium.egui_ctx.pixels_per_point();
                 size_px.y *= render_info.egui_glium.egui_ctx.pixels_per_point();
 
-                if size_px.x != render_texture.width() as f32
-                    || size_px.y != render_texture.height() as f32
-                {
-                    render_texture
-                        .resize(&facade, size_px.x as u32, size_px.y as u32)
-                        .unwrap();
-                    render_info.egui_glium.painter.replace_native_texture(
-                        egui_texture,
-                        render_texture.texture.clone(),
-                        egui::TextureOptions::default(),
-                    );
+                // only the surface that is rendered to has to match
+                let size_px = (size_px.x as u32, size_px.y as u32);
+                if msaa_enabled && msaa_surface.size() != size_px {
+                    msaa_surface.resize(&facade, size_px.0, size_px.1).unwrap();
+                }
+                if !msaa_enabled && render_texture.size() != size_px {
+                    render_texture.resize(&facade, size_px.0, size_px.1).unwrap();
                 }
 
-                // render to 'render_texture'
-                let mut buffer = render_texture.frame_buffer(&facade).unwrap();
-
-                buffer.clear_color_and_depth((0.0, 0.0, 0.0, 1.0), 1.0);
+                // render to 'render_texture' (or the multisampled surface), the renderer clears
+                // it first
+                let mut buffer = match msaa_enabled {
+                    true => msaa_surface.frame_buffer(&facade).unwrap(),
+                    false => render_texture.frame_buffer(&facade).unwrap(),
+                };
+
+                camera.update_aspect_from_dimensions(size_px.0, size_px.1);
+
+                // Has to outlive the scene, the buffers are shared so this is cheap
+                let debug_models = match debug_view {
+                    true => models
+                        .iter()
+                        .map(|model| model.with_shader(normal_debug.clone()))
+                        .collect(),
+                    false => Vec::new(),
+                };
+                let phong_models = match phong_view && !debug_view {
+                    true => models
+                        .iter()
+                        .map(|model| model.with_shader(phong.clone()))
+                        .collect(),
+                    false => Vec::new(),
+                };
+
+                let light_position = [10.0, 10.0, 3.0];
 
                 let mut scene = renderer.begin_scene();
-                scene.scene_data.projection = Perspective3::new(
-                    render_texture.width().max(1) as f32 / render_texture.height().max(1) as f32,
-                    70.0f32.to_radians(),
-                    0.1,
-                    100000.0,
-                )
-                .as_matrix()
-                .clone()
-                .into();
+                scene.scene_data.projection = camera.get_projection_matrix().into();
 
                 scene.scene_data.camera = camera.clone();
+                scene.scene_data.set_exposure(exposure);
+                scene.scene_data.set_prefilter_lod_bias(reflection_lod_bias);
+                scene.scene_data.set_ibl_intensity(ibl_intensity);
+                scene.scene_data.set_fog(fog_enabled.then_some(fog));
+                scene.scene_data.set_background(background);
+                scene
+                    .scene_data
+                    .set_wireframe_overlay(wireframe_overlay_enabled.then_some(wireframe_overlay));
                 scene.scene_data.set_scene_object(pbr_skybox.clone());
-
-                for model in &models {
+                scene
+                    .scene_data
+                    .get_lights_mut()
+                    .add_light(light_position, [1500.0; 3]);
+
+                if debug_view {
+                    for model in &debug_models {
+                        model.publish(&mut scene);
+                    }
+                } else if phong_view {
+                    for model in &phong_models {
+                        model.publish(&mut scene);
+                    }
+                } else {
+                    for model in &models {
+                        model.publish(&mut scene);
+                    }
+                }
+                for model in &instanced_models {
                     model.publish(&mut scene);
                 }
+                if let Some(model) = selected.and_then(|i| models.get(i)) {
+                    model.render_outline(&mut scene, &outline);
+                }
                 skybox.publish(&mut scene);
 
+                if let Some(debug_draw) = scene.get_debug_draw_mut() {
+                    for sub in models.iter().flat_map(|model| model.get_sub_models()) {
+                        if let Some(bounds) = sub.get_world_bounds() {
+                            debug_draw.aabb(bounds.min, bounds.max, [1.0, 1.0, 0.0]);
+                        }
+                    }
+                    debug_draw.axes(&Matrix4::new_translation(&light_position.into()));
+                }
+
                 scene.finish(&mut Renderable::from(&mut buffer));
 
+                let rendered = match msaa_enabled {
+                    true => msaa_surface.resolve(&facade).unwrap(),
+                    false => &render_texture.texture,
+                };
+                let displayed = match fxaa_enabled {
+                    true => Rc::new(fxaa.apply_srgb(&facade, rendered)),
+                    false => rendered.clone(),
+                };
+                if take_screenshot {
+                    take_screenshot = false;
+                    // Only the scene, without the ui, as it is shown on screen
+                    if let Err(err) =
+                        TextureSaver::srgb_to_fs_in_background(&displayed, "screenshot.png", true)
+                    {
+                        eprintln!("Failed to take screenshot: {}", err);
+                    }
+                }
+                render_info.egui_glium.painter.replace_native_texture(
+                    egui_texture,
+                    displayed,
+                    egui::TextureOptions::default(),
+                );
+
                 // show our rendered texture, but the image is upside, down so let's change the uv
                 // coords of the image
-                ui.add(
-                    egui::widgets::Image::new(egui_texture, size).uv(egui::Rect {
-                        min: [0.0, 1.0].into(),
-                        max: [1.0, 0.0].into(),
-                    }),
+                let response = ui.add(
+                    egui::widgets::Image::new(egui_texture, size)
+                        .uv(egui::Rect {
+                            min: [0.0, 1.0].into(),
+                            max: [1.0, 0.0].into(),
+                        })
+                        .sense(egui::Sense::click_and_drag()),
                 );
+
+                // only start looking around when the right click is on the scene
+                fly.borrow_mut().set_pointer_blocked(!response.hovered());
+
+                // orbit the camera by dragging the image and zoom by scrolling over it
+                if !fly_enabled && response.dragged_by(egui::PointerButton::Primary) {
+                    let delta = response.drag_delta() * ui.ctx().pixels_per_point();
+                    orbit.rotate(delta.x, delta.y);
+                    orbit.update_camera(&mut camera);
+                }
+                if !fly_enabled && response.hovered() {
+                    let scroll = ui.input().scroll_delta.y;
+                    if scroll != 0.0 {
+                        orbit.zoom(scroll / 50.0);
+                        orbit.update_camera(&mut camera);
+                    }
+                }
+
+                // select the closest model under the cursor
+                if let Some(pointer) = response.interact_pointer_pos() {
+                    if response.clicked() {
+                        let rect = response.rect;
+                        let ndc_x = (pointer.x - rect.min.x) / rect.width() * 2.0 - 1.0;
+                        let ndc_y = 1.0 - (pointer.y - rect.min.y) / rect.height() * 2.0;
+                        let (origin, direction) = camera.screen_ray(ndc_x, ndc_y);
+
+                        selected = models
+                            .iter()
+                            .enumerate()
+                            .filter_map(|(i, model)| {
+                                Some((i, model.intersects_ray(&origin, &direction)?))
+                            })
+                            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+                            .map(|(i, _)| i);
+                    }
+                }
             });
     });
 
@@ -253,64 +721,8 @@ fn create_window() -> Window {
 
     let context_builder = glutin::ContextBuilder::new()
         .with_depth_buffer(0)
-        .with_stencil_buffer(0)
+        .with_stencil_buffer(8)
         .with_vsync(true);
 
     Window::create(window_builder, context_builder)
 }
-
-pub struct RenderSurface {
-    pub texture: Rc<SrgbTexture2d>,
-    pub depth: DepthTexture2d,
-}
-
-impl RenderSurface {
-    pub fn new(
-        facade: &impl Facade,
-        width: u32,
-        height: u32,
-    ) -> Result<Self, Box<dyn std::error::Error>> {
-        let texture = SrgbTexture2d::empty(facade, width, height)?;
-        let depth = DepthTexture2d::empty(facade, width, height)?;
-
-        Ok(Self {
-            texture: Rc::new(texture),
-            depth,
-        })
-    }
-
-    pub fn resize(
-        &mut self,
-        facade: &impl Facade,
-        width: u32,
-        height: u32,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        self.texture = Rc::new(SrgbTexture2d::empty(facade, width, height)?);
-        self.depth = DepthTexture2d::empty(facade, width, height)?;
-
-        Ok(())
-    }
-
-    pub fn frame_buffer(
-        &self,
-        facade: &impl Facade,
-    ) -> Result<SimpleFrameBuffer, Box<dyn std::error::Error>> {
-        Ok(SimpleFrameBuffer::with_depth_buffer(
-            facade,
-            &*self.texture,
-            &self.depth,
-        )?)
-    }
-
-    pub fn size(&self) -> (u32, u32) {
-        (self.texture.width(), self.texture.height())
-    }
-
-    pub fn width(&self) -> u32 {
-        self.texture.width()
-    }
-
-    pub fn height(&self) -> u32 {
-        self.texture.height()
-    }
-}
@@ -0,0 +1,159 @@
+//! Errors returned when loading resources
+
+use std::error::Error;
+use std::fmt;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::path::PathBuf;
+
+#[derive(Debug)]
+pub enum RendererError {
+    /// Reading a file failed (ex. the file doesn't exist)
+    Io(std::io::Error),
+    /// The file exists but the format isn't supported
+    UnsupportedFormat(String),
+    /// Decoding an image failed
+    Image(image::ImageError),
+    /// A dds file is truncated or its header is malformed
+    InvalidDds(&'static str),
+    /// A ktx2 file is truncated or its header is malformed
+    InvalidKtx2(&'static str),
+    /// Assimp failed to import a model
+    AssimpError(String),
+    /// The path can't be handed to assimp (ex. it isn't valid unicode)
+    InvalidPath(PathBuf),
+    /// Creating or drawing with a gpu resource failed
+    Glium(String),
+    /// One of the six images of a cubemap doesn't exist
+    MissingCubemapFace { face: &'static str, path: PathBuf },
+    /// The resolution of a generated texture isn't a power of two or is too small for the
+    /// requested number of mipmaps
+    InvalidResolution { resolution: u32, mipmaps: u32 },
+    /// A face of a cubemap isn't square or has a different size than the first face
+    CubemapFaceSize {
+        face: &'static str,
+        size: (u32, u32),
+        expected: (u32, u32),
+    },
+    /// A mesh has an index past the end of its vertices
+    IndexOutOfRange { index: u32, vertices: usize },
+    /// A material was rendered in a scene without a [scene
+    /// object](crate::renderer::SceneData::set_scene_object) it needs
+    MissingSceneObject {
+        material: String,
+        object: &'static str,
+    },
+}
+
+impl Display for RendererError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Io(err) => write!(f, "io error: {}", err),
+            Self::UnsupportedFormat(format) => write!(f, "unsupported format: {}", format),
+            Self::Image(err) => write!(f, "image error: {}", err),
+            Self::InvalidDds(reason) => write!(f, "invalid dds file: {}", reason),
+            Self::InvalidKtx2(reason) => write!(f, "invalid ktx2 file: {}", reason),
+            Self::AssimpError(err) => write!(f, "assimp error: {}", err),
+            Self::InvalidPath(path) => write!(f, "invalid path: {:?}", path),
+            Self::Glium(err) => write!(f, "glium error: {}", err),
+            Self::MissingCubemapFace { face, path } => {
+                write!(f, "missing cubemap face {}: {:?}", face, path)
+            }
+            Self::InvalidResolution {
+                resolution,
+                mipmaps,
+            } => write!(
+                f,
+                "resolution {} has to be a power of two of at least 2^{} for {} mipmaps",
+                resolution, mipmaps, mipmaps
+            ),
+            Self::CubemapFaceSize {
+                face,
+                size,
+                expected,
+            } => write!(
+                f,
+                "cubemap face {} is {}x{}, expected {}x{}",
+                face, size.0, size.1, expected.0, expected.1
+            ),
+            Self::IndexOutOfRange { index, vertices } => write!(
+                f,
+                "index {} is out of range for a mesh with {} vertices",
+                index, vertices
+            ),
+            Self::MissingSceneObject { material, object } => write!(
+                f,
+                "{} material requires a {} scene object but none was set",
+                material, object
+            ),
+        }
+    }
+}
+
+impl Error for RendererError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::Io(err) => Some(err),
+            Self::Image(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for RendererError {
+    fn from(err: std::io::Error) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<image::ImageError> for RendererError {
+    fn from(err: image::ImageError) -> Self {
+        match err {
+            image::ImageError::IoError(err) => Self::Io(err),
+            image::ImageError::Unsupported(err) => Self::UnsupportedFormat(err.to_string()),
+            err => Self::Image(err),
+        }
+    }
+}
+
+impl From<russimp::RussimpError> for RendererError {
+    fn from(err: russimp::RussimpError) -> Self {
+        Self::AssimpError(err.to_string())
+    }
+}
+
+impl From<glium::texture::TextureCreationError> for RendererError {
+    fn from(err: glium::texture::TextureCreationError) -> Self {
+        Self::Glium(err.to_string())
+    }
+}
+
+impl From<glium::vertex::BufferCreationError> for RendererError {
+    fn from(err: glium::vertex::BufferCreationError) -> Self {
+        Self::Glium(err.to_string())
+    }
+}
+
+impl From<glium::index::BufferCreationError> for RendererError {
+    fn from(err: glium::index::BufferCreationError) -> Self {
+        Self::Glium(err.to_string())
+    }
+}
+
+impl From<glium::framebuffer::ValidationError> for RendererError {
+    fn from(err: glium::framebuffer::ValidationError) -> Self {
+        Self::Glium(err.to_string())
+    }
+}
+
+impl From<glium::DrawError> for RendererError {
+    fn from(err: glium::DrawError) -> Self {
+        Self::Glium(err.to_string())
+    }
+}
+
+impl From<glium::program::ProgramCreationError> for RendererError {
+    fn from(err: glium::program::ProgramCreationError) -> Self {
+        Self::Glium(err.to_string())
+    }
+}
@@ -1,6 +1,7 @@
 #[macro_use]
 pub extern crate glium;
 pub use glium::glutin;
+pub mod error;
 pub mod renderer;
 pub mod shader;
 pub mod shaders;
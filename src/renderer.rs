@@ -1,41 +1,282 @@
+use glium::backend::Facade;
+use glium::draw_parameters::BackfaceCullingMode;
+use glium::draw_parameters::PolygonOffset;
 use glium::index::IndicesSource;
 use glium::uniforms::Uniforms;
 use glium::vertex::MultiVerticesSource;
 use glium::DrawError;
 use glium::DrawParameters;
+use glium::PolygonMode;
 use glium::Program;
+use glium::Rect;
 use glium::Surface;
+use nalgebra::Vector3;
 use std::any::Any;
 use std::any::TypeId;
 use std::collections::HashMap;
+use std::collections::HashSet;
+use std::ops::Deref;
+use std::time::Duration;
 
 use glium::vertex::VerticesSource;
 use glium::{framebuffer::SimpleFrameBuffer, Frame};
 
+use crate::error::RendererError;
 use crate::shader::Shader;
+use crate::shaders::debug_draw::DebugDraw;
+use crate::shaders::depth_prepass::DepthPrepass;
+use crate::shaders::ssao::Ssao;
 use crate::utils::camera::Camera;
+use crate::utils::lights::DirectionalLight;
+use crate::utils::lights::Lights;
+use crate::utils::shadow_map::ShadowMap;
+
+/// What was rendered during the last [`RenderScene::finish`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RenderStats {
+    /// Draw calls made on the surface: one per published entry, one more per entry drawn by the
+    /// depth pre-pass, and one for the debug draw lines. Instanced entries still only take one.
+    pub draw_calls: u32,
+    /// Estimated from the index count, entries without an index buffer aren't counted
+    pub triangles: u32,
+    /// The number of different shader types that were drawn
+    pub material_batches: u32,
+    /// Entries that were left out because none of their layers were rendered, see
+    /// [`RenderScene::finish_layers`]
+    pub culled: u32,
+    /// Draw calls that returned an error, the first one is printed by [`RenderScene::finish`]. A
+    /// failed [ssao](Renderer::enable_ssao) pass counts as one.
+    pub failed_draws: u32,
+    /// Entries that weren't drawn because their material [is missing something from the
+    /// scene](Shader::check_scene)
+    pub skipped: u32,
+}
 
 pub struct Renderer {
-    polygons: u32,
+    stats: RenderStats,
+    triangles_by_material: HashMap<TypeId, u32>,
+    material_names: HashMap<TypeId, String>,
+    failed_checks: HashSet<TypeId>,
+    shadow_map: Option<ShadowMap>,
+    depth_prepass: Option<DepthPrepass>,
+    ssao: Option<Ssao>,
+    debug_draw: Option<DebugDraw>,
+    polygon_mode: PolygonMode,
+    line_width: f32,
+    clear_color: Option<[f32; 4]>,
+    clear_depth: f32,
+    clear: bool,
+    sort_opaque: bool,
+    time: f32,
 }
 
 impl Renderer {
     pub fn new() -> Self {
-        Self { polygons: 0 }
+        Self {
+            stats: RenderStats::default(),
+            triangles_by_material: HashMap::new(),
+            material_names: HashMap::new(),
+            failed_checks: HashSet::new(),
+            shadow_map: None,
+            depth_prepass: None,
+            ssao: None,
+            debug_draw: None,
+            polygon_mode: PolygonMode::Fill,
+            line_width: 1.0,
+            clear_color: None,
+            clear_depth: 1.0,
+            clear: true,
+            sort_opaque: false,
+            time: 0.0,
+        }
+    }
+
+    /// How polygons are rasterized in every scene started after this, ex. `PolygonMode::Line` for
+    /// wireframes
+    pub fn set_polygon_mode(&mut self, polygon_mode: PolygonMode) {
+        self.polygon_mode = polygon_mode;
+    }
+    pub fn get_polygon_mode(&self) -> PolygonMode {
+        self.polygon_mode
+    }
+
+    /// Width in pixels of lines when using `PolygonMode::Line`, also used as the point size for
+    /// `PolygonMode::Point`
+    pub fn set_line_width(&mut self, line_width: f32) {
+        self.line_width = line_width;
+    }
+    pub fn get_line_width(&self) -> f32 {
+        self.line_width
+    }
+
+    /// The color [`RenderScene::finish`] clears the surface to before drawing, along with the
+    /// depth. Nothing is cleared until a color is set.
+    pub fn set_clear_color(&mut self, clear_color: [f32; 4]) {
+        self.clear_color = Some(clear_color);
+    }
+    pub fn get_clear_color(&self) -> Option<[f32; 4]> {
+        self.clear_color
+    }
+
+    /// The depth the surface is cleared to, 1.0 by default
+    pub fn set_clear_depth(&mut self, clear_depth: f32) {
+        self.clear_depth = clear_depth;
+    }
+    pub fn get_clear_depth(&self) -> f32 {
+        self.clear_depth
+    }
+
+    /// Set to false when clearing the surface yourself, ex. to draw several scenes on top of each
+    /// other. True by default, but only has an effect once a [clear
+    /// color](Self::set_clear_color) is set.
+    pub fn set_clear(&mut self, clear: bool) {
+        self.clear = clear;
+    }
+    pub fn get_clear(&self) -> bool {
+        self.clear
+    }
+
+    /// Draws opaque entries front to back by their distance to the camera, false by default
+    ///
+    /// Closer objects fill the depth buffer first, so fragments hidden behind them fail the depth
+    /// test before they are shaded, but entries of the same material are no longer drawn
+    /// together. With the [depth pre-pass](Self::enable_depth_prepass) hidden fragments are
+    /// already rejected whether or not the entries are sorted.
+    pub fn set_sort_opaque(&mut self, sort_opaque: bool) {
+        self.sort_opaque = sort_opaque;
+    }
+    pub fn get_sort_opaque(&self) -> bool {
+        self.sort_opaque
+    }
+
+    /// Adds `delta` to the [time](SceneData::get_time) of every scene started after this, call it
+    /// once per frame to animate materials
+    pub fn advance_time(&mut self, delta: Duration) {
+        self.time += delta.as_secs_f32();
+    }
+    /// Seconds passed to [`advance_time`](Self::advance_time) so far
+    pub fn get_time(&self) -> f32 {
+        self.time
+    }
+    /// Ex. 0 to restart animations, the time loses precision after running for many hours
+    pub fn set_time(&mut self, time: f32) {
+        self.time = time;
+    }
+
+    /// Renders a shadow map for the scene's directional light every frame
+    ///
+    /// `resolution` is the width and height of the depth texture. Shadows are only rendered when
+    /// the scene has a [`DirectionalLight`].
+    pub fn enable_shadows(&mut self, facade: &impl Facade, resolution: u32) {
+        self.shadow_map = Some(ShadowMap::new(facade, resolution));
+    }
+    pub fn disable_shadows(&mut self) {
+        self.shadow_map = None;
+    }
+    pub fn get_shadow_map_mut(&mut self) -> Option<&mut ShadowMap> {
+        self.shadow_map.as_mut()
+    }
+
+    /// Renders the depth of opaque geometry before shading anything
+    ///
+    /// The expensive fragment shaders then only run for the closest surface of each pixel instead
+    /// of for every overlapping object, at the cost of an extra draw call per entry. Only entries
+    /// whose material [supports it](Shader::supports_depth_prepass) are in the pre-pass.
+    pub fn enable_depth_prepass(&mut self, facade: &impl Facade) {
+        self.depth_prepass = Some(DepthPrepass::load_from_fs(facade));
+    }
+    pub fn disable_depth_prepass(&mut self) {
+        self.depth_prepass = None;
+    }
+    pub fn is_depth_prepass_enabled(&self) -> bool {
+        self.depth_prepass.is_some()
+    }
+
+    /// Renders screen space ambient occlusion every frame, which materials multiply into their
+    /// ambient lighting
+    ///
+    /// Only entries whose material [supports the depth pre-pass](Shader::supports_depth_prepass)
+    /// occlude or are occluded. See [`Ssao`] for the extra render targets it needs.
+    pub fn enable_ssao(&mut self, facade: &impl Facade) {
+        self.ssao = Some(Ssao::new(facade));
+    }
+    pub fn disable_ssao(&mut self) {
+        self.ssao = None;
+    }
+    pub fn is_ssao_enabled(&self) -> bool {
+        self.ssao.is_some()
+    }
+    pub fn get_ssao_mut(&mut self) -> Option<&mut Ssao> {
+        self.ssao.as_mut()
+    }
+
+    /// Draws the lines queued in [`DebugDraw`] over the scene at the end of every frame
+    pub fn enable_debug_draw(&mut self, facade: &impl Facade) {
+        self.debug_draw = Some(DebugDraw::new(facade));
+    }
+    pub fn disable_debug_draw(&mut self) {
+        self.debug_draw = None;
+    }
+    pub fn is_debug_draw_enabled(&self) -> bool {
+        self.debug_draw.is_some()
+    }
+    pub fn get_debug_draw_mut(&mut self) -> Option<&mut DebugDraw> {
+        self.debug_draw.as_mut()
     }
 
     pub fn begin_scene(&mut self) -> RenderScene {
         return RenderScene::new(self);
     }
+    /// The stats of the last finished scene
+    pub fn get_stats(&self) -> &RenderStats {
+        &self.stats
+    }
+    /// The triangles of the last finished scene, split up by the type of shader that drew them
+    ///
+    /// Use [`get_material_name`](Self::get_material_name) to display the keys.
+    pub fn get_triangles_by_material(&self) -> &HashMap<TypeId, u32> {
+        &self.triangles_by_material
+    }
+    /// The [debug name](Shader::debug_name) of a shader type drawn in the last finished scene
+    pub fn get_material_name(&self, type_id: &TypeId) -> Option<&str> {
+        self.material_names.get(type_id).map(String::as_str)
+    }
+    /// Same as [`RenderStats::triangles`] from [`get_stats`](Self::get_stats)
     pub fn get_polygons(&self) -> u32 {
-        self.polygons
+        self.stats.triangles
     }
 }
 
+/// The layer entries are published to unless another one is given, see
+/// [`RenderScene::publish_in_layer`]
+pub const DEFAULT_LAYER: u32 = 1;
+/// Mask for [`RenderScene::finish_layers`] that renders every layer
+pub const ALL_LAYERS: u32 = u32::MAX;
+
 pub struct RenderEntry<'a> {
     vertex_buffer: VerticesSource<'a>,
     index_buffer: IndicesSource<'a>,
-    material: &'a dyn Shader,
+    material: EntryMaterial<'a>,
+    layer: u32,
+}
+
+/// Materials are usually borrowed from the published model, but ones created while publishing
+/// (ex. [`Model::render_outline`](crate::utils::model::Model::render_outline)) have to be owned
+/// by the scene
+enum EntryMaterial<'a> {
+    Owned(Box<dyn Shader>),
+    Borrowed(&'a dyn Shader),
+}
+
+impl<'a> Deref for EntryMaterial<'a> {
+    type Target = dyn Shader;
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            Self::Owned(shader) => &**shader,
+            Self::Borrowed(shader) => *shader,
+        }
+    }
 }
 
 impl<'a> RenderEntry<'a> {
@@ -44,21 +285,131 @@ impl<'a> RenderEntry<'a> {
         surface: &mut Renderable,
         scene: &SceneData,
         world: impl Into<[[f32; 4]; 4]>,
-    ) {
+    ) -> Result<(), DrawError> {
         self.material.render(
             self.vertex_buffer,
             self.index_buffer,
             surface,
             scene.projection,
             world.into(),
-            &scene,
-        );
+            scene,
+        )
+    }
+}
+
+/// How HDR colors are mapped to the displayable `0.0..=1.0` range
+///
+/// The values match the `toneMapping` uniform in the shaders.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ToneMapping {
+    /// Colors are only scaled by the exposure and then clamped
+    None = 0,
+    Reinhard = 1,
+    /// Filmic curve, keeps more contrast than Reinhard
+    ACES = 2,
+}
+
+/// Exponential distance fog, see [`SceneData::set_fog`]
+///
+/// The color is blended in after tone mapping, so it is the color that is shown in the distance.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Fog {
+    pub color: [f32; 3],
+    /// How quickly things fade into the fog, at a distance of `1 / density` a surface is about
+    /// 63% fog
+    pub density: f32,
+}
+
+impl Fog {
+    pub fn new(color: [f32; 3], density: f32) -> Self {
+        Self { color, density }
+    }
+}
+
+/// What is drawn behind the scene by the [`Skybox`](crate::shaders::skybox::Skybox) material,
+/// see [`SceneData::set_background`]
+///
+/// Only the visible background changes, the [`PBRSkybox`](crate::utils::pbr_skybox::PBRSkybox)
+/// still lights the scene. The colors aren't tone mapped or fogged, they are shown as given.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Background {
+    /// The environment map of the scene's `PBRSkybox`
+    Skybox,
+    SolidColor([f32; 3]),
+    /// Blends from `bottom` at the bottom of the viewport to `top` at the top
+    Gradient {
+        top: [f32; 3],
+        bottom: [f32; 3],
+    },
+}
+
+/// Edges of the triangles drawn over the shaded surface, see
+/// [`SceneData::set_wireframe_overlay`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WireframeOverlay {
+    pub color: [f32; 3],
+    /// Width of the lines in pixels
+    pub thickness: f32,
+}
+
+impl WireframeOverlay {
+    pub fn new(color: [f32; 3], thickness: f32) -> Self {
+        Self { color, thickness }
+    }
+}
+
+/// Offset added to the depth of a material's fragments, see [`Shader::get_depth_bias`]
+///
+/// Negative values move surfaces toward the camera, ex. to draw decals or stacked flat geometry
+/// on top of the surface they lie on without z-fighting. Same as `glPolygonOffset`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DepthBias {
+    /// Scaled by how steep the surface is relative to the camera
+    pub factor: f32,
+    /// Scaled by the smallest difference the depth buffer can store
+    pub units: f32,
+}
+
+impl DepthBias {
+    pub fn new(factor: f32, units: f32) -> Self {
+        Self { factor, units }
+    }
+
+    /// The bias as draw parameters, applied to points, lines, and filled polygons
+    pub fn to_polygon_offset(bias: Option<Self>) -> PolygonOffset {
+        match bias {
+            Some(bias) => PolygonOffset {
+                factor: bias.factor,
+                units: bias.units,
+                point: true,
+                line: true,
+                fill: true,
+            },
+            None => PolygonOffset::default(),
+        }
     }
 }
 
 pub struct SceneData {
     pub projection: [[f32; 4]; 4],
     pub camera: Camera,
+    lights: Lights,
+    directional_light: Option<DirectionalLight>,
+    shadow_map: Option<ShadowMap>,
+    ssao: Option<Ssao>,
+    depth_prepass: bool,
+    polygon_mode: PolygonMode,
+    line_width: f32,
+    backface_culling: BackfaceCullingMode,
+    exposure: f32,
+    tone_mapping: ToneMapping,
+    prefilter_lod_bias: f32,
+    ibl_intensity: f32,
+    fog: Option<Fog>,
+    background: Background,
+    wireframe_overlay: Option<WireframeOverlay>,
+    viewport: Option<Rect>,
+    time: f32,
     scene_objects: HashMap<TypeId, Box<dyn Any>>,
     scene_vars: HashMap<&'static str, Box<dyn Any>>,
 }
@@ -74,10 +425,187 @@ impl SceneData {
         Self {
             projection: [[0.0; 4]; 4],
             camera: Camera::new(),
+            lights: Lights::new(),
+            directional_light: None,
+            shadow_map: None,
+            ssao: None,
+            depth_prepass: false,
+            polygon_mode: PolygonMode::Fill,
+            line_width: 1.0,
+            backface_culling: BackfaceCullingMode::CullingDisabled,
+            exposure: 1.0,
+            tone_mapping: ToneMapping::ACES,
+            prefilter_lod_bias: 0.0,
+            ibl_intensity: 1.0,
+            fog: None,
+            background: Background::Skybox,
+            wireframe_overlay: None,
+            viewport: None,
+            time: 0.0,
             scene_objects: HashMap::new(),
             scene_vars: HashMap::new(),
         }
     }
+    pub fn get_lights(&self) -> &Lights {
+        &self.lights
+    }
+    pub fn get_lights_mut(&mut self) -> &mut Lights {
+        &mut self.lights
+    }
+    pub fn set_lights(&mut self, lights: Lights) {
+        self.lights = lights;
+    }
+    pub fn get_directional_light(&self) -> Option<&DirectionalLight> {
+        self.directional_light.as_ref()
+    }
+    pub fn set_directional_light(&mut self, light: Option<DirectionalLight>) {
+        self.directional_light = light;
+    }
+    /// Set by [`RenderScene::finish`] when shadows are enabled on the [`Renderer`]
+    pub fn get_shadow_map(&self) -> Option<&ShadowMap> {
+        self.shadow_map.as_ref()
+    }
+    /// Whether the depth of every entry that [supports it](Shader::supports_depth_prepass) was
+    /// already rendered, set by [`RenderScene::finish`]
+    pub fn get_depth_prepass(&self) -> bool {
+        self.depth_prepass
+    }
+    pub fn set_shadow_map(&mut self, shadow_map: Option<ShadowMap>) {
+        self.shadow_map = shadow_map;
+    }
+    /// Set by [`RenderScene::finish`] when ssao is enabled on the [`Renderer`]
+    pub fn get_ssao(&self) -> Option<&Ssao> {
+        self.ssao.as_ref()
+    }
+    pub fn set_ssao(&mut self, ssao: Option<Ssao>) {
+        self.ssao = ssao;
+    }
+
+    pub fn get_polygon_mode(&self) -> PolygonMode {
+        self.polygon_mode
+    }
+    pub fn set_polygon_mode(&mut self, polygon_mode: PolygonMode) {
+        self.polygon_mode = polygon_mode;
+    }
+    pub fn get_line_width(&self) -> f32 {
+        self.line_width
+    }
+    pub fn set_line_width(&mut self, line_width: f32) {
+        self.line_width = line_width;
+    }
+
+    pub fn get_backface_culling(&self) -> BackfaceCullingMode {
+        self.backface_culling
+    }
+    /// The culling used by materials that don't set their own, nothing is culled by default
+    ///
+    /// Front faces are counter clockwise, so `CullClockwise` removes the back faces. Models with
+    /// the opposite winding look hollow with it, those need `CullCounterClockwise`.
+    pub fn set_backface_culling(&mut self, backface_culling: BackfaceCullingMode) {
+        self.backface_culling = backface_culling;
+    }
+
+    pub fn get_exposure(&self) -> f32 {
+        self.exposure
+    }
+    /// Multiplies the color before tone mapping, higher values brighten the scene
+    pub fn set_exposure(&mut self, exposure: f32) {
+        self.exposure = exposure;
+    }
+    pub fn get_tone_mapping(&self) -> ToneMapping {
+        self.tone_mapping
+    }
+    pub fn set_tone_mapping(&mut self, tone_mapping: ToneMapping) {
+        self.tone_mapping = tone_mapping;
+    }
+    pub fn get_prefilter_lod_bias(&self) -> f32 {
+        self.prefilter_lod_bias
+    }
+    /// Added to the mip level of the prefilter map that reflections are read from, 0 by default
+    ///
+    /// Each mip level is blurrier, matching a higher roughness. Negative values sharpen the
+    /// reflections (ex. when a low resolution prefilter map looks blocky on smooth metals) and
+    /// positive values blur them, without regenerating the map.
+    pub fn set_prefilter_lod_bias(&mut self, prefilter_lod_bias: f32) {
+        self.prefilter_lod_bias = prefilter_lod_bias;
+    }
+    pub fn get_ibl_intensity(&self) -> f32 {
+        self.ibl_intensity
+    }
+    /// Scales the ambient lighting from the environment maps (both diffuse and specular), 1.0 by
+    /// default
+    ///
+    /// For balancing the ambient light against the other lights without baking the maps again.
+    /// The skybox itself isn't affected.
+    pub fn set_ibl_intensity(&mut self, ibl_intensity: f32) {
+        self.ibl_intensity = ibl_intensity;
+    }
+    pub fn get_fog(&self) -> Option<&Fog> {
+        self.fog.as_ref()
+    }
+    /// Fades surfaces into the fog color by their distance from the camera, none by default
+    ///
+    /// The skybox isn't fogged, so the environment still shows on the horizon.
+    pub fn set_fog(&mut self, fog: Option<Fog>) {
+        self.fog = fog;
+    }
+
+    pub fn get_background(&self) -> Background {
+        self.background
+    }
+    /// What the skybox model draws, the environment map by default
+    ///
+    /// A solid color or gradient is drawn in place of the environment map while image based
+    /// lighting keeps using it, ex. for product shots on a plain backdrop.
+    pub fn set_background(&mut self, background: Background) {
+        self.background = background;
+    }
+
+    pub fn get_wireframe_overlay(&self) -> Option<&WireframeOverlay> {
+        self.wireframe_overlay.as_ref()
+    }
+    /// Draws the edges of every triangle over the shaded surface to inspect the topology, none by
+    /// default
+    ///
+    /// Unlike a [line polygon mode](Self::set_polygon_mode) the surface is still shaded. Only the
+    /// PBR material draws the overlay, in the same draw call as the surface.
+    pub fn set_wireframe_overlay(&mut self, wireframe_overlay: Option<WireframeOverlay>) {
+        self.wireframe_overlay = wireframe_overlay;
+    }
+
+    /// The part of the surface the scene is drawn into, the whole surface when `None`
+    pub fn get_viewport(&self) -> Option<Rect> {
+        self.viewport
+    }
+    /// Set by [`RenderScene::finish_viewport`], materials get it through
+    /// [`apply_draw_parameters`](Self::apply_draw_parameters)
+    pub fn set_viewport(&mut self, viewport: Option<Rect>) {
+        self.viewport = viewport;
+    }
+
+    /// Seconds since the renderer started, for animated materials. Set from
+    /// [`Renderer::advance_time`] when the scene begins.
+    pub fn get_time(&self) -> f32 {
+        self.time
+    }
+    pub fn set_time(&mut self, time: f32) {
+        self.time = time;
+    }
+
+    /// Applies the scene's polygon mode, line width, and viewport to a material's draw parameters
+    pub fn apply_draw_parameters<'a>(
+        &self,
+        draw_parameters: DrawParameters<'a>,
+    ) -> DrawParameters<'a> {
+        DrawParameters {
+            polygon_mode: self.polygon_mode,
+            line_width: Some(self.line_width),
+            point_size: Some(self.line_width),
+            viewport: self.viewport.or(draw_parameters.viewport),
+            ..draw_parameters
+        }
+    }
+
     pub fn get_scene_object_raw<T: 'static + Sized>(&self) -> Option<&Box<dyn Any>> {
         self.scene_objects.get(&TypeId::of::<T>())
     }
@@ -110,33 +638,158 @@ pub struct RenderScene<'a> {
 
 impl<'a> RenderScene<'a> {
     fn new(renderer: &'a mut Renderer) -> Self {
+        let mut scene_data = SceneData::new();
+        scene_data.set_polygon_mode(renderer.polygon_mode);
+        scene_data.set_line_width(renderer.line_width);
+        scene_data.set_time(renderer.time);
+
         Self {
-            scene_data: SceneData::new(),
+            scene_data,
             entries: HashMap::new(),
             renderer,
         }
     }
 
+    /// Adds an entry to the [`DEFAULT_LAYER`]
     pub fn publish<V, I>(&mut self, vertex_buffer: V, index_buffer: I, shader: &'a dyn Shader)
     where
         V: Into<VerticesSource<'a>>,
         I: Into<IndicesSource<'a>>,
     {
-        let entry = RenderEntry {
+        self.publish_in_layer(vertex_buffer, index_buffer, shader, DEFAULT_LAYER);
+    }
+
+    /// Same as [`publish`](Self::publish) with a bitmask of the layers the entry is in
+    ///
+    /// [`finish_layers`](Self::finish_layers) only renders entries in the requested layers, ex.
+    /// to leave ui geometry out of a reflection pass.
+    pub fn publish_in_layer<V, I>(
+        &mut self,
+        vertex_buffer: V,
+        index_buffer: I,
+        shader: &'a dyn Shader,
+        layer: u32,
+    ) where
+        V: Into<VerticesSource<'a>>,
+        I: Into<IndicesSource<'a>>,
+    {
+        self.push_entry(RenderEntry {
             vertex_buffer: vertex_buffer.into(),
             index_buffer: index_buffer.into(),
-            material: shader,
-        };
+            material: EntryMaterial::Borrowed(shader),
+            layer,
+        });
+    }
 
-        let type_id = shader.as_any().type_id();
+    /// Same as [`publish_in_layer`](Self::publish_in_layer) for a material that only lives for
+    /// this scene
+    pub fn publish_owned<V, I>(
+        &mut self,
+        vertex_buffer: V,
+        index_buffer: I,
+        shader: impl Shader,
+        layer: u32,
+    ) where
+        V: Into<VerticesSource<'a>>,
+        I: Into<IndicesSource<'a>>,
+    {
+        self.push_entry(RenderEntry {
+            vertex_buffer: vertex_buffer.into(),
+            index_buffer: index_buffer.into(),
+            material: EntryMaterial::Owned(Box::new(shader)),
+            layer,
+        });
+    }
+
+    fn push_entry(&mut self, entry: RenderEntry<'a>) {
+        let type_id = entry.material.as_any().type_id();
 
         self.entries.entry(type_id).or_insert(Vec::new());
 
         self.entries.get_mut(&type_id).unwrap().push(entry);
     }
 
+    /// The renderer's [`DebugDraw`] for queueing lines this frame, if it is
+    /// [enabled](Renderer::enable_debug_draw)
+    pub fn get_debug_draw_mut(&mut self) -> Option<&mut DebugDraw> {
+        self.renderer.debug_draw.as_mut()
+    }
+
     /// Render all the items that have been submitted
-    pub fn finish(mut self, surface: &mut Renderable) {
+    ///
+    /// Opaque entries are drawn first, front to back if [sorting is
+    /// enabled](Renderer::set_sort_opaque), followed by the [background](Shader::is_background)
+    /// (ex. the skybox). Entries whose material [is transparent](Shader::is_transparent) are
+    /// drawn afterwards, sorted back to front by their distance to the camera so they blend
+    /// correctly. Depth writes are disabled for the transparent pass (by the material), so
+    /// transparent objects are still hidden behind opaque ones but never hide each other.
+    ///
+    /// If shadows are [enabled](Renderer::enable_shadows) and the scene has a directional light,
+    /// the opaque entries are first rendered into the shadow map. With the [depth
+    /// pre-pass](Renderer::enable_depth_prepass) enabled, the depth of the supported entries is
+    /// rendered before anything else.
+    ///
+    /// The surface is cleared first if the renderer has a [clear
+    /// color](Renderer::set_clear_color). Lines queued in the [debug
+    /// draw](Renderer::enable_debug_draw) are drawn last and then cleared.
+    ///
+    /// A failed draw call doesn't stop the other entries from being drawn. Failures are counted in
+    /// [`RenderStats::failed_draws`] and the first error of the frame is printed. Materials that
+    /// [fail their scene check](Shader::check_scene) aren't drawn at all, their entries are
+    /// counted in [`RenderStats::skipped`] and the error is printed the first time each material
+    /// fails.
+    ///
+    /// Returns what was rendered, which can also be read later with [`Renderer::get_stats`].
+    pub fn finish(self, surface: &mut Renderable) -> RenderStats {
+        self.finish_layers(surface, ALL_LAYERS)
+    }
+
+    /// Same as [`finish`](Self::finish), but the scene is only drawn into `rect` of the surface
+    ///
+    /// Only `rect` is cleared, so finishing several scenes into different parts of the same
+    /// surface (ex. split screen with a camera each) keeps what the others drew. The projection
+    /// should use the aspect ratio of `rect` instead of the surface's.
+    pub fn finish_viewport(mut self, surface: &mut Renderable, rect: Rect) -> RenderStats {
+        self.scene_data.set_viewport(Some(rect));
+        self.finish_layers(surface, ALL_LAYERS)
+    }
+
+    /// Same as [`finish`](Self::finish), but only entries in one of the layers of `mask` are
+    /// rendered
+    ///
+    /// The other entries are left out of every pass, including the shadow map, ssao, and depth
+    /// pre-pass, and are only counted in [`RenderStats::culled`].
+    pub fn finish_layers(mut self, surface: &mut Renderable, mask: u32) -> RenderStats {
+        let mut culled = 0;
+        self.entries.retain(|_, entries| {
+            let count = entries.len();
+            entries.retain(|entry| entry.layer & mask != 0);
+            culled += (count - entries.len()) as u32;
+            !entries.is_empty()
+        });
+
+        let mut skipped = 0;
+        self.entries.retain(|type_id, entries| {
+            match entries[0].material.check_scene(&self.scene_data) {
+                Ok(()) => true,
+                Err(err) => {
+                    if self.renderer.failed_checks.insert(*type_id) {
+                        eprintln!("skipping {} entries: {}", entries.len(), err);
+                    }
+                    skipped += entries.len() as u32;
+                    false
+                }
+            }
+        });
+
+        if let (true, Some(color)) = (self.renderer.clear, self.renderer.clear_color) {
+            surface.clear_color_and_depth_in(
+                self.scene_data.get_viewport().as_ref(),
+                color.into(),
+                self.renderer.clear_depth,
+            );
+        }
+
         //let skybox = match &self.scene_data.skybox {
         //Some(skybox) => self.entries.remove(&skybox.get_skybox().as_any().type_id()),
         //None => None,
@@ -150,21 +803,188 @@ impl<'a> RenderScene<'a> {
         //}
         //}
 
-        let mut vertices = 0;
-        for values in self.entries.into_values() {
-            for entry in values {
-                // Crudely count indices
-                vertices += match &entry.index_buffer {
-                    IndicesSource::IndexBuffer { buffer, .. } => buffer.get_elements_count(),
-                    IndicesSource::MultidrawArray { buffer, .. } => buffer.get_elements_count(),
-                    _ => 0,
-                };
-                entry.render(surface, &self.scene_data, world);
+        let mut stats = RenderStats {
+            material_batches: self.entries.len() as u32,
+            culled,
+            skipped,
+            ..Default::default()
+        };
+
+        let entries = self.entries.into_values().flatten().collect::<Vec<_>>();
+        let mut first_error = None;
+
+        let light = self.scene_data.get_directional_light().copied();
+        if let (Some(shadow_map), Some(light)) = (self.renderer.shadow_map.as_mut(), light) {
+            let casters = entries
+                .iter()
+                .filter(|entry| entry.material.casts_shadows())
+                .map(|entry| {
+                    (
+                        entry.vertex_buffer.clone(),
+                        entry.index_buffer.clone(),
+                        entry.material.get_model_mat(),
+                    )
+                });
+
+            shadow_map.render(&light, self.scene_data.camera.position, casters);
+            self.scene_data.set_shadow_map(Some(shadow_map.clone()));
+        }
+
+        if let Some(ssao) = self.renderer.ssao.as_mut() {
+            let occluders = entries
+                .iter()
+                .filter(|entry| entry.material.supports_depth_prepass())
+                .map(|entry| {
+                    (
+                        entry.vertex_buffer.clone(),
+                        entry.index_buffer.clone(),
+                        entry.material.get_model_mat(),
+                    )
+                });
+
+            let (width, height) = match self.scene_data.get_viewport() {
+                Some(rect) => (rect.width, rect.height),
+                None => surface.get_dimensions(),
+            };
+            let result = ssao.render(
+                width,
+                height,
+                self.scene_data.projection.into(),
+                self.scene_data.camera.get_view_matrix(),
+                occluders,
+            );
+            Self::record_draw(result, &mut stats, &mut first_error);
+            self.scene_data.set_ssao(Some(ssao.clone()));
+        }
+
+        if let Some(depth_prepass) = &self.renderer.depth_prepass {
+            for entry in entries
+                .iter()
+                .filter(|entry| entry.material.supports_depth_prepass())
+            {
+                let result = depth_prepass.render(
+                    entry.vertex_buffer.clone(),
+                    entry.index_buffer.clone(),
+                    surface,
+                    entry.material.get_model_mat(),
+                    entry.material.get_backface_culling(&self.scene_data),
+                    entry.material.get_depth_bias(),
+                    &self.scene_data,
+                );
+                Self::record_draw(result, &mut stats, &mut first_error);
+                stats.draw_calls += 1;
+            }
+            self.scene_data.depth_prepass = true;
+        }
+
+        self.renderer.triangles_by_material.clear();
+        self.renderer.material_names.clear();
+
+        let mut opaque = Vec::new();
+        let mut transparent = Vec::new();
+        let mut background = Vec::new();
+        for entry in entries {
+            // Crudely count indices, assuming that each polygon is a triangle
+            let vertices = match &entry.index_buffer {
+                IndicesSource::IndexBuffer { buffer, .. } => buffer.get_elements_count(),
+                IndicesSource::MultidrawArray { buffer, .. } => buffer.get_elements_count(),
+                _ => 0,
+            } * entry.material.get_instance_count();
+            let triangles = vertices as u32 / 3;
+            stats.triangles += triangles;
+            stats.draw_calls += 1;
+
+            let type_id = entry.material.as_any().type_id();
+            *self
+                .renderer
+                .triangles_by_material
+                .entry(type_id)
+                .or_default() += triangles;
+            self.renderer
+                .material_names
+                .entry(type_id)
+                .or_insert_with(|| entry.material.debug_name().to_string());
+
+            if entry.material.is_transparent() {
+                let distance = Self::camera_distance(&self.scene_data, &entry);
+                transparent.push((distance, entry));
+            } else if entry.material.is_background() {
+                background.push(entry);
+            } else if self.renderer.sort_opaque {
+                let distance = Self::camera_distance(&self.scene_data, &entry);
+                opaque.push((distance, entry));
+            } else {
+                opaque.push((0.0, entry));
+            }
+        }
+
+        // Closest first so they hide as much as possible of what is drawn after them
+        if self.renderer.sort_opaque {
+            opaque.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+        }
+
+        for (_, entry) in opaque {
+            let result = entry.render(surface, &self.scene_data, world);
+            Self::record_draw(result, &mut stats, &mut first_error);
+        }
+
+        // Only the pixels nothing else covered are left to shade
+        for entry in background {
+            let result = entry.render(surface, &self.scene_data, world);
+            Self::record_draw(result, &mut stats, &mut first_error);
+        }
+
+        // Furthest first so closer objects get blended on top
+        transparent.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+
+        for (_, entry) in transparent {
+            let result = entry.render(surface, &self.scene_data, world);
+            Self::record_draw(result, &mut stats, &mut first_error);
+        }
+
+        if let Some(debug_draw) = self.renderer.debug_draw.as_mut() {
+            if !debug_draw.is_empty() {
+                let result = debug_draw.render(
+                    surface,
+                    self.scene_data.projection,
+                    world,
+                    self.scene_data.get_viewport(),
+                );
+                Self::record_draw(result, &mut stats, &mut first_error);
+                stats.draw_calls += 1;
+                debug_draw.clear();
             }
         }
 
-        // Assume that each polygon is a triangle (vertices / 3)
-        self.renderer.polygons = vertices as u32 / 3;
+        if let Some(err) = first_error {
+            eprintln!(
+                "{} draw call(s) failed, first error: {}",
+                stats.failed_draws, err
+            );
+        }
+
+        self.renderer.stats = stats;
+
+        stats
+    }
+
+    /// Squared distance from the camera to the origin of the entry's model matrix
+    fn camera_distance(scene_data: &SceneData, entry: &RenderEntry) -> f32 {
+        let model = entry.material.get_model_mat();
+        let position = Vector3::new(model[(0, 3)], model[(1, 3)], model[(2, 3)]);
+
+        (position - scene_data.camera.position).norm_squared()
+    }
+
+    fn record_draw(
+        result: Result<(), impl Into<RendererError>>,
+        stats: &mut RenderStats,
+        first_error: &mut Option<RendererError>,
+    ) {
+        if let Err(err) = result {
+            stats.failed_draws += 1;
+            first_error.get_or_insert(err.into());
+        }
     }
 }
 
@@ -194,6 +1014,44 @@ impl<'a> Renderable<'a> {
             }
         }
     }
+
+    /// Only clears the stencil buffer, ex. before a material uses it as a mask
+    pub fn clear_stencil(&mut self, value: i32) {
+        match self {
+            Self::Frame(frame) => frame.clear_stencil(value),
+            Self::SimpleFrameBuffer(frame) => frame.clear_stencil(value),
+        }
+    }
+
+    pub fn get_dimensions(&self) -> (u32, u32) {
+        match self {
+            Self::Frame(frame) => frame.get_dimensions(),
+            Self::SimpleFrameBuffer(frame) => frame.get_dimensions(),
+        }
+    }
+
+    pub fn clear_color_and_depth(&mut self, color: (f32, f32, f32, f32), depth: f32) {
+        match self {
+            Self::Frame(frame) => frame.clear_color_and_depth(color, depth),
+            Self::SimpleFrameBuffer(frame) => frame.clear_color_and_depth(color, depth),
+        }
+    }
+
+    /// Same as [`clear_color_and_depth`](Self::clear_color_and_depth), but only clears `rect`
+    /// when there is one
+    pub fn clear_color_and_depth_in(
+        &mut self,
+        rect: Option<&Rect>,
+        color: (f32, f32, f32, f32),
+        depth: f32,
+    ) {
+        match self {
+            Self::Frame(frame) => frame.clear(rect, Some(color), false, Some(depth), None),
+            Self::SimpleFrameBuffer(frame) => {
+                frame.clear(rect, Some(color), false, Some(depth), None)
+            }
+        }
+    }
 }
 
 impl<'a> From<&'a mut Frame> for Renderable<'a> {
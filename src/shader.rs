@@ -1,11 +1,21 @@
+use crate::error::RendererError;
+use crate::renderer::DepthBias;
 use crate::renderer::Renderable;
 use crate::renderer::SceneData;
+use glium::backend::Context;
 use glium::backend::Facade;
+use glium::draw_parameters::BackfaceCullingMode;
 use glium::index::IndicesSource;
+use glium::program::ProgramCreationError;
 use glium::vertex::VerticesSource;
+use glium::DrawError;
 use glium::Program;
 use nalgebra::Matrix4;
 use std::any::Any;
+use std::path::Path;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::time::SystemTime;
 
 pub trait Shader: 'static {
     /// Render the material
@@ -13,6 +23,11 @@ pub trait Shader: 'static {
     /// Renders the given index and vertex buffers to the given surface. This also gives you access
     /// to the struct that implements this trait. That is how you can render materials with
     /// unique variables
+    ///
+    /// Errors from drawing should be returned rather than unwrapped, [`RenderScene::finish`] keeps
+    /// drawing the other entries and reports the first error.
+    ///
+    /// [`RenderScene::finish`]: crate::renderer::RenderScene::finish
     fn render<'a>(
         &self,
         vertex_buffer: VerticesSource<'a>,
@@ -21,7 +36,7 @@ pub trait Shader: 'static {
         camera: [[f32; 4]; 4],
         position: [[f32; 4]; 4],
         scene_data: &SceneData,
-    );
+    ) -> Result<(), DrawError>;
 
     fn get_model_mat(&self) -> Matrix4<f32>;
 
@@ -31,8 +46,9 @@ pub trait Shader: 'static {
     fn as_any(&self) -> &dyn Any;
     fn as_any_mut(&mut self) -> &mut dyn Any;
 
-    /// Determines if the passed material is the same type and has the same content. Might be used
-    /// for batching later on (group objects with identical materials).
+    /// Determines if the passed material is the same type and has the same content, so objects
+    /// drawn with either look the same. Used to group objects with identical materials, ex. by
+    /// [`Model::merge_sub_models`](crate::utils::model::Model::merge_sub_models).
     fn equal_shader(&self, shader: &dyn Any) -> bool;
 
     /// Determines if the passed material is the same type
@@ -56,14 +72,16 @@ pub trait Shader: 'static {
     ///
     /// # Example
     /// ```
-    /// struct Mat<T: Material> {
+    /// use opengl_renderer::shader::Shader;
+    ///
+    /// struct Mat<T: Shader> {
     ///     material: T
     /// }
     ///
-    /// impl<T: Material> Clone for Mat<T> {
+    /// impl<T: Shader> Clone for Mat<T> {
     ///     fn clone(&self) -> Self {
     ///         Self {
-    ///             // clone_material would not work since it is not a concrete type
+    ///             // clone_shader would not work since it is not a concrete type
     ///             material: self.material.clone_sized()
     ///         }
     ///     }
@@ -72,11 +90,110 @@ pub trait Shader: 'static {
     fn clone_sized(&self) -> Self
     where
         Self: Sized;
+
+    /// Whether the material needs to be blended with what is behind it
+    ///
+    /// Transparent entries are drawn by [`RenderScene::finish`] after every opaque entry, sorted
+    /// back to front. Materials that return true should disable depth writes so transparent
+    /// objects don't hide each other.
+    ///
+    /// [`RenderScene::finish`]: crate::renderer::RenderScene::finish
+    fn is_transparent(&self) -> bool {
+        false
+    }
+
+    /// Whether the material fills whatever the rest of the scene didn't cover, ex. the skybox,
+    /// false by default
+    ///
+    /// [`RenderScene::finish`] draws these entries after every other opaque entry and before the
+    /// transparent ones, so only the pixels that are still empty get shaded. They are left out
+    /// of the [opaque sort](crate::renderer::Renderer::set_sort_opaque).
+    ///
+    /// [`RenderScene::finish`]: crate::renderer::RenderScene::finish
+    fn is_background(&self) -> bool {
+        false
+    }
+
+    /// How many times each draw call renders the geometry, more than 1 when instancing
+    fn get_instance_count(&self) -> usize {
+        1
+    }
+
+    /// Whether the entry can be drawn by the depth pre-pass
+    ///
+    /// Only valid for opaque materials that use the regular [`Vertex`](crate::vertex::Vertex)
+    /// layout and place vertices at `projection * view * model * position`. When
+    /// [`SceneData::get_depth_prepass`] is true, materials returning true are drawn after their
+    /// depth is already in the depth buffer, so they should test with `IfLessOrEqual` and skip
+    /// writing depth.
+    ///
+    /// [`SceneData::get_depth_prepass`]: crate::renderer::SceneData::get_depth_prepass
+    fn supports_depth_prepass(&self) -> bool {
+        false
+    }
+
+    /// Whether the geometry is drawn into the [`ShadowMap`], true for opaque materials by default
+    ///
+    /// The shadow map only applies the model matrix, so materials that move their vertices some
+    /// other way (ex. instancing or skinning) or aren't a real object (ex. the skybox) should
+    /// return false instead of casting a shadow in the wrong place.
+    ///
+    /// [`ShadowMap`]: crate::utils::shadow_map::ShadowMap
+    fn casts_shadows(&self) -> bool {
+        !self.is_transparent()
+    }
+
+    /// Which faces the material doesn't draw, nothing is culled by default
+    ///
+    /// The depth pre-pass uses the same mode so it only writes the depth of faces that are drawn
+    /// afterwards.
+    fn get_backface_culling(&self, _scene_data: &SceneData) -> BackfaceCullingMode {
+        BackfaceCullingMode::CullingDisabled
+    }
+
+    /// Offset added to the depth of the material's fragments, none by default
+    ///
+    /// The depth pre-pass uses the same bias so the depths still match.
+    fn get_depth_bias(&self) -> Option<DepthBias> {
+        None
+    }
+
+    /// Checks that the scene has everything the material needs, ex. the
+    /// [`PBRSkybox`](crate::utils::pbr_skybox::PBRSkybox) of the PBR material
+    ///
+    /// [`RenderScene::finish`] calls this once per material type before drawing anything. When it
+    /// fails the entries of that material are left out of every pass and the error is printed,
+    /// instead of panicking in [`render`](Self::render).
+    ///
+    /// [`RenderScene::finish`]: crate::renderer::RenderScene::finish
+    fn check_scene(&self, _scene_data: &SceneData) -> Result<(), RendererError> {
+        Ok(())
+    }
+
+    /// Name shown in debug output, ex. [`Renderer::get_material_name`]
+    ///
+    /// Defaults to the name of the type without its module path.
+    ///
+    /// [`Renderer::get_material_name`]: crate::renderer::Renderer::get_material_name
+    fn debug_name(&self) -> &str {
+        let name = std::any::type_name::<Self>();
+        name.rsplit("::").next().unwrap_or(name)
+    }
+
+    /// Recompiles the shader's program if its source files changed
+    ///
+    /// Only does something for materials using a [`HotReloadProgram`]. Meant to be called every
+    /// frame while working on shaders. Returns true if the program was reloaded.
+    fn reload_if_changed(&mut self, _facade: &Rc<Context>) -> bool {
+        false
+    }
 }
 
 /// A simple macro which will include fragment and vertex shaders in the binary
 ///
-/// The preferred way to load shaders since portability is guaranteed.
+/// The preferred way to load shaders since portability is guaranteed. A geometry shader can be
+/// passed between the vertex and fragment shaders, ex. `insert_program!("./vertex.glsl",
+/// "./geometry.glsl", "./fragment.glsl", facade)`.
 #[macro_export]
 macro_rules! insert_program {
     ($vertex:expr, $fragment:expr, $facade:expr) => {
@@ -88,11 +205,163 @@ macro_rules! insert_program {
         )
         .expect(&format!("Error compiling shader"))
     };
+    ($vertex:expr, $geometry:expr, $fragment:expr, $facade:expr) => {
+        Program::from_source(
+            $facade,
+            &include_str!($vertex),
+            &include_str!($fragment),
+            Some(&include_str!($geometry)),
+        )
+        .expect(&format!("Error compiling shader"))
+    };
 }
 
 pub use insert_program;
 
 pub fn compile_program(facade: &impl Facade, vertex: &str, fragment: &str) -> Program {
-    Program::from_source(facade, &vertex, &fragment, None)
-        .expect(&format!("Error compiling shader"))
+    try_compile_program(facade, vertex, fragment).expect("Error compiling shader")
+}
+
+/// Same as [`compile_program`] but returns the error instead of panicking
+pub fn try_compile_program(
+    facade: &impl Facade,
+    vertex: &str,
+    fragment: &str,
+) -> Result<Program, ProgramCreationError> {
+    Program::from_source(facade, vertex, fragment, None)
+}
+
+/// A program that is loaded from the filesystem and can be recompiled while the app is running
+///
+/// Unlike [`insert_program`] the shaders aren't included in the binary, so this should only be
+/// used while working on shaders.
+#[derive(Clone)]
+pub struct HotReloadProgram {
+    vertex_path: PathBuf,
+    fragment_path: PathBuf,
+    geometry_path: Option<PathBuf>,
+    vertex_modified: SystemTime,
+    fragment_modified: SystemTime,
+    geometry_modified: Option<SystemTime>,
+    program: Rc<Program>,
+}
+
+impl HotReloadProgram {
+    pub fn new(
+        facade: &impl Facade,
+        vertex_path: impl AsRef<Path>,
+        fragment_path: impl AsRef<Path>,
+    ) -> Result<Self, RendererError> {
+        Self::load(facade, vertex_path.as_ref(), fragment_path.as_ref(), None)
+    }
+
+    /// Same as [`new`](Self::new) for programs with a geometry shader, ex. [`PBR`]
+    ///
+    /// [`PBR`]: crate::shaders::pbr::PBR
+    pub fn with_geometry(
+        facade: &impl Facade,
+        vertex_path: impl AsRef<Path>,
+        geometry_path: impl AsRef<Path>,
+        fragment_path: impl AsRef<Path>,
+    ) -> Result<Self, RendererError> {
+        Self::load(
+            facade,
+            vertex_path.as_ref(),
+            fragment_path.as_ref(),
+            Some(geometry_path.as_ref()),
+        )
+    }
+
+    fn load(
+        facade: &impl Facade,
+        vertex_path: &Path,
+        fragment_path: &Path,
+        geometry_path: Option<&Path>,
+    ) -> Result<Self, RendererError> {
+        let vertex = std::fs::read_to_string(vertex_path)?;
+        let fragment = std::fs::read_to_string(fragment_path)?;
+        let geometry = geometry_path.map(std::fs::read_to_string).transpose()?;
+        let program = Program::from_source(facade, &vertex, &fragment, geometry.as_deref())?;
+
+        Ok(Self {
+            vertex_modified: Self::modified(vertex_path)?,
+            fragment_modified: Self::modified(fragment_path)?,
+            geometry_modified: geometry_path.map(Self::modified).transpose()?,
+            vertex_path: vertex_path.to_path_buf(),
+            fragment_path: fragment_path.to_path_buf(),
+            geometry_path: geometry_path.map(Path::to_path_buf),
+            program: Rc::new(program),
+        })
+    }
+
+    pub fn get_program(&self) -> &Rc<Program> {
+        &self.program
+    }
+
+    /// Recompiles the program if any of its shader files was modified since the last check
+    ///
+    /// If compilation fails the error is printed and the old program is kept, so a typo doesn't
+    /// crash the running app. Returns true if the program was replaced.
+    pub fn reload_if_changed(&mut self, facade: &impl Facade) -> bool {
+        // The file might be in the middle of being saved, just try again next time
+        let geometry_modified = self
+            .geometry_path
+            .as_deref()
+            .map(Self::modified)
+            .transpose();
+        let (vertex_modified, fragment_modified, geometry_modified) = match (
+            Self::modified(&self.vertex_path),
+            Self::modified(&self.fragment_path),
+            geometry_modified,
+        ) {
+            (Ok(vertex), Ok(fragment), Ok(geometry)) => (vertex, fragment, geometry),
+            _ => return false,
+        };
+
+        if vertex_modified == self.vertex_modified
+            && fragment_modified == self.fragment_modified
+            && geometry_modified == self.geometry_modified
+        {
+            return false;
+        }
+
+        // Update the timestamps even if compilation fails so it isn't retried every frame
+        self.vertex_modified = vertex_modified;
+        self.fragment_modified = fragment_modified;
+        self.geometry_modified = geometry_modified;
+
+        let sources = std::fs::read_to_string(&self.vertex_path).and_then(|vertex| {
+            let fragment = std::fs::read_to_string(&self.fragment_path)?;
+            let geometry = self
+                .geometry_path
+                .as_deref()
+                .map(std::fs::read_to_string)
+                .transpose()?;
+
+            Ok((vertex, fragment, geometry))
+        });
+
+        let (vertex, fragment, geometry) = match sources {
+            Ok(sources) => sources,
+            Err(err) => {
+                eprintln!("Error reading shader: {}", err);
+                return false;
+            }
+        };
+
+        match Program::from_source(facade, &vertex, &fragment, geometry.as_deref()) {
+            Ok(program) => {
+                self.program = Rc::new(program);
+                true
+            }
+            Err(err) => {
+                eprintln!("Error compiling shader, keeping the old program: {}", err);
+                false
+            }
+        }
+    }
+
+    fn modified(path: &Path) -> std::io::Result<SystemTime> {
+        std::fs::metadata(path)?.modified()
+    }
 }
@@ -0,0 +1,15 @@
+#version 330 core
+out vec4 FragColor;
+in vec2 TexCoords;
+
+uniform sampler2D image;
+uniform sampler2D bloom;
+uniform float intensity;
+
+void main()
+{
+    vec4 color = texture(image, TexCoords);
+    vec3 bloomColor = texture(bloom, TexCoords).rgb;
+
+    FragColor = vec4(color.rgb + bloomColor * intensity, color.a);
+}
@@ -0,0 +1,194 @@
+use crate::insert_program;
+use crate::utils::shapes;
+use crate::vertex::Vertex;
+use glium::backend::Facade;
+use glium::framebuffer::SimpleFrameBuffer;
+use glium::uniforms::{
+    MagnifySamplerFilter, MinifySamplerFilter, Sampler, SamplerWrapFunction, Uniforms,
+};
+use glium::DrawParameters;
+use glium::IndexBuffer;
+use glium::Program;
+use glium::Surface;
+use glium::Texture2d;
+use glium::VertexBuffer;
+use std::rc::Rc;
+
+/// Makes bright parts of an HDR image bleed into their surroundings
+///
+/// Everything above the threshold is extracted at half resolution, blurred at several
+/// progressively smaller sizes, and added back onto the image. The input should not be tone
+/// mapped yet (ex. rendered into a float texture with
+/// [`ToneMapping::None`](crate::renderer::ToneMapping::None)), otherwise nothing gets above a
+/// threshold of 1.
+pub struct Bloom {
+    threshold_program: Rc<Program>,
+    blur_program: Rc<Program>,
+    composite_program: Rc<Program>,
+    quad: (VertexBuffer<Vertex>, IndexBuffer<u32>),
+    threshold: f32,
+    intensity: f32,
+    levels: usize,
+}
+
+impl Bloom {
+    pub fn new(facade: &impl Facade) -> Self {
+        let threshold_program =
+            Rc::new(insert_program!("./vertex.glsl", "./threshold.glsl", facade));
+        let blur_program = Rc::new(insert_program!("./vertex.glsl", "./blur.glsl", facade));
+        let composite_program =
+            Rc::new(insert_program!("./vertex.glsl", "./composite.glsl", facade));
+
+        Self {
+            threshold_program,
+            blur_program,
+            composite_program,
+            quad: shapes::fullscreen_quad(facade),
+            threshold: 1.0,
+            intensity: 0.3,
+            levels: 5,
+        }
+    }
+
+    /// Brightness a color needs before it blooms, 1.0 by default
+    pub fn get_threshold(&self) -> f32 {
+        self.threshold
+    }
+    pub fn set_threshold(&mut self, threshold: f32) {
+        self.threshold = threshold;
+    }
+
+    /// How strongly the bloom is added onto the image, 0.3 by default
+    pub fn get_intensity(&self) -> f32 {
+        self.intensity
+    }
+    pub fn set_intensity(&mut self, intensity: f32) {
+        self.intensity = intensity;
+    }
+
+    /// How many times the bright parts are downsampled and blurred, 5 by default
+    ///
+    /// Every level halves the resolution, so more levels spread the bloom further.
+    pub fn get_levels(&self) -> usize {
+        self.levels
+    }
+    pub fn set_levels(&mut self, levels: usize) {
+        self.levels = levels.max(1);
+    }
+
+    /// Returns a copy of `input` with bloom applied
+    pub fn apply(&self, facade: &impl Facade, input: &Texture2d) -> Texture2d {
+        let (mut width, mut height) = ((input.width() / 2).max(1), (input.height() / 2).max(1));
+
+        let bright = Self::create_texture(facade, width, height);
+        self.pass(
+            facade,
+            &self.threshold_program,
+            &bright,
+            uniform! {
+                image: Self::sample(input),
+                threshold: self.threshold,
+            },
+        );
+
+        // The first blur pass of every level reads from the previous level, which downsamples it
+        let mut blurred: Vec<Texture2d> = Vec::with_capacity(self.levels);
+        for level in 0..self.levels {
+            if level > 0 {
+                if width == 1 && height == 1 {
+                    break;
+                }
+                width = (width / 2).max(1);
+                height = (height / 2).max(1);
+            }
+
+            let source = blurred.last().unwrap_or(&bright);
+            let ping = Self::create_texture(facade, width, height);
+            let pong = Self::create_texture(facade, width, height);
+
+            self.pass(
+                facade,
+                &self.blur_program,
+                &ping,
+                uniform! { image: Self::sample(source), horizontal: true },
+            );
+            self.pass(
+                facade,
+                &self.blur_program,
+                &pong,
+                uniform! { image: Self::sample(&ping), horizontal: false },
+            );
+
+            blurred.push(pong);
+        }
+
+        // Add each level onto the next larger one, smallest first
+        let mut bloom = blurred.pop().unwrap();
+        while let Some(level) = blurred.pop() {
+            let combined = Self::create_texture(facade, level.width(), level.height());
+            self.pass(
+                facade,
+                &self.composite_program,
+                &combined,
+                uniform! {
+                    image: Self::sample(&level),
+                    bloom: Self::sample(&bloom),
+                    intensity: 1.0f32,
+                },
+            );
+            bloom = combined;
+        }
+
+        let output = Self::create_texture(facade, input.width(), input.height());
+        self.pass(
+            facade,
+            &self.composite_program,
+            &output,
+            uniform! {
+                image: Self::sample(input),
+                bloom: Self::sample(&bloom),
+                intensity: self.intensity,
+            },
+        );
+
+        output
+    }
+
+    fn pass(
+        &self,
+        facade: &impl Facade,
+        program: &Program,
+        target: &Texture2d,
+        uniforms: impl Uniforms,
+    ) {
+        let mut fb = SimpleFrameBuffer::new(facade, target).unwrap();
+
+        fb.draw(
+            &self.quad.0,
+            &self.quad.1,
+            program,
+            &uniforms,
+            &DrawParameters::default(),
+        )
+        .unwrap();
+    }
+
+    fn create_texture(facade: &impl Facade, width: u32, height: u32) -> Texture2d {
+        Texture2d::empty_with_format(
+            facade,
+            glium::texture::UncompressedFloatFormat::F16F16F16F16,
+            glium::texture::MipmapsOption::NoMipmap,
+            width,
+            height,
+        )
+        .unwrap()
+    }
+
+    fn sample(texture: &Texture2d) -> Sampler<'_, Texture2d> {
+        texture
+            .sampled()
+            .wrap_function(SamplerWrapFunction::Clamp)
+            .minify_filter(MinifySamplerFilter::Linear)
+            .magnify_filter(MagnifySamplerFilter::Linear)
+    }
+}
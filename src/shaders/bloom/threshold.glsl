@@ -0,0 +1,17 @@
+#version 330 core
+out vec4 FragColor;
+in vec2 TexCoords;
+
+uniform sampler2D image;
+uniform float threshold;
+
+void main()
+{
+    vec3 color = texture(image, TexCoords).rgb;
+    float brightness = max(color.r, max(color.g, color.b));
+
+    // Only keep the part of the color that is above the threshold
+    float contribution = max(brightness - threshold, 0.0) / max(brightness, 0.0001);
+
+    FragColor = vec4(color * contribution, 1.0);
+}
@@ -2,13 +2,11 @@ use crate::insert_program;
 use crate::utils::shapes;
 use glium::backend::Facade;
 use glium::framebuffer::SimpleFrameBuffer;
-use glium::index::NoIndices;
 use glium::texture::DepthTexture2d;
 use glium::DrawParameters;
 use glium::Program;
 use glium::Surface;
 use glium::Texture2d;
-use glium::VertexBuffer;
 use std::rc::Rc;
 
 pub struct BRDF {
@@ -22,17 +20,21 @@ impl BRDF {
         Self { program }
     }
     pub fn compute(&self, facade: &impl Facade) -> Texture2d {
+        self.compute_with_resolution(facade, 512)
+    }
+
+    /// Same as [`compute`](Self::compute) with a custom width and height, 512 by default
+    pub fn compute_with_resolution(&self, facade: &impl Facade, resolution: u32) -> Texture2d {
         let brdf = Texture2d::empty_with_format(
             facade,
             glium::texture::UncompressedFloatFormat::F16F16,
             glium::texture::MipmapsOption::NoMipmap,
-            512,
-            512,
+            resolution,
+            resolution,
         )
         .unwrap();
 
-        let vb = VertexBuffer::new(facade, &shapes::get_quad()).unwrap();
-        let ib = NoIndices(glium::index::PrimitiveType::TriangleStrip);
+        let (vb, ib) = shapes::fullscreen_quad(facade);
 
         let mut fb = SimpleFrameBuffer::new(facade, &brdf).unwrap();
 
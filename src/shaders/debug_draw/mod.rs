@@ -0,0 +1,204 @@
+use crate::insert_program;
+use crate::renderer::Renderable;
+use crate::utils::aabb::Aabb;
+use crate::utils::camera::Camera;
+use glium::backend::Context;
+use glium::backend::Facade;
+use glium::index::{NoIndices, PrimitiveType};
+use glium::DrawError;
+use glium::DrawParameters;
+use glium::Program;
+use glium::Rect;
+use glium::VertexBuffer;
+use nalgebra::{Matrix4, Point3, Vector3};
+use std::rc::Rc;
+
+/// A corner of a line drawn by [`DebugDraw`]
+#[derive(Clone, Copy, Debug)]
+pub struct LineVertex {
+    pub position: [f32; 3],
+    pub color: [f32; 3],
+}
+
+implement_vertex!(LineVertex, position, color);
+
+/// Immediate mode lines for debugging, ex. bounding boxes or light positions
+///
+/// Lines are queued in world space every frame and drawn unlit with a single draw call. When
+/// [enabled](crate::renderer::Renderer::enable_debug_draw) on the renderer,
+/// [`RenderScene::finish`] draws the queued lines after everything else and clears them, so they
+/// have to be queued again for the next frame.
+///
+/// [`RenderScene::finish`]: crate::renderer::RenderScene::finish
+pub struct DebugDraw {
+    program: Rc<Program>,
+    lines: Vec<LineVertex>,
+    vertex_buffer: Option<VertexBuffer<LineVertex>>,
+    line_width: f32,
+    depth_test: bool,
+    facade: Rc<Context>,
+}
+
+impl DebugDraw {
+    pub fn new(facade: &impl Facade) -> Self {
+        let program = Rc::new(insert_program!("./vertex.glsl", "./fragment.glsl", facade));
+
+        Self {
+            program,
+            lines: Vec::new(),
+            vertex_buffer: None,
+            line_width: 1.0,
+            depth_test: false,
+            facade: facade.get_context().clone(),
+        }
+    }
+
+    /// Width of the lines in pixels, 1 by default
+    pub fn get_line_width(&self) -> f32 {
+        self.line_width
+    }
+    pub fn set_line_width(&mut self, line_width: f32) {
+        self.line_width = line_width;
+    }
+
+    /// Whether lines are hidden behind the scene, false by default so they are always visible
+    pub fn get_depth_test(&self) -> bool {
+        self.depth_test
+    }
+    pub fn set_depth_test(&mut self, depth_test: bool) {
+        self.depth_test = depth_test;
+    }
+
+    /// Queues a line from `a` to `b`
+    pub fn line(&mut self, a: Vector3<f32>, b: Vector3<f32>, color: [f32; 3]) {
+        self.lines.push(LineVertex {
+            position: a.into(),
+            color,
+        });
+        self.lines.push(LineVertex {
+            position: b.into(),
+            color,
+        });
+    }
+
+    /// Queues the twelve edges of the box between `min` and `max`
+    pub fn aabb(&mut self, min: Vector3<f32>, max: Vector3<f32>, color: [f32; 3]) {
+        self.box_edges(&Aabb::new(min, max).get_corners(), color);
+    }
+
+    /// Queues the twelve edges of the volume the camera sees, ex. to check culling or what the
+    /// shadow map covers from another camera
+    pub fn frustum(&mut self, camera: &Camera, color: [f32; 3]) {
+        self.box_edges(&camera.frustum_corners(), color);
+    }
+
+    fn box_edges(&mut self, corners: &[Vector3<f32>; 8], color: [f32; 3]) {
+        // Corners are indexed by their bits, x is the first bit, y the second and z the third
+        for (a, b) in [
+            (0, 1),
+            (2, 3),
+            (4, 5),
+            (6, 7),
+            (0, 2),
+            (1, 3),
+            (4, 6),
+            (5, 7),
+            (0, 4),
+            (1, 5),
+            (2, 6),
+            (3, 7),
+        ] {
+            self.line(corners[a], corners[b], color);
+        }
+    }
+
+    /// Queues the x, y, and z axes of `transform` in red, green, and blue
+    ///
+    /// The axes are one unit long before the transform, so they show its scale too.
+    pub fn axes(&mut self, transform: &Matrix4<f32>) {
+        let origin = transform.transform_point(&Point3::origin()).coords;
+
+        for (axis, color) in [
+            (Vector3::x(), [1.0, 0.0, 0.0]),
+            (Vector3::y(), [0.0, 1.0, 0.0]),
+            (Vector3::z(), [0.0, 0.0, 1.0]),
+        ] {
+            let end = transform.transform_point(&axis.into()).coords;
+            self.line(origin, end, color);
+        }
+    }
+
+    /// The number of queued lines
+    pub fn get_line_count(&self) -> usize {
+        self.lines.len() / 2
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+
+    /// Removes every queued line
+    pub fn clear(&mut self) {
+        self.lines.clear();
+    }
+
+    /// Draws every queued line, the lines stay queued
+    ///
+    /// The vertex buffer is only reallocated when there are more lines than ever before. The
+    /// `viewport` limits the lines to a part of the surface, see
+    /// [`RenderScene::finish_viewport`](crate::renderer::RenderScene::finish_viewport).
+    pub fn render(
+        &mut self,
+        surface: &mut Renderable,
+        projection: [[f32; 4]; 4],
+        view: [[f32; 4]; 4],
+        viewport: Option<Rect>,
+    ) -> Result<(), DrawError> {
+        if self.lines.is_empty() {
+            return Ok(());
+        }
+
+        let capacity = self.vertex_buffer.as_ref().map_or(0, |vb| vb.len());
+        if capacity < self.lines.len() {
+            self.vertex_buffer = Some(
+                VertexBuffer::empty_dynamic(&self.facade, self.lines.len().next_power_of_two())
+                    .unwrap(),
+            );
+        }
+
+        let vertex_buffer = self
+            .vertex_buffer
+            .as_ref()
+            .unwrap()
+            .slice(0..self.lines.len())
+            .unwrap();
+        vertex_buffer.write(&self.lines);
+
+        let uniforms = uniform! {
+            projection: projection,
+            view: view,
+        };
+
+        let draw_parameters = DrawParameters {
+            depth: glium::Depth {
+                test: match self.depth_test {
+                    true => glium::DepthTest::IfLessOrEqual,
+                    false => glium::DepthTest::Overwrite,
+                },
+                write: false,
+                ..Default::default()
+            },
+            line_width: Some(self.line_width),
+            viewport,
+            ..Default::default()
+        };
+
+        surface.draw(
+            vertex_buffer,
+            NoIndices(PrimitiveType::LinesList),
+            &self.program,
+            &uniforms,
+            &draw_parameters,
+        )
+    }
+}
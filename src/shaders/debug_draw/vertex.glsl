@@ -0,0 +1,15 @@
+#version 330 core
+in vec3 position;
+in vec3 color;
+
+out vec3 Color;
+
+uniform mat4 projection;
+uniform mat4 view;
+
+void main()
+{
+    Color = color;
+
+    gl_Position = projection * view * vec4(position, 1.0);
+}
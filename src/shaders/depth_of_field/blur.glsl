@@ -0,0 +1,26 @@
+#version 330 core
+out vec4 FragColor;
+in vec2 TexCoords;
+
+uniform sampler2D image;
+uniform bool horizontal;
+// Distance in pixels between the taps, spreads the 9 taps over the blur radius
+uniform float spacing;
+
+// 9 tap gaussian, only half of the weights are needed since it is symmetric
+const float weight[5] = float[] (0.227027, 0.1945946, 0.1216216, 0.054054, 0.016216);
+
+void main()
+{
+    vec2 texelSize = 1.0 / vec2(textureSize(image, 0));
+    vec2 direction = (horizontal ? vec2(texelSize.x, 0.0) : vec2(0.0, texelSize.y)) * spacing;
+
+    vec4 result = texture(image, TexCoords) * weight[0];
+    for (int i = 1; i < 5; ++i)
+    {
+        result += texture(image, TexCoords + direction * i) * weight[i];
+        result += texture(image, TexCoords - direction * i) * weight[i];
+    }
+
+    FragColor = result;
+}
@@ -0,0 +1,31 @@
+#version 330 core
+out vec4 FragColor;
+in vec2 TexCoords;
+
+uniform sampler2D image;
+uniform sampler2D blurred;
+uniform sampler2D depth;
+
+uniform float near;
+uniform float far;
+uniform float focusDistance;
+uniform float focalRange;
+
+// Distance from the camera of a value from a perspective depth buffer
+float linearizeDepth(float depth)
+{
+    float z = depth * 2.0 - 1.0;
+    return (2.0 * near * far) / (far + near - z * (far - near));
+}
+
+void main()
+{
+    vec4 color = texture(image, TexCoords);
+    vec4 blurColor = texture(blurred, TexCoords);
+    float distance = linearizeDepth(texture(depth, TexCoords).r);
+
+    // Circle of confusion, 0 inside the focal range and fully blurred one range further out
+    float coc = clamp((abs(distance - focusDistance) - focalRange) / max(focalRange, 0.0001), 0.0, 1.0);
+
+    FragColor = mix(color, blurColor, coc);
+}
@@ -0,0 +1,177 @@
+use crate::insert_program;
+use crate::utils::shapes;
+use crate::vertex::Vertex;
+use glium::backend::Facade;
+use glium::framebuffer::SimpleFrameBuffer;
+use glium::texture::DepthTexture2d;
+use glium::uniforms::{
+    MagnifySamplerFilter, MinifySamplerFilter, Sampler, SamplerWrapFunction, Uniforms,
+};
+use glium::DrawParameters;
+use glium::IndexBuffer;
+use glium::Program;
+use glium::Surface;
+use glium::Texture2d;
+use glium::VertexBuffer;
+use std::rc::Rc;
+
+/// Blurs what is in front of or behind the focus distance, like a camera lens
+///
+/// A blurred copy of the image is blended in by each pixel's circle of confusion, which is
+/// computed from its distance in the depth texture. Everything within
+/// [`focal_range`](Self::set_focal_range) of the [focus distance](Self::set_focus_distance) is
+/// sharp, and the blur fades in over another focal range past that.
+///
+/// The depth has to come from a perspective projection with the same near and far planes as
+/// [`set_clip_planes`](Self::set_clip_planes), ex. from
+/// [`RenderSurface::get_depth_texture`](crate::utils::render_surface::RenderSurface::get_depth_texture).
+pub struct DepthOfField {
+    blur_program: Rc<Program>,
+    composite_program: Rc<Program>,
+    quad: (VertexBuffer<Vertex>, IndexBuffer<u32>),
+    focus_distance: f32,
+    focal_range: f32,
+    max_blur: f32,
+    near: f32,
+    far: f32,
+}
+
+impl DepthOfField {
+    pub fn new(facade: &impl Facade) -> Self {
+        let blur_program = Rc::new(insert_program!("./vertex.glsl", "./blur.glsl", facade));
+        let composite_program =
+            Rc::new(insert_program!("./vertex.glsl", "./composite.glsl", facade));
+
+        Self {
+            blur_program,
+            composite_program,
+            quad: shapes::fullscreen_quad(facade),
+            focus_distance: 10.0,
+            focal_range: 5.0,
+            max_blur: 8.0,
+            near: 0.1,
+            far: 1000.0,
+        }
+    }
+
+    /// Distance from the camera that is in focus, 10 by default
+    pub fn get_focus_distance(&self) -> f32 {
+        self.focus_distance
+    }
+    pub fn set_focus_distance(&mut self, focus_distance: f32) {
+        self.focus_distance = focus_distance;
+    }
+
+    /// How far in front of and behind the focus distance things are still sharp, 5 by default
+    pub fn get_focal_range(&self) -> f32 {
+        self.focal_range
+    }
+    pub fn set_focal_range(&mut self, focal_range: f32) {
+        self.focal_range = focal_range.max(0.0);
+    }
+
+    /// Radius in pixels of the blur for things that are completely out of focus, 8 by default
+    pub fn get_max_blur(&self) -> f32 {
+        self.max_blur
+    }
+    pub fn set_max_blur(&mut self, max_blur: f32) {
+        self.max_blur = max_blur.max(0.0);
+    }
+
+    /// The near and far planes of the camera the depth was rendered with, the
+    /// [`Camera`](crate::utils::camera::Camera) defaults of 0.1 and 1000 by default
+    pub fn get_clip_planes(&self) -> (f32, f32) {
+        (self.near, self.far)
+    }
+    pub fn set_clip_planes(&mut self, near: f32, far: f32) {
+        self.near = near;
+        self.far = far;
+    }
+
+    /// Returns a copy of `color` with depth of field applied, `depth` has to be the same size
+    pub fn apply(
+        &self,
+        facade: &impl Facade,
+        color: &Texture2d,
+        depth: &DepthTexture2d,
+    ) -> Texture2d {
+        let (width, height) = (color.width(), color.height());
+        // The 9 taps of the blur are spread over the radius
+        let spacing = self.max_blur / 4.0;
+
+        let ping = Self::create_texture(facade, width, height);
+        let blurred = Self::create_texture(facade, width, height);
+        self.pass(
+            facade,
+            &self.blur_program,
+            &ping,
+            uniform! { image: Self::sample(color), horizontal: true, spacing: spacing },
+        );
+        self.pass(
+            facade,
+            &self.blur_program,
+            &blurred,
+            uniform! { image: Self::sample(&ping), horizontal: false, spacing: spacing },
+        );
+
+        let output = Self::create_texture(facade, width, height);
+        self.pass(
+            facade,
+            &self.composite_program,
+            &output,
+            uniform! {
+                image: Self::sample(color),
+                blurred: Self::sample(&blurred),
+                depth: depth
+                    .sampled()
+                    .wrap_function(SamplerWrapFunction::Clamp)
+                    .minify_filter(MinifySamplerFilter::Nearest)
+                    .magnify_filter(MagnifySamplerFilter::Nearest),
+                near: self.near,
+                far: self.far,
+                focusDistance: self.focus_distance,
+                focalRange: self.focal_range,
+            },
+        );
+
+        output
+    }
+
+    fn pass(
+        &self,
+        facade: &impl Facade,
+        program: &Program,
+        target: &Texture2d,
+        uniforms: impl Uniforms,
+    ) {
+        let mut fb = SimpleFrameBuffer::new(facade, target).unwrap();
+
+        fb.draw(
+            &self.quad.0,
+            &self.quad.1,
+            program,
+            &uniforms,
+            &DrawParameters::default(),
+        )
+        .unwrap();
+    }
+
+    fn create_texture(facade: &impl Facade, width: u32, height: u32) -> Texture2d {
+        Texture2d::empty_with_format(
+            facade,
+            glium::texture::UncompressedFloatFormat::F16F16F16F16,
+            glium::texture::MipmapsOption::NoMipmap,
+            width,
+            height,
+        )
+        .unwrap()
+    }
+
+    fn sample(texture: &Texture2d) -> Sampler<'_, Texture2d> {
+        texture
+            .sampled()
+            .wrap_function(SamplerWrapFunction::Clamp)
+            .minify_filter(MinifySamplerFilter::Linear)
+            .magnify_filter(MagnifySamplerFilter::Linear)
+    }
+}
@@ -0,0 +1,6 @@
+#version 330 core
+
+// only the depth is needed, which OpenGL writes for us
+void main()
+{
+}
@@ -0,0 +1,77 @@
+use crate::insert_program;
+use crate::renderer::DepthBias;
+use crate::renderer::Renderable;
+use crate::renderer::SceneData;
+use glium::backend::Facade;
+use glium::draw_parameters::BackfaceCullingMode;
+use glium::index::IndicesSource;
+use glium::vertex::VerticesSource;
+use glium::DrawError;
+use glium::DrawParameters;
+use glium::Program;
+use nalgebra::Matrix4;
+use std::rc::Rc;
+
+/// Renders only the depth of geometry, used for the depth pre-pass
+///
+/// See [`Renderer::enable_depth_prepass`](crate::renderer::Renderer::enable_depth_prepass).
+#[derive(Clone)]
+pub struct DepthPrepass {
+    program: Rc<Program>,
+}
+
+impl DepthPrepass {
+    pub fn load_from_fs(facade: &impl Facade) -> Self {
+        let program = Rc::new(insert_program!("./vertex.glsl", "./fragment.glsl", facade));
+
+        Self { program }
+    }
+
+    /// Writes the depth of the geometry to `surface` without touching its colors
+    ///
+    /// `backface_culling` and `depth_bias` should match the material's, see
+    /// [`Shader::get_backface_culling`] and [`Shader::get_depth_bias`]. Otherwise the depth the
+    /// material is tested against isn't the depth it produces.
+    ///
+    /// [`Shader::get_backface_culling`]: crate::shader::Shader::get_backface_culling
+    /// [`Shader::get_depth_bias`]: crate::shader::Shader::get_depth_bias
+    pub fn render<'a>(
+        &self,
+        vertices: VerticesSource<'a>,
+        indices: IndicesSource<'a>,
+        surface: &mut Renderable,
+        model: Matrix4<f32>,
+        backface_culling: BackfaceCullingMode,
+        depth_bias: Option<DepthBias>,
+        scene_data: &SceneData,
+    ) -> Result<(), DrawError> {
+        let view: [[f32; 4]; 4] = scene_data.camera.get_view_matrix().into();
+        let model: [[f32; 4]; 4] = model.into();
+
+        let uniforms = uniform! {
+            projection: scene_data.projection,
+            view: view,
+            model: model,
+        };
+
+        let draw_parameters = scene_data.apply_draw_parameters(DrawParameters {
+            depth: glium::Depth {
+                test: glium::DepthTest::IfLess,
+                write: true,
+                ..Default::default()
+            },
+            color_mask: (false, false, false, false),
+            backface_culling,
+            polygon_offset: DepthBias::to_polygon_offset(depth_bias),
+            ..Default::default()
+        });
+
+        surface.draw(
+            vertices,
+            indices,
+            &self.program,
+            &uniforms,
+            &draw_parameters,
+        )
+    }
+}
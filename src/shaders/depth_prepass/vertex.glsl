@@ -0,0 +1,16 @@
+#version 330 core
+in vec3 position;
+
+uniform mat4 projection;
+uniform mat4 view;
+uniform mat4 model;
+
+// has to match the depth of the main pass exactly, so this is calculated the same way as in pbr
+invariant gl_Position;
+
+void main()
+{
+    vec3 worldPos = vec3(model * vec4(position, 1.0));
+
+    gl_Position = projection * view * vec4(worldPos, 1.0);
+}
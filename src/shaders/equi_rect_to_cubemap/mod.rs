@@ -4,11 +4,15 @@ use glium::Texture2d;
 use glium::{backend::Facade, DrawParameters};
 use glium::{IndexBuffer, VertexBuffer};
 use nalgebra::Matrix4;
+use std::error::Error;
+use std::path::Path;
 use std::rc::Rc;
 
 use glium::{Program, Surface};
 
 use crate::insert_program;
+use crate::utils::cubemap_render::{camera_directions, CUBE_LAYERS};
+use crate::utils::texture_loader::TextureLoader;
 
 pub struct EquiRectCubemap {
     program: Rc<Program>,
@@ -30,16 +34,7 @@ impl EquiRectCubemap {
         )
         .unwrap();
 
-        let layers = [
-            glium::texture::CubeLayer::PositiveX,
-            glium::texture::CubeLayer::NegativeX,
-            glium::texture::CubeLayer::PositiveY,
-            glium::texture::CubeLayer::NegativeY,
-            glium::texture::CubeLayer::PositiveZ,
-            glium::texture::CubeLayer::NegativeZ,
-        ];
-
-        let camera_dir = Self::directions();
+        let camera_dir = camera_directions();
 
         let cube_vertices = VertexBuffer::new(facade, &crate::utils::shapes::get_cube()).unwrap();
         let cube_indices = IndexBuffer::new(
@@ -52,7 +47,7 @@ impl EquiRectCubemap {
         let depth_buffer = DepthTexture2d::empty(facade, resolution, resolution).unwrap();
 
         for i in 0..6 {
-            let image = cubemap.main_level().image(layers[i]);
+            let image = cubemap.main_level().image(CUBE_LAYERS[i]);
             let mut surface =
                 SimpleFrameBuffer::with_depth_buffer(facade, image, &depth_buffer).unwrap();
             surface.clear_color_and_depth((1.0, 1.0, 1.0, 1.0), 1.0);
@@ -91,38 +86,18 @@ impl EquiRectCubemap {
         cubemap
     }
 
-    fn directions() -> [Matrix4<f32>; 6] {
-        [
-            Matrix4::look_at_rh(
-                &[0.0, 0.0, 0.0].into(),
-                &[1.0, 0.0, 0.0].into(),
-                &[0.0, -1.0, 0.0].into(),
-            ),
-            Matrix4::look_at_rh(
-                &[0.0, 0.0, 0.0].into(),
-                &[-1.0, 0.0, 0.0].into(),
-                &[0.0, -1.0, 0.0].into(),
-            ),
-            Matrix4::look_at_rh(
-                &[0.0, 0.0, 0.0].into(),
-                &[0.0, 1.0, 0.0].into(),
-                &[0.0, 0.0, 1.0].into(),
-            ),
-            Matrix4::look_at_rh(
-                &[0.0, 0.0, 0.0].into(),
-                &[0.0, -1.0, 0.0].into(),
-                &[0.0, 0.0, -1.0].into(),
-            ),
-            Matrix4::look_at_rh(
-                &[0.0, 0.0, 0.0].into(),
-                &[0.0, 0.0, 1.0].into(),
-                &[0.0, -1.0, 0.0].into(),
-            ),
-            Matrix4::look_at_rh(
-                &[0.0, 0.0, 0.0].into(),
-                &[0.0, 0.0, -1.0].into(),
-                &[0.0, -1.0, 0.0].into(),
-            ),
-        ]
+    /// Loads an equirectangular HDR image and renders it to a cubemap
+    ///
+    /// Nothing is written to the filesystem, the image is flipped the same way as
+    /// [`TextureLoader::from_fs_hdr`].
+    pub fn compute_from_fs_hdr(
+        &self,
+        facade: &impl Facade,
+        path: impl AsRef<Path>,
+        resolution: u32,
+    ) -> Result<Cubemap, Box<dyn Error>> {
+        let texture = TextureLoader::from_fs_hdr(facade, path)?;
+
+        Ok(self.compute(facade, &texture, resolution))
     }
 }
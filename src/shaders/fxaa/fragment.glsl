@@ -0,0 +1,153 @@
+#version 330 core
+out vec4 FragColor;
+in vec2 TexCoords;
+
+// Sampled as linear colors, srgb textures are decoded by OpenGL before filtering
+uniform sampler2D image;
+uniform int searchSteps;
+uniform float edgeThreshold;
+uniform float edgeThresholdMin;
+uniform float subpixelQuality;
+
+// Edges are detected on perceived brightness, the square root approximates gamma correction
+float luma(vec3 color)
+{
+    return sqrt(dot(color, vec3(0.299, 0.587, 0.114)));
+}
+
+float lumaAt(vec2 uv)
+{
+    return luma(texture(image, uv).rgb);
+}
+
+// Based on FXAA 3.11 by Timothy Lottes
+void main()
+{
+    vec2 texel = 1.0 / vec2(textureSize(image, 0));
+    vec4 center = texture(image, TexCoords);
+
+    float lumaCenter = luma(center.rgb);
+    float lumaDown = luma(textureOffset(image, TexCoords, ivec2(0, -1)).rgb);
+    float lumaUp = luma(textureOffset(image, TexCoords, ivec2(0, 1)).rgb);
+    float lumaLeft = luma(textureOffset(image, TexCoords, ivec2(-1, 0)).rgb);
+    float lumaRight = luma(textureOffset(image, TexCoords, ivec2(1, 0)).rgb);
+
+    float lumaMin = min(lumaCenter, min(min(lumaDown, lumaUp), min(lumaLeft, lumaRight)));
+    float lumaMax = max(lumaCenter, max(max(lumaDown, lumaUp), max(lumaLeft, lumaRight)));
+    float lumaRange = lumaMax - lumaMin;
+
+    // Not an edge, or too dark to notice
+    if (lumaRange < max(edgeThresholdMin, lumaMax * edgeThreshold))
+    {
+        FragColor = center;
+        return;
+    }
+
+    float lumaDownLeft = luma(textureOffset(image, TexCoords, ivec2(-1, -1)).rgb);
+    float lumaUpRight = luma(textureOffset(image, TexCoords, ivec2(1, 1)).rgb);
+    float lumaUpLeft = luma(textureOffset(image, TexCoords, ivec2(-1, 1)).rgb);
+    float lumaDownRight = luma(textureOffset(image, TexCoords, ivec2(1, -1)).rgb);
+
+    float lumaDownUp = lumaDown + lumaUp;
+    float lumaLeftRight = lumaLeft + lumaRight;
+    float lumaLeftCorners = lumaDownLeft + lumaUpLeft;
+    float lumaDownCorners = lumaDownLeft + lumaDownRight;
+    float lumaRightCorners = lumaDownRight + lumaUpRight;
+    float lumaUpCorners = lumaUpRight + lumaUpLeft;
+
+    float edgeHorizontal = abs(-2.0 * lumaLeft + lumaLeftCorners)
+        + abs(-2.0 * lumaCenter + lumaDownUp) * 2.0
+        + abs(-2.0 * lumaRight + lumaRightCorners);
+    float edgeVertical = abs(-2.0 * lumaUp + lumaUpCorners)
+        + abs(-2.0 * lumaCenter + lumaLeftRight) * 2.0
+        + abs(-2.0 * lumaDown + lumaDownCorners);
+    bool isHorizontal = edgeHorizontal >= edgeVertical;
+
+    // Find which side of the pixel the edge is on
+    float luma1 = isHorizontal ? lumaDown : lumaLeft;
+    float luma2 = isHorizontal ? lumaUp : lumaRight;
+    float gradient1 = luma1 - lumaCenter;
+    float gradient2 = luma2 - lumaCenter;
+    bool is1Steepest = abs(gradient1) >= abs(gradient2);
+    float gradientScaled = 0.25 * max(abs(gradient1), abs(gradient2));
+
+    float stepLength = isHorizontal ? texel.y : texel.x;
+    float lumaLocalAverage;
+    if (is1Steepest)
+    {
+        stepLength = -stepLength;
+        lumaLocalAverage = 0.5 * (luma1 + lumaCenter);
+    }
+    else
+    {
+        lumaLocalAverage = 0.5 * (luma2 + lumaCenter);
+    }
+
+    // Walk along the edge in both directions until its end is found
+    vec2 currentUv = TexCoords;
+    if (isHorizontal)
+        currentUv.y += stepLength * 0.5;
+    else
+        currentUv.x += stepLength * 0.5;
+
+    vec2 offset = isHorizontal ? vec2(texel.x, 0.0) : vec2(0.0, texel.y);
+    vec2 uv1 = currentUv - offset;
+    vec2 uv2 = currentUv + offset;
+
+    float lumaEnd1 = lumaAt(uv1) - lumaLocalAverage;
+    float lumaEnd2 = lumaAt(uv2) - lumaLocalAverage;
+    bool reached1 = abs(lumaEnd1) >= gradientScaled;
+    bool reached2 = abs(lumaEnd2) >= gradientScaled;
+
+    if (!reached1)
+        uv1 -= offset;
+    if (!reached2)
+        uv2 += offset;
+
+    for (int i = 1; i < searchSteps && !(reached1 && reached2); ++i)
+    {
+        // Take bigger steps the further away the end is
+        float stepScale = i < 4 ? 1.0 : (i < 8 ? 2.0 : 4.0);
+
+        if (!reached1)
+            lumaEnd1 = lumaAt(uv1) - lumaLocalAverage;
+        if (!reached2)
+            lumaEnd2 = lumaAt(uv2) - lumaLocalAverage;
+
+        reached1 = abs(lumaEnd1) >= gradientScaled;
+        reached2 = abs(lumaEnd2) >= gradientScaled;
+
+        if (!reached1)
+            uv1 -= offset * stepScale;
+        if (!reached2)
+            uv2 += offset * stepScale;
+    }
+
+    float distance1 = isHorizontal ? (TexCoords.x - uv1.x) : (TexCoords.y - uv1.y);
+    float distance2 = isHorizontal ? (uv2.x - TexCoords.x) : (uv2.y - TexCoords.y);
+    bool isDirection1 = distance1 < distance2;
+    float distanceFinal = min(distance1, distance2);
+    float edgeThickness = distance1 + distance2;
+    float pixelOffset = -distanceFinal / edgeThickness + 0.5;
+
+    // Only offset if the end of the edge agrees with the center
+    bool isLumaCenterSmaller = lumaCenter < lumaLocalAverage;
+    bool correctVariation = ((isDirection1 ? lumaEnd1 : lumaEnd2) < 0.0) != isLumaCenterSmaller;
+    float finalOffset = correctVariation ? pixelOffset : 0.0;
+
+    // Subpixel aliasing, for details thinner than a pixel
+    float lumaAverage = (1.0 / 12.0) * (2.0 * (lumaDownUp + lumaLeftRight) + lumaLeftCorners + lumaRightCorners);
+    float subPixelOffset1 = clamp(abs(lumaAverage - lumaCenter) / lumaRange, 0.0, 1.0);
+    float subPixelOffset2 = (-2.0 * subPixelOffset1 + 3.0) * subPixelOffset1 * subPixelOffset1;
+    float subPixelOffsetFinal = subPixelOffset2 * subPixelOffset2 * subpixelQuality;
+
+    finalOffset = max(finalOffset, subPixelOffsetFinal);
+
+    vec2 finalUv = TexCoords;
+    if (isHorizontal)
+        finalUv.y += finalOffset * stepLength;
+    else
+        finalUv.x += finalOffset * stepLength;
+
+    FragColor = vec4(texture(image, finalUv).rgb, center.a);
+}
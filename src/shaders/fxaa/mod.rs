@@ -0,0 +1,130 @@
+use crate::insert_program;
+use crate::utils::shapes;
+use crate::vertex::Vertex;
+use glium::backend::Facade;
+use glium::framebuffer::SimpleFrameBuffer;
+use glium::texture::SrgbTexture2d;
+use glium::uniforms::{
+    AsUniformValue, MagnifySamplerFilter, MinifySamplerFilter, SamplerWrapFunction,
+};
+use glium::DrawParameters;
+use glium::IndexBuffer;
+use glium::Program;
+use glium::Surface;
+use glium::Texture2d;
+use glium::VertexBuffer;
+use std::rc::Rc;
+
+/// Trades speed for how well edges are smoothed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FxaaQuality {
+    Low,
+    Medium,
+    High,
+}
+
+impl FxaaQuality {
+    /// `(search steps, edge threshold, minimum edge threshold, subpixel quality)`
+    fn settings(&self) -> (i32, f32, f32, f32) {
+        match self {
+            Self::Low => (4, 0.25, 0.0833, 0.5),
+            Self::Medium => (8, 0.166, 0.0625, 0.75),
+            Self::High => (12, 0.125, 0.0312, 1.0),
+        }
+    }
+}
+
+/// Fast approximate anti-aliasing
+///
+/// Smooths jagged edges after rendering by blurring along edges found in the image, useful when
+/// rendering into textures that don't have multisampling.
+pub struct Fxaa {
+    program: Rc<Program>,
+    quad: (VertexBuffer<Vertex>, IndexBuffer<u32>),
+    quality: FxaaQuality,
+}
+
+impl Fxaa {
+    pub fn new(facade: &impl Facade) -> Self {
+        let program = Rc::new(insert_program!("./vertex.glsl", "./fragment.glsl", facade));
+
+        Self {
+            program,
+            quad: shapes::fullscreen_quad(facade),
+            quality: FxaaQuality::Medium,
+        }
+    }
+
+    pub fn get_quality(&self) -> FxaaQuality {
+        self.quality
+    }
+    pub fn set_quality(&mut self, quality: FxaaQuality) {
+        self.quality = quality;
+    }
+
+    /// Returns an anti-aliased copy of `input`
+    pub fn apply(&self, facade: &impl Facade, input: &Texture2d) -> Texture2d {
+        let output = Texture2d::empty_with_format(
+            facade,
+            glium::texture::UncompressedFloatFormat::F16F16F16F16,
+            glium::texture::MipmapsOption::NoMipmap,
+            input.width(),
+            input.height(),
+        )
+        .unwrap();
+
+        let mut fb = SimpleFrameBuffer::new(facade, &output).unwrap();
+        self.draw(
+            &mut fb,
+            input
+                .sampled()
+                .wrap_function(SamplerWrapFunction::Clamp)
+                .minify_filter(MinifySamplerFilter::Linear)
+                .magnify_filter(MagnifySamplerFilter::Linear),
+        );
+
+        output
+    }
+
+    /// Returns an anti-aliased copy of `input`, for chaining after rendering to an srgb texture
+    ///
+    /// OpenGL converts the colors to linear space when sampling and back to srgb when writing,
+    /// so the image is filtered in linear space.
+    pub fn apply_srgb(&self, facade: &impl Facade, input: &SrgbTexture2d) -> SrgbTexture2d {
+        let output = SrgbTexture2d::empty(facade, input.width(), input.height()).unwrap();
+
+        let mut fb = SimpleFrameBuffer::new(facade, &output).unwrap();
+        self.draw(
+            &mut fb,
+            input
+                .sampled()
+                .wrap_function(SamplerWrapFunction::Clamp)
+                .minify_filter(MinifySamplerFilter::Linear)
+                .magnify_filter(MagnifySamplerFilter::Linear),
+        );
+
+        output
+    }
+
+    fn draw(&self, fb: &mut SimpleFrameBuffer, image: impl AsUniformValue) {
+        let (search_steps, edge_threshold, edge_threshold_min, subpixel_quality) =
+            self.quality.settings();
+
+        let uniforms = uniform! {
+            image: image,
+            searchSteps: search_steps,
+            edgeThreshold: edge_threshold,
+            edgeThresholdMin: edge_threshold_min,
+            subpixelQuality: subpixel_quality,
+        };
+
+        fb.draw(
+            &self.quad.0,
+            &self.quad.1,
+            &self.program,
+            &uniforms,
+            &DrawParameters::default(),
+        )
+        .unwrap();
+    }
+}
@@ -8,6 +8,7 @@ use nalgebra::Matrix4;
 use std::rc::Rc;
 
 use crate::insert_program;
+use crate::utils::cubemap_render::{camera_directions, CUBE_LAYERS};
 use glium::{backend::Facade, texture::Cubemap, Program};
 
 pub struct IrradianceConvolution {
@@ -22,8 +23,18 @@ impl IrradianceConvolution {
     }
 
     pub fn calculate(&self, facade: &impl Facade, environment_map: &Cubemap) -> Cubemap {
-        let resolution = 32;
+        self.calculate_with_resolution(facade, environment_map, 32)
+    }
 
+    /// Same as [`calculate`](Self::calculate) with a custom size for each face, 32 by default
+    ///
+    /// Irradiance has very little detail, so a higher resolution rarely makes a difference.
+    pub fn calculate_with_resolution(
+        &self,
+        facade: &impl Facade,
+        environment_map: &Cubemap,
+        resolution: u32,
+    ) -> Cubemap {
         let cubemap = Cubemap::empty_with_format(
             facade,
             glium::texture::UncompressedFloatFormat::F16F16F16,
@@ -32,16 +43,7 @@ impl IrradianceConvolution {
         )
         .unwrap();
 
-        let layers = [
-            glium::texture::CubeLayer::PositiveX,
-            glium::texture::CubeLayer::NegativeX,
-            glium::texture::CubeLayer::PositiveY,
-            glium::texture::CubeLayer::NegativeY,
-            glium::texture::CubeLayer::PositiveZ,
-            glium::texture::CubeLayer::NegativeZ,
-        ];
-
-        let camera_dirs = Self::camera_directions();
+        let camera_dirs = camera_directions();
 
         let cube_vertices = VertexBuffer::new(facade, &crate::utils::shapes::get_cube()).unwrap();
         let cube_indices = IndexBuffer::new(
@@ -54,7 +56,7 @@ impl IrradianceConvolution {
         let depth_buffer = DepthTexture2d::empty(facade, resolution, resolution).unwrap();
 
         for i in 0..6 {
-            let image = cubemap.main_level().image(layers[i]);
+            let image = cubemap.main_level().image(CUBE_LAYERS[i]);
             let mut surface =
                 SimpleFrameBuffer::with_depth_buffer(facade, image, &depth_buffer).unwrap();
             surface.clear_color_and_depth((1.0, 1.0, 1.0, 1.0), 1.0);
@@ -89,38 +91,4 @@ impl IrradianceConvolution {
 
         cubemap
     }
-    fn camera_directions() -> [Matrix4<f32>; 6] {
-        [
-            Matrix4::look_at_rh(
-                &[0.0, 0.0, 0.0].into(),
-                &[1.0, 0.0, 0.0].into(),
-                &[0.0, -1.0, 0.0].into(),
-            ),
-            Matrix4::look_at_rh(
-                &[0.0, 0.0, 0.0].into(),
-                &[-1.0, 0.0, 0.0].into(),
-                &[0.0, -1.0, 0.0].into(),
-            ),
-            Matrix4::look_at_rh(
-                &[0.0, 0.0, 0.0].into(),
-                &[0.0, 1.0, 0.0].into(),
-                &[0.0, 0.0, 1.0].into(),
-            ),
-            Matrix4::look_at_rh(
-                &[0.0, 0.0, 0.0].into(),
-                &[0.0, -1.0, 0.0].into(),
-                &[0.0, 0.0, -1.0].into(),
-            ),
-            Matrix4::look_at_rh(
-                &[0.0, 0.0, 0.0].into(),
-                &[0.0, 0.0, 1.0].into(),
-                &[0.0, -1.0, 0.0].into(),
-            ),
-            Matrix4::look_at_rh(
-                &[0.0, 0.0, 0.0].into(),
-                &[0.0, 0.0, -1.0].into(),
-                &[0.0, -1.0, 0.0].into(),
-            ),
-        ]
-    }
 }
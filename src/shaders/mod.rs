@@ -1,6 +1,17 @@
+pub mod bloom;
 pub mod brdf;
+pub mod debug_draw;
+pub mod depth_of_field;
+pub mod depth_prepass;
 pub mod equi_rect_to_cubemap;
+pub mod fxaa;
 pub mod irradiance_convolution;
+pub mod normal_debug;
+pub mod outline;
 pub mod pbr;
+pub mod phong;
 pub mod prefilter;
+pub mod shadow_depth;
 pub mod skybox;
+pub mod ssao;
+pub mod unlit_textured;
@@ -0,0 +1,45 @@
+#version 330 core
+out vec4 FragColor;
+
+in vec2 TexCoords;
+in vec3 Normal;
+in vec4 Tangent;
+
+// mode has to match normal_debug::NormalDebugMode
+uniform int mode;
+uniform float near;
+uniform float far;
+uniform float depthRange;
+
+// Maps -1..1 to 0..1 so negative directions are still visible
+vec3 encodeDirection(vec3 direction)
+{
+    return normalize(direction) * 0.5 + 0.5;
+}
+
+void main()
+{
+    vec3 color;
+
+    if (mode == 0)
+    {
+        color = encodeDirection(Normal);
+    }
+    else if (mode == 1)
+    {
+        color = vec3(fract(TexCoords), 0.0);
+    }
+    else if (mode == 2)
+    {
+        // A zero tangent means the mesh has none
+        color = length(Tangent.xyz) > 0.0 ? encodeDirection(Tangent.xyz) : vec3(0.0);
+    }
+    else
+    {
+        float ndc = gl_FragCoord.z * 2.0 - 1.0;
+        float linearDepth = (2.0 * near * far) / (far + near - ndc * (far - near));
+        color = vec3(clamp(linearDepth / depthRange, 0.0, 1.0));
+    }
+
+    FragColor = vec4(color, 1.0);
+}
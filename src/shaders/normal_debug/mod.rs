@@ -0,0 +1,150 @@
+use crate::insert_program;
+use crate::shader::Shader;
+use egui::Ui;
+use glium::backend::Facade;
+use glium::DrawParameters;
+use glium::Program;
+use nalgebra::Matrix4;
+use std::any::Any;
+use std::rc::Rc;
+
+/// What [`NormalDebug`] outputs as the color
+///
+/// The values match the `mode` uniform in the shader.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NormalDebugMode {
+    /// World space normals, mapped from `-1..1` to `0..1`
+    Normals = 0,
+    /// Texture coordinates as red and green, wrapped to `0..1`
+    TexCoords = 1,
+    /// World space tangents like the normals, black when the mesh has no tangents
+    Tangents = 2,
+    /// Linear distance from the camera, white at the depth range
+    Depth = 3,
+}
+
+/// Unlit material for checking the geometry of a model
+///
+/// Useful when a loaded model looks wrong and it isn't clear whether the normals, texture
+/// coordinates, or tangents are the problem. Uses the regular [`Vertex`](crate::vertex::Vertex)
+/// layout, so any model can be drawn with it (see [`Model::with_shader`]).
+///
+/// [`Model::with_shader`]: crate::utils::model::Model::with_shader
+#[derive(Clone)]
+pub struct NormalDebug {
+    program: Rc<Program>,
+    mode: NormalDebugMode,
+    depth_range: f32,
+    model: Matrix4<f32>,
+}
+
+impl NormalDebug {
+    pub fn load_from_fs(facade: &impl Facade) -> Self {
+        let program = Rc::new(insert_program!("./vertex.glsl", "./fragment.glsl", facade));
+
+        Self {
+            program,
+            mode: NormalDebugMode::Normals,
+            depth_range: 10.0,
+            model: Matrix4::new_translation(&[0.0; 3].into()),
+        }
+    }
+
+    pub fn get_mode(&self) -> NormalDebugMode {
+        self.mode
+    }
+    pub fn set_mode(&mut self, mode: NormalDebugMode) {
+        self.mode = mode;
+    }
+
+    /// The distance that is shown as white in [`NormalDebugMode::Depth`], 10 by default
+    pub fn get_depth_range(&self) -> f32 {
+        self.depth_range
+    }
+    pub fn set_depth_range(&mut self, depth_range: f32) {
+        self.depth_range = depth_range;
+    }
+
+    pub fn debug_ui(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.selectable_value(&mut self.mode, NormalDebugMode::Normals, "normals");
+            ui.selectable_value(&mut self.mode, NormalDebugMode::TexCoords, "uv");
+            ui.selectable_value(&mut self.mode, NormalDebugMode::Tangents, "tangents");
+            ui.selectable_value(&mut self.mode, NormalDebugMode::Depth, "depth");
+        });
+
+        if self.mode == NormalDebugMode::Depth {
+            ui.add(egui::widgets::Slider::new(&mut self.depth_range, 0.1..=100.0).text("range"));
+        }
+    }
+}
+
+impl Shader for NormalDebug {
+    fn render<'a>(
+        &self,
+        vertex_buffer: glium::vertex::VerticesSource<'a>,
+        index_buffer: glium::index::IndicesSource<'a>,
+        surface: &mut crate::renderer::Renderable,
+        camera: [[f32; 4]; 4],
+        position: [[f32; 4]; 4],
+        scene_data: &crate::renderer::SceneData,
+    ) -> Result<(), glium::DrawError> {
+        let model_matrix: [[f32; 4]; 4] = self.model.into();
+
+        let uniforms = uniform! {
+            projection: camera,
+            view: position,
+            model: model_matrix,
+            mode: self.mode as i32,
+            near: scene_data.camera.get_near(),
+            far: scene_data.camera.get_far(),
+            depthRange: self.depth_range,
+        };
+
+        let draw_parameters = scene_data.apply_draw_parameters(DrawParameters {
+            depth: glium::Depth {
+                test: glium::DepthTest::IfLess,
+                write: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+
+        surface.draw(
+            vertex_buffer,
+            index_buffer,
+            &self.program,
+            &uniforms,
+            &draw_parameters,
+        )
+    }
+
+    fn get_model_mat(&self) -> Matrix4<f32> {
+        self.model
+    }
+
+    fn set_model_mat(&mut self, model: Matrix4<f32>) {
+        self.model = model;
+    }
+
+    fn equal_shader(&self, _shader: &dyn std::any::Any) -> bool {
+        false
+    }
+
+    fn to_any(self) -> Box<dyn Any> {
+        Box::new(self)
+    }
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn clone_shader(&self) -> Box<dyn Shader> {
+        Box::new(self.clone())
+    }
+    fn clone_sized(&self) -> Self {
+        self.clone()
+    }
+}
@@ -0,0 +1,22 @@
+#version 330 core
+in vec3 position;
+in vec3 normal;
+in vec2 tex_coords;
+in vec4 tangent;
+
+out vec2 TexCoords;
+out vec3 Normal;
+out vec4 Tangent;
+
+uniform mat4 projection;
+uniform mat4 view;
+uniform mat4 model;
+
+void main()
+{
+    TexCoords = tex_coords;
+    Normal = mat3(model) * normal;
+    Tangent = vec4(mat3(model) * tangent.xyz, tangent.w);
+
+    gl_Position = projection * view * model * vec4(position, 1.0);
+}
@@ -0,0 +1,172 @@
+use crate::insert_program;
+use crate::renderer::Renderable;
+use crate::renderer::SceneData;
+use crate::shader::Shader;
+use glium::backend::Facade;
+use glium::draw_parameters::Stencil;
+use glium::draw_parameters::StencilOperation;
+use glium::draw_parameters::StencilTest;
+use glium::index::IndicesSource;
+use glium::vertex::VerticesSource;
+use glium::DrawError;
+use glium::DrawParameters;
+use glium::Program;
+use nalgebra::Matrix4;
+use std::any::Any;
+use std::rc::Rc;
+
+/// Solid colored outline around a model, ex. to highlight the selected one
+///
+/// Drawn in two passes: the model's silhouette is written to the stencil buffer, then the model
+/// is drawn again pushed out along its normals, only where the stencil test fails. What is left
+/// is a band of [`width`](Self::set_width) around the silhouette. Usually published on top of
+/// the regular model with [`Model::render_outline`].
+///
+/// The outline is drawn after every opaque entry and ignores depth, so it stays visible behind
+/// other objects. The surface needs a stencil buffer, without one the whole model is filled with
+/// the color. Hard edges split the outline where their vertices have different normals.
+///
+/// [`Model::render_outline`]: crate::utils::model::Model::render_outline
+#[derive(Clone)]
+pub struct Outline {
+    program: Rc<Program>,
+    color: [f32; 3],
+    width: f32,
+    model: Matrix4<f32>,
+}
+
+impl Outline {
+    pub fn load_from_fs(facade: &impl Facade) -> Self {
+        let program = Rc::new(insert_program!("./vertex.glsl", "./fragment.glsl", facade));
+
+        Self {
+            program,
+            color: [1.0, 0.6, 0.0],
+            width: 0.02,
+            model: Matrix4::new_translation(&[0.0; 3].into()),
+        }
+    }
+
+    pub fn get_color(&self) -> [f32; 3] {
+        self.color
+    }
+    pub fn set_color(&mut self, color: [f32; 3]) {
+        self.color = color;
+    }
+
+    /// How far the outline reaches past the model in world units, 0.02 by default
+    pub fn get_width(&self) -> f32 {
+        self.width
+    }
+    pub fn set_width(&mut self, width: f32) {
+        self.width = width;
+    }
+}
+
+impl Shader for Outline {
+    fn render<'a>(
+        &self,
+        vertex_buffer: VerticesSource<'a>,
+        index_buffer: IndicesSource<'a>,
+        surface: &mut Renderable,
+        camera: [[f32; 4]; 4],
+        position: [[f32; 4]; 4],
+        scene_data: &SceneData,
+    ) -> Result<(), DrawError> {
+        let model_matrix: [[f32; 4]; 4] = self.model.into();
+
+        // Leftovers from another outline would cut holes into this one
+        surface.clear_stencil(0);
+
+        let mask_uniforms = uniform! {
+            projection: camera,
+            view: position,
+            model: model_matrix,
+            width: 0.0f32,
+            color: self.color,
+        };
+
+        let mask_parameters = DrawParameters {
+            color_mask: (false, false, false, false),
+            stencil: Stencil {
+                reference_value_clockwise: 1,
+                depth_pass_operation_clockwise: StencilOperation::Replace,
+                reference_value_counter_clockwise: 1,
+                depth_pass_operation_counter_clockwise: StencilOperation::Replace,
+                ..Default::default()
+            },
+            viewport: scene_data.get_viewport(),
+            ..Default::default()
+        };
+
+        surface.draw(
+            vertex_buffer.clone(),
+            index_buffer.clone(),
+            &self.program,
+            &mask_uniforms,
+            &mask_parameters,
+        )?;
+
+        let outline_uniforms = uniform! {
+            projection: camera,
+            view: position,
+            model: model_matrix,
+            width: self.width,
+            color: self.color,
+        };
+
+        let outline_parameters = DrawParameters {
+            stencil: Stencil {
+                test_clockwise: StencilTest::IfNotEqual { mask: 0xFF },
+                reference_value_clockwise: 1,
+                test_counter_clockwise: StencilTest::IfNotEqual { mask: 0xFF },
+                reference_value_counter_clockwise: 1,
+                ..Default::default()
+            },
+            viewport: scene_data.get_viewport(),
+            ..Default::default()
+        };
+
+        surface.draw(
+            vertex_buffer,
+            index_buffer,
+            &self.program,
+            &outline_uniforms,
+            &outline_parameters,
+        )
+    }
+
+    fn get_model_mat(&self) -> Matrix4<f32> {
+        self.model
+    }
+
+    fn set_model_mat(&mut self, model: Matrix4<f32>) {
+        self.model = model;
+    }
+
+    fn equal_shader(&self, _shader: &dyn Any) -> bool {
+        false
+    }
+
+    fn to_any(self) -> Box<dyn Any> {
+        Box::new(self)
+    }
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn clone_shader(&self) -> Box<dyn Shader> {
+        Box::new(self.clone())
+    }
+    fn clone_sized(&self) -> Self {
+        self.clone()
+    }
+
+    /// Drawn with the transparent entries so it ends up on top of the opaque ones
+    fn is_transparent(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,17 @@
+#version 330 core
+in vec3 position;
+in vec3 normal;
+
+uniform mat4 projection;
+uniform mat4 view;
+uniform mat4 model;
+// World space distance the surface is pushed out along its normals, 0 for the stencil mask
+uniform float width;
+
+void main()
+{
+    vec4 worldPosition = model * vec4(position, 1.0);
+    worldPosition.xyz += normalize(mat3(model) * normal) * width;
+
+    gl_Position = projection * view * worldPosition;
+}
@@ -1,8 +1,18 @@
 #version 330 core
 out vec4 FragColor;
-in vec2 TexCoords;
-in vec3 WorldPos;
-in vec3 Normal;
+in VertexData {
+    vec2 TexCoords;
+    vec2 TexCoords2;
+    vec3 WorldPos;
+    vec3 Normal;
+    vec4 Tangent;
+    // vertex color multiplied with the instance albedo
+    vec4 AlbedoTint;
+    // baked ambient occlusion, see utils::vertex_ao
+    float VertexAo;
+};
+// 1 at one corner of the triangle and 0 on the opposite edge, only set by geometry.glsl
+noperspective in vec3 Barycentric;
 
 // material parameters
 uniform sampler2D albedo_map;
@@ -10,41 +20,184 @@ uniform sampler2D normal_map;
 uniform sampler2D metallic_map;
 uniform sampler2D roughness_map;
 uniform sampler2D ao_map;
+uniform sampler2D emissive_map;
+// packed occlusion (r), roughness (g), metallic (b) like glTF, replaces the separate maps
+uniform sampler2D orm_map;
+uniform bool useOrmMap;
+uniform bool ormOcclusion;
+// sample the ao map with the second set of texture coordinates
+uniform bool aoUv2;
+// back faces are drawn too, their normals are flipped so they are lit like front faces
+uniform bool doubleSided;
+// parallax occlusion mapping, white is the top of the surface
+uniform sampler2D height_map;
+uniform bool useHeightMap;
+uniform float heightScale;
+uniform float alpha;
+// 0 opaque, 1 mask, 2 blend, see shaders::pbr::AlphaMode
+uniform int alphaMode;
+uniform float alphaCutoff;
+// plain values that replace the maps, see shaders::pbr::PBRScalarOverride
+uniform bool useAlbedoValue;
+uniform vec3 albedoValue;
+uniform bool useMetallicValue;
+uniform float metallicValue;
+uniform bool useRoughnessValue;
+uniform float roughnessValue;
+uniform bool useAoValue;
+uniform float aoValue;
+uniform bool useEmissiveValue;
+uniform vec3 emissiveValue;
 
 // IBL
 uniform samplerCube irradiance_map;
 uniform samplerCube prefilter_map;
+// turns world space directions into directions in the environment maps
+uniform mat3 environmentRotation;
 uniform sampler2D brdfLUT;
+// highest mip level of the prefilter map
+uniform float maxReflectionLod;
+// added to the mip level picked from the roughness, negative values sharpen reflections
+uniform float prefilterLodBias;
+// scales the diffuse and specular lighting from the environment
+uniform float iblIntensity;
 
-// lights
-uniform vec3 lightPositions;
-uniform vec3 lightColors;
+// lights, MAX_LIGHTS has to match utils::lights::MAX_LIGHTS
+#define MAX_LIGHTS 16
+uniform vec3 lightPositions[MAX_LIGHTS];
+uniform vec3 lightColors[MAX_LIGHTS];
+// constant, linear, and quadratic attenuation coefficients
+uniform vec3 lightAttenuations[MAX_LIGHTS];
+// 0 when the light has no range
+uniform float lightRanges[MAX_LIGHTS];
+uniform int numLights;
+
+// directional light, dirLightColor is black when there isn't one
+uniform vec3 dirLightDirection;
+uniform vec3 dirLightColor;
+
+// shadow map of the directional light, only bound when useShadows is true
+uniform sampler2D shadowMap;
+uniform mat4 lightSpaceMatrix;
+uniform bool useShadows;
+
+// screen space ambient occlusion, the size of the surface, only bound when useSsao is true
+uniform sampler2D ssaoMap;
+uniform bool useSsao;
+// bottom left corner of the viewport, the occlusion only covers the viewport
+uniform vec2 viewportOrigin;
 
 uniform vec3 camPos;
 
+// exponential distance fog, fogDensity is 0 when there is no fog
+uniform vec3 fogColor;
+uniform float fogDensity;
+
+// edges of the triangles drawn over the shaded surface, see renderer::WireframeOverlay
+uniform bool wireframeOverlay;
+uniform vec3 wireframeColor;
+// in pixels
+uniform float wireframeThickness;
+
+// tone mapping, toneMapping has to match renderer::ToneMapping
+uniform float exposure;
+uniform int toneMapping;
+
+// Narkowicz's fit of the ACES filmic curve
+vec3 ACESFilm(vec3 x)
+{
+    const float a = 2.51;
+    const float b = 0.03;
+    const float c = 2.43;
+    const float d = 0.59;
+    const float e = 0.14;
+    return clamp((x * (a * x + b)) / (x * (c * x + d) + e), 0.0, 1.0);
+}
+
+vec3 toneMap(vec3 color)
+{
+    color *= exposure;
+
+    if (toneMapping == 1)
+        return color / (color + vec3(1.0));
+    if (toneMapping == 2)
+        return ACESFilm(color);
+
+    return color;
+}
+
 const float PI = 3.14159265359;
 // ----------------------------------------------------------------------------
-// Easy trick to get tangent-normals to world-space to keep PBR code simplified.
-// Don't worry if you don't get what's going on; you generally want to do normal 
-// mapping the usual way for performance anyways; I do plan make a note of this 
-// technique somewhere later in the normal mapping tutorial.
-vec3 getNormalFromMap()
+// Uses the tangent vertex attribute to build the TBN matrix. Meshes without tangents (a zero
+// tangent) fall back to calculating the tangent from the screen-space derivatives.
+mat3 getTBN()
 {
-    vec3 tangentNormal = texture(normal_map, TexCoords).xyz * 2.0 - 1.0;
+    vec3 N = normalize(Normal);
+    vec3 T;
+    vec3 B;
 
-    vec3 Q1  = dFdx(WorldPos);
-    vec3 Q2  = dFdy(WorldPos);
-    vec2 st1 = dFdx(TexCoords);
-    vec2 st2 = dFdy(TexCoords);
+    if (dot(Tangent.xyz, Tangent.xyz) > 0.0) {
+        // re-orthogonalize T with respect to N
+        T = normalize(Tangent.xyz - dot(Tangent.xyz, N) * N);
+        B = cross(N, T) * (Tangent.w < 0.0 ? -1.0 : 1.0);
+    } else {
+        vec3 Q1  = dFdx(WorldPos);
+        vec3 Q2  = dFdy(WorldPos);
+        vec2 st1 = dFdx(TexCoords);
+        vec2 st2 = dFdy(TexCoords);
 
-    vec3 N   = normalize(Normal);
-    vec3 T  = normalize(Q1*st2.t - Q2*st1.t);
-    vec3 B  = -normalize(cross(N, T));
-    mat3 TBN = mat3(T, B, N);
+        T = normalize(Q1*st2.t - Q2*st1.t);
+        B = -normalize(cross(N, T));
+    }
+
+    if (doubleSided && !gl_FrontFacing)
+        return -mat3(T, B, N);
+
+    return mat3(T, B, N);
+}
+// ----------------------------------------------------------------------------
+vec3 getNormalFromMap(mat3 TBN, vec2 texCoords)
+{
+    vec3 tangentNormal = texture(normal_map, texCoords).xyz * 2.0 - 1.0;
 
     return normalize(TBN * tangentNormal);
 }
 // ----------------------------------------------------------------------------
+// Steps along the view ray through the height map until it goes below the surface, then
+// interpolates between the last two steps. V is the direction to the camera in tangent space.
+vec2 parallaxOcclusion(vec2 texCoords, vec3 V)
+{
+    // the loop makes the derivatives undefined, so take them from the original coordinates
+    vec2 dx = dFdx(texCoords);
+    vec2 dy = dFdy(texCoords);
+
+    // more layers when looking along the surface, where the offset is largest
+    const float minLayers = 8.0;
+    const float maxLayers = 32.0;
+    float numLayers = mix(maxLayers, minLayers, abs(V.z));
+    float layerDepth = 1.0 / numLayers;
+    vec2 deltaTexCoords = V.xy / max(V.z, 0.05) * heightScale / numLayers;
+
+    float currentLayerDepth = 0.0;
+    vec2 currentTexCoords = texCoords;
+    float currentDepth = 1.0 - textureGrad(height_map, currentTexCoords, dx, dy).r;
+
+    for (int i = 0; i < int(maxLayers) && currentLayerDepth < currentDepth; i++)
+    {
+        currentTexCoords -= deltaTexCoords;
+        currentDepth = 1.0 - textureGrad(height_map, currentTexCoords, dx, dy).r;
+        currentLayerDepth += layerDepth;
+    }
+
+    vec2 prevTexCoords = currentTexCoords + deltaTexCoords;
+    float afterDepth = currentDepth - currentLayerDepth;
+    float beforeDepth = 1.0 - textureGrad(height_map, prevTexCoords, dx, dy).r
+        - currentLayerDepth + layerDepth;
+    float weight = afterDepth / (afterDepth - beforeDepth);
+
+    return mix(currentTexCoords, prevTexCoords, weight);
+}
+// ----------------------------------------------------------------------------
 float DistributionGGX(vec3 N, vec3 H, float roughness)
 {
     float a = roughness*roughness;
@@ -89,15 +242,107 @@ vec3 fresnelSchlickRoughness(float cosTheta, vec3 F0, float roughness)
     return F0 + (max(vec3(1.0 - roughness), F0) - F0) * pow(clamp(1.0 - cosTheta, 0.0, 1.0), 5.0);
 }   
 // ----------------------------------------------------------------------------
+// outgoing radiance from a single light, L points from the fragment towards the light
+vec3 calculateLight(vec3 N, vec3 V, vec3 L, vec3 radiance, vec3 F0, vec3 albedo, float metallic, float roughness)
+{
+    vec3 H = normalize(V + L);
+
+    // Cook-Torrance BRDF
+    float NDF = DistributionGGX(N, H, roughness);   
+    float G   = GeometrySmith(N, V, L, roughness);      
+    vec3 F    = fresnelSchlick(max(dot(H, V), 0.0), F0);
+       
+    vec3 numerator    = NDF * G * F; 
+    float denominator = 4.0 * max(dot(N, V), 0.0) * max(dot(N, L), 0.0) + 0.0001; // + 0.0001 to prevent divide by zero
+    vec3 specular = numerator / denominator;
+    
+    // kS is equal to Fresnel
+    vec3 kS = F;
+    // for energy conservation, the diffuse and specular light can't
+    // be above 1.0 (unless the surface emits light); to preserve this
+    // relationship the diffuse component (kD) should equal 1.0 - kS.
+    vec3 kD = vec3(1.0) - kS;
+    // multiply kD by the inverse metalness such that only non-metals 
+    // have diffuse lighting, or a linear blend if partly metal (pure metals
+    // have no diffuse light).
+    kD *= 1.0 - metallic;	  
+
+    // scale light by NdotL
+    float NdotL = max(dot(N, L), 0.0);        
+
+    // note that we already multiplied the BRDF by the Fresnel (kS) so we won't multiply by kS again
+    return (kD * albedo / PI + specular) * radiance * NdotL;
+}
+// ----------------------------------------------------------------------------
+// how much of the directional light is blocked (0 = lit, 1 = fully shadowed), uses 3x3 PCF
+float calculateShadow(vec3 N, vec3 L)
+{
+    if (!useShadows)
+        return 0.0;
+
+    vec4 lightSpacePos = lightSpaceMatrix * vec4(WorldPos, 1.0);
+    vec3 projCoords = lightSpacePos.xyz / lightSpacePos.w * 0.5 + 0.5;
+
+    // outside of the shadow map
+    if (projCoords.z > 1.0 || any(lessThan(projCoords.xy, vec2(0.0))) || any(greaterThan(projCoords.xy, vec2(1.0))))
+        return 0.0;
+
+    // bias to prevent shadow acne on surfaces facing away from the light
+    float bias = max(0.005 * (1.0 - dot(N, L)), 0.0005);
+    vec2 texelSize = 1.0 / textureSize(shadowMap, 0);
+
+    float shadow = 0.0;
+    for (int x = -1; x <= 1; ++x)
+    {
+        for (int y = -1; y <= 1; ++y)
+        {
+            float closestDepth = texture(shadowMap, projCoords.xy + vec2(x, y) * texelSize).r;
+            shadow += projCoords.z - bias > closestDepth ? 1.0 : 0.0;
+        }
+    }
+
+    return shadow / 9.0;
+}
+// ----------------------------------------------------------------------------
 void main()
 {		
-    vec3 albedo     = texture(albedo_map, TexCoords).rgb;
-    float metallic  = texture(metallic_map, TexCoords).b;
-    float roughness = texture(roughness_map, TexCoords).g;
-    float ao        = texture(ao_map, TexCoords).r;
-
-    vec3 N = getNormalFromMap();
+    mat3 TBN = getTBN();
     vec3 V = normalize(camPos - WorldPos);
+
+    vec2 texCoords = TexCoords;
+    if (useHeightMap)
+        texCoords = parallaxOcclusion(TexCoords, normalize(transpose(TBN) * V));
+
+    vec4 albedoSample = texture(albedo_map, texCoords);
+    vec3 albedo     = albedoSample.rgb * AlbedoTint.rgb;
+    float baseAlpha = albedoSample.a * alpha;
+    if (alphaMode == 1 && baseAlpha < alphaCutoff)
+        discard;
+    float metallic  = texture(metallic_map, texCoords).b;
+    float roughness = texture(roughness_map, texCoords).g;
+    float ao        = texture(ao_map, aoUv2 ? TexCoords2 : texCoords).r;
+    if (useOrmMap)
+    {
+        vec3 orm = texture(orm_map, texCoords).rgb;
+        metallic = orm.b;
+        roughness = orm.g;
+        if (ormOcclusion)
+            ao = orm.r;
+    }
+    vec3 emissive   = texture(emissive_map, texCoords).rgb;
+
+    if (useAlbedoValue)
+        albedo = albedoValue * AlbedoTint.rgb;
+    if (useMetallicValue)
+        metallic = metallicValue;
+    if (useRoughnessValue)
+        roughness = roughnessValue;
+    if (useAoValue)
+        ao = aoValue;
+    if (useEmissiveValue)
+        emissive = emissiveValue;
+
+    vec3 N = getNormalFromMap(TBN, texCoords);
     vec3 R = reflect(-V, N);
 
     // calculate reflectance at normal incidence; if dia-electric (like plastic) use F0 
@@ -107,63 +352,74 @@ void main()
 
     // reflectance equation
     vec3 Lo = vec3(0.0);
+    for (int i = 0; i < numLights && i < MAX_LIGHTS; ++i)
     {
         // calculate per-light radiance
-        vec3 L = normalize(lightPositions - WorldPos);
-        vec3 H = normalize(V + L);
-        float distance = length(lightPositions - WorldPos);
-        float attenuation = 1.0 / (distance * distance);
-        vec3 radiance = lightColors * attenuation;
-
-        // Cook-Torrance BRDF
-        float NDF = DistributionGGX(N, H, roughness);   
-        float G   = GeometrySmith(N, V, L, roughness);      
-        vec3 F    = fresnelSchlick(max(dot(H, V), 0.0), F0);
-           
-        vec3 numerator    = NDF * G * F; 
-        float denominator = 4.0 * max(dot(N, V), 0.0) * max(dot(N, L), 0.0) + 0.0001; // + 0.0001 to prevent divide by zero
-        vec3 specular = numerator / denominator;
-        
-        // kS is equal to Fresnel
-        vec3 kS = F;
-        // for energy conservation, the diffuse and specular light can't
-        // be above 1.0 (unless the surface emits light); to preserve this
-        // relationship the diffuse component (kD) should equal 1.0 - kS.
-        vec3 kD = vec3(1.0) - kS;
-        // multiply kD by the inverse metalness such that only non-metals 
-        // have diffuse lighting, or a linear blend if partly metal (pure metals
-        // have no diffuse light).
-        kD *= 1.0 - metallic;	  
-
-        // scale light by NdotL
-        float NdotL = max(dot(N, L), 0.0);        
-
-        // add to outgoing radiance Lo
-        Lo += (kD * albedo / PI + specular) * radiance * NdotL;  // note that we already multiplied the BRDF by the Fresnel (kS) so we won't multiply by kS again
-    }   
+        float distance = length(lightPositions[i] - WorldPos);
+        float range = lightRanges[i];
+        if (range > 0.0 && distance >= range)
+            continue;
+
+        vec3 L = normalize(lightPositions[i] - WorldPos);
+        vec3 coefficients = lightAttenuations[i];
+        float attenuation = 1.0 / max(coefficients.x + coefficients.y * distance + coefficients.z * distance * distance, 0.0001);
+
+        // fade out towards the range so the cutoff isn't visible
+        if (range > 0.0)
+        {
+            float falloff = clamp(1.0 - pow(distance / range, 4.0), 0.0, 1.0);
+            attenuation *= falloff * falloff;
+        }
+
+        vec3 radiance = lightColors[i] * attenuation;
+
+        Lo += calculateLight(N, V, L, radiance, F0, albedo, metallic, roughness);
+    }
+
+    // directional light, infinitely far away so there is no attenuation
+    vec3 dirL = normalize(-dirLightDirection);
+    float shadow = calculateShadow(N, dirL);
+    Lo += calculateLight(N, V, dirL, dirLightColor, F0, albedo, metallic, roughness) * (1.0 - shadow);
     
     // ambient lighting (we now use IBL as the ambient term)
     vec3 kS = fresnelSchlickRoughness(max(dot(N, V), 0.0), F0, roughness);
     vec3 kD = 1.0 - kS;
     kD *= 1.0 - metallic;	  
     
-    vec3 irradiance = texture(irradiance_map, N).rgb;
+    vec3 irradiance = texture(irradiance_map, environmentRotation * N).rgb;
     vec3 diffuse      = irradiance * albedo;
     
     // sample both the pre-filter map and the BRDF lut and combine them together as per the Split-Sum approximation to get the IBL specular part.
-    const float MAX_REFLECTION_LOD = 4.0;
-    vec3 prefilteredColor = textureLod(prefilter_map, R,  roughness * MAX_REFLECTION_LOD).rgb;    
+    float reflectionLod = clamp(roughness * maxReflectionLod + prefilterLodBias, 0.0, maxReflectionLod);
+    vec3 prefilteredColor = textureLod(prefilter_map, environmentRotation * R, reflectionLod).rgb;    
     vec2 brdf  = texture(brdfLUT, vec2(max(dot(N, V), 0.0), roughness)).rg;
     vec3 specular = prefilteredColor * (kS * brdf.x + brdf.y);
 
-    vec3 ambient = (kD * diffuse + specular) * ao;
+    ao *= VertexAo;
+    if (useSsao)
+        ao *= texture(ssaoMap, (gl_FragCoord.xy - viewportOrigin) / vec2(textureSize(ssaoMap, 0))).r;
+
+    vec3 ambient = (kD * diffuse + specular) * ao * iblIntensity;
 
     vec3 color = ambient + Lo;
 
-    //// HDR tonemapping
-    //color = color / (color + vec3(1.0));
-    //// gamma correct
-    //color = pow(color, vec3(1.0/2.2)); 
+    // HDR tonemapping, gamma correction is done by the srgb framebuffer
+    color = toneMap(color);
+
+    // emissive is added after tone mapping so it isn't dimmed
+    color += emissive;
+
+    // fog is blended last so distant surfaces end up exactly at the fog color
+    float fogAmount = 1.0 - exp(-fogDensity * length(camPos - WorldPos));
+    color = mix(color, fogColor, fogAmount);
+
+    if (wireframeOverlay)
+    {
+        // distance to the closest edge in pixels, the last pixel is smoothed
+        vec3 pixels = Barycentric / fwidth(Barycentric);
+        float edge = min(min(pixels.x, pixels.y), pixels.z);
+        color = mix(wireframeColor, color, smoothstep(wireframeThickness - 1.0, wireframeThickness, edge));
+    }
 
-    FragColor = vec4(color, 1.0);
+    FragColor = vec4(color, alphaMode == 2 ? baseAlpha : alpha);
 }
@@ -0,0 +1,50 @@
+#version 330 core
+// Passes each triangle through unchanged, adding the barycentric coordinates that the wireframe
+// overlay is drawn with. Only compiled into the program while the overlay is drawn.
+layout(triangles) in;
+layout(triangle_strip, max_vertices = 3) out;
+
+in VertexData {
+    vec2 TexCoords;
+    vec2 TexCoords2;
+    vec3 WorldPos;
+    vec3 Normal;
+    vec4 Tangent;
+    vec4 AlbedoTint;
+    float VertexAo;
+} inputs[];
+
+out VertexData {
+    vec2 TexCoords;
+    vec2 TexCoords2;
+    vec3 WorldPos;
+    vec3 Normal;
+    vec4 Tangent;
+    vec4 AlbedoTint;
+    float VertexAo;
+};
+noperspective out vec3 Barycentric;
+
+// has to match the depth pre-pass exactly
+invariant gl_Position;
+
+void main()
+{
+    for (int i = 0; i < 3; i++)
+    {
+        TexCoords = inputs[i].TexCoords;
+        TexCoords2 = inputs[i].TexCoords2;
+        WorldPos = inputs[i].WorldPos;
+        Normal = inputs[i].Normal;
+        Tangent = inputs[i].Tangent;
+        AlbedoTint = inputs[i].AlbedoTint;
+        VertexAo = inputs[i].VertexAo;
+
+        Barycentric = vec3(0.0);
+        Barycentric[i] = 1.0;
+
+        gl_Position = gl_in[i].gl_Position;
+        EmitVertex();
+    }
+    EndPrimitive();
+}
@@ -1,24 +1,45 @@
 use egui::Ui;
 use glium::backend::Context;
+use glium::draw_parameters::BackfaceCullingMode;
 use glium::texture::Cubemap;
+use glium::uniforms::Sampler;
+use glium::uniforms::UniformValue;
+use glium::uniforms::Uniforms;
 use glium::Blend;
 use glium::DrawParameters;
 use glium::Texture2d;
+use glium::VertexBuffer;
 use glium::{backend::Facade, Program};
+use image::DynamicImage;
 use nalgebra::Matrix4;
+use serde::{Deserialize, Serialize};
 use std::any::Any;
+use std::cell::OnceCell;
+use std::error::Error;
+use std::path::{Path, PathBuf};
 use std::rc::Rc;
 
+use crate::error::RendererError;
+use crate::renderer::DepthBias;
+use crate::shader::HotReloadProgram;
+use crate::shader::Shader;
+use crate::shaders::ssao::Ssao;
 use crate::utils::pbr_skybox::PBRSkybox;
+use crate::utils::shadow_map::ShadowMap;
 use crate::utils::texture_loader::TextureLoader;
-use crate::{insert_program, shader::Shader};
+use crate::utils::texture_loader::TextureOptions;
+use crate::utils::texture_registry::TextureRegistry;
+use crate::vertex::Instance;
+use crate::vertex::SkinWeights;
 
-#[derive(Clone)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct PBRSimple {
     pub albedo: [f32; 3],
     pub metallic: f32,
     pub roughness: f32,
     pub ao: f32,
+    pub emissive: [f32; 3],
+    pub alpha: f32,
 }
 
 impl Default for PBRSimple {
@@ -28,6 +49,159 @@ impl Default for PBRSimple {
             metallic: 0.0,
             roughness: 0.05,
             ao: 1.0,
+            emissive: [0.0; 3],
+            alpha: 1.0,
+        }
+    }
+}
+
+/// Plain values that replace maps of the [`PBRTextures`] of a [`PBR`]
+///
+/// The values are uploaded as uniforms, so changing them is instant and doesn't allocate a
+/// texture. Clones of a [`PBR`] share their textures, so giving each clone its own override (ex.
+/// a grid of spheres with different roughness) doesn't create any textures per material. `None`
+/// uses the map as usual.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PBRScalarOverride {
+    /// Still multiplied with the vertex color and instance albedo
+    pub albedo: Option<[f32; 3]>,
+    pub metallic: Option<f32>,
+    pub roughness: Option<f32>,
+    pub ao: Option<f32>,
+    pub emissive: Option<[f32; 3]>,
+}
+
+impl PBRScalarOverride {
+    pub fn debug_ui(&mut self, ui: &mut Ui) {
+        Self::debug_color(ui, "albedo", &mut self.albedo, [1.0; 3]);
+        Self::debug_value(ui, "metallic", &mut self.metallic, 0.0);
+        Self::debug_value(ui, "roughness", &mut self.roughness, 0.5);
+        Self::debug_value(ui, "ao", &mut self.ao, 1.0);
+        Self::debug_color(ui, "emissive", &mut self.emissive, [0.0; 3]);
+    }
+
+    fn debug_value(ui: &mut Ui, label: &str, value: &mut Option<f32>, default: f32) {
+        ui.horizontal(|ui| {
+            let mut enabled = value.is_some();
+            if ui.checkbox(&mut enabled, label).changed() {
+                *value = enabled.then_some(default);
+            }
+            if let Some(value) = value {
+                ui.add(egui::widgets::Slider::new(value, 0.0..=1.0));
+            }
+        });
+    }
+
+    fn debug_color(ui: &mut Ui, label: &str, value: &mut Option<[f32; 3]>, default: [f32; 3]) {
+        ui.horizontal(|ui| {
+            let mut enabled = value.is_some();
+            if ui.checkbox(&mut enabled, label).changed() {
+                *value = enabled.then_some(default);
+            }
+            if let Some(value) = value {
+                egui::widgets::color_picker::color_edit_button_rgb(ui, value);
+            }
+        });
+    }
+}
+
+/// How a [`PBR`] uses the alpha of its albedo, same as the `alphaMode` of glTF materials
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum AlphaMode {
+    /// The albedo map's alpha is ignored, only the [alpha](PBRTextures::set_alpha) is used
+    #[default]
+    Opaque,
+    /// Fragments where the albedo map's alpha times the alpha is below the cutoff are discarded,
+    /// the rest are opaque. Used for cutouts like leaves and fences.
+    Mask(f32),
+    /// The albedo map's alpha times the alpha is blended, the material is always [transparent
+    /// ](Shader::is_transparent)
+    Blend,
+}
+
+impl AlphaMode {
+    /// The cutoff glTF uses when a masked material doesn't have one
+    pub const DEFAULT_CUTOFF: f32 = 0.5;
+
+    /// Whether a fragment with the albedo map's alpha times the alpha of `alpha` is thrown away,
+    /// the same test the shader does
+    pub fn discards(&self, alpha: f32) -> bool {
+        match self {
+            Self::Mask(cutoff) => alpha < *cutoff,
+            _ => false,
+        }
+    }
+
+    /// Passed to the shader as `alphaMode`
+    fn as_uniform(&self) -> i32 {
+        match self {
+            Self::Opaque => 0,
+            Self::Mask(_) => 1,
+            Self::Blend => 2,
+        }
+    }
+}
+
+/// The maps of [`PBRTextures`] that can be loaded from a file
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PBRMap {
+    Albedo,
+    Metallic,
+    Roughness,
+    Ao,
+    Normal,
+    Emissive,
+    Orm,
+    Height,
+}
+
+impl PBRMap {
+    /// The albedo and emissive maps are colors and are stored as srgb, the rest are linear data
+    pub fn is_color(&self) -> bool {
+        matches!(self, Self::Albedo | Self::Emissive)
+    }
+}
+
+/// The files the maps of [`PBRTextures`] were loaded from
+///
+/// `None` means the map is a solid color or was set from memory.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct PBRTextureSources {
+    pub albedo: Option<PathBuf>,
+    pub metallic: Option<PathBuf>,
+    pub roughness: Option<PathBuf>,
+    pub ao: Option<PathBuf>,
+    pub normal: Option<PathBuf>,
+    pub emissive: Option<PathBuf>,
+    pub orm: Option<PathBuf>,
+    #[serde(default)]
+    pub height: Option<PathBuf>,
+}
+
+impl PBRTextureSources {
+    pub fn get(&self, map: PBRMap) -> Option<&PathBuf> {
+        match map {
+            PBRMap::Albedo => self.albedo.as_ref(),
+            PBRMap::Metallic => self.metallic.as_ref(),
+            PBRMap::Roughness => self.roughness.as_ref(),
+            PBRMap::Ao => self.ao.as_ref(),
+            PBRMap::Normal => self.normal.as_ref(),
+            PBRMap::Emissive => self.emissive.as_ref(),
+            PBRMap::Orm => self.orm.as_ref(),
+            PBRMap::Height => self.height.as_ref(),
+        }
+    }
+
+    fn get_mut(&mut self, map: PBRMap) -> &mut Option<PathBuf> {
+        match map {
+            PBRMap::Albedo => &mut self.albedo,
+            PBRMap::Metallic => &mut self.metallic,
+            PBRMap::Roughness => &mut self.roughness,
+            PBRMap::Ao => &mut self.ao,
+            PBRMap::Normal => &mut self.normal,
+            PBRMap::Emissive => &mut self.emissive,
+            PBRMap::Orm => &mut self.orm,
+            PBRMap::Height => &mut self.height,
         }
     }
 }
@@ -39,13 +213,26 @@ pub struct PBRTextures {
     roughness: Rc<Texture2d>,
     ao: Rc<Texture2d>,
     normal: Rc<Texture2d>,
+    emissive: Rc<Texture2d>,
+    orm: Option<Rc<Texture2d>>,
+    orm_occlusion: bool,
+    height: Option<Rc<Texture2d>>,
+    height_scale: f32,
+    ao_uv2: bool,
+    anisotropy: u16,
+    texture_options: TextureOptions,
+    alpha: f32,
+    sources: PBRTextureSources,
     facade: Rc<Context>,
 }
 
 impl PBRTextures {
     pub fn from_simple(facade: &impl Facade, simple: PBRSimple) -> Self {
-        let create_texture =
-            |data: [f32; 3]| Rc::new(TextureLoader::from_memory_f32(facade, &data, 1, 1).unwrap());
+        let create_texture = |data: [f32; 3]| {
+            let texture = Rc::new(TextureLoader::from_memory_f32(facade, &data, 1, 1).unwrap());
+            TextureRegistry::track_texture(&texture);
+            texture
+        };
 
         Self {
             albedo: create_texture(simple.albedo),
@@ -53,24 +240,261 @@ impl PBRTextures {
             roughness: create_texture([simple.roughness; 3]),
             ao: create_texture([simple.ao; 3]),
             normal: create_texture([0.5, 0.5, 1.0]),
+            emissive: create_texture(simple.emissive),
+            orm: None,
+            orm_occlusion: false,
+            height: None,
+            height_scale: 0.05,
+            ao_uv2: false,
+            anisotropy: 1,
+            texture_options: TextureOptions::default(),
+            alpha: simple.alpha,
+            sources: Default::default(),
             facade: facade.get_context().clone(),
         }
     }
 
+    /// Reads the values back from the maps that are a single color
+    ///
+    /// Maps that are an actual texture use the default value instead.
+    pub fn to_simple(&self) -> PBRSimple {
+        let default = PBRSimple::default();
+        let read_pixel = |texture: &Texture2d| {
+            if texture.width() != 1 || texture.height() != 1 {
+                return None;
+            }
+
+            // Safe since every float format can be read back as rgba floats
+            let pixels: Vec<Vec<(f32, f32, f32, f32)>> =
+                unsafe { texture.unchecked_read::<_, (f32, f32, f32, f32)>() };
+            let (r, g, b, _) = pixels[0][0];
+            Some([r, g, b])
+        };
+
+        PBRSimple {
+            albedo: read_pixel(&self.albedo).unwrap_or(default.albedo),
+            metallic: read_pixel(&self.metallic).map_or(default.metallic, |p| p[0]),
+            roughness: read_pixel(&self.roughness).map_or(default.roughness, |p| p[0]),
+            ao: read_pixel(&self.ao).map_or(default.ao, |p| p[0]),
+            emissive: read_pixel(&self.emissive).unwrap_or(default.emissive),
+            alpha: self.alpha,
+        }
+    }
+
+    /// Loads a map from a file and remembers where it came from
+    ///
+    /// The albedo and emissive maps are color maps and are read as srgb, the rest as linear data.
+    /// Mipmaps are generated unless the [texture options](Self::set_texture_options) turn them
+    /// off, so the map doesn't shimmer in the distance.
+    pub fn load_map(&mut self, map: PBRMap, path: impl AsRef<Path>) -> Result<(), Box<dyn Error>> {
+        let path = path.as_ref();
+        let options = self.texture_options;
+        let texture = match map.is_color() {
+            true => TextureLoader::from_fs_srgb_with_options(&self.facade, path, options)?,
+            false => TextureLoader::from_fs_with_options(&self.facade, path, options)?,
+        };
+        self.set_map(map, Rc::new(texture));
+        *self.sources.get_mut(map) = Some(path.to_path_buf());
+
+        Ok(())
+    }
+
+    /// Same as [`load_map`](Self::load_map) for an image that didn't come from a file, ex. one
+    /// embedded in a model
+    ///
+    /// The map has no source afterwards, so it can't be saved in a
+    /// [`MaterialDescription`](crate::utils::scene_description::MaterialDescription).
+    pub fn load_map_image(
+        &mut self,
+        map: PBRMap,
+        image: DynamicImage,
+    ) -> Result<(), Box<dyn Error>> {
+        let options = self.texture_options;
+        let texture = match map.is_color() {
+            true => TextureLoader::from_image_srgb_with_options(&self.facade, image, options)?,
+            false => TextureLoader::from_image_with_options(&self.facade, image, options)?,
+        };
+
+        self.set_map(map, Rc::new(texture));
+        *self.sources.get_mut(map) = None;
+
+        Ok(())
+    }
+
+    /// Same as [`load_map`](Self::load_map) for a texture that was already loaded from `path`,
+    /// ex. by a [`TextureLoadQueue`](crate::utils::texture_load_queue::TextureLoadQueue)
+    pub fn set_loaded_map(&mut self, map: PBRMap, texture: Rc<Texture2d>, path: PathBuf) {
+        self.set_map(map, texture);
+        *self.sources.get_mut(map) = Some(path);
+    }
+
+    /// Sets the texture of `map`, ex. to replace any map with a
+    /// [missing texture](TextureLoader::missing_texture)
+    pub fn set_map(&mut self, map: PBRMap, texture: Rc<Texture2d>) {
+        match map {
+            PBRMap::Albedo => self.set_albedo(texture),
+            PBRMap::Metallic => self.set_metallic(texture),
+            PBRMap::Roughness => self.set_roughness(texture),
+            PBRMap::Ao => self.set_ao(texture),
+            PBRMap::Normal => self.set_normal(texture),
+            PBRMap::Emissive => self.set_emissive(texture),
+            PBRMap::Orm => self.set_orm_map(texture),
+            PBRMap::Height => self.set_height_map(texture),
+        }
+    }
+
+    /// Loads every map that has a source, stopping at the first one that fails
+    pub fn load_sources(&mut self, sources: &PBRTextureSources) -> Result<(), Box<dyn Error>> {
+        for map in [
+            PBRMap::Albedo,
+            PBRMap::Metallic,
+            PBRMap::Roughness,
+            PBRMap::Ao,
+            PBRMap::Normal,
+            PBRMap::Emissive,
+            PBRMap::Orm,
+            PBRMap::Height,
+        ] {
+            if let Some(path) = sources.get(map) {
+                self.load_map(map, path)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// The files the maps were loaded from with [`load_map`](Self::load_map)
+    ///
+    /// Setting a map directly clears its source.
+    pub fn get_sources(&self) -> &PBRTextureSources {
+        &self.sources
+    }
+
     pub fn set_albedo(&mut self, texture: Rc<Texture2d>) {
+        TextureRegistry::track_texture(&texture);
         self.albedo = texture;
+        self.sources.albedo = None;
     }
     pub fn set_metallic(&mut self, texture: Rc<Texture2d>) {
+        TextureRegistry::track_texture(&texture);
         self.metallic = texture;
+        self.sources.metallic = None;
     }
     pub fn set_roughness(&mut self, texture: Rc<Texture2d>) {
+        TextureRegistry::track_texture(&texture);
         self.roughness = texture;
+        self.sources.roughness = None;
     }
     pub fn set_ao(&mut self, texture: Rc<Texture2d>) {
+        TextureRegistry::track_texture(&texture);
         self.ao = texture;
+        self.sources.ao = None;
     }
     pub fn set_normal(&mut self, texture: Rc<Texture2d>) {
+        TextureRegistry::track_texture(&texture);
         self.normal = texture;
+        self.sources.normal = None;
+    }
+    pub fn set_emissive(&mut self, texture: Rc<Texture2d>) {
+        TextureRegistry::track_texture(&texture);
+        self.emissive = texture;
+        self.sources.emissive = None;
+    }
+    /// Uses a packed occlusion (red), roughness (green), metallic (blue) texture like glTF
+    ///
+    /// Replaces the metallic and roughness maps until [`clear_orm_map`](Self::clear_orm_map) is
+    /// called. The ao map is still used unless [`set_orm_occlusion`](Self::set_orm_occlusion)
+    /// is enabled, since glTF files don't always store occlusion in the red channel.
+    pub fn set_orm_map(&mut self, texture: Rc<Texture2d>) {
+        TextureRegistry::track_texture(&texture);
+        self.orm = Some(texture);
+        self.sources.orm = None;
+    }
+    pub fn get_orm_map(&self) -> Option<&Rc<Texture2d>> {
+        self.orm.as_ref()
+    }
+    pub fn clear_orm_map(&mut self) {
+        self.orm = None;
+        self.orm_occlusion = false;
+        self.sources.orm = None;
+    }
+    /// Reads the ambient occlusion from the red channel of the orm map
+    pub fn set_orm_occlusion(&mut self, orm_occlusion: bool) {
+        self.orm_occlusion = orm_occlusion;
+    }
+    pub fn get_orm_occlusion(&self) -> bool {
+        self.orm_occlusion
+    }
+    /// Uses parallax occlusion mapping to make the surface look displaced by the height map
+    ///
+    /// White is the top of the surface. Without a height map the texture coordinates aren't
+    /// changed at all, which skips the expensive ray marching in the shader. Needs a valid tangent
+    /// space, like normal mapping.
+    pub fn set_height_map(&mut self, texture: Rc<Texture2d>) {
+        TextureRegistry::track_texture(&texture);
+        self.height = Some(texture);
+        self.sources.height = None;
+    }
+    pub fn get_height_map(&self) -> Option<&Rc<Texture2d>> {
+        self.height.as_ref()
+    }
+    pub fn clear_height_map(&mut self) {
+        self.height = None;
+        self.sources.height = None;
+    }
+    /// How deep the height map goes, in texture coordinates, 0.05 by default
+    pub fn set_height_scale(&mut self, height_scale: f32) {
+        self.height_scale = height_scale;
+    }
+    pub fn get_height_scale(&self) -> f32 {
+        self.height_scale
+    }
+    /// Samples the ao map with the second set of texture coordinates
+    /// ([`Vertex::tex_coords2`](crate::vertex::Vertex::tex_coords2))
+    pub fn set_ao_uv2(&mut self, ao_uv2: bool) {
+        self.ao_uv2 = ao_uv2;
+    }
+    pub fn get_ao_uv2(&self) -> bool {
+        self.ao_uv2
+    }
+    /// Sets how many samples are taken when a map is viewed at a glancing angle, 1 (off) by default
+    ///
+    /// Higher levels keep textures sharp on surfaces like floors seen from the side. Clamped to
+    /// what the GPU supports.
+    pub fn set_anisotropy(&mut self, level: u16) {
+        let max = self.facade.get_max_anisotropy_support().unwrap_or(1);
+        self.anisotropy = level.clamp(1, max.max(1));
+    }
+    pub fn get_anisotropy(&self) -> u16 {
+        self.anisotropy
+    }
+    /// How every map is wrapped and filtered, repeating with trilinear filtering by default
+    ///
+    /// `generate_mipmaps` applies to maps loaded afterwards, maps that are already loaded keep
+    /// their mipmaps. The single color maps never have any, the mipmap filters use the largest
+    /// level of maps without mipmaps.
+    pub fn set_texture_options(&mut self, texture_options: TextureOptions) {
+        self.texture_options = texture_options;
+    }
+    pub fn get_texture_options(&self) -> TextureOptions {
+        self.texture_options
+    }
+    fn sample<'t>(&self, map: &'t Texture2d) -> Sampler<'t, Texture2d> {
+        self.texture_options
+            .sampled(map)
+            .anisotropy(self.anisotropy)
+    }
+    /// Opacity of the material, 1 by default
+    ///
+    /// Multiplied with the alpha channel of the albedo map when the
+    /// [alpha mode](PBR::set_alpha_mode) masks or blends, where maps without an alpha channel
+    /// count as fully opaque. An alpha below 1 makes the material
+    /// [transparent](Shader::is_transparent).
+    pub fn get_alpha(&self) -> f32 {
+        self.alpha
+    }
+    pub fn set_alpha(&mut self, alpha: f32) {
+        self.alpha = alpha;
     }
 
     pub fn debug_ui(&mut self, ui: &mut Ui) {
@@ -100,34 +524,65 @@ impl PBRTextures {
                     .into(),
             );
         }
-        if ui.button("select").clicked() {
-            if let Some(file) = rfd::FileDialog::new().pick_file() {
-                self.set_albedo(TextureLoader::from_fs(&self.facade, &file).unwrap().into());
+        self.debug_select(ui, PBRMap::Albedo);
+        ui.add(egui::widgets::Slider::new(&mut self.alpha, 0.0..=1.0).text("alpha"));
+
+        if self.orm.is_some() {
+            ui.label("using packed occlusion/roughness/metallic map");
+            if ui.button("clear").clicked() {
+                self.clear_orm_map();
             }
+            ui.separator();
         }
 
         // Metallic
         if let Some(texture) = self.debug_slider(ui, "metallic", &self.metallic, 0) {
             self.set_metallic(texture.into());
         }
+        self.debug_select(ui, PBRMap::Metallic);
+        ui.separator();
 
         // Roughness
         if let Some(texture) = self.debug_slider(ui, "roughness", &self.roughness, 0) {
             self.set_roughness(texture.into());
         }
+        self.debug_select(ui, PBRMap::Roughness);
+        ui.separator();
 
         // Ambient Occlusion
         if let Some(texture) = self.debug_slider(ui, "ao", &self.ao, 0) {
             self.set_ao(texture.into());
         }
+        ui.checkbox(&mut self.ao_uv2, "second uv set");
+        self.debug_select(ui, PBRMap::Ao);
+        ui.separator();
 
         ui.label("normal");
-        if ui.button("select").clicked() {
-            if let Some(file) = rfd::FileDialog::new().pick_file() {
-                self.set_normal(TextureLoader::from_fs(&self.facade, &file).unwrap().into());
+        self.debug_select(ui, PBRMap::Normal);
+
+        ui.label("emissive");
+        self.debug_select(ui, PBRMap::Emissive);
+        ui.separator();
+
+        ui.label("height");
+        if self.height.is_some() {
+            ui.add(
+                egui::widgets::Slider::new(&mut self.height_scale, 0.0..=0.2).text("height scale"),
+            );
+            if ui.button("clear").clicked() {
+                self.clear_height_map();
             }
         }
+        self.debug_select(ui, PBRMap::Height);
         ui.separator();
+
+        let mut anisotropic = self.anisotropy > 1;
+        if ui
+            .checkbox(&mut anisotropic, "16x anisotropic filtering")
+            .changed()
+        {
+            self.set_anisotropy(if anisotropic { 16 } else { 1 });
+        }
     }
 
     fn debug_slider(
@@ -159,45 +614,439 @@ impl PBRTextures {
             result = TextureLoader::from_memory_f32(&self.facade, &[1.0; 3], 1, 1).ok()
         }
 
+        result
+    }
+
+    fn debug_select(&mut self, ui: &mut Ui, map: PBRMap) {
+        if let Some(path) = self.sources.get(map) {
+            ui.label(path.display().to_string());
+        }
+
         if ui.button("select").clicked() {
             if let Some(file) = rfd::FileDialog::new().pick_file() {
-                result = TextureLoader::from_fs(&self.facade, &file).ok();
+                if let Err(err) = self.load_map(map, &file) {
+                    eprintln!("failed to load {}: {}", file.display(), err);
+                }
             }
         }
+    }
+}
 
-        ui.separator();
+/// Equal when every map is the same texture and the other settings match
+///
+/// Textures are compared by pointer, not by their contents. Clones share their textures, while
+/// maps loaded separately from the same file are different textures. The
+/// [sources](PBRTextures::get_sources) aren't compared, they only follow the maps.
+impl PartialEq for PBRTextures {
+    fn eq(&self, other: &Self) -> bool {
+        let same_map = |a: &Option<Rc<Texture2d>>, b: &Option<Rc<Texture2d>>| match (a, b) {
+            (Some(a), Some(b)) => Rc::ptr_eq(a, b),
+            (None, None) => true,
+            _ => false,
+        };
 
-        result
+        Rc::ptr_eq(&self.albedo, &other.albedo)
+            && Rc::ptr_eq(&self.metallic, &other.metallic)
+            && Rc::ptr_eq(&self.roughness, &other.roughness)
+            && Rc::ptr_eq(&self.ao, &other.ao)
+            && Rc::ptr_eq(&self.normal, &other.normal)
+            && Rc::ptr_eq(&self.emissive, &other.emissive)
+            && same_map(&self.orm, &other.orm)
+            && same_map(&self.height, &other.height)
+            && self.orm_occlusion == other.orm_occlusion
+            && self.height_scale == other.height_scale
+            && self.ao_uv2 == other.ao_uv2
+            && self.anisotropy == other.anisotropy
+            && self.texture_options == other.texture_options
+            && self.alpha == other.alpha
     }
 }
 
+/// The maximum amount of bones a skinned [`PBR`] can use
+///
+/// This has to match `MAX_BONES` in `vertex_skinned.glsl`. Every bone takes up 16 uniform
+/// components, together with the other matrices this stays under the 1024 that OpenGL 3.3
+/// guarantees for a vertex shader. Bones past this limit aren't uploaded, the vertices they move
+/// are left in place.
+pub const MAX_BONES: usize = 60;
+
+/// The bones of a skinned [`PBR`], see [`PBR::load_skinned`]
+#[derive(Clone)]
+struct Skin {
+    weights: Rc<VertexBuffer<SkinWeights>>,
+    bones: Vec<[[f32; 4]; 4]>,
+}
+
+/// Uploads the bone matrices as `bones[]` and how many were set as `boneCount`
+struct BoneUniforms<'a, U: Uniforms> {
+    bones: &'a [[[f32; 4]; 4]],
+    uniforms: U,
+}
+
+impl<'a, U: Uniforms> Uniforms for BoneUniforms<'a, U> {
+    fn visit_values<'b, F: FnMut(&str, UniformValue<'b>)>(&'b self, mut output: F) {
+        self.uniforms.visit_values(&mut output);
+
+        output(
+            "boneCount",
+            UniformValue::SignedInt(self.bones.len().min(MAX_BONES) as i32),
+        );
+        for (i, bone) in self.bones.iter().take(MAX_BONES).enumerate() {
+            output(&format!("bones[{}]", i), UniformValue::Mat4(*bone));
+        }
+    }
+}
+
+// The geometry shader is only needed for the wireframe overlay, every variant is compiled without
+// it and again with it the first time the overlay is drawn
+const VERTEX: &str = include_str!("./vertex.glsl");
+const VERTEX_INSTANCED: &str = include_str!("./vertex_instanced.glsl");
+const VERTEX_SKINNED: &str = include_str!("./vertex_skinned.glsl");
+const GEOMETRY: &str = include_str!("./geometry.glsl");
+const FRAGMENT: &str = include_str!("./fragment.glsl");
+
+fn compile_program(facade: &impl Facade, vertex: &str, geometry: Option<&str>) -> Program {
+    Program::from_source(facade, vertex, FRAGMENT, geometry).expect("Error compiling shader")
+}
+
 #[derive(Clone)]
 pub struct PBR {
     program: Rc<Program>,
+    /// The program with `geometry.glsl`, see [`get_program`](Self::get_program)
+    wireframe_program: Rc<OnceCell<Program>>,
+    vertex_source: &'static str,
+    hot_reload: Option<HotReloadProgram>,
+    instances: Option<Rc<VertexBuffer<Instance>>>,
+    skin: Option<Skin>,
     pbr_params: PBRTextures,
+    scalar_override: PBRScalarOverride,
+    cull_mode: Option<BackfaceCullingMode>,
+    double_sided: bool,
+    depth_bias: Option<DepthBias>,
+    alpha_mode: AlphaMode,
     model: Matrix4<f32>,
 }
 
+/// Configures a [`PBR`] in one expression instead of mutating its [`PBRTextures`]
+///
+/// Values are used for every map that doesn't get a texture. The textures are only loaded by
+/// [`build`](Self::build).
+///
+/// # Example
+/// ```no_run
+/// # fn example(facade: &impl glium::backend::Facade) -> Result<(), Box<dyn std::error::Error>> {
+/// use opengl_renderer::shaders::pbr::PBRBuilder;
+///
+/// let pbr = PBRBuilder::new()
+///     .albedo_texture("resources/albedo.png")
+///     .normal_texture("resources/normal.png")
+///     .metallic(1.0)
+///     .roughness(0.3)
+///     .build(facade)?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct PBRBuilder {
+    simple: PBRSimple,
+    textures: Vec<(PBRMap, PathBuf)>,
+}
+
+impl PBRBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn albedo(mut self, albedo: [f32; 3]) -> Self {
+        self.simple.albedo = albedo;
+        self
+    }
+    pub fn metallic(mut self, metallic: f32) -> Self {
+        self.simple.metallic = metallic;
+        self
+    }
+    pub fn roughness(mut self, roughness: f32) -> Self {
+        self.simple.roughness = roughness;
+        self
+    }
+    pub fn ao(mut self, ao: f32) -> Self {
+        self.simple.ao = ao;
+        self
+    }
+    pub fn emissive(mut self, emissive: [f32; 3]) -> Self {
+        self.simple.emissive = emissive;
+        self
+    }
+    pub fn alpha(mut self, alpha: f32) -> Self {
+        self.simple.alpha = alpha;
+        self
+    }
+
+    /// Loads `map` from a file when building, see [`PBRTextures::load_map`]
+    pub fn texture(mut self, map: PBRMap, path: impl AsRef<Path>) -> Self {
+        self.textures.push((map, path.as_ref().to_path_buf()));
+        self
+    }
+    pub fn albedo_texture(self, path: impl AsRef<Path>) -> Self {
+        self.texture(PBRMap::Albedo, path)
+    }
+    pub fn metallic_texture(self, path: impl AsRef<Path>) -> Self {
+        self.texture(PBRMap::Metallic, path)
+    }
+    pub fn roughness_texture(self, path: impl AsRef<Path>) -> Self {
+        self.texture(PBRMap::Roughness, path)
+    }
+    pub fn ao_texture(self, path: impl AsRef<Path>) -> Self {
+        self.texture(PBRMap::Ao, path)
+    }
+    pub fn normal_texture(self, path: impl AsRef<Path>) -> Self {
+        self.texture(PBRMap::Normal, path)
+    }
+    pub fn emissive_texture(self, path: impl AsRef<Path>) -> Self {
+        self.texture(PBRMap::Emissive, path)
+    }
+
+    /// Loads the textures and creates the material, failing if any texture can't be loaded
+    pub fn build(self, facade: &impl Facade) -> Result<PBR, Box<dyn Error>> {
+        let mut params = PBRTextures::from_simple(facade, self.simple);
+        for (map, path) in &self.textures {
+            params.load_map(*map, path)?;
+        }
+
+        let mut pbr = PBR::load_from_fs(facade);
+        pbr.set_pbr_params(params);
+
+        Ok(pbr)
+    }
+}
+
 impl PBR {
     pub fn load_from_fs(facade: &impl Facade) -> Self {
-        let program = Rc::new(insert_program!("./vertex.glsl", "./fragment.glsl", facade));
+        Self {
+            program: Rc::new(compile_program(facade, VERTEX, None)),
+            wireframe_program: Rc::new(OnceCell::new()),
+            vertex_source: VERTEX,
+            hot_reload: None,
+            instances: None,
+            skin: None,
+            pbr_params: PBRTextures::from_simple(facade, Default::default()),
+            scalar_override: Default::default(),
+            cull_mode: None,
+            double_sided: false,
+            depth_bias: None,
+            alpha_mode: AlphaMode::Opaque,
+            model: Matrix4::new_translation(&[0.0; 3].into()),
+        }
+    }
+
+    /// Loads the instanced variant of the shader
+    ///
+    /// Everything is drawn once per [`Instance`] in a single draw call, with the model matrix and
+    /// albedo of each instance coming from a vertex attribute. Nothing is drawn until
+    /// [`set_instances`](Self::set_instances) is called. Instanced materials don't cast shadows.
+    pub fn load_instanced(facade: &impl Facade) -> Self {
+        Self {
+            program: Rc::new(compile_program(facade, VERTEX_INSTANCED, None)),
+            wireframe_program: Rc::new(OnceCell::new()),
+            vertex_source: VERTEX_INSTANCED,
+            hot_reload: None,
+            instances: Some(Rc::new(VertexBuffer::empty(facade, 0).unwrap())),
+            skin: None,
+            pbr_params: PBRTextures::from_simple(facade, Default::default()),
+            scalar_override: Default::default(),
+            cull_mode: None,
+            double_sided: false,
+            depth_bias: None,
+            alpha_mode: AlphaMode::Opaque,
+            model: Matrix4::new_translation(&[0.0; 3].into()),
+        }
+    }
 
+    /// Loads the skinned variant of the shader
+    ///
+    /// Each vertex is moved by up to 4 bones, `weights` needs an entry for every vertex of the
+    /// buffer that is drawn. Until [`set_bone_matrices`](Self::set_bone_matrices) is called the
+    /// vertices stay in their bind pose. Skinned materials skip the depth pre-pass and don't cast
+    /// shadows.
+    pub fn load_skinned(facade: &impl Facade, weights: Rc<VertexBuffer<SkinWeights>>) -> Self {
         Self {
-            program,
+            program: Rc::new(compile_program(facade, VERTEX_SKINNED, None)),
+            wireframe_program: Rc::new(OnceCell::new()),
+            vertex_source: VERTEX_SKINNED,
+            hot_reload: None,
+            instances: None,
+            skin: Some(Skin {
+                weights,
+                bones: Vec::new(),
+            }),
             pbr_params: PBRTextures::from_simple(facade, Default::default()),
+            scalar_override: Default::default(),
+            cull_mode: None,
+            double_sided: false,
+            depth_bias: None,
+            alpha_mode: AlphaMode::Opaque,
             model: Matrix4::new_translation(&[0.0; 3].into()),
         }
     }
 
+    /// Sets the transforms of the bones from their bind pose to their current pose, only valid
+    /// on shaders created with [`load_skinned`](Self::load_skinned)
+    ///
+    /// Only the first [`MAX_BONES`] are used.
+    pub fn set_bone_matrices(&mut self, bones: &[Matrix4<f32>]) {
+        if let Some(skin) = self.skin.as_mut() {
+            skin.bones.clear();
+            skin.bones
+                .extend(bones.iter().map(|bone| Into::<[[f32; 4]; 4]>::into(*bone)));
+        }
+    }
+
+    pub fn get_skin_weights(&self) -> Option<&Rc<VertexBuffer<SkinWeights>>> {
+        self.skin.as_ref().map(|skin| &skin.weights)
+    }
+
+    /// Sets the per-instance buffer, only valid on shaders created with
+    /// [`load_instanced`](Self::load_instanced)
+    pub fn set_instances(&mut self, instances: Rc<VertexBuffer<Instance>>) {
+        self.instances = Some(instances);
+    }
+
+    pub fn get_instances(&self) -> Option<&Rc<VertexBuffer<Instance>>> {
+        self.instances.as_ref()
+    }
+
+    /// Loads the program from the given files instead of the ones included in the binary
+    ///
+    /// Use [`Shader::reload_if_changed`] to pick up changes to the files. The program is used
+    /// with and without the wireframe overlay, create it with
+    /// [`HotReloadProgram::with_geometry`] and `geometry.glsl` to see the overlay.
+    pub fn set_hot_reload(&mut self, hot_reload: HotReloadProgram) {
+        self.program = hot_reload.get_program().clone();
+        self.hot_reload = Some(hot_reload);
+    }
+
+    /// The program to draw with, the variant with the geometry shader is only compiled and used
+    /// while the [wireframe overlay](crate::renderer::SceneData::set_wireframe_overlay) is drawn
+    fn get_program(&self, wireframe_overlay: bool) -> &Program {
+        if !wireframe_overlay || self.hot_reload.is_some() {
+            return &self.program;
+        }
+
+        self.wireframe_program.get_or_init(|| {
+            compile_program(&self.pbr_params.facade, self.vertex_source, Some(GEOMETRY))
+        })
+    }
+
     pub fn set_pbr_params(&mut self, params: PBRTextures) {
         self.pbr_params = params;
     }
 
+    pub fn get_pbr_params(&self) -> &PBRTextures {
+        &self.pbr_params
+    }
+
     pub fn get_pbr_params_mut(&mut self) -> &mut PBRTextures {
         &mut self.pbr_params
     }
 
+    pub fn get_scalar_override(&self) -> &PBRScalarOverride {
+        &self.scalar_override
+    }
+    pub fn get_scalar_override_mut(&mut self) -> &mut PBRScalarOverride {
+        &mut self.scalar_override
+    }
+    pub fn set_scalar_override(&mut self, scalar_override: PBRScalarOverride) {
+        self.scalar_override = scalar_override;
+    }
+
+    pub fn get_cull_mode(&self) -> Option<BackfaceCullingMode> {
+        self.cull_mode
+    }
+    /// The faces this material doesn't draw, `None` by default to use the [scene's
+    /// culling](crate::renderer::SceneData::set_backface_culling)
+    ///
+    /// Switch between the culling modes when a model looks hollow or inside out, its winding
+    /// order is probably flipped.
+    pub fn set_cull_mode(&mut self, cull_mode: Option<BackfaceCullingMode>) {
+        self.cull_mode = cull_mode;
+    }
+
+    pub fn is_double_sided(&self) -> bool {
+        self.double_sided
+    }
+    /// Draws both sides of every face, ex. for thin surfaces like cloth or leaves, false by
+    /// default
+    ///
+    /// Nothing is culled while enabled, no matter the [cull mode](Self::set_cull_mode). The
+    /// normals of back faces are flipped so both sides are lit. Set for glTF materials marked
+    /// `doubleSided` when loading a [`Model`](crate::utils::model::Model).
+    pub fn set_double_sided(&mut self, double_sided: bool) {
+        self.double_sided = double_sided;
+    }
+
+    pub fn get_depth_bias(&self) -> Option<DepthBias> {
+        self.depth_bias
+    }
+    /// Pushes the surface toward (negative) or away from the camera in depth, `None` by default
+    ///
+    /// Give decals a small negative bias, ex. `DepthBias::new(-1.0, -1.0)`, so they don't z-fight
+    /// with the surface below them.
+    pub fn set_depth_bias(&mut self, depth_bias: Option<DepthBias>) {
+        self.depth_bias = depth_bias;
+    }
+
+    pub fn get_alpha_mode(&self) -> AlphaMode {
+        self.alpha_mode
+    }
+    /// How the alpha of the albedo map is used, [`AlphaMode::Opaque`] by default
+    ///
+    /// Masked and blended materials aren't drawn by the depth pre-pass and don't cast shadows
+    /// since their depth depends on the albedo map.
+    pub fn set_alpha_mode(&mut self, alpha_mode: AlphaMode) {
+        self.alpha_mode = alpha_mode;
+    }
+
     pub fn debug_ui(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Alpha");
+            ui.selectable_value(&mut self.alpha_mode, AlphaMode::Opaque, "opaque");
+            if ui
+                .selectable_label(matches!(self.alpha_mode, AlphaMode::Mask(_)), "mask")
+                .clicked()
+            {
+                self.alpha_mode = AlphaMode::Mask(AlphaMode::DEFAULT_CUTOFF);
+            }
+            ui.selectable_value(&mut self.alpha_mode, AlphaMode::Blend, "blend");
+        });
+        if let AlphaMode::Mask(cutoff) = &mut self.alpha_mode {
+            ui.add(egui::widgets::Slider::new(cutoff, 0.0..=1.0).text("alpha cutoff"));
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("Culling");
+            ui.selectable_value(&mut self.cull_mode, None, "scene");
+            ui.selectable_value(
+                &mut self.cull_mode,
+                Some(BackfaceCullingMode::CullingDisabled),
+                "none",
+            );
+            ui.selectable_value(
+                &mut self.cull_mode,
+                Some(BackfaceCullingMode::CullClockwise),
+                "cw",
+            );
+            ui.selectable_value(
+                &mut self.cull_mode,
+                Some(BackfaceCullingMode::CullCounterClockwise),
+                "ccw",
+            );
+        });
+        ui.checkbox(&mut self.double_sided, "double sided");
+
+        ui.label("Overrides");
+        self.scalar_override.debug_ui(ui);
+        ui.separator();
+
         self.pbr_params.debug_ui(ui);
     }
 }
@@ -211,10 +1060,14 @@ impl Shader for PBR {
         camera: [[f32; 4]; 4],
         position: [[f32; 4]; 4],
         scene_data: &crate::renderer::SceneData,
-    ) {
+    ) -> Result<(), glium::DrawError> {
         let model_matrix: [[f32; 4]; 4] = self.model.into();
 
-        let pbr_skybox = scene_data.get_scene_object::<PBRSkybox>().unwrap();
+        let pbr_skybox = match scene_data.get_scene_object::<PBRSkybox>() {
+            Some(pbr_skybox) => pbr_skybox,
+            // Reported by check_scene, so RenderScene::finish doesn't get here without one
+            None => return Ok(()),
+        };
 
         let irradiance_map = pbr_skybox
             .get_irradiance()
@@ -240,50 +1093,151 @@ impl Shader for PBR {
             .minify_filter(glium::uniforms::MinifySamplerFilter::Linear)
             .magnify_filter(glium::uniforms::MagnifySamplerFilter::Linear);
 
+        // A black light doesn't contribute anything, so it is the same as having no light
+        let (dir_light_direction, dir_light_color) = match scene_data.get_directional_light() {
+            Some(light) => (light.direction, light.color),
+            None => ([0.0, -1.0, 0.0], [0.0; 3]),
+        };
+        // No density is the same as no fog
+        let (fog_color, fog_density) = match scene_data.get_fog() {
+            Some(fog) => (fog.color, fog.density),
+            None => ([0.0; 3], 0.0),
+        };
+        let viewport_origin = match scene_data.get_viewport() {
+            Some(rect) => [rect.left as f32, rect.bottom as f32],
+            None => [0.0; 2],
+        };
+
+        let (wireframe_color, wireframe_thickness) = match scene_data.get_wireframe_overlay() {
+            Some(overlay) => (overlay.color, overlay.thickness),
+            None => ([0.0; 3], 0.0),
+        };
+
         let uniforms = uniform! {
             projection: camera,
             view: position,
             model: model_matrix,
-            albedo_map: &*self.pbr_params.albedo,
-            metallic_map: &*self.pbr_params.metallic,
-            roughness_map: &*self.pbr_params.roughness,
-            ao_map: &*self.pbr_params.ao,
-            normal_map: &*self.pbr_params.normal,
-            lightPositions: [10.0f32, 10.0, 3.0],
-            lightColors: [1500.0f32;3],
+            albedo_map: self.pbr_params.sample(&self.pbr_params.albedo),
+            metallic_map: self.pbr_params.sample(&self.pbr_params.metallic),
+            roughness_map: self.pbr_params.sample(&self.pbr_params.roughness),
+            ao_map: self.pbr_params.sample(&self.pbr_params.ao),
+            normal_map: self.pbr_params.sample(&self.pbr_params.normal),
+            emissive_map: self.pbr_params.sample(&self.pbr_params.emissive),
+            alpha: self.pbr_params.alpha,
+            alphaMode: self.alpha_mode.as_uniform(),
+            alphaCutoff: match self.alpha_mode {
+                AlphaMode::Mask(cutoff) => cutoff,
+                _ => 0.0,
+            },
+            useAlbedoValue: self.scalar_override.albedo.is_some(),
+            albedoValue: self.scalar_override.albedo.unwrap_or_default(),
+            useMetallicValue: self.scalar_override.metallic.is_some(),
+            metallicValue: self.scalar_override.metallic.unwrap_or_default(),
+            useRoughnessValue: self.scalar_override.roughness.is_some(),
+            roughnessValue: self.scalar_override.roughness.unwrap_or_default(),
+            useAoValue: self.scalar_override.ao.is_some(),
+            aoValue: self.scalar_override.ao.unwrap_or_default(),
+            useEmissiveValue: self.scalar_override.emissive.is_some(),
+            emissiveValue: self.scalar_override.emissive.unwrap_or_default(),
+            // Every sampler needs a texture, the metallic map is never read when useOrmMap is false
+            orm_map: self.pbr_params.sample(self.pbr_params.orm.as_ref().unwrap_or(&self.pbr_params.metallic)),
+            useOrmMap: self.pbr_params.orm.is_some(),
+            // Same as the orm map, never read when useHeightMap is false
+            height_map: self.pbr_params.sample(self.pbr_params.height.as_ref().unwrap_or(&self.pbr_params.metallic)),
+            useHeightMap: self.pbr_params.height.is_some(),
+            heightScale: self.pbr_params.height_scale,
+            ormOcclusion: self.pbr_params.orm_occlusion,
+            aoUv2: self.pbr_params.ao_uv2,
+            doubleSided: self.double_sided,
+            exposure: scene_data.get_exposure(),
+            toneMapping: scene_data.get_tone_mapping() as i32,
+            dirLightDirection: dir_light_direction,
+            dirLightColor: dir_light_color,
+            fogColor: fog_color,
+            fogDensity: fog_density,
+            wireframeOverlay: scene_data.get_wireframe_overlay().is_some(),
+            wireframeColor: wireframe_color,
+            wireframeThickness: wireframe_thickness,
+            viewportOrigin: viewport_origin,
             camPos: Into::<[f32; 3]>::into(scene_data.camera.position),
             irradiance_map: irradiance_map,
             prefilter_map: prefilter_map,
             brdfLUT: brdf_lut,
+            maxReflectionLod: (pbr_skybox.get_prefilter().get_mipmap_levels() - 1) as f32,
+            prefilterLodBias: scene_data.get_prefilter_lod_bias(),
+            iblIntensity: scene_data.get_ibl_intensity(),
+            environmentRotation: Into::<[[f32; 3]; 3]>::into(pbr_skybox.get_environment_rotation()),
+        };
+        let uniforms = scene_data.get_lights().with_uniforms(uniforms);
+        let uniforms =
+            ShadowMap::with_uniforms(scene_data.get_shadow_map(), pbr_skybox.get_brdf(), uniforms);
+        // Entries that aren't in the pre-pass aren't in the occlusion either, so they would pick
+        // up the occlusion of whatever is behind them
+        let ssao = scene_data
+            .get_ssao()
+            .filter(|_| self.supports_depth_prepass());
+        let uniforms = Ssao::with_uniforms(ssao, pbr_skybox.get_brdf(), uniforms);
+        let uniforms = BoneUniforms {
+            bones: self.skin.as_ref().map_or(&[], |skin| &skin.bones[..]),
+            uniforms,
         };
 
-        surface
-            .draw(
+        let draw_parameters = scene_data.apply_draw_parameters(DrawParameters {
+            // The depth is already there after the pre-pass, only the closest surface matches it.
+            // IfLessOrEqual instead of IfEqual is just to be safe, nothing can be closer.
+            depth: match scene_data.get_depth_prepass() && self.supports_depth_prepass() {
+                true => glium::Depth {
+                    test: glium::DepthTest::IfLessOrEqual,
+                    write: false,
+                    ..Default::default()
+                },
+                false => glium::Depth {
+                    test: glium::DepthTest::IfLess,
+                    write: !self.is_transparent(),
+                    ..Default::default()
+                },
+            },
+            blend: Blend {
+                color: glium::BlendingFunction::Addition {
+                    source: glium::LinearBlendingFactor::SourceAlpha,
+                    destination: glium::LinearBlendingFactor::OneMinusSourceAlpha,
+                },
+                alpha: glium::BlendingFunction::Addition {
+                    source: glium::LinearBlendingFactor::One,
+                    destination: glium::LinearBlendingFactor::Zero,
+                },
+                ..Default::default()
+            },
+            backface_culling: self.get_backface_culling(scene_data),
+            polygon_offset: DepthBias::to_polygon_offset(self.depth_bias),
+            ..Default::default()
+        });
+
+        let program = self.get_program(scene_data.get_wireframe_overlay().is_some());
+
+        match (&self.instances, &self.skin) {
+            (Some(instances), _) => surface.draw(
+                (vertex_buffer, instances.per_instance().unwrap()),
+                index_buffer,
+                program,
+                &uniforms,
+                &draw_parameters,
+            ),
+            (None, Some(skin)) => surface.draw(
+                (vertex_buffer, &*skin.weights),
+                index_buffer,
+                program,
+                &uniforms,
+                &draw_parameters,
+            ),
+            (None, None) => surface.draw(
                 vertex_buffer,
                 index_buffer,
-                &self.program,
+                program,
                 &uniforms,
-                &DrawParameters {
-                    depth: glium::Depth {
-                        test: glium::DepthTest::IfLess,
-                        write: true,
-                        ..Default::default()
-                    },
-                    blend: Blend {
-                        color: glium::BlendingFunction::Addition {
-                            source: glium::LinearBlendingFactor::SourceAlpha,
-                            destination: glium::LinearBlendingFactor::OneMinusSourceAlpha,
-                        },
-                        alpha: glium::BlendingFunction::Addition {
-                            source: glium::LinearBlendingFactor::One,
-                            destination: glium::LinearBlendingFactor::Zero,
-                        },
-                        ..Default::default()
-                    },
-                    ..Default::default()
-                },
-            )
-            .unwrap();
+                &draw_parameters,
+            ),
+        }
     }
 
     fn get_model_mat(&self) -> Matrix4<f32> {
@@ -294,8 +1248,36 @@ impl Shader for PBR {
         self.model = model;
     }
 
-    fn equal_shader(&self, _shader: &dyn std::any::Any) -> bool {
-        false
+    /// Equal when both have [equal textures](PBRTextures) and draw them the same way
+    ///
+    /// Instanced materials are only equal when they share their instance buffer. Skinned
+    /// materials are never equal, since their bones change every frame, and neither are
+    /// materials with [hot reloaded](Self::set_hot_reload) shaders, since each has its own
+    /// program.
+    fn equal_shader(&self, shader: &dyn std::any::Any) -> bool {
+        let other = match shader.downcast_ref::<Self>() {
+            Some(other) => other,
+            None => return false,
+        };
+
+        let same_instances = match (&self.instances, &other.instances) {
+            (Some(a), Some(b)) => Rc::ptr_eq(a, b),
+            (None, None) => true,
+            _ => false,
+        };
+
+        // Each material compiles its own program, the variant follows from the instances and skin
+        same_instances
+            && self.skin.is_none()
+            && other.skin.is_none()
+            && self.hot_reload.is_none()
+            && other.hot_reload.is_none()
+            && self.pbr_params == other.pbr_params
+            && self.scalar_override == other.scalar_override
+            && self.cull_mode == other.cull_mode
+            && self.double_sided == other.double_sided
+            && self.depth_bias == other.depth_bias
+            && self.alpha_mode == other.alpha_mode
     }
 
     fn to_any(self) -> Box<dyn Any> {
@@ -314,4 +1296,64 @@ impl Shader for PBR {
     fn clone_sized(&self) -> Self {
         self.clone()
     }
+
+    fn get_instance_count(&self) -> usize {
+        match &self.instances {
+            Some(instances) => instances.len(),
+            None => 1,
+        }
+    }
+
+    fn is_transparent(&self) -> bool {
+        self.alpha_mode == AlphaMode::Blend || self.pbr_params.alpha < 1.0
+    }
+
+    fn get_backface_culling(&self, scene_data: &crate::renderer::SceneData) -> BackfaceCullingMode {
+        if self.double_sided {
+            return BackfaceCullingMode::CullingDisabled;
+        }
+
+        self.cull_mode
+            .unwrap_or_else(|| scene_data.get_backface_culling())
+    }
+
+    fn get_depth_bias(&self) -> Option<DepthBias> {
+        self.depth_bias
+    }
+
+    fn supports_depth_prepass(&self) -> bool {
+        // The pre-pass doesn't apply the per-instance transforms or bones, or discard masked
+        // fragments
+        !self.is_transparent()
+            && self.instances.is_none()
+            && self.skin.is_none()
+            && self.alpha_mode == AlphaMode::Opaque
+    }
+
+    fn casts_shadows(&self) -> bool {
+        // The shadow map doesn't apply the per-instance transforms or bones either, and would
+        // give masked fragments the shadow of a solid quad
+        !self.is_transparent()
+            && self.instances.is_none()
+            && self.skin.is_none()
+            && self.alpha_mode == AlphaMode::Opaque
+    }
+
+    fn check_scene(&self, scene_data: &crate::renderer::SceneData) -> Result<(), RendererError> {
+        PBRSkybox::require(scene_data, self.debug_name()).map(|_| ())
+    }
+
+    fn reload_if_changed(&mut self, facade: &Rc<Context>) -> bool {
+        let hot_reload = match self.hot_reload.as_mut() {
+            Some(hot_reload) => hot_reload,
+            None => return false,
+        };
+
+        if !hot_reload.reload_if_changed(facade) {
+            return false;
+        }
+
+        self.program = hot_reload.get_program().clone();
+        true
+    }
 }
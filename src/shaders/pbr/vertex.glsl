@@ -2,20 +2,41 @@
 in vec3 position;
 in vec3 normal;
 in vec2 tex_coords;
+in vec2 tex_coords2;
+in vec4 tangent;
+in vec4 color;
+in float ao;
 
-out vec2 TexCoords;
-out vec3 WorldPos;
-out vec3 Normal;
+// passed through the geometry shader while the wireframe overlay is drawn, see geometry.glsl
+out VertexData {
+    vec2 TexCoords;
+    vec2 TexCoords2;
+    vec3 WorldPos;
+    vec3 Normal;
+    vec4 Tangent;
+    vec4 AlbedoTint;
+    float VertexAo;
+};
+// no edges, geometry.glsl replaces it when the wireframe overlay is drawn
+noperspective out vec3 Barycentric;
 
 uniform mat4 projection;
 uniform mat4 view;
 uniform mat4 model;
 
+// has to match the depth pre-pass exactly
+invariant gl_Position;
+
 void main()
 {
     TexCoords = tex_coords;
+    TexCoords2 = tex_coords2;
     WorldPos = vec3(model * vec4(position, 1.0));
     Normal = mat3(model) * normal;   
+    Tangent = vec4(mat3(model) * tangent.xyz, tangent.w);
+    AlbedoTint = color;
+    VertexAo = ao;
+    Barycentric = vec3(1.0);
 
     gl_Position =  projection * view * vec4(WorldPos, 1.0);
 }
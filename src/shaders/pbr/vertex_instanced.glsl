@@ -0,0 +1,45 @@
+#version 330 core
+in vec3 position;
+in vec3 normal;
+in vec2 tex_coords;
+in vec2 tex_coords2;
+in vec4 tangent;
+in vec4 color;
+in float ao;
+
+// per instance
+in mat4 instance_model;
+in vec3 instance_albedo;
+
+// passed through the geometry shader while the wireframe overlay is drawn, see geometry.glsl
+out VertexData {
+    vec2 TexCoords;
+    vec2 TexCoords2;
+    vec3 WorldPos;
+    vec3 Normal;
+    vec4 Tangent;
+    vec4 AlbedoTint;
+    float VertexAo;
+};
+// no edges, geometry.glsl replaces it when the wireframe overlay is drawn
+noperspective out vec3 Barycentric;
+
+uniform mat4 projection;
+uniform mat4 view;
+uniform mat4 model;
+
+void main()
+{
+    mat4 world = model * instance_model;
+
+    TexCoords = tex_coords;
+    TexCoords2 = tex_coords2;
+    WorldPos = vec3(world * vec4(position, 1.0));
+    Normal = mat3(world) * normal;   
+    Tangent = vec4(mat3(world) * tangent.xyz, tangent.w);
+    AlbedoTint = vec4(instance_albedo, 1.0) * color;
+    VertexAo = ao;
+    Barycentric = vec3(1.0);
+
+    gl_Position =  projection * view * vec4(WorldPos, 1.0);
+}
@@ -0,0 +1,66 @@
+#version 330 core
+in vec3 position;
+in vec3 normal;
+in vec2 tex_coords;
+in vec2 tex_coords2;
+in vec4 tangent;
+in vec4 color;
+in float ao;
+
+// per vertex, see vertex::SkinWeights
+in ivec4 bone_ids;
+in vec4 bone_weights;
+
+// passed through the geometry shader while the wireframe overlay is drawn, see geometry.glsl
+out VertexData {
+    vec2 TexCoords;
+    vec2 TexCoords2;
+    vec3 WorldPos;
+    vec3 Normal;
+    vec4 Tangent;
+    vec4 AlbedoTint;
+    float VertexAo;
+};
+// no edges, geometry.glsl replaces it when the wireframe overlay is drawn
+noperspective out vec3 Barycentric;
+
+uniform mat4 projection;
+uniform mat4 view;
+uniform mat4 model;
+
+// has to match shaders::pbr::MAX_BONES
+#define MAX_BONES 60
+uniform mat4 bones[MAX_BONES];
+// bones at or past this haven't been set yet and leave the vertex in its bind pose
+uniform int boneCount;
+
+void main()
+{
+    mat4 skin = mat4(0.0);
+    float totalWeight = 0.0;
+    for (int i = 0; i < 4; i++)
+    {
+        if (bone_ids[i] < 0 || bone_ids[i] >= MAX_BONES)
+            continue;
+
+        mat4 bone = bone_ids[i] < boneCount ? bones[bone_ids[i]] : mat4(1.0);
+        skin += bone * bone_weights[i];
+        totalWeight += bone_weights[i];
+    }
+    // vertices without bones stay where they are
+    if (totalWeight == 0.0)
+        skin = mat4(1.0);
+
+    mat4 world = model * skin;
+
+    TexCoords = tex_coords;
+    TexCoords2 = tex_coords2;
+    WorldPos = vec3(world * vec4(position, 1.0));
+    Normal = mat3(world) * normal;
+    Tangent = vec4(mat3(world) * tangent.xyz, tangent.w);
+    AlbedoTint = color;
+    VertexAo = ao;
+    Barycentric = vec3(1.0);
+
+    gl_Position =  projection * view * vec4(WorldPos, 1.0);
+}
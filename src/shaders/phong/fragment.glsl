@@ -0,0 +1,108 @@
+#version 330 core
+out vec4 FragColor;
+
+in vec3 WorldPos;
+in vec3 Normal;
+in vec4 Tint;
+
+uniform vec3 ambient;
+uniform vec3 diffuse;
+uniform vec3 specular;
+uniform float shininess;
+
+// lights, MAX_LIGHTS has to match utils::lights::MAX_LIGHTS
+#define MAX_LIGHTS 16
+uniform vec3 lightPositions[MAX_LIGHTS];
+uniform vec3 lightColors[MAX_LIGHTS];
+// constant, linear, and quadratic attenuation
+uniform vec3 lightAttenuations[MAX_LIGHTS];
+// 0 means the light reaches everything
+uniform float lightRanges[MAX_LIGHTS];
+uniform int numLights;
+
+// the color is black when there is no directional light
+uniform vec3 dirLightDirection;
+uniform vec3 dirLightColor;
+
+uniform vec3 camPos;
+
+// exponential distance fog, fogDensity is 0 when there is no fog
+uniform vec3 fogColor;
+uniform float fogDensity;
+
+// tone mapping, toneMapping has to match renderer::ToneMapping
+uniform float exposure;
+uniform int toneMapping;
+
+// Narkowicz's fit of the ACES filmic curve
+vec3 ACESFilm(vec3 x)
+{
+    const float a = 2.51;
+    const float b = 0.03;
+    const float c = 2.43;
+    const float d = 0.59;
+    const float e = 0.14;
+    return clamp((x * (a * x + b)) / (x * (c * x + d) + e), 0.0, 1.0);
+}
+
+vec3 toneMap(vec3 color)
+{
+    color *= exposure;
+
+    if (toneMapping == 1)
+        return color / (color + vec3(1.0));
+    if (toneMapping == 2)
+        return ACESFilm(color);
+
+    return color;
+}
+
+// diffuse and specular of a single light, radiance already includes the attenuation
+vec3 calculateLight(vec3 N, vec3 V, vec3 L, vec3 radiance, vec3 diffuseColor)
+{
+    float diff = max(dot(N, L), 0.0);
+    // no highlight on the side facing away from the light
+    float spec = diff > 0.0 ? pow(max(dot(V, reflect(-L, N)), 0.0), shininess) : 0.0;
+
+    return (diffuseColor * diff + specular * spec) * radiance;
+}
+
+void main()
+{
+    vec3 N = normalize(Normal);
+    vec3 V = normalize(camPos - WorldPos);
+    vec3 diffuseColor = diffuse * Tint.rgb;
+
+    vec3 color = ambient * diffuseColor;
+
+    for (int i = 0; i < numLights && i < MAX_LIGHTS; ++i)
+    {
+        float distance = length(lightPositions[i] - WorldPos);
+        float range = lightRanges[i];
+        if (range > 0.0 && distance >= range)
+            continue;
+
+        vec3 L = normalize(lightPositions[i] - WorldPos);
+        vec3 coefficients = lightAttenuations[i];
+        float attenuation = 1.0 / max(coefficients.x + coefficients.y * distance + coefficients.z * distance * distance, 0.0001);
+
+        // same falloff as pbr so the cutoff isn't visible
+        if (range > 0.0)
+        {
+            float falloff = clamp(1.0 - pow(distance / range, 4.0), 0.0, 1.0);
+            attenuation *= falloff * falloff;
+        }
+
+        color += calculateLight(N, V, L, lightColors[i] * attenuation, diffuseColor);
+    }
+
+    color += calculateLight(N, V, normalize(-dirLightDirection), dirLightColor, diffuseColor);
+
+    // gamma correction is done by the srgb framebuffer
+    color = toneMap(color);
+
+    float fogAmount = 1.0 - exp(-fogDensity * length(camPos - WorldPos));
+    color = mix(color, fogColor, fogAmount);
+
+    FragColor = vec4(color, 1.0);
+}
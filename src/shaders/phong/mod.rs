@@ -0,0 +1,214 @@
+use crate::insert_program;
+use crate::renderer::DepthBias;
+use crate::shader::Shader;
+use egui::Ui;
+use glium::backend::Facade;
+use glium::draw_parameters::BackfaceCullingMode;
+use glium::DrawParameters;
+use glium::Program;
+use nalgebra::Matrix4;
+use serde::{Deserialize, Serialize};
+use std::any::Any;
+use std::rc::Rc;
+
+/// The colors of a [`Phong`] material
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PhongParams {
+    /// Multiplied with the diffuse color, lights the sides facing away from every light
+    pub ambient: [f32; 3],
+    /// Multiplied with the vertex colors
+    pub diffuse: [f32; 3],
+    pub specular: [f32; 3],
+    /// Higher values give smaller, sharper highlights
+    pub shininess: f32,
+}
+
+impl Default for PhongParams {
+    fn default() -> Self {
+        Self {
+            ambient: [0.05; 3],
+            diffuse: [0.8; 3],
+            specular: [0.5; 3],
+            shininess: 32.0,
+        }
+    }
+}
+
+/// Classic ambient, diffuse, and specular lighting, for stylized scenes that don't need [`PBR`]
+///
+/// Lit by the point lights and directional light of the scene with the same attenuation as
+/// [`PBR`], and tone mapped and fogged the same way so both can be mixed in a scene. The
+/// environment isn't used, the ambient color takes its place.
+///
+/// [`PBR`]: crate::shaders::pbr::PBR
+#[derive(Clone)]
+pub struct Phong {
+    program: Rc<Program>,
+    params: PhongParams,
+    depth_bias: Option<DepthBias>,
+    model: Matrix4<f32>,
+}
+
+impl Phong {
+    pub fn load_from_fs(facade: &impl Facade) -> Self {
+        let program = Rc::new(insert_program!("./vertex.glsl", "./fragment.glsl", facade));
+
+        Self {
+            program,
+            params: PhongParams::default(),
+            depth_bias: None,
+            model: Matrix4::new_translation(&[0.0; 3].into()),
+        }
+    }
+
+    pub fn get_params(&self) -> &PhongParams {
+        &self.params
+    }
+    pub fn get_params_mut(&mut self) -> &mut PhongParams {
+        &mut self.params
+    }
+    pub fn set_params(&mut self, params: PhongParams) {
+        self.params = params;
+    }
+
+    pub fn get_depth_bias(&self) -> Option<DepthBias> {
+        self.depth_bias
+    }
+    /// Pushes the surface toward (negative) or away from the camera in depth, `None` by default
+    pub fn set_depth_bias(&mut self, depth_bias: Option<DepthBias>) {
+        self.depth_bias = depth_bias;
+    }
+
+    pub fn debug_ui(&mut self, ui: &mut Ui) {
+        let colors = [
+            ("ambient", &mut self.params.ambient),
+            ("diffuse", &mut self.params.diffuse),
+            ("specular", &mut self.params.specular),
+        ];
+        for (label, color) in colors {
+            ui.horizontal(|ui| {
+                egui::widgets::color_picker::color_edit_button_rgb(ui, color);
+                ui.label(label);
+            });
+        }
+
+        ui.add(
+            egui::widgets::Slider::new(&mut self.params.shininess, 1.0..=256.0).text("shininess"),
+        );
+    }
+}
+
+impl Shader for Phong {
+    fn render<'a>(
+        &self,
+        vertex_buffer: glium::vertex::VerticesSource<'a>,
+        index_buffer: glium::index::IndicesSource<'a>,
+        surface: &mut crate::renderer::Renderable,
+        camera: [[f32; 4]; 4],
+        position: [[f32; 4]; 4],
+        scene_data: &crate::renderer::SceneData,
+    ) -> Result<(), glium::DrawError> {
+        let model_matrix: [[f32; 4]; 4] = self.model.into();
+
+        // A black light doesn't contribute anything, so it is the same as having no light
+        let (dir_light_direction, dir_light_color) = match scene_data.get_directional_light() {
+            Some(light) => (light.direction, light.color),
+            None => ([0.0, -1.0, 0.0], [0.0; 3]),
+        };
+        // No density is the same as no fog
+        let (fog_color, fog_density) = match scene_data.get_fog() {
+            Some(fog) => (fog.color, fog.density),
+            None => ([0.0; 3], 0.0),
+        };
+
+        let uniforms = uniform! {
+            projection: camera,
+            view: position,
+            model: model_matrix,
+            ambient: self.params.ambient,
+            diffuse: self.params.diffuse,
+            specular: self.params.specular,
+            shininess: self.params.shininess,
+            dirLightDirection: dir_light_direction,
+            dirLightColor: dir_light_color,
+            fogColor: fog_color,
+            fogDensity: fog_density,
+            exposure: scene_data.get_exposure(),
+            toneMapping: scene_data.get_tone_mapping() as i32,
+            camPos: Into::<[f32; 3]>::into(scene_data.camera.position),
+        };
+        let uniforms = scene_data.get_lights().with_uniforms(uniforms);
+
+        let draw_parameters = scene_data.apply_draw_parameters(DrawParameters {
+            // Same as pbr, the closest surface already has its depth after the pre-pass
+            depth: match scene_data.get_depth_prepass() {
+                true => glium::Depth {
+                    test: glium::DepthTest::IfLessOrEqual,
+                    write: false,
+                    ..Default::default()
+                },
+                false => glium::Depth {
+                    test: glium::DepthTest::IfLess,
+                    write: true,
+                    ..Default::default()
+                },
+            },
+            backface_culling: self.get_backface_culling(scene_data),
+            polygon_offset: DepthBias::to_polygon_offset(self.depth_bias),
+            ..Default::default()
+        });
+
+        surface.draw(
+            vertex_buffer,
+            index_buffer,
+            &self.program,
+            &uniforms,
+            &draw_parameters,
+        )
+    }
+
+    fn get_model_mat(&self) -> Matrix4<f32> {
+        self.model
+    }
+
+    fn set_model_mat(&mut self, model: Matrix4<f32>) {
+        self.model = model;
+    }
+
+    /// Equal when both have the same colors and depth bias
+    fn equal_shader(&self, shader: &dyn std::any::Any) -> bool {
+        match shader.downcast_ref::<Self>() {
+            Some(other) => self.params == other.params && self.depth_bias == other.depth_bias,
+            None => false,
+        }
+    }
+
+    fn to_any(self) -> Box<dyn Any> {
+        Box::new(self)
+    }
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn clone_shader(&self) -> Box<dyn Shader> {
+        Box::new(self.clone())
+    }
+    fn clone_sized(&self) -> Self {
+        self.clone()
+    }
+
+    fn get_backface_culling(&self, scene_data: &crate::renderer::SceneData) -> BackfaceCullingMode {
+        scene_data.get_backface_culling()
+    }
+
+    fn get_depth_bias(&self) -> Option<DepthBias> {
+        self.depth_bias
+    }
+
+    fn supports_depth_prepass(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,24 @@
+#version 330 core
+in vec3 position;
+in vec3 normal;
+in vec4 color;
+
+out vec3 WorldPos;
+out vec3 Normal;
+out vec4 Tint;
+
+uniform mat4 projection;
+uniform mat4 view;
+uniform mat4 model;
+
+// has to match the depth pre-pass exactly
+invariant gl_Position;
+
+void main()
+{
+    WorldPos = vec3(model * vec4(position, 1.0));
+    Normal = mat3(model) * normal;
+    Tint = color;
+
+    gl_Position = projection * view * vec4(WorldPos, 1.0);
+}
@@ -1,4 +1,6 @@
+use crate::error::RendererError;
 use crate::insert_program;
+use crate::utils::cubemap_render::{camera_directions, CUBE_LAYERS};
 use crate::utils::shapes;
 use glium::backend::Facade;
 use glium::framebuffer::SimpleFrameBuffer;
@@ -22,23 +24,38 @@ impl Prefilter {
         Self { program }
     }
     pub fn compute(&self, facade: &impl Facade, env_map: &Cubemap) -> Cubemap {
-        let resolution = 128;
+        self.compute_with_resolution(facade, env_map, 128, 4)
+            .unwrap()
+    }
+
+    /// Same as [`compute`](Self::compute) with a custom face size and number of mipmaps
+    ///
+    /// Each mipmap stores the reflections for a higher roughness, from 0 at the main level to 1
+    /// at the last mipmap. `mipmaps` doesn't count the main level and has to be at least 1. The
+    /// defaults are a resolution of 128 with 4 mipmaps.
+    ///
+    /// The resolution has to be a power of two that can be halved `mipmaps` times, otherwise
+    /// OpenGL would silently create fewer mipmaps than requested.
+    pub fn compute_with_resolution(
+        &self,
+        facade: &impl Facade,
+        env_map: &Cubemap,
+        resolution: u32,
+        mipmaps: u32,
+    ) -> Result<Cubemap, RendererError> {
+        if mipmaps == 0 || !resolution.is_power_of_two() || resolution.trailing_zeros() < mipmaps {
+            return Err(RendererError::InvalidResolution {
+                resolution,
+                mipmaps,
+            });
+        }
+
         let cubemap = Cubemap::empty_with_format(
             facade,
             glium::texture::UncompressedFloatFormat::F16F16F16,
-            glium::texture::MipmapsOption::EmptyMipmapsMax(4),
+            glium::texture::MipmapsOption::EmptyMipmapsMax(mipmaps),
             resolution,
-        )
-        .unwrap();
-
-        let layers = [
-            glium::texture::CubeLayer::PositiveX,
-            glium::texture::CubeLayer::NegativeX,
-            glium::texture::CubeLayer::PositiveY,
-            glium::texture::CubeLayer::NegativeY,
-            glium::texture::CubeLayer::PositiveZ,
-            glium::texture::CubeLayer::NegativeZ,
-        ];
+        )?;
 
         let vb = VertexBuffer::new(facade, &shapes::get_cube()).unwrap();
         let ib = IndexBuffer::new(
@@ -50,7 +67,7 @@ impl Prefilter {
 
         let perspective = Matrix4::new_perspective(1.0, 90.0f32.to_radians(), 0.1, 10.0);
 
-        for (layer_id, camera_dir) in layers.into_iter().zip(Self::camera_directions()) {
+        for (layer_id, camera_dir) in CUBE_LAYERS.into_iter().zip(camera_directions()) {
             let uniforms = uniform! {
                 view: Into::<[[f32; 4]; 4]>::into(camera_dir),
                 projection: Into::<[[f32; 4]; 4]>::into(perspective),
@@ -79,40 +96,6 @@ impl Prefilter {
             }
         }
 
-        cubemap
-    }
-    fn camera_directions() -> [Matrix4<f32>; 6] {
-        [
-            Matrix4::look_at_rh(
-                &[0.0, 0.0, 0.0].into(),
-                &[1.0, 0.0, 0.0].into(),
-                &[0.0, -1.0, 0.0].into(),
-            ),
-            Matrix4::look_at_rh(
-                &[0.0, 0.0, 0.0].into(),
-                &[-1.0, 0.0, 0.0].into(),
-                &[0.0, -1.0, 0.0].into(),
-            ),
-            Matrix4::look_at_rh(
-                &[0.0, 0.0, 0.0].into(),
-                &[0.0, 1.0, 0.0].into(),
-                &[0.0, 0.0, 1.0].into(),
-            ),
-            Matrix4::look_at_rh(
-                &[0.0, 0.0, 0.0].into(),
-                &[0.0, -1.0, 0.0].into(),
-                &[0.0, 0.0, -1.0].into(),
-            ),
-            Matrix4::look_at_rh(
-                &[0.0, 0.0, 0.0].into(),
-                &[0.0, 0.0, 1.0].into(),
-                &[0.0, -1.0, 0.0].into(),
-            ),
-            Matrix4::look_at_rh(
-                &[0.0, 0.0, 0.0].into(),
-                &[0.0, 0.0, -1.0].into(),
-                &[0.0, -1.0, 0.0].into(),
-            ),
-        ]
+        Ok(cubemap)
     }
 }
@@ -0,0 +1,64 @@
+use crate::insert_program;
+use glium::backend::Facade;
+use glium::framebuffer::SimpleFrameBuffer;
+use glium::index::IndicesSource;
+use glium::texture::DepthTexture2d;
+use glium::vertex::VerticesSource;
+use glium::DrawParameters;
+use glium::Program;
+use glium::Surface;
+use nalgebra::Matrix4;
+use std::rc::Rc;
+
+/// Renders geometry to a depth texture, used for shadow maps
+#[derive(Clone)]
+pub struct ShadowDepth {
+    program: Rc<Program>,
+}
+
+impl ShadowDepth {
+    pub fn load_from_fs(facade: &impl Facade) -> Self {
+        let program = Rc::new(insert_program!("./vertex.glsl", "./fragment.glsl", facade));
+
+        Self { program }
+    }
+
+    /// Clears `depth` and renders every `(vertices, indices, model matrix)` in `casters` to it
+    pub fn compute<'a>(
+        &self,
+        facade: &impl Facade,
+        depth: &DepthTexture2d,
+        light_space_matrix: Matrix4<f32>,
+        casters: impl IntoIterator<Item = (VerticesSource<'a>, IndicesSource<'a>, Matrix4<f32>)>,
+    ) {
+        let mut fb = SimpleFrameBuffer::depth_only(facade, depth).unwrap();
+        fb.clear_depth(1.0);
+
+        let light_space_matrix: [[f32; 4]; 4] = light_space_matrix.into();
+
+        for (vertices, indices, model) in casters {
+            let model: [[f32; 4]; 4] = model.into();
+
+            let uniforms = uniform! {
+                lightSpaceMatrix: light_space_matrix,
+                model: model,
+            };
+
+            fb.draw(
+                vertices,
+                indices,
+                &self.program,
+                &uniforms,
+                &DrawParameters {
+                    depth: glium::Depth {
+                        test: glium::DepthTest::IfLess,
+                        write: true,
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        }
+    }
+}
@@ -2,15 +2,58 @@
 out vec4 FragColor;
 
 in vec3 localPos;
+in vec4 clipPos;
   
 uniform samplerCube environmentMap;
+// turns the world space direction into the direction in the cubemap
+uniform mat3 environmentRotation;
+uniform vec3 tint;
+
+// backgroundMode has to match the Background variant drawn by the Skybox material,
+// 0 is the environment map and 1 blends from backgroundBottom to backgroundTop
+uniform int backgroundMode;
+uniform vec3 backgroundTop;
+uniform vec3 backgroundBottom;
+
+// tone mapping, toneMapping has to match renderer::ToneMapping
+uniform float exposure;
+uniform int toneMapping;
+
+// Narkowicz's fit of the ACES filmic curve
+vec3 ACESFilm(vec3 x)
+{
+    const float a = 2.51;
+    const float b = 0.03;
+    const float c = 2.43;
+    const float d = 0.59;
+    const float e = 0.14;
+    return clamp((x * (a * x + b)) / (x * (c * x + d) + e), 0.0, 1.0);
+}
+
+vec3 toneMap(vec3 color)
+{
+    color *= exposure;
+
+    if (toneMapping == 1)
+        return color / (color + vec3(1.0));
+    if (toneMapping == 2)
+        return ACESFilm(color);
+
+    return color;
+}
   
 void main()
 {
-    vec3 envColor = texture(environmentMap, localPos).rgb;
+    if (backgroundMode == 1)
+    {
+        float height = clamp(clipPos.y / clipPos.w * 0.5 + 0.5, 0.0, 1.0);
+        FragColor = vec4(mix(backgroundBottom, backgroundTop, height), 1.0);
+        return;
+    }
+
+    vec3 envColor = texture(environmentMap, environmentRotation * localPos).rgb * tint;
     
-    //envColor = envColor / (envColor + vec3(1.0));
-    //envColor = pow(envColor, vec3(1.0/2.2)); 
+    envColor = toneMap(envColor);
   
     FragColor = vec4(envColor, 1.0);
 }
@@ -1,7 +1,10 @@
+use crate::error::RendererError;
 use crate::insert_program;
+use crate::renderer::Background;
 use crate::shader::Shader;
 use crate::utils::pbr_skybox::PBRSkybox;
 use glium::backend::Facade;
+use glium::uniforms::Uniforms;
 use glium::DrawParameters;
 use glium::Program;
 use nalgebra::Matrix4;
@@ -21,7 +24,35 @@ impl Skybox {
     }
 }
 
+impl Skybox {
+    fn draw<'a>(
+        &self,
+        vertex_buffer: glium::vertex::VerticesSource<'a>,
+        index_buffer: glium::index::IndicesSource<'a>,
+        surface: &mut crate::renderer::Renderable,
+        uniforms: &impl Uniforms,
+        scene_data: &crate::renderer::SceneData,
+    ) -> Result<(), glium::DrawError> {
+        surface.draw(
+            vertex_buffer,
+            index_buffer,
+            &self.program,
+            uniforms,
+            &DrawParameters {
+                depth: glium::Depth {
+                    test: glium::DepthTest::IfLessOrEqual,
+                    write: true,
+                    ..Default::default()
+                },
+                viewport: scene_data.get_viewport(),
+                ..Default::default()
+            },
+        )
+    }
+}
+
 impl Shader for Skybox {
+    /// Draws the scene's [`Background`], the environment map of the [`PBRSkybox`] by default
     fn render<'a>(
         &self,
         vertex_buffer: glium::vertex::VerticesSource<'a>,
@@ -30,34 +61,44 @@ impl Shader for Skybox {
         camera: [[f32; 4]; 4],
         position: [[f32; 4]; 4],
         scene_data: &crate::renderer::SceneData,
-    ) {
-        let cubemap = scene_data
-            .get_scene_object::<PBRSkybox>()
-            .expect("no skybox provided")
-            .get_skybox();
+    ) -> Result<(), glium::DrawError> {
+        // A solid color is a gradient between the same color
+        let gradient = match scene_data.get_background() {
+            Background::Skybox => None,
+            Background::SolidColor(color) => Some((color, color)),
+            Background::Gradient { top, bottom } => Some((top, bottom)),
+        };
+
+        if let Some((top, bottom)) = gradient {
+            let uniforms = uniform! {
+                projection: camera,
+                view: position,
+                backgroundMode: 1,
+                backgroundTop: top,
+                backgroundBottom: bottom,
+            };
+
+            return self.draw(vertex_buffer, index_buffer, surface, &uniforms, scene_data);
+        }
+
+        let pbr_skybox = match scene_data.get_scene_object::<PBRSkybox>() {
+            Some(pbr_skybox) => pbr_skybox,
+            None => return Ok(()),
+        };
+        let environment_rotation: [[f32; 3]; 3] = pbr_skybox.get_environment_rotation().into();
 
         let uniforms = uniform! {
             projection: camera,
             view: position,
-            environmentMap: &**cubemap
+            backgroundMode: 0,
+            environmentMap: &**pbr_skybox.get_skybox(),
+            environmentRotation: environment_rotation,
+            tint: pbr_skybox.get_tint(),
+            exposure: scene_data.get_exposure(),
+            toneMapping: scene_data.get_tone_mapping() as i32,
         };
 
-        surface
-            .draw(
-                vertex_buffer,
-                index_buffer,
-                &self.program,
-                &uniforms,
-                &DrawParameters {
-                    depth: glium::Depth {
-                        test: glium::DepthTest::IfLessOrEqual,
-                        write: true,
-                        ..Default::default()
-                    },
-                    ..Default::default()
-                },
-            )
-            .unwrap();
+        self.draw(vertex_buffer, index_buffer, surface, &uniforms, scene_data)
     }
 
     fn get_model_mat(&self) -> Matrix4<f32> {
@@ -66,6 +107,22 @@ impl Shader for Skybox {
 
     fn set_model_mat(&mut self, _model: Matrix4<f32>) {}
 
+    fn casts_shadows(&self) -> bool {
+        false
+    }
+
+    fn is_background(&self) -> bool {
+        true
+    }
+
+    fn check_scene(&self, scene_data: &crate::renderer::SceneData) -> Result<(), RendererError> {
+        // The environment map is only needed when it is shown
+        match scene_data.get_background() {
+            Background::Skybox => PBRSkybox::require(scene_data, self.debug_name()).map(|_| ()),
+            _ => Ok(()),
+        }
+    }
+
     fn equal_shader(&self, _shader: &dyn std::any::Any) -> bool {
         false
     }
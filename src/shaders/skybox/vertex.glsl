@@ -5,13 +5,15 @@ uniform mat4 projection;
 uniform mat4 view;
 
 out vec3 localPos;
+// interpolated in clip space so the screen height is exact even for corners behind the camera
+out vec4 clipPos;
 
 void main()
 {
     localPos = position;
 
     mat4 rotView = mat4(mat3(view)); // remove translation from the view matrix
-    vec4 clipPos = projection * rotView * vec4(localPos, 1.0);
+    clipPos = projection * rotView * vec4(localPos, 1.0);
 
     gl_Position = clipPos.xyww;
 }
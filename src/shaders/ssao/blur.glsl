@@ -0,0 +1,22 @@
+#version 330 core
+out vec4 FragColor;
+in vec2 TexCoords;
+
+uniform sampler2D image;
+
+// box blur the size of the noise texture, which removes its repeating pattern
+void main()
+{
+    vec2 texelSize = 1.0 / vec2(textureSize(image, 0));
+
+    float result = 0.0;
+    for (int x = -2; x < 2; ++x)
+    {
+        for (int y = -2; y < 2; ++y)
+        {
+            result += texture(image, TexCoords + vec2(x, y) * texelSize).r;
+        }
+    }
+
+    FragColor = vec4(vec3(result / 16.0), 1.0);
+}
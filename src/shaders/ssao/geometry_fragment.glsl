@@ -0,0 +1,9 @@
+#version 330 core
+out vec4 FragColor;
+in vec3 ViewNormal;
+
+// view space normals, the depth is written to the depth texture by OpenGL
+void main()
+{
+    FragColor = vec4(normalize(ViewNormal), 1.0);
+}
@@ -0,0 +1,17 @@
+#version 330 core
+in vec3 position;
+in vec3 normal;
+
+uniform mat4 projection;
+uniform mat4 view;
+uniform mat4 model;
+
+out vec3 ViewNormal;
+
+void main()
+{
+    mat4 modelView = view * model;
+    ViewNormal = mat3(transpose(inverse(modelView))) * normal;
+
+    gl_Position = projection * modelView * vec4(position, 1.0);
+}
@@ -0,0 +1,379 @@
+use crate::error::RendererError;
+use crate::insert_program;
+use crate::utils::shapes;
+use crate::utils::texture_loader::TextureLoader;
+use crate::vertex::Vertex;
+use glium::backend::Context;
+use glium::backend::Facade;
+use glium::framebuffer::SimpleFrameBuffer;
+use glium::index::IndicesSource;
+use glium::texture::DepthTexture2d;
+use glium::texture::MipmapsOption;
+use glium::texture::UncompressedFloatFormat::{self, F16F16F16F16, U8};
+use glium::uniforms::{
+    MagnifySamplerFilter, MinifySamplerFilter, SamplerBehavior, SamplerWrapFunction, UniformValue,
+    Uniforms,
+};
+use glium::vertex::VerticesSource;
+use glium::DrawParameters;
+use glium::IndexBuffer;
+use glium::Program;
+use glium::Surface;
+use glium::Texture2d;
+use glium::VertexBuffer;
+use nalgebra::Matrix4;
+use nalgebra::Vector3;
+use std::rc::Rc;
+
+/// The most samples that can be taken per pixel
+///
+/// This has to match `MAX_SAMPLES` in the shader.
+pub const MAX_SAMPLES: usize = 64;
+
+/// Screen space ambient occlusion
+///
+/// Darkens creases and places where objects touch, which the ambient lighting from the
+/// environment can't do by itself. Created by [`Renderer::enable_ssao`], which renders it every
+/// frame before the main pass and puts a copy on [`SceneData`] for the materials to sample.
+///
+/// # Render targets
+///
+/// Besides the surface, this needs the following textures, which are resized to the size of the
+/// surface when it changes:
+///
+/// - an `F16F16F16F16` texture with the view space normals and a depth texture. Every entry that
+///   [supports the depth pre-pass](crate::shader::Shader::supports_depth_prepass) is drawn into
+///   them, so the geometry is drawn one extra time.
+/// - two single channel `U8` textures, one with the occlusion and one with the blurred occlusion
+///   that materials sample.
+///
+/// [`Renderer::enable_ssao`]: crate::renderer::Renderer::enable_ssao
+/// [`SceneData`]: crate::renderer::SceneData
+#[derive(Clone)]
+pub struct Ssao {
+    geometry_program: Rc<Program>,
+    ssao_program: Rc<Program>,
+    blur_program: Rc<Program>,
+    quad: Rc<(VertexBuffer<Vertex>, IndexBuffer<u32>)>,
+    kernel: [[f32; 3]; MAX_SAMPLES],
+    noise: Rc<Texture2d>,
+    normals: Rc<Texture2d>,
+    depth: Rc<DepthTexture2d>,
+    occlusion: Rc<Texture2d>,
+    blurred: Rc<Texture2d>,
+    radius: f32,
+    bias: f32,
+    sample_count: usize,
+    facade: Rc<Context>,
+}
+
+impl Ssao {
+    pub fn new(facade: &impl Facade) -> Self {
+        let geometry_program = Rc::new(insert_program!(
+            "./geometry_vertex.glsl",
+            "./geometry_fragment.glsl",
+            facade
+        ));
+        let ssao_program = Rc::new(insert_program!("./vertex.glsl", "./ssao.glsl", facade));
+        let blur_program = Rc::new(insert_program!("./vertex.glsl", "./blur.glsl", facade));
+
+        Self {
+            geometry_program,
+            ssao_program,
+            blur_program,
+            quad: Rc::new(shapes::fullscreen_quad(facade)),
+            kernel: Self::create_kernel(),
+            noise: Rc::new(Self::create_noise(facade)),
+            normals: Self::create_texture(facade, F16F16F16F16, 1, 1).unwrap(),
+            depth: Rc::new(DepthTexture2d::empty(facade, 1, 1).unwrap()),
+            occlusion: Self::create_texture(facade, U8, 1, 1).unwrap(),
+            blurred: Self::create_texture(facade, U8, 1, 1).unwrap(),
+            radius: 0.5,
+            bias: 0.025,
+            sample_count: 32,
+            facade: facade.get_context().clone(),
+        }
+    }
+
+    /// How far around a surface other geometry can occlude it, 0.5 by default
+    pub fn get_radius(&self) -> f32 {
+        self.radius
+    }
+    pub fn set_radius(&mut self, radius: f32) {
+        self.radius = radius;
+    }
+
+    /// Depth difference ignored when comparing samples, prevents flat surfaces from occluding
+    /// themselves. 0.025 by default.
+    pub fn get_bias(&self) -> f32 {
+        self.bias
+    }
+    pub fn set_bias(&mut self, bias: f32) {
+        self.bias = bias;
+    }
+
+    /// Samples per pixel, 32 by default and at most [`MAX_SAMPLES`]
+    pub fn get_sample_count(&self) -> usize {
+        self.sample_count
+    }
+    pub fn set_sample_count(&mut self, sample_count: usize) {
+        self.sample_count = sample_count.clamp(1, MAX_SAMPLES);
+    }
+
+    /// The blurred occlusion of the last rendered frame, white is not occluded
+    pub fn get_occlusion(&self) -> &Rc<Texture2d> {
+        &self.blurred
+    }
+
+    /// Renders the occlusion of `entries` as seen from the camera
+    ///
+    /// `width` and `height` have to be the size of the surface the scene is rendered to, since
+    /// materials look up the occlusion by their position on screen. If a draw call or resizing the
+    /// render targets fails, the occlusion is left as it was and the error is returned.
+    pub fn render<'a>(
+        &mut self,
+        width: u32,
+        height: u32,
+        projection: Matrix4<f32>,
+        view: Matrix4<f32>,
+        entries: impl IntoIterator<Item = (VerticesSource<'a>, IndicesSource<'a>, Matrix4<f32>)>,
+    ) -> Result<(), RendererError> {
+        if self.blurred.dimensions() != (width, height) {
+            self.resize(width, height)?;
+        }
+
+        let inverse_projection: [[f32; 4]; 4] = projection
+            .try_inverse()
+            .unwrap_or_else(Matrix4::identity)
+            .into();
+        let projection: [[f32; 4]; 4] = projection.into();
+        let view: [[f32; 4]; 4] = view.into();
+
+        let mut fb =
+            SimpleFrameBuffer::with_depth_buffer(&self.facade, &*self.normals, &*self.depth)?;
+        fb.clear_color_and_depth((0.0, 0.0, 1.0, 1.0), 1.0);
+
+        for (vertices, indices, model) in entries {
+            let model: [[f32; 4]; 4] = model.into();
+
+            let uniforms = uniform! {
+                projection: projection,
+                view: view,
+                model: model,
+            };
+
+            fb.draw(
+                vertices,
+                indices,
+                &self.geometry_program,
+                &uniforms,
+                &DrawParameters {
+                    depth: glium::Depth {
+                        test: glium::DepthTest::IfLess,
+                        write: true,
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+            )?;
+        }
+
+        let uniforms = uniform! {
+            depthMap: self.depth
+                .sampled()
+                .wrap_function(SamplerWrapFunction::Clamp)
+                .minify_filter(MinifySamplerFilter::Nearest)
+                .magnify_filter(MagnifySamplerFilter::Nearest),
+            normalMap: self.normals
+                .sampled()
+                .wrap_function(SamplerWrapFunction::Clamp)
+                .minify_filter(MinifySamplerFilter::Nearest)
+                .magnify_filter(MagnifySamplerFilter::Nearest),
+            noiseMap: self.noise
+                .sampled()
+                .wrap_function(SamplerWrapFunction::Repeat)
+                .minify_filter(MinifySamplerFilter::Nearest)
+                .magnify_filter(MagnifySamplerFilter::Nearest),
+            sampleCount: self.sample_count as i32,
+            radius: self.radius,
+            bias: self.bias,
+            projection: projection,
+            invProjection: inverse_projection,
+        };
+        let uniforms = KernelUniforms {
+            kernel: &self.kernel[..self.sample_count],
+            uniforms,
+        };
+        self.pass(&self.ssao_program, &self.occlusion, &uniforms)?;
+
+        let uniforms = uniform! {
+            image: self.occlusion
+                .sampled()
+                .wrap_function(SamplerWrapFunction::Clamp)
+                .minify_filter(MinifySamplerFilter::Nearest)
+                .magnify_filter(MagnifySamplerFilter::Nearest),
+        };
+        self.pass(&self.blur_program, &self.blurred, &uniforms)
+    }
+
+    /// Adds `ssaoMap` and `useSsao` to the given uniforms
+    ///
+    /// Every sampler needs a texture bound, so `fallback` is bound to `ssaoMap` when there is no
+    /// occlusion. It is never read since `useSsao` is false.
+    pub fn with_uniforms<'a, U: Uniforms>(
+        ssao: Option<&'a Ssao>,
+        fallback: &'a Texture2d,
+        uniforms: U,
+    ) -> SsaoUniforms<'a, U> {
+        SsaoUniforms {
+            ssao,
+            fallback,
+            uniforms,
+        }
+    }
+
+    fn pass(
+        &self,
+        program: &Program,
+        target: &Texture2d,
+        uniforms: &impl Uniforms,
+    ) -> Result<(), RendererError> {
+        let mut fb = SimpleFrameBuffer::new(&self.facade, target)?;
+
+        fb.draw(
+            &self.quad.0,
+            &self.quad.1,
+            program,
+            uniforms,
+            &DrawParameters::default(),
+        )?;
+
+        Ok(())
+    }
+
+    fn resize(&mut self, width: u32, height: u32) -> Result<(), RendererError> {
+        self.normals = Self::create_texture(&self.facade, F16F16F16F16, width, height)?;
+        self.depth = Rc::new(DepthTexture2d::empty(&self.facade, width, height)?);
+        self.occlusion = Self::create_texture(&self.facade, U8, width, height)?;
+        self.blurred = Self::create_texture(&self.facade, U8, width, height)?;
+
+        Ok(())
+    }
+
+    fn create_texture(
+        facade: &impl Facade,
+        format: UncompressedFloatFormat,
+        width: u32,
+        height: u32,
+    ) -> Result<Rc<Texture2d>, RendererError> {
+        Ok(Rc::new(Texture2d::empty_with_format(
+            facade,
+            format,
+            MipmapsOption::NoMipmap,
+            width,
+            height,
+        )?))
+    }
+
+    /// Points in the hemisphere along +z, more of them close to the center
+    ///
+    /// Uses the Halton sequence instead of random numbers so every run looks the same.
+    fn create_kernel() -> [[f32; 3]; MAX_SAMPLES] {
+        let mut kernel = [[0.0; 3]; MAX_SAMPLES];
+
+        for (i, sample) in kernel.iter_mut().enumerate() {
+            let index = i as u32 + 1;
+            let direction = Vector3::new(
+                halton(index, 2) * 2.0 - 1.0,
+                halton(index, 3) * 2.0 - 1.0,
+                halton(index, 5).max(0.05),
+            )
+            .normalize();
+
+            let scale = i as f32 / MAX_SAMPLES as f32;
+            let scale = 0.1 + scale * scale * 0.9;
+
+            *sample = (direction * halton(index, 7).max(0.1) * scale).into();
+        }
+
+        kernel
+    }
+
+    /// 4x4 rotations around the normal, tiled across the screen
+    fn create_noise(facade: &impl Facade) -> Texture2d {
+        let noise = (1..=16)
+            .flat_map(|i| {
+                let angle = halton(i, 2) * std::f32::consts::TAU;
+                [angle.cos(), angle.sin(), 0.0]
+            })
+            .collect::<Vec<_>>();
+
+        TextureLoader::from_memory_f32(facade, &noise, 4, 4).unwrap()
+    }
+}
+
+/// Element `index` of the Halton sequence with the given base, in `0..1`
+fn halton(mut index: u32, base: u32) -> f32 {
+    let mut result = 0.0;
+    let mut fraction = 1.0;
+
+    while index > 0 {
+        fraction /= base as f32;
+        result += fraction * (index % base) as f32;
+        index /= base;
+    }
+
+    result
+}
+
+struct KernelUniforms<'a, U: Uniforms> {
+    kernel: &'a [[f32; 3]],
+    uniforms: U,
+}
+
+impl<'a, U: Uniforms> Uniforms for KernelUniforms<'a, U> {
+    fn visit_values<'b, F: FnMut(&str, UniformValue<'b>)>(&'b self, mut output: F) {
+        self.uniforms.visit_values(&mut output);
+
+        for (i, sample) in self.kernel.iter().enumerate() {
+            output(&format!("samples[{}]", i), UniformValue::Vec3(*sample));
+        }
+    }
+}
+
+pub struct SsaoUniforms<'a, U: Uniforms> {
+    ssao: Option<&'a Ssao>,
+    fallback: &'a Texture2d,
+    uniforms: U,
+}
+
+impl<'a, U: Uniforms> Uniforms for SsaoUniforms<'a, U> {
+    fn visit_values<'b, F: FnMut(&str, UniformValue<'b>)>(&'b self, mut output: F) {
+        self.uniforms.visit_values(&mut output);
+
+        match self.ssao {
+            Some(ssao) => {
+                let behavior = SamplerBehavior {
+                    wrap_function: (
+                        SamplerWrapFunction::Clamp,
+                        SamplerWrapFunction::Clamp,
+                        SamplerWrapFunction::Clamp,
+                    ),
+                    minify_filter: MinifySamplerFilter::Nearest,
+                    magnify_filter: MagnifySamplerFilter::Nearest,
+                    ..Default::default()
+                };
+
+                output(
+                    "ssaoMap",
+                    UniformValue::Texture2d(&ssao.blurred, Some(behavior)),
+                );
+                output("useSsao", UniformValue::Bool(true));
+            }
+            None => {
+                output("ssaoMap", UniformValue::Texture2d(self.fallback, None));
+                output("useSsao", UniformValue::Bool(false));
+            }
+        }
+    }
+}
@@ -0,0 +1,65 @@
+#version 330 core
+out vec4 FragColor;
+in vec2 TexCoords;
+
+uniform sampler2D depthMap;
+// view space normals
+uniform sampler2D normalMap;
+// small tiled texture of random rotations around the normal
+uniform sampler2D noiseMap;
+
+// hemisphere kernel pointing along +z, MAX_SAMPLES has to match shaders::ssao::MAX_SAMPLES
+#define MAX_SAMPLES 64
+uniform vec3 samples[MAX_SAMPLES];
+uniform int sampleCount;
+uniform float radius;
+uniform float bias;
+
+uniform mat4 projection;
+uniform mat4 invProjection;
+
+vec3 viewPosition(vec2 uv)
+{
+    float depth = texture(depthMap, uv).r;
+    vec4 position = invProjection * vec4(vec3(uv, depth) * 2.0 - 1.0, 1.0);
+
+    return position.xyz / position.w;
+}
+
+void main()
+{
+    // nothing was drawn here, so nothing can be occluded
+    if (texture(depthMap, TexCoords).r >= 1.0)
+    {
+        FragColor = vec4(1.0);
+        return;
+    }
+
+    vec3 fragPos = viewPosition(TexCoords);
+    vec3 normal = normalize(texture(normalMap, TexCoords).xyz);
+
+    // rotate the kernel randomly around the normal so fewer samples are needed, the blur pass
+    // removes the resulting noise pattern
+    vec2 noiseScale = vec2(textureSize(depthMap, 0)) / vec2(textureSize(noiseMap, 0));
+    vec3 randomVec = texture(noiseMap, TexCoords * noiseScale).xyz;
+    vec3 tangent = normalize(randomVec - normal * dot(randomVec, normal));
+    vec3 bitangent = cross(normal, tangent);
+    mat3 TBN = mat3(tangent, bitangent, normal);
+
+    int count = clamp(sampleCount, 1, MAX_SAMPLES);
+    float occlusion = 0.0;
+    for (int i = 0; i < count; ++i)
+    {
+        vec3 samplePos = fragPos + TBN * samples[i] * radius;
+
+        vec4 offset = projection * vec4(samplePos, 1.0);
+        vec2 sampleUv = offset.xy / offset.w * 0.5 + 0.5;
+        float sampleDepth = viewPosition(sampleUv).z;
+
+        // geometry far in front of the fragment shouldn't darken it
+        float rangeCheck = smoothstep(0.0, 1.0, radius / abs(fragPos.z - sampleDepth));
+        occlusion += (sampleDepth >= samplePos.z + bias ? 1.0 : 0.0) * rangeCheck;
+    }
+
+    FragColor = vec4(vec3(1.0 - occlusion / float(count)), 1.0);
+}
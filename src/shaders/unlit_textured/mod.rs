@@ -0,0 +1,154 @@
+use crate::insert_program;
+use crate::shader::Shader;
+use crate::utils::texture_registry::TextureRegistry;
+use glium::backend::Facade;
+use glium::uniforms::SamplerWrapFunction;
+use glium::Blend;
+use glium::DrawParameters;
+use glium::Program;
+use glium::Texture2d;
+use nalgebra::Matrix4;
+use std::any::Any;
+use std::rc::Rc;
+
+/// Draws a single texture as is, without any lighting
+///
+/// Useful for decals, debug quads, or showing a rendered texture inside the scene.
+#[derive(Clone)]
+pub struct UnlitTextured {
+    program: Rc<Program>,
+    texture: Rc<Texture2d>,
+    uv_scroll: [f32; 2],
+    transparent: bool,
+    model: Matrix4<f32>,
+}
+
+impl UnlitTextured {
+    pub fn load_from_fs(facade: &impl Facade, texture: Rc<Texture2d>) -> Self {
+        let program = Rc::new(insert_program!("./vertex.glsl", "./fragment.glsl", facade));
+        TextureRegistry::track_texture(&texture);
+
+        Self {
+            program,
+            texture,
+            uv_scroll: [0.0; 2],
+            transparent: false,
+            model: Matrix4::new_translation(&[0.0; 3].into()),
+        }
+    }
+
+    pub fn get_texture(&self) -> &Rc<Texture2d> {
+        &self.texture
+    }
+    pub fn set_texture(&mut self, texture: Rc<Texture2d>) {
+        TextureRegistry::track_texture(&texture);
+        self.texture = texture;
+    }
+
+    pub fn get_uv_scroll(&self) -> [f32; 2] {
+        self.uv_scroll
+    }
+    /// Moves the texture coordinates by this much every second of the [scene's
+    /// time](crate::renderer::SceneData::get_time), ex. for flowing water or conveyor belts
+    ///
+    /// The texture repeats while scrolling instead of being mirrored.
+    pub fn set_uv_scroll(&mut self, uv_scroll: [f32; 2]) {
+        self.uv_scroll = uv_scroll;
+    }
+
+    /// Blends the texture by its alpha instead of drawing it opaque, false by default
+    ///
+    /// Transparent entries are drawn after the opaque ones and don't write depth, see
+    /// [`Shader::is_transparent`].
+    pub fn set_transparent(&mut self, transparent: bool) {
+        self.transparent = transparent;
+    }
+}
+
+impl Shader for UnlitTextured {
+    fn render<'a>(
+        &self,
+        vertex_buffer: glium::vertex::VerticesSource<'a>,
+        index_buffer: glium::index::IndicesSource<'a>,
+        surface: &mut crate::renderer::Renderable,
+        camera: [[f32; 4]; 4],
+        position: [[f32; 4]; 4],
+        scene_data: &crate::renderer::SceneData,
+    ) -> Result<(), glium::DrawError> {
+        let model_matrix: [[f32; 4]; 4] = self.model.into();
+
+        let wrap = match self.uv_scroll == [0.0; 2] {
+            true => SamplerWrapFunction::Mirror,
+            false => SamplerWrapFunction::Repeat,
+        };
+
+        let uniforms = uniform! {
+            projection: camera,
+            view: position,
+            model: model_matrix,
+            image: self.texture.sampled().wrap_function(wrap),
+            uvScroll: self.uv_scroll,
+            time: scene_data.get_time(),
+        };
+
+        let draw_parameters = scene_data.apply_draw_parameters(DrawParameters {
+            depth: glium::Depth {
+                test: glium::DepthTest::IfLess,
+                write: !self.transparent,
+                ..Default::default()
+            },
+            blend: match self.transparent {
+                true => Blend::alpha_blending(),
+                false => Blend::default(),
+            },
+            ..Default::default()
+        });
+
+        surface.draw(
+            vertex_buffer,
+            index_buffer,
+            &self.program,
+            &uniforms,
+            &draw_parameters,
+        )
+    }
+
+    fn get_model_mat(&self) -> Matrix4<f32> {
+        self.model
+    }
+
+    fn set_model_mat(&mut self, model: Matrix4<f32>) {
+        self.model = model;
+    }
+
+    /// Equal when both draw the same texture the same way
+    fn equal_shader(&self, shader: &dyn std::any::Any) -> bool {
+        match shader.downcast_ref::<Self>() {
+            Some(other) => {
+                Rc::ptr_eq(&self.texture, &other.texture) && self.transparent == other.transparent
+            }
+            None => false,
+        }
+    }
+
+    fn to_any(self) -> Box<dyn Any> {
+        Box::new(self)
+    }
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+
+    fn clone_shader(&self) -> Box<dyn Shader> {
+        Box::new(self.clone())
+    }
+    fn clone_sized(&self) -> Self {
+        self.clone()
+    }
+
+    fn is_transparent(&self) -> bool {
+        self.transparent
+    }
+}
@@ -0,0 +1,20 @@
+#version 330 core
+in vec3 position;
+in vec2 tex_coords;
+
+out vec2 TexCoords;
+
+uniform mat4 projection;
+uniform mat4 view;
+uniform mat4 model;
+
+uniform vec2 uvScroll;
+uniform float time;
+
+void main()
+{
+    // wrapped so the coordinates don't lose precision after running for a while
+    TexCoords = tex_coords + fract(uvScroll * time);
+
+    gl_Position = projection * view * model * vec4(position, 1.0);
+}
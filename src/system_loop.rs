@@ -1,11 +1,50 @@
+use std::cell::RefCell;
+use std::error::Error;
+use std::rc::Rc;
 use std::time::{Duration, Instant};
 
+use crate::renderer::Renderable;
+use crate::utils::camera::Camera;
+use crate::utils::texture_saver::ImageSaver;
 use crate::window::Window;
 use egui::FontDefinitions;
 use egui_glium::EguiGlium;
-use glium::{glutin, Frame, Surface};
+use glium::framebuffer::SimpleFrameBuffer;
+use glium::texture::{DepthStencilTexture2d, SrgbTexture2d};
+use glium::{glutin, Frame, HeadlessRenderer, Surface};
+use glutin::dpi::PhysicalSize;
 use glutin::event::Event;
 use glutin::event_loop::EventLoop;
+use glutin::ContextBuilder;
+use image::DynamicImage;
+
+/// The fonts of the ui, egui is built without its default fonts so Roboto is bundled instead
+///
+/// Also used to rasterize [`BillboardText`](crate::utils::billboard_text::BillboardText).
+pub fn font_definitions() -> FontDefinitions {
+    let mut fonts = FontDefinitions::default();
+
+    fonts.font_data.insert(
+        "my_font".to_owned(),
+        egui::FontData::from_static(include_bytes!("../resources/fonts/Roboto-Medium.ttf")),
+    ); // .ttf and .otf supported
+
+    // Put my font first (highest priority):
+    fonts
+        .families
+        .get_mut(&egui::FontFamily::Proportional)
+        .unwrap()
+        .insert(0, "my_font".to_owned());
+
+    // Put my font as last fallback for monospace:
+    fonts
+        .families
+        .get_mut(&egui::FontFamily::Monospace)
+        .unwrap()
+        .push("my_font".to_owned());
+
+    fonts
+}
 
 pub struct RenderInfo<'a> {
     pub target: &'a mut Frame,
@@ -18,6 +57,7 @@ pub struct SystemLoop {
     window: Window,
     render_handlers: Vec<Box<dyn FnMut(&mut RenderInfo)>>,
     event_handlers: Vec<Box<dyn FnMut(&Event<'_, ()>)>>,
+    cameras: Vec<Rc<RefCell<Camera>>>,
     egui_glium: EguiGlium,
     event_loop: EventLoop<()>,
 }
@@ -36,6 +76,7 @@ impl SystemLoop {
             window,
             render_handlers: Vec::new(),
             event_handlers: Vec::new(),
+            cameras: Vec::new(),
             egui_glium,
             event_loop,
         }
@@ -49,6 +90,20 @@ impl SystemLoop {
         self.event_handlers.push(Box::new(event));
     }
 
+    /// Keeps the aspect ratio of `camera` matching the window whenever it is resized
+    ///
+    /// The aspect ratio is set from the current size right away. Only useful for cameras that
+    /// render to the whole window, ones rendering to a texture should call
+    /// [`Camera::update_aspect_from_dimensions`] with the size of the texture instead.
+    pub fn register_camera(&mut self, camera: Rc<RefCell<Camera>>) {
+        let size = self.window.display.gl_window().window().inner_size();
+        camera
+            .borrow_mut()
+            .update_aspect_from_dimensions(size.width, size.height);
+
+        self.cameras.push(camera);
+    }
+
     pub fn get_egui_glium(&self) -> &EguiGlium {
         &self.egui_glium
     }
@@ -61,38 +116,14 @@ impl SystemLoop {
             window,
             mut render_handlers,
             mut event_handlers,
+            cameras,
             mut egui_glium,
             event_loop,
         } = self;
 
         let mut last_frame = Instant::now();
 
-        let font_defs = {
-            let mut fonts = FontDefinitions::default();
-
-            fonts.font_data.insert(
-                "my_font".to_owned(),
-                egui::FontData::from_static(include_bytes!("../resources/fonts/Roboto-Medium.ttf")),
-            ); // .ttf and .otf supported
-
-            // Put my font first (highest priority):
-            fonts
-                .families
-                .get_mut(&egui::FontFamily::Proportional)
-                .unwrap()
-                .insert(0, "my_font".to_owned());
-
-            // Put my font as last fallback for monospace:
-            fonts
-                .families
-                .get_mut(&egui::FontFamily::Monospace)
-                .unwrap()
-                .push("my_font".to_owned());
-
-            fonts
-        };
-
-        egui_glium.egui_ctx.set_fonts(font_defs);
+        egui_glium.egui_ctx.set_fonts(font_definitions());
 
         event_loop.run(move |event, _, control_flow| {
             for event_handler in &mut event_handlers {
@@ -132,6 +163,14 @@ impl SystemLoop {
                     ..
                 } => *control_flow = glutin::event_loop::ControlFlow::Exit,
                 Event::WindowEvent { event, .. } => {
+                    if let glutin::event::WindowEvent::Resized(size) = &event {
+                        for camera in &cameras {
+                            camera
+                                .borrow_mut()
+                                .update_aspect_from_dimensions(size.width, size.height);
+                        }
+                    }
+
                     let _ = egui_glium.on_event(&event);
                 }
                 _ => (),
@@ -141,3 +180,68 @@ impl SystemLoop {
         });
     }
 }
+
+/// Renders without a window, for generating images (ex. thumbnails or tests)
+///
+/// There is no event loop to drive, each call to [`render_once`](Self::render_once) renders a
+/// single frame and reads it back. A display connection is still needed on most platforms to
+/// create the context, on Linux CI something like xvfb works.
+pub struct HeadlessSystem {
+    renderer: Rc<HeadlessRenderer>,
+    width: u32,
+    height: u32,
+    // The context can depend on the event loop it was created with, so keep it alive
+    _event_loop: EventLoop<()>,
+}
+
+impl HeadlessSystem {
+    pub fn new(width: u32, height: u32) -> Result<Self, Box<dyn Error>> {
+        let event_loop = EventLoop::new();
+        let context = ContextBuilder::new()
+            .with_depth_buffer(24)
+            .with_stencil_buffer(8)
+            .build_headless(&event_loop, PhysicalSize::new(width, height))?;
+        let context = unsafe { context.make_current() }.map_err(|(_, err)| err)?;
+
+        // Same as SystemLoop, raw opengl is needed to create cubemaps
+        gl::load_with(|s| context.get_proc_address(s));
+
+        Ok(Self {
+            renderer: Rc::new(HeadlessRenderer::new(context)?),
+            width,
+            height,
+            _event_loop: event_loop,
+        })
+    }
+
+    pub fn get_facade(&self) -> &Rc<HeadlessRenderer> {
+        &self.renderer
+    }
+
+    pub fn get_size(&self) -> (u32, u32) {
+        (self.width, self.height)
+    }
+
+    /// Renders a single frame and returns it as an upright image
+    ///
+    /// The surface is cleared before `render` is called. Like the window it renders into an srgb
+    /// texture, so the image has the same colors as it would on screen.
+    pub fn render_once(
+        &self,
+        render: impl FnOnce(&mut Renderable),
+    ) -> Result<DynamicImage, Box<dyn Error>> {
+        let texture = SrgbTexture2d::empty(&*self.renderer, self.width, self.height)?;
+        // Has a stencil buffer for materials that use it, ex. outlines
+        let depth = DepthStencilTexture2d::empty(&*self.renderer, self.width, self.height)?;
+
+        {
+            let mut frame_buffer =
+                SimpleFrameBuffer::with_depth_stencil_buffer(&*self.renderer, &texture, &depth)?;
+            frame_buffer.clear_color_and_depth((0.0, 0.0, 0.0, 0.0), 1.0);
+
+            render(&mut (&mut frame_buffer).into());
+        }
+
+        Ok(ImageSaver::from_srgb_texture(&texture)?.flipv())
+    }
+}
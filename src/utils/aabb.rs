@@ -0,0 +1,104 @@
+use crate::vertex::Vertex;
+use nalgebra::Matrix4;
+use nalgebra::Vector3;
+
+/// An axis aligned bounding box
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: Vector3<f32>,
+    pub max: Vector3<f32>,
+}
+
+impl Aabb {
+    pub fn new(min: Vector3<f32>, max: Vector3<f32>) -> Self {
+        Self { min, max }
+    }
+
+    /// The smallest box containing every vertex position, `None` if there are no vertices
+    pub fn from_vertices(vertices: &[Vertex]) -> Option<Self> {
+        let first = Vector3::from(vertices.first()?.position);
+
+        Some(
+            vertices
+                .iter()
+                .fold(Self::new(first, first), |aabb, vertex| {
+                    let position = Vector3::from(vertex.position);
+
+                    Self::new(aabb.min.inf(&position), aabb.max.sup(&position))
+                }),
+        )
+    }
+
+    pub fn get_center(&self) -> Vector3<f32> {
+        (self.min + self.max) / 2.0
+    }
+
+    /// Half of the size along each axis, the distance from the center to the faces
+    pub fn get_extents(&self) -> Vector3<f32> {
+        (self.max - self.min) / 2.0
+    }
+
+    /// The smallest box containing both boxes
+    pub fn union(&self, other: &Aabb) -> Self {
+        Self::new(self.min.inf(&other.min), self.max.sup(&other.max))
+    }
+
+    /// The eight corners of the box
+    pub fn get_corners(&self) -> [Vector3<f32>; 8] {
+        let (min, max) = (self.min, self.max);
+
+        [
+            Vector3::new(min.x, min.y, min.z),
+            Vector3::new(max.x, min.y, min.z),
+            Vector3::new(min.x, max.y, min.z),
+            Vector3::new(max.x, max.y, min.z),
+            Vector3::new(min.x, min.y, max.z),
+            Vector3::new(max.x, min.y, max.z),
+            Vector3::new(min.x, max.y, max.z),
+            Vector3::new(max.x, max.y, max.z),
+        ]
+    }
+
+    /// The box containing this box after it is transformed by `matrix`
+    pub fn transform(&self, matrix: &Matrix4<f32>) -> Self {
+        let corners = self
+            .get_corners()
+            .map(|corner| matrix.transform_point(&corner.into()).coords);
+
+        corners[1..]
+            .iter()
+            .fold(Self::new(corners[0], corners[0]), |aabb, corner| {
+                Self::new(aabb.min.inf(corner), aabb.max.sup(corner))
+            })
+    }
+
+    /// Distance along the ray to where it enters the box
+    ///
+    /// Uses the slab method. `direction` doesn't have to be normalized, the distance is in
+    /// multiples of `direction`. Returns 0 if `origin` is inside the box and `None` if the ray
+    /// misses.
+    pub fn intersects_ray(&self, origin: &Vector3<f32>, direction: &Vector3<f32>) -> Option<f32> {
+        let mut t_min = 0.0f32;
+        let mut t_max = f32::INFINITY;
+
+        for axis in 0..3 {
+            // Division by 0 gives +-infinity which the comparisons below handle
+            let inverse = 1.0 / direction[axis];
+            let mut t0 = (self.min[axis] - origin[axis]) * inverse;
+            let mut t1 = (self.max[axis] - origin[axis]) * inverse;
+
+            if inverse < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+
+            t_min = t_min.max(t0);
+            t_max = t_max.min(t1);
+
+            if t_max < t_min {
+                return None;
+            }
+        }
+
+        Some(t_min)
+    }
+}
@@ -0,0 +1,545 @@
+use crate::error::RendererError;
+use crate::renderer::RenderScene;
+use crate::shader::Shader;
+use crate::shaders::pbr::MAX_BONES;
+use crate::shaders::pbr::PBR;
+use crate::utils::model::assimp_matrix;
+use crate::utils::model::LoadOptions;
+use crate::utils::model::Model;
+use crate::vertex::SkinWeights;
+use glium::backend::Facade;
+use glium::VertexBuffer;
+use nalgebra::Matrix4;
+use nalgebra::Quaternion;
+use nalgebra::UnitQuaternion;
+use nalgebra::Vector3;
+use russimp::node::Node;
+use russimp::scene::Scene;
+use std::collections::HashMap;
+use std::path::Path;
+use std::rc::Rc;
+use std::time::Duration;
+
+/// Used when the file doesn't say how fast its animations are
+const DEFAULT_TICKS_PER_SECOND: f64 = 25.0;
+
+struct SkeletonNode {
+    name: String,
+    parent: Option<usize>,
+    /// Transform relative to the parent when no animation moves the node
+    bind: Matrix4<f32>,
+}
+
+struct Bone {
+    node: usize,
+    /// Moves a vertex from the space of the mesh into the space of the bone in its bind pose
+    offset: Matrix4<f32>,
+}
+
+/// The node hierarchy of a model and the nodes that vertices are attached to
+pub struct Skeleton {
+    /// Parents always come before their children
+    nodes: Vec<SkeletonNode>,
+    bones: Vec<Bone>,
+    bone_names: HashMap<String, usize>,
+}
+
+impl Skeleton {
+    fn from_scene(scene: &Scene) -> Self {
+        let mut nodes = Vec::new();
+        if let Some(root) = &scene.root {
+            Self::collect_nodes(&root.borrow(), None, &mut nodes);
+        }
+
+        Self {
+            nodes,
+            bones: Vec::new(),
+            bone_names: HashMap::new(),
+        }
+    }
+
+    fn collect_nodes(node: &Node, parent: Option<usize>, nodes: &mut Vec<SkeletonNode>) {
+        let index = nodes.len();
+        nodes.push(SkeletonNode {
+            name: node.name.clone(),
+            parent,
+            bind: assimp_matrix(&node.transformation),
+        });
+
+        for child in &node.children {
+            Self::collect_nodes(&child.borrow(), Some(index), nodes);
+        }
+    }
+
+    /// Index of the bone with the given name, adding it if it's new
+    ///
+    /// Meshes list their own bones, a bone shared by several meshes only gets one index.
+    fn bone_index(&mut self, bone: &russimp::bone::Bone) -> Option<usize> {
+        if let Some(index) = self.bone_names.get(&bone.name) {
+            return Some(*index);
+        }
+
+        let node = self.nodes.iter().position(|node| node.name == bone.name)?;
+        let index = self.bones.len();
+        self.bones.push(Bone {
+            node,
+            offset: assimp_matrix(&bone.offset_matrix),
+        });
+        self.bone_names.insert(bone.name.clone(), index);
+
+        Some(index)
+    }
+
+    pub fn bone_count(&self) -> usize {
+        self.bones.len()
+    }
+
+    /// The bone matrices for `clip` at `ticks`, or for the bind pose without a clip
+    ///
+    /// Nodes the clip doesn't animate keep their bind transform.
+    pub fn pose(&self, clip: Option<&AnimationClip>, ticks: f64) -> Vec<Matrix4<f32>> {
+        let mut globals: Vec<Matrix4<f32>> = Vec::with_capacity(self.nodes.len());
+
+        for node in &self.nodes {
+            let local = clip
+                .and_then(|clip| clip.channels.get(&node.name))
+                .map(|channel| channel.sample(ticks))
+                .unwrap_or(node.bind);
+
+            globals.push(match node.parent {
+                Some(parent) => globals[parent] * local,
+                None => local,
+            });
+        }
+
+        self.bones
+            .iter()
+            .map(|bone| globals[bone.node] * bone.offset)
+            .collect()
+    }
+}
+
+/// The keyframes of a single node, times are in ticks
+///
+/// Positions and scales are interpolated linearly and rotations spherically. Before the first and
+/// after the last key the value of that key is held.
+#[derive(Clone, Debug, Default)]
+pub struct Channel {
+    pub positions: Vec<(f64, Vector3<f32>)>,
+    pub rotations: Vec<(f64, UnitQuaternion<f32>)>,
+    pub scales: Vec<(f64, Vector3<f32>)>,
+}
+
+impl Channel {
+    /// The transform of the node relative to its parent
+    pub fn sample(&self, ticks: f64) -> Matrix4<f32> {
+        let position = sample_keys(&self.positions, ticks, |a, b, t| a.lerp(b, t))
+            .unwrap_or_else(Vector3::zeros);
+        let rotation = sample_keys(&self.rotations, ticks, |a, b, t| {
+            a.try_slerp(b, t, f32::EPSILON)
+                .unwrap_or(if t < 0.5 { *a } else { *b })
+        })
+        .unwrap_or_else(UnitQuaternion::identity);
+        let scale = sample_keys(&self.scales, ticks, |a, b, t| a.lerp(b, t))
+            .unwrap_or_else(|| Vector3::new(1.0, 1.0, 1.0));
+
+        Matrix4::new_translation(&position)
+            * rotation.to_homogeneous()
+            * Matrix4::new_nonuniform_scaling(&scale)
+    }
+}
+
+/// Interpolates between the keys around `ticks`, `None` when there are no keys
+fn sample_keys<T: Copy>(
+    keys: &[(f64, T)],
+    ticks: f64,
+    interpolate: impl Fn(&T, &T, f32) -> T,
+) -> Option<T> {
+    let (first, last) = (keys.first()?, keys.last()?);
+    if ticks <= first.0 {
+        return Some(first.1);
+    }
+    if ticks >= last.0 {
+        return Some(last.1);
+    }
+
+    // The first key after `ticks`, which can't be the first key because of the checks above
+    let next = keys.partition_point(|(time, _)| *time <= ticks);
+    let (start, end) = (&keys[next - 1], &keys[next]);
+    let t = ((ticks - start.0) / (end.0 - start.0)) as f32;
+
+    Some(interpolate(&start.1, &end.1, t))
+}
+
+/// A named animation of the nodes of a [`Skeleton`]
+#[derive(Clone, Debug)]
+pub struct AnimationClip {
+    name: String,
+    duration: f64,
+    ticks_per_second: f64,
+    channels: HashMap<String, Channel>,
+}
+
+impl AnimationClip {
+    /// `duration` is in ticks, channels are looked up by the name of the node they move
+    pub fn new(
+        name: impl Into<String>,
+        duration: f64,
+        ticks_per_second: f64,
+        channels: HashMap<String, Channel>,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            duration,
+            ticks_per_second: if ticks_per_second > 0.0 {
+                ticks_per_second
+            } else {
+                DEFAULT_TICKS_PER_SECOND
+            },
+            channels,
+        }
+    }
+
+    fn from_assimp(animation: &russimp::animation::Animation) -> Self {
+        let vector = |v: &russimp::Vector3D| Vector3::new(v.x, v.y, v.z);
+
+        let channels = animation
+            .channels
+            .iter()
+            .map(|channel| {
+                let positions = channel
+                    .position_keys
+                    .iter()
+                    .map(|key| (key.time, vector(&key.value)))
+                    .collect();
+                let rotations = channel
+                    .rotation_keys
+                    .iter()
+                    .map(|key| {
+                        let q = &key.value;
+                        let rotation = Quaternion::new(q.w, q.x, q.y, q.z);
+                        (key.time, UnitQuaternion::from_quaternion(rotation))
+                    })
+                    .collect();
+                let scales = channel
+                    .scaling_keys
+                    .iter()
+                    .map(|key| (key.time, vector(&key.value)))
+                    .collect();
+
+                let channel_keys = Channel {
+                    positions,
+                    rotations,
+                    scales,
+                };
+
+                (channel.name.clone(), channel_keys)
+            })
+            .collect();
+
+        Self::new(
+            animation.name.clone(),
+            animation.duration,
+            animation.ticks_per_second,
+            channels,
+        )
+    }
+
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_duration(&self) -> Duration {
+        Duration::from_secs_f64(self.duration / self.ticks_per_second)
+    }
+
+    /// Converts a time since the start of the clip to ticks, wrapping around when `looping`
+    pub fn ticks_at(&self, time: Duration, looping: bool) -> f64 {
+        let ticks = time.as_secs_f64() * self.ticks_per_second;
+
+        match looping && self.duration > 0.0 {
+            true => ticks % self.duration,
+            false => ticks.min(self.duration),
+        }
+    }
+}
+
+/// A [`Model`] with a skeleton that plays the animations stored in its file
+///
+/// Meshes with bones use [`PBR::load_skinned`], so each vertex can follow up to 4 of the first
+/// [`MAX_BONES`] bones of the model. Call [`advance`](Self::advance) every frame while playing.
+pub struct AnimatedModel {
+    model: Model<PBR>,
+    skeleton: Skeleton,
+    clips: Vec<AnimationClip>,
+    current: Option<usize>,
+    time: Duration,
+    playing: bool,
+    looping: bool,
+}
+
+impl AnimatedModel {
+    pub fn load_from_fs(
+        facade: &impl Facade,
+        path: impl AsRef<Path>,
+    ) -> Result<Self, RendererError> {
+        let options = LoadOptions {
+            hierarchical: true,
+            ..Default::default()
+        };
+        let (mut model, scene, mesh_indices) =
+            Model::<PBR>::load_scene(facade, path.as_ref(), options, None)?;
+        let mut skeleton = Skeleton::from_scene(&scene);
+
+        let mut weights: HashMap<usize, Rc<VertexBuffer<SkinWeights>>> = HashMap::new();
+        for (sub, mesh_index) in model.get_sub_models_mut().iter_mut().zip(mesh_indices) {
+            let mesh = &scene.meshes[mesh_index];
+            if mesh.bones.is_empty() {
+                continue;
+            }
+
+            let buffer = match weights.get(&mesh_index) {
+                Some(buffer) => buffer.clone(),
+                None => {
+                    let mut vertices = vec![SkinWeights::default(); mesh.vertices.len()];
+                    for bone in &mesh.bones {
+                        // Bones past MAX_BONES have no matrix in the shader, see the warning below
+                        let bone_id = match skeleton.bone_index(bone) {
+                            Some(bone_id) if bone_id < MAX_BONES => bone_id as i32,
+                            _ => continue,
+                        };
+
+                        for weight in &bone.weights {
+                            // Malformed files can name vertices the mesh doesn't have
+                            let vertex = match vertices.get_mut(weight.vertex_id as usize) {
+                                Some(vertex) => vertex,
+                                None => continue,
+                            };
+                            // LimitBoneWeights leaves at most 4 bones on a vertex
+                            if let Some(slot) = vertex.bone_weights.iter().position(|w| *w == 0.0) {
+                                vertex.bone_ids[slot] = bone_id;
+                                vertex.bone_weights[slot] = weight.weight;
+                            }
+                        }
+                    }
+
+                    // Skipped bones would otherwise pull their vertices toward the origin
+                    for vertex in &mut vertices {
+                        let total: f32 = vertex.bone_weights.iter().sum();
+                        if total > 0.0 {
+                            vertex.bone_weights.iter_mut().for_each(|w| *w /= total);
+                        }
+                    }
+
+                    let buffer = Rc::new(VertexBuffer::new(facade, &vertices)?);
+                    weights.insert(mesh_index, buffer.clone());
+                    buffer
+                }
+            };
+
+            let shader = sub.get_shader();
+            let mut skinned = PBR::load_skinned(facade, buffer);
+            skinned.set_pbr_params(shader.get_pbr_params().clone());
+            skinned.set_cull_mode(shader.get_cull_mode());
+            skinned.set_double_sided(shader.is_double_sided());
+            skinned.set_depth_bias(shader.get_depth_bias());
+            skinned.set_alpha_mode(shader.get_alpha_mode());
+            skinned.set_model_mat(shader.get_model_mat());
+            *sub.get_shader_mut() = skinned;
+
+            // The bone matrices already place the vertices relative to the root of the model
+            sub.set_node_mat(Matrix4::identity());
+        }
+
+        if skeleton.bone_count() > MAX_BONES {
+            eprintln!(
+                "{} has {} bones, only the first {} are animated and the rest are ignored",
+                path.as_ref().display(),
+                skeleton.bone_count(),
+                MAX_BONES
+            );
+        }
+
+        model.update_bounds();
+
+        let mut animated = Self {
+            model,
+            skeleton,
+            clips: scene
+                .animations
+                .iter()
+                .map(AnimationClip::from_assimp)
+                .collect(),
+            current: None,
+            time: Duration::ZERO,
+            playing: false,
+            looping: true,
+        };
+        animated.apply_pose();
+
+        Ok(animated)
+    }
+
+    /// Poses the model at `time` into the animation called `name` without playing it
+    ///
+    /// Returns false when the model has no animation with that name.
+    pub fn update_animation(&mut self, name: &str, time: Duration) -> bool {
+        let index = match self.clips.iter().position(|clip| clip.name == name) {
+            Some(index) => index,
+            None => return false,
+        };
+
+        self.current = Some(index);
+        self.time = time;
+        self.apply_pose();
+
+        true
+    }
+
+    /// Plays the animation called `name` from the start
+    ///
+    /// Returns false when the model has no animation with that name.
+    pub fn play(&mut self, name: &str) -> bool {
+        self.playing = self.update_animation(name, Duration::ZERO);
+        self.playing
+    }
+
+    pub fn pause(&mut self) {
+        self.playing = false;
+    }
+
+    /// Continues the current animation from where it was paused
+    pub fn resume(&mut self) {
+        self.playing = self.current.is_some();
+    }
+
+    /// Goes back to the bind pose
+    pub fn stop(&mut self) {
+        self.current = None;
+        self.playing = false;
+        self.time = Duration::ZERO;
+        self.apply_pose();
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    /// Non-looping animations stop on their last frame
+    pub fn set_looping(&mut self, looping: bool) {
+        self.looping = looping;
+    }
+
+    pub fn get_looping(&self) -> bool {
+        self.looping
+    }
+
+    /// Moves the current animation forward by `delta` if it's playing
+    pub fn advance(&mut self, delta: Duration) {
+        let clip = match (self.playing, self.current) {
+            (true, Some(current)) => &self.clips[current],
+            _ => return,
+        };
+
+        let duration = clip.get_duration();
+        self.time += delta;
+        if self.time >= duration {
+            match self.looping && !duration.is_zero() {
+                true => {
+                    let time = self.time.as_secs_f64() % duration.as_secs_f64();
+                    self.time = Duration::from_secs_f64(time);
+                }
+                false => {
+                    self.time = duration;
+                    self.playing = false;
+                }
+            }
+        }
+
+        self.apply_pose();
+    }
+
+    pub fn get_animation_names(&self) -> impl Iterator<Item = &str> {
+        self.clips.iter().map(|clip| clip.get_name())
+    }
+
+    pub fn get_current_animation(&self) -> Option<&AnimationClip> {
+        self.current.map(|current| &self.clips[current])
+    }
+
+    pub fn get_time(&self) -> Duration {
+        self.time
+    }
+
+    pub fn get_skeleton(&self) -> &Skeleton {
+        &self.skeleton
+    }
+
+    pub fn get_model(&self) -> &Model<PBR> {
+        &self.model
+    }
+
+    /// Changing the shaders of skinned sub models loses their bones
+    pub fn get_model_mut(&mut self) -> &mut Model<PBR> {
+        &mut self.model
+    }
+
+    pub fn publish<'a>(&'a self, scene: &mut RenderScene<'a>) {
+        self.model.publish(scene);
+    }
+
+    fn apply_pose(&mut self) {
+        let clip = self.get_current_animation();
+        let ticks = clip.map_or(0.0, |clip| clip.ticks_at(self.time, self.looping));
+        let bones = self.skeleton.pose(clip, ticks);
+
+        for sub in self.model.get_sub_models_mut() {
+            sub.get_shader_mut().set_bone_matrices(&bones);
+        }
+    }
+
+    pub fn debug_ui(&mut self, ui: &mut egui::Ui) {
+        let current = self
+            .get_current_animation()
+            .map_or("bind pose".to_owned(), |clip| clip.get_name().to_owned());
+
+        let mut selected = None;
+        egui::ComboBox::from_label("animation")
+            .selected_text(current)
+            .show_ui(ui, |ui| {
+                for name in self.get_animation_names() {
+                    if ui.selectable_label(false, name).clicked() {
+                        selected = Some(name.to_owned());
+                    }
+                }
+            });
+        if let Some(name) = selected {
+            self.play(&name);
+        }
+
+        ui.horizontal(|ui| {
+            if self.playing {
+                if ui.button("pause").clicked() {
+                    self.pause();
+                }
+            } else if ui.button("play").clicked() {
+                self.resume();
+            }
+            if ui.button("stop").clicked() {
+                self.stop();
+            }
+            ui.checkbox(&mut self.looping, "loop");
+        });
+
+        if let Some(duration) = self.get_current_animation().map(|clip| clip.get_duration()) {
+            let mut seconds = self.time.as_secs_f32();
+            let slider = egui::Slider::new(&mut seconds, 0.0..=duration.as_secs_f32()).text("time");
+            if ui.add(slider).changed() {
+                self.time = Duration::from_secs_f32(seconds);
+                self.apply_pose();
+            }
+        }
+
+        self.model.debug_ui(ui);
+    }
+}
@@ -0,0 +1,237 @@
+use crate::renderer::RenderScene;
+use crate::renderer::DEFAULT_LAYER;
+use crate::shader::Shader;
+use crate::shaders::unlit_textured::UnlitTextured;
+use crate::system_loop::font_definitions;
+use crate::utils::shapes;
+use crate::vertex::Vertex;
+use egui::epaint::text::Fonts;
+use egui::epaint::ImageData;
+use egui::Color32;
+use egui::FontId;
+use glium::backend::Facade;
+use glium::texture::MipmapsOption;
+use glium::texture::RawImage2d;
+use glium::texture::UncompressedFloatFormat;
+use glium::IndexBuffer;
+use glium::Texture2d;
+use glium::VertexBuffer;
+use image::Rgba;
+use image::RgbaImage;
+use nalgebra::Matrix4;
+use nalgebra::Vector3;
+use std::error::Error;
+use std::rc::Rc;
+
+/// Lays out `text` with the [ui font](font_definitions) and draws it into an image that is just
+/// large enough to hold it
+///
+/// `font_size` is in pixels. The glyphs are `color` with their coverage as alpha, everything else
+/// is transparent. Lines are broken at `\n` only.
+///
+/// A new glyph atlas is rasterized for every call, so it is meant for text that rarely changes.
+pub fn rasterize_text(text: &str, font_size: f32, color: [f32; 3]) -> RgbaImage {
+    let fonts = Fonts::new(1.0, 2048, font_definitions());
+    let galley = fonts.layout_no_wrap(
+        text.to_string(),
+        FontId::proportional(font_size),
+        Color32::WHITE,
+    );
+
+    // Nothing was rasterized before the layout, so the first delta is the whole atlas
+    let atlas = match fonts.font_image_delta().map(|delta| delta.image) {
+        Some(ImageData::Font(atlas)) => atlas,
+        _ => return RgbaImage::new(1, 1),
+    };
+
+    let width = (galley.size().x.ceil() as u32).max(1);
+    let height = (galley.size().y.ceil() as u32).max(1);
+    let mut image = RgbaImage::new(width, height);
+    let [r, g, b] = color.map(|channel| (channel.clamp(0.0, 1.0) * 255.0).round() as u8);
+
+    for glyph in galley.rows.iter().flat_map(|row| &row.glyphs) {
+        let uv_rect = glyph.uv_rect;
+        if uv_rect.is_nothing() {
+            continue;
+        }
+
+        let left = (glyph.pos.x + uv_rect.offset.x).round() as i64;
+        let top = (glyph.pos.y + uv_rect.offset.y).round() as i64;
+
+        for atlas_y in uv_rect.min[1] as usize..uv_rect.max[1] as usize {
+            for atlas_x in uv_rect.min[0] as usize..uv_rect.max[0] as usize {
+                let x = left + (atlas_x - uv_rect.min[0] as usize) as i64;
+                let y = top + (atlas_y - uv_rect.min[1] as usize) as i64;
+                if x < 0 || y < 0 || x >= width as i64 || y >= height as i64 {
+                    continue;
+                }
+
+                let coverage = atlas.pixels[atlas_y * atlas.size[0] + atlas_x];
+                let alpha = (coverage.clamp(0.0, 1.0) * 255.0).round() as u8;
+
+                // Neighbouring glyphs can overlap slightly, keep the strongest coverage
+                let pixel = image.get_pixel_mut(x as u32, y as u32);
+                *pixel = Rgba([r, g, b, pixel[3].max(alpha)]);
+            }
+        }
+    }
+
+    image
+}
+
+/// Text drawn at a point in the world that always faces the camera, ex. for object names or
+/// measurements
+///
+/// The text is rasterized with [`rasterize_text`] once and drawn on a quad with a transparent
+/// [`UnlitTextured`] material. The quad is [`get_height`](Self::get_height) world units tall and
+/// centered on the position, so like the rest of the scene it gets smaller with distance. Labels
+/// are hidden behind opaque geometry but not behind each other.
+pub struct BillboardText {
+    text: String,
+    font_size: f32,
+    color: [f32; 3],
+    position: Vector3<f32>,
+    height: f32,
+    aspect_ratio: f32,
+    vertex_buffer: VertexBuffer<Vertex>,
+    index_buffer: IndexBuffer<u32>,
+    material: UnlitTextured,
+}
+
+impl BillboardText {
+    /// White text at `position` that is half a unit tall
+    pub fn new(
+        facade: &impl Facade,
+        text: &str,
+        position: Vector3<f32>,
+    ) -> Result<Self, Box<dyn Error>> {
+        let font_size = 32.0;
+        let color = [1.0; 3];
+        let (texture, aspect_ratio) = Self::create_texture(facade, text, font_size, color)?;
+
+        let mut material = UnlitTextured::load_from_fs(facade, texture);
+        material.set_transparent(true);
+
+        let (vertex_buffer, index_buffer) = shapes::fullscreen_quad(facade);
+
+        Ok(Self {
+            text: text.to_string(),
+            font_size,
+            color,
+            position,
+            height: 0.5,
+            aspect_ratio,
+            vertex_buffer,
+            index_buffer,
+            material,
+        })
+    }
+
+    fn create_texture(
+        facade: &impl Facade,
+        text: &str,
+        font_size: f32,
+        color: [f32; 3],
+    ) -> Result<(Rc<Texture2d>, f32), Box<dyn Error>> {
+        let image = rasterize_text(text, font_size, color);
+        let (width, height) = image.dimensions();
+
+        // Flipped like every other texture so it is the right way up on the quad
+        let texture = Texture2d::with_format(
+            facade,
+            RawImage2d::from_raw_rgba_reversed(&image.into_raw(), (width, height)),
+            UncompressedFloatFormat::U8U8U8U8,
+            MipmapsOption::AutoGeneratedMipmaps,
+        )?;
+
+        Ok((Rc::new(texture), width as f32 / height as f32))
+    }
+
+    fn update_texture(&mut self, facade: &impl Facade) -> Result<(), Box<dyn Error>> {
+        let (texture, aspect_ratio) =
+            Self::create_texture(facade, &self.text, self.font_size, self.color)?;
+        self.material.set_texture(texture);
+        self.aspect_ratio = aspect_ratio;
+
+        Ok(())
+    }
+
+    pub fn get_text(&self) -> &str {
+        &self.text
+    }
+    /// Rasterizes the new text, the width of the quad changes to fit it
+    pub fn set_text(&mut self, facade: &impl Facade, text: &str) -> Result<(), Box<dyn Error>> {
+        self.text = text.to_string();
+        self.update_texture(facade)
+    }
+
+    pub fn get_color(&self) -> [f32; 3] {
+        self.color
+    }
+    /// Rasterizes the text again in the new color
+    pub fn set_color(
+        &mut self,
+        facade: &impl Facade,
+        color: [f32; 3],
+    ) -> Result<(), Box<dyn Error>> {
+        self.color = color;
+        self.update_texture(facade)
+    }
+
+    /// Size in pixels the text is rasterized at, 32 by default
+    ///
+    /// Only changes how sharp the text is, the size in the world is set by
+    /// [`set_height`](Self::set_height).
+    pub fn get_font_size(&self) -> f32 {
+        self.font_size
+    }
+    pub fn set_font_size(
+        &mut self,
+        facade: &impl Facade,
+        font_size: f32,
+    ) -> Result<(), Box<dyn Error>> {
+        self.font_size = font_size;
+        self.update_texture(facade)
+    }
+
+    pub fn get_position(&self) -> &Vector3<f32> {
+        &self.position
+    }
+    pub fn set_position(&mut self, position: Vector3<f32>) {
+        self.position = position;
+    }
+
+    /// Height of the quad in world units, 0.5 by default. The width follows from the text.
+    pub fn get_height(&self) -> f32 {
+        self.height
+    }
+    pub fn set_height(&mut self, height: f32) {
+        self.height = height;
+    }
+
+    /// The model matrix that turns the quad toward a camera with the given view matrix
+    pub fn get_model_mat(&self, view: &Matrix4<f32>) -> Matrix4<f32> {
+        // The inverse of the view's rotation brings the quad back to facing the camera
+        let rotation = view.fixed_view::<3, 3>(0, 0).transpose().to_homogeneous();
+        // The quad goes from -1 to 1
+        let scale = Vector3::new(self.height * self.aspect_ratio, self.height, 1.0) / 2.0;
+
+        Matrix4::new_translation(&self.position)
+            * rotation
+            * Matrix4::new_nonuniform_scaling(&scale)
+    }
+
+    /// Adds the label to the scene, facing the scene's camera
+    pub fn publish<'a>(&'a self, scene: &mut RenderScene<'a>) {
+        self.publish_in_layer(scene, DEFAULT_LAYER);
+    }
+    /// Same as [`publish`](Self::publish) in the given layers, see
+    /// [`RenderScene::publish_in_layer`]
+    pub fn publish_in_layer<'a>(&'a self, scene: &mut RenderScene<'a>, layer: u32) {
+        let view = scene.scene_data.camera.get_view_matrix();
+        let mut material = self.material.clone();
+        material.set_model_mat(self.get_model_mat(&view));
+
+        scene.publish_owned(&self.vertex_buffer, &self.index_buffer, material, layer);
+    }
+}
@@ -1,8 +1,34 @@
+use glium::glutin::event::DeviceEvent;
+use glium::glutin::event::ElementState;
+use glium::glutin::event::Event;
+use glium::glutin::event::KeyboardInput;
+use glium::glutin::event::MouseButton;
+use glium::glutin::event::MouseScrollDelta;
+use glium::glutin::event::VirtualKeyCode;
+use glium::glutin::event::WindowEvent;
 use nalgebra::Matrix4;
 use nalgebra::Vector3;
+use nalgebra::Vector4;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::time::Duration;
 
 const WORLD_UP: Vector3<f32> = Vector3::new(0.0, 1.0, 0.0);
 
+/// How the camera projects the scene onto the screen
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum ProjectionKind {
+    /// `fovy` is the vertical field of view in radians, the horizontal one follows from the
+    /// aspect ratio. `width` and `height` are only used for the aspect ratio.
+    Perspective { fovy: f32, width: f32, height: f32 },
+    Orthographic {
+        left: f32,
+        right: f32,
+        bottom: f32,
+        top: f32,
+    },
+}
+
 #[derive(Clone)]
 pub struct Camera {
     forward: Vector3<f32>,
@@ -12,9 +38,14 @@ pub struct Camera {
     yaw: f32,
     pitch: f32,
     roll: f32,
+    target: Option<Vector3<f32>>,
+    projection: ProjectionKind,
+    near: f32,
+    far: f32,
 }
 
 impl Camera {
+    /// Creates a camera with a perspective projection
     pub fn new() -> Self {
         let mut s = Self {
             forward: nalgebra::vector![0.0, 0.0, -1.0],
@@ -24,6 +55,14 @@ impl Camera {
             yaw: -std::f32::consts::PI / 2.0,
             pitch: 0.0,
             roll: 0.0,
+            target: None,
+            projection: ProjectionKind::Perspective {
+                fovy: 70.0f32.to_radians(),
+                width: 1.0,
+                height: 1.0,
+            },
+            near: 0.1,
+            far: 1000.0,
         };
 
         s.update_vectors();
@@ -31,22 +70,237 @@ impl Camera {
         s
     }
 
+    /// Creates a camera with a perspective projection at `eye` looking at `target`
+    ///
+    /// The yaw and pitch are derived from the direction, so the camera can be moved around
+    /// afterwards like any other. Use [`set_target`](Self::set_target) to keep looking at the
+    /// target while moving.
+    pub fn look_at(eye: Vector3<f32>, target: Vector3<f32>, up: Vector3<f32>) -> Self {
+        let mut s = Self::new();
+        s.position = eye;
+        s.up = up.normalize();
+        s.set_direction(target - eye);
+
+        s
+    }
+
+    /// Creates a camera with a perspective projection from a horizontal field of view in radians
+    ///
+    /// The projection still stores the vertical field of view, so resizing the camera afterwards
+    /// keeps the vertical one. Call [`set_fovx`](Self::set_fovx) again after resizing to keep
+    /// the horizontal one instead.
+    pub fn new_hfov(fovx: f32, width: f32, height: f32) -> Self {
+        let mut s = Self::new();
+        s.set_width(width);
+        s.set_height(height);
+        s.set_fovx(fovx);
+
+        s
+    }
+
+    /// Creates a camera with an orthographic projection
+    pub fn new_orthographic(
+        left: f32,
+        right: f32,
+        bottom: f32,
+        top: f32,
+        near: f32,
+        far: f32,
+    ) -> Self {
+        let mut s = Self::new();
+        s.projection = ProjectionKind::Orthographic {
+            left,
+            right,
+            bottom,
+            top,
+        };
+        s.near = near;
+        s.far = far;
+
+        s
+    }
+
+    pub fn get_projection_kind(&self) -> ProjectionKind {
+        self.projection
+    }
+    pub fn set_projection_kind(&mut self, projection: ProjectionKind) {
+        self.projection = projection;
+    }
+
+    pub fn get_near(&self) -> f32 {
+        self.near
+    }
+    /// Sets the distance to the near clipping plane, 0.1 by default
+    ///
+    /// Depth precision is mostly spent close to the near plane, so raising it does much more
+    /// against z-fighting than lowering the far plane.
+    pub fn set_near(&mut self, near: f32) {
+        self.near = near;
+    }
+
+    pub fn get_far(&self) -> f32 {
+        self.far
+    }
+    /// Sets the distance to the far clipping plane, 1000 by default
+    ///
+    /// Anything further away is clipped. A larger range between the near and far planes leaves
+    /// less depth precision for everything in between, causing z-fighting on distant geometry.
+    pub fn set_far(&mut self, far: f32) {
+        self.far = far;
+    }
+
+    /// Sets the width used for the aspect ratio. Does nothing for orthographic cameras.
+    pub fn set_width(&mut self, new_width: f32) {
+        if let ProjectionKind::Perspective { width, .. } = &mut self.projection {
+            *width = new_width;
+        }
+    }
+
+    /// Sets the height used for the aspect ratio. Does nothing for orthographic cameras.
+    pub fn set_height(&mut self, new_height: f32) {
+        if let ProjectionKind::Perspective { height, .. } = &mut self.projection {
+            *height = new_height;
+        }
+    }
+
+    /// Sets the vertical field of view in radians. Does nothing for orthographic cameras.
+    pub fn set_fovy(&mut self, new_fovy: f32) {
+        if let ProjectionKind::Perspective { fovy, .. } = &mut self.projection {
+            *fovy = new_fovy;
+        }
+    }
+
+    /// The vertical field of view in radians, `None` for orthographic cameras
+    pub fn get_fovy(&self) -> Option<f32> {
+        match self.projection {
+            ProjectionKind::Perspective { fovy, .. } => Some(fovy),
+            ProjectionKind::Orthographic { .. } => None,
+        }
+    }
+
+    /// The horizontal field of view in radians, derived from the vertical one and the aspect
+    /// ratio. `None` for orthographic cameras.
+    pub fn get_fovx(&self) -> Option<f32> {
+        let fovy = self.get_fovy()?;
+
+        Some(2.0 * ((fovy / 2.0).tan() * self.get_aspect_ratio()).atan())
+    }
+
+    /// Sets the vertical field of view so the horizontal one matches `fovx` at the current aspect
+    /// ratio. Does nothing for orthographic cameras.
+    pub fn set_fovx(&mut self, fovx: f32) {
+        let fovy = 2.0 * ((fovx / 2.0).tan() / self.get_aspect_ratio()).atan();
+        self.set_fovy(fovy);
+    }
+
+    /// Width divided by height of the projection
+    pub fn get_aspect_ratio(&self) -> f32 {
+        match self.projection {
+            ProjectionKind::Perspective { width, height, .. } => Self::aspect_ratio(width, height),
+            ProjectionKind::Orthographic {
+                left,
+                right,
+                bottom,
+                top,
+            } => Self::aspect_ratio(right - left, top - bottom),
+        }
+    }
+
+    /// Sets the aspect ratio without knowing the size of the surface, keeps the height
+    ///
+    /// Does nothing for orthographic cameras, their aspect ratio comes from their bounds.
+    pub fn set_aspect_ratio(&mut self, aspect_ratio: f32) {
+        if let ProjectionKind::Perspective { width, height, .. } = &mut self.projection {
+            if *height <= 0.0 {
+                *height = 1.0;
+            }
+            *width = aspect_ratio * *height;
+        }
+    }
+
+    /// Sets the aspect ratio from the size of the surface in pixels, ex. after the window is
+    /// resized. Does nothing for orthographic cameras.
+    pub fn update_aspect_from_dimensions(&mut self, width: u32, height: u32) {
+        self.set_width(width as f32);
+        self.set_height(height as f32);
+    }
+
+    /// A zero sized surface, ex. a minimized window, is treated as square
+    fn aspect_ratio(width: f32, height: f32) -> f32 {
+        if width > 0.0 && height > 0.0 {
+            width / height
+        } else {
+            1.0
+        }
+    }
+
+    /// The point the camera keeps looking at, if any
+    pub fn get_target(&self) -> Option<&Vector3<f32>> {
+        self.target.as_ref()
+    }
+    /// Keeps the camera looking at `target` wherever [`position`](Self::position) is moved to
+    ///
+    /// The orientation follows the target until it is cleared with `None` or the yaw or pitch
+    /// is set directly. Clearing it keeps the current orientation.
+    pub fn set_target(&mut self, target: Option<Vector3<f32>>) {
+        if target.is_none() {
+            self.set_direction(self.get_forward());
+        }
+        self.target = target;
+    }
+
+    /// The direction the camera is looking in
+    pub fn get_forward(&self) -> Vector3<f32> {
+        match self.target {
+            // Looking at a point from exactly on top of it has no direction, keep the last one
+            Some(target) if target != self.position => (target - self.position).normalize(),
+            _ => self.forward,
+        }
+    }
+
     pub fn get_yaw_rad(&self) -> f32 {
-        self.yaw
+        match self.target {
+            Some(_) => {
+                let forward = self.get_forward();
+                forward.z.atan2(forward.x)
+            }
+            None => self.yaw,
+        }
     }
     pub fn get_pitch_rad(&self) -> f32 {
-        self.pitch
+        match self.target {
+            Some(_) => self.get_forward().y.clamp(-1.0, 1.0).asin(),
+            None => self.pitch,
+        }
     }
+    /// Also stops following the [target](Self::set_target)
     pub fn set_yaw_rad(&mut self, yaw: f32) {
+        self.pitch = self.get_pitch_rad();
+        self.target = None;
         self.yaw = yaw;
         self.update_vectors();
     }
 
+    /// Also stops following the [target](Self::set_target)
     pub fn set_pitch_rad(&mut self, pitch: f32) {
+        self.yaw = self.get_yaw_rad();
+        self.target = None;
         self.pitch = pitch;
         self.update_vectors();
     }
 
+    /// Points the camera along `direction` by deriving the yaw and pitch from it
+    fn set_direction(&mut self, direction: Vector3<f32>) {
+        let direction = match direction.try_normalize(f32::EPSILON) {
+            Some(direction) => direction,
+            None => return,
+        };
+
+        self.yaw = direction.z.atan2(direction.x);
+        self.pitch = direction.y.clamp(-1.0, 1.0).asin();
+        self.update_vectors();
+    }
+
     fn update_vectors(&mut self) {
         //front.x = cos(glm::radians(Yaw)) * cos(glm::radians(Pitch));
         //front.y = sin(glm::radians(Pitch));
@@ -63,8 +317,400 @@ impl Camera {
     pub fn get_view_matrix(&self) -> Matrix4<f32> {
         Matrix4::look_at_rh(
             &self.position.into(),
-            &(self.position + self.forward).into(),
-            &WORLD_UP,
+            &(self.position + self.get_forward()).into(),
+            &self.up,
         )
     }
+
+    pub fn get_projection_matrix(&self) -> Matrix4<f32> {
+        match self.projection {
+            ProjectionKind::Perspective {
+                fovy,
+                width,
+                height,
+            } => Matrix4::new_perspective(
+                Self::aspect_ratio(width, height),
+                fovy,
+                self.near,
+                self.far,
+            ),
+            ProjectionKind::Orthographic {
+                left,
+                right,
+                bottom,
+                top,
+            } => Matrix4::new_orthographic(left, right, bottom, top, self.near, self.far),
+        }
+    }
+
+    /// Gets the ray going through a point on the screen
+    ///
+    /// `ndc_x` and `ndc_y` are in normalized device coordinates (`-1.0..=1.0`, with y pointing
+    /// up). Returns the ray's origin on the near plane and its normalized direction, both in
+    /// world space.
+    pub fn screen_ray(&self, ndc_x: f32, ndc_y: f32) -> (Vector3<f32>, Vector3<f32>) {
+        let inverse = match (self.get_projection_matrix() * self.get_view_matrix()).try_inverse() {
+            Some(inverse) => inverse,
+            None => return (self.position, self.get_forward()),
+        };
+
+        let unproject = |ndc_z: f32| {
+            let point = inverse * Vector4::new(ndc_x, ndc_y, ndc_z, 1.0);
+
+            point.xyz() / point.w
+        };
+
+        let near = unproject(-1.0);
+        let far = unproject(1.0);
+
+        (near, (far - near).normalize())
+    }
+
+    /// The eight corners of the volume the camera sees, in world space
+    ///
+    /// Indexed like [`Aabb::get_corners`](crate::utils::aabb::Aabb::get_corners): x is the first
+    /// bit, y the second, and z the third, so the first four corners are on the near plane. Can
+    /// be drawn with [`DebugDraw::frustum`](crate::shaders::debug_draw::DebugDraw::frustum).
+    pub fn frustum_corners(&self) -> [Vector3<f32>; 8] {
+        let inverse = match (self.get_projection_matrix() * self.get_view_matrix()).try_inverse() {
+            Some(inverse) => inverse,
+            None => return [self.position; 8],
+        };
+
+        std::array::from_fn(|i| {
+            let ndc = |bit: usize| match i & bit {
+                0 => -1.0,
+                _ => 1.0,
+            };
+            let point = inverse * Vector4::new(ndc(1), ndc(2), ndc(4), 1.0);
+
+            point.xyz() / point.w
+        })
+    }
+}
+
+/// What is needed to restore a [`Camera`], ex. to reproduce a view in a bug report
+///
+/// Saved with the rest of a [`SceneDescription`](crate::utils::scene_description::SceneDescription).
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CameraDescription {
+    pub position: [f32; 3],
+    /// Radians, ignored while there is a target
+    pub yaw: f32,
+    /// Radians, ignored while there is a target
+    pub pitch: f32,
+    pub target: Option<[f32; 3]>,
+    pub projection: ProjectionKind,
+    pub near: f32,
+    pub far: f32,
+}
+
+impl CameraDescription {
+    pub fn from_camera(camera: &Camera) -> Self {
+        Self {
+            position: camera.position.into(),
+            yaw: camera.get_yaw_rad(),
+            pitch: camera.get_pitch_rad(),
+            target: camera.get_target().map(|target| (*target).into()),
+            projection: camera.get_projection_kind(),
+            near: camera.get_near(),
+            far: camera.get_far(),
+        }
+    }
+
+    pub fn to_camera(&self) -> Camera {
+        let mut camera = Camera::new();
+        camera.position = self.position.into();
+        camera.yaw = self.yaw;
+        camera.pitch = self.pitch;
+        camera.update_vectors();
+        camera.target = self.target.map(Vector3::from);
+        camera.projection = self.projection;
+        camera.near = self.near;
+        camera.far = self.far;
+
+        camera
+    }
+}
+
+/// Orbits a [`Camera`] around a target point
+///
+/// Dragging with the left mouse button rotates around the target and scrolling zooms in and out.
+/// Events can either be passed in through [`handle_event`](Self::handle_event) (ex. from
+/// [`SystemLoop::subscribe_events`]) or driven directly with [`rotate`](Self::rotate) and
+/// [`zoom`](Self::zoom).
+///
+/// [`SystemLoop::subscribe_events`]: crate::system_loop::SystemLoop::subscribe_events
+#[derive(Clone, Debug)]
+pub struct OrbitController {
+    pub target: Vector3<f32>,
+    radius: f32,
+    yaw: f32,
+    pitch: f32,
+    /// Radians per pixel dragged
+    pub sensitivity: f32,
+    /// How much one line of scrolling scales the radius
+    pub zoom_speed: f32,
+    dragging: bool,
+    last_cursor: Option<(f64, f64)>,
+}
+
+impl OrbitController {
+    /// Keeps the pitch away from the poles, where the view matrix would flip
+    const MAX_PITCH: f32 = 89.0 * std::f32::consts::PI / 180.0;
+    const MIN_RADIUS: f32 = 0.01;
+
+    pub fn new(target: Vector3<f32>, radius: f32) -> Self {
+        Self {
+            target,
+            radius: radius.max(Self::MIN_RADIUS),
+            yaw: -std::f32::consts::PI / 2.0,
+            pitch: 0.0,
+            sensitivity: 0.005,
+            zoom_speed: 0.1,
+            dragging: false,
+            last_cursor: None,
+        }
+    }
+
+    pub fn get_radius(&self) -> f32 {
+        self.radius
+    }
+    pub fn set_radius(&mut self, radius: f32) {
+        self.radius = radius.max(Self::MIN_RADIUS);
+    }
+
+    pub fn get_yaw_rad(&self) -> f32 {
+        self.yaw
+    }
+    pub fn set_yaw_rad(&mut self, yaw: f32) {
+        self.yaw = yaw;
+    }
+
+    pub fn get_pitch_rad(&self) -> f32 {
+        self.pitch
+    }
+    pub fn set_pitch_rad(&mut self, pitch: f32) {
+        self.pitch = pitch.clamp(-Self::MAX_PITCH, Self::MAX_PITCH);
+    }
+
+    /// Rotates around the target by a mouse movement in pixels
+    pub fn rotate(&mut self, dx: f32, dy: f32) {
+        self.yaw += dx * self.sensitivity;
+        self.set_pitch_rad(self.pitch - dy * self.sensitivity);
+    }
+
+    /// Zooms by a number of scroll lines, positive values zoom in
+    pub fn zoom(&mut self, lines: f32) {
+        self.set_radius(self.radius * (1.0 - self.zoom_speed).powf(lines));
+    }
+
+    /// Updates the controller from a window event, returns true if the controller changed
+    pub fn handle_event(&mut self, event: &Event<'_, ()>) -> bool {
+        let event = match event {
+            Event::WindowEvent { event, .. } => event,
+            _ => return false,
+        };
+
+        match event {
+            WindowEvent::MouseInput {
+                state,
+                button: MouseButton::Left,
+                ..
+            } => {
+                self.dragging = *state == ElementState::Pressed;
+                false
+            }
+            WindowEvent::CursorMoved { position, .. } => {
+                let last_cursor = self.last_cursor.replace((position.x, position.y));
+
+                match last_cursor {
+                    Some((x, y)) if self.dragging => {
+                        self.rotate((position.x - x) as f32, (position.y - y) as f32);
+                        true
+                    }
+                    _ => false,
+                }
+            }
+            WindowEvent::CursorLeft { .. } => {
+                self.last_cursor = None;
+                false
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                let lines = match delta {
+                    MouseScrollDelta::LineDelta(_, y) => *y,
+                    // Roughly how many pixels are in a line
+                    MouseScrollDelta::PixelDelta(position) => position.y as f32 / 20.0,
+                };
+
+                self.zoom(lines);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Moves and rotates `camera` so it looks at the target
+    pub fn update_camera(&self, camera: &mut Camera) {
+        camera.set_yaw_rad(self.yaw);
+        camera.set_pitch_rad(self.pitch);
+        camera.position = self.target - camera.forward * self.radius;
+    }
+}
+
+/// Free flying [`Camera`] controlled with the keyboard and mouse
+///
+/// WASD moves relative to where the camera is facing horizontally, E and Q move up and down.
+/// Looking around only happens while the capture button (right mouse button by default) is held,
+/// so the left mouse button stays free for the ui. Events are passed in through
+/// [`handle_event`](Self::handle_event) (ex. from [`SystemLoop::subscribe_events`]) and the
+/// camera is moved once per frame with [`update_camera`](Self::update_camera).
+///
+/// The controller sees every event, including the ones meant for egui. Use
+/// [`set_pointer_blocked`](Self::set_pointer_blocked) and
+/// [`set_keyboard_blocked`](Self::set_keyboard_blocked) (ex. from egui's `wants_pointer_input`
+/// and `wants_keyboard_input`) to keep it from reacting to ui interactions.
+///
+/// [`SystemLoop::subscribe_events`]: crate::system_loop::SystemLoop::subscribe_events
+#[derive(Clone, Debug)]
+pub struct FlyController {
+    /// Units moved per second
+    pub speed: f32,
+    /// Scales the speed, ex. for quickly crossing large scenes
+    pub speed_multiplier: f32,
+    /// Radians per unit of mouse movement
+    pub sensitivity: f32,
+    pub capture_button: MouseButton,
+    pressed: HashSet<VirtualKeyCode>,
+    capturing: bool,
+    look_delta: (f32, f32),
+    pointer_blocked: bool,
+    keyboard_blocked: bool,
+}
+
+impl FlyController {
+    /// Keeps the pitch away from the poles, where the view matrix would flip
+    const MAX_PITCH: f32 = 89.0 * std::f32::consts::PI / 180.0;
+
+    pub fn new(speed: f32) -> Self {
+        Self {
+            speed,
+            speed_multiplier: 1.0,
+            sensitivity: 0.003,
+            capture_button: MouseButton::Right,
+            pressed: HashSet::new(),
+            capturing: false,
+            look_delta: (0.0, 0.0),
+            pointer_blocked: false,
+            keyboard_blocked: false,
+        }
+    }
+
+    /// Whether the capture button is held, mouse movement rotates the camera while it is
+    pub fn is_capturing(&self) -> bool {
+        self.capturing
+    }
+
+    /// Stops the capture button from starting to look around, ex. while the cursor is over ui
+    ///
+    /// Doesn't interrupt looking around that already started.
+    pub fn set_pointer_blocked(&mut self, blocked: bool) {
+        self.pointer_blocked = blocked;
+    }
+
+    /// Ignores key presses and releases all pressed keys, ex. while typing into a text field
+    pub fn set_keyboard_blocked(&mut self, blocked: bool) {
+        self.keyboard_blocked = blocked;
+
+        if blocked {
+            self.pressed.clear();
+        }
+    }
+
+    /// Updates the controller from a window or device event
+    pub fn handle_event(&mut self, event: &Event<'_, ()>) {
+        match event {
+            Event::WindowEvent { event, .. } => match event {
+                WindowEvent::KeyboardInput {
+                    input:
+                        KeyboardInput {
+                            virtual_keycode: Some(key),
+                            state,
+                            ..
+                        },
+                    ..
+                } => match state {
+                    ElementState::Pressed if !self.keyboard_blocked => {
+                        self.pressed.insert(*key);
+                    }
+                    ElementState::Pressed => (),
+                    ElementState::Released => {
+                        self.pressed.remove(key);
+                    }
+                },
+                WindowEvent::MouseInput { state, button, .. } if *button == self.capture_button => {
+                    self.capturing = match state {
+                        ElementState::Pressed => self.capturing || !self.pointer_blocked,
+                        ElementState::Released => false,
+                    };
+                }
+                // Releases never arrive when the window loses focus
+                WindowEvent::Focused(false) => {
+                    self.pressed.clear();
+                    self.capturing = false;
+                }
+                _ => (),
+            },
+            // Raw motion keeps working when the cursor hits the edge of the window
+            Event::DeviceEvent {
+                event: DeviceEvent::MouseMotion { delta },
+                ..
+            } if self.capturing => {
+                self.look_delta.0 += delta.0 as f32;
+                self.look_delta.1 += delta.1 as f32;
+            }
+            _ => (),
+        }
+    }
+
+    /// Applies the mouse movement since the last update and moves the camera by the pressed keys
+    ///
+    /// `delta` is the time since the last frame. Returns true if the camera changed.
+    pub fn update_camera(&mut self, camera: &mut Camera, delta: Duration) -> bool {
+        let mut changed = false;
+
+        let (dx, dy) = std::mem::take(&mut self.look_delta);
+        if dx != 0.0 || dy != 0.0 {
+            let pitch = camera.get_pitch_rad() - dy * self.sensitivity;
+            camera.set_yaw_rad(camera.get_yaw_rad() + dx * self.sensitivity);
+            camera.set_pitch_rad(pitch.clamp(-Self::MAX_PITCH, Self::MAX_PITCH));
+            changed = true;
+        }
+
+        let yaw = camera.get_yaw_rad();
+        let forward = Vector3::new(yaw.cos(), 0.0, yaw.sin());
+        let right = forward.cross(&WORLD_UP);
+
+        let mut direction = Vector3::zeros();
+        for (key, step) in [
+            (VirtualKeyCode::W, forward),
+            (VirtualKeyCode::S, -forward),
+            (VirtualKeyCode::D, right),
+            (VirtualKeyCode::A, -right),
+            (VirtualKeyCode::E, WORLD_UP),
+            (VirtualKeyCode::Q, -WORLD_UP),
+        ] {
+            if self.pressed.contains(&key) {
+                direction += step;
+            }
+        }
+
+        // Moving diagonally shouldn't be faster
+        if let Some(direction) = direction.try_normalize(f32::EPSILON) {
+            camera.position += direction * self.speed * self.speed_multiplier * delta.as_secs_f32();
+            changed = true;
+        }
+
+        changed
+    }
 }
@@ -1,9 +1,22 @@
+use crate::error::RendererError;
+use glium::backend::Facade;
+use glium::texture::Cubemap;
+use glium::texture::MipmapsOption;
 use glium::texture::RawImage2d;
+use glium::texture::TextureAnyImage;
+use glium::texture::UncompressedFloatFormat;
+use glium::GlObject;
+use glium::Rect;
 use glium::{pixel_buffer::PixelBuffer, texture::CubeLayer};
+use image::io::Reader as ImageReader;
 use image::DynamicImage;
+use std::error::Error;
+use std::path::Path;
+use std::path::PathBuf;
 
 type Pixel = (u8, u8, u8, u8);
 
+#[derive(Clone, Copy)]
 pub struct CubemapLayoutBuffer<'a> {
     pub x_pos: &'a PixelBuffer<Pixel>,
     pub x_neg: &'a PixelBuffer<Pixel>,
@@ -25,7 +38,9 @@ impl<'a> CubemapLayoutBuffer<'a> {
         }
     }
 
-    pub fn to_cubemap(&self) {}
+    pub fn to_cubemap(&self, facade: &impl Facade) -> Result<Cubemap, Box<dyn Error>> {
+        Ok(CubemapLayout::try_from(*self)?.to_cubemap(facade)?)
+    }
 }
 
 pub struct CubemapLayout {
@@ -38,6 +53,20 @@ pub struct CubemapLayout {
 }
 
 impl CubemapLayout {
+    /// Creates a layout from faces in the order of [`CubemapLoader::LAYERS`]
+    pub fn from_array(faces: [DynamicImage; 6]) -> Self {
+        let [x_pos, x_neg, y_pos, y_neg, z_pos, z_neg] = faces;
+
+        Self {
+            x_pos,
+            x_neg,
+            y_pos,
+            y_neg,
+            z_pos,
+            z_neg,
+        }
+    }
+
     pub fn get_from_gl_enum(&self, layer: CubeLayer) -> &DynamicImage {
         match layer {
             CubeLayer::PositiveX => &self.x_pos,
@@ -49,7 +78,35 @@ impl CubemapLayout {
         }
     }
 
-    pub fn to_cubemap(self) {}
+    pub fn to_cubemap(&self, facade: &impl Facade) -> Result<Cubemap, RendererError> {
+        CubemapLoader::load_cubemap(facade, self)
+    }
+
+    /// Saves every face to `directory` with the names [`CubemapLoader::load_from_fs`] expects
+    ///
+    /// The directory is created if it doesn't exist. The format is inferred from `extension`,
+    /// faces are only kept as floats when saved as `exr`.
+    pub fn save_to_fs(
+        &self,
+        directory: impl AsRef<Path>,
+        extension: &str,
+    ) -> Result<(), RendererError> {
+        std::fs::create_dir_all(directory.as_ref())?;
+
+        let paths = CubemapLoader::create_paths(directory, extension);
+        for (layer, path) in CubemapLoader::LAYERS.iter().zip(paths) {
+            let image = self.get_from_gl_enum(*layer);
+
+            // Most formats (ex. jpeg) don't support an alpha channel
+            match extension {
+                "exr" => DynamicImage::ImageRgb32F(image.to_rgb32f()).save(path)?,
+                "png" | "tiff" | "tif" | "webp" => image.save(path)?,
+                _ => DynamicImage::ImageRgb8(image.to_rgb8()).save(path)?,
+            }
+        }
+
+        Ok(())
+    }
 }
 
 impl<'a> TryFrom<CubemapLayoutBuffer<'a>> for CubemapLayout {
@@ -81,3 +138,331 @@ impl<'a> TryFrom<CubemapLayoutBuffer<'a>> for CubemapLayout {
         })
     }
 }
+
+pub struct CubemapLoader {}
+
+impl CubemapLoader {
+    /// The order that the faces are stored in OpenGL
+    pub const LAYERS: [CubeLayer; 6] = [
+        CubeLayer::PositiveX,
+        CubeLayer::NegativeX,
+        CubeLayer::PositiveY,
+        CubeLayer::NegativeY,
+        CubeLayer::PositiveZ,
+        CubeLayer::NegativeZ,
+    ];
+
+    /// The file names of each face, in the same order as [`LAYERS`](Self::LAYERS)
+    pub const FACE_NAMES: [&'static str; 6] = ["right", "left", "top", "bottom", "front", "back"];
+
+    /// Gets the path of each face in `directory`, in the same order as [`LAYERS`](Self::LAYERS)
+    pub fn create_paths(directory: impl AsRef<Path>, extension: &str) -> [PathBuf; 6] {
+        Self::FACE_NAMES.map(|name| directory.as_ref().join(format!("{}.{}", name, extension)))
+    }
+
+    /// Loads a cubemap from a directory containing `right`, `left`, `top`, `bottom`, `front`,
+    /// and `back` images
+    ///
+    /// Every face is checked to exist before any of them are decoded, the error names the first
+    /// face that is missing.
+    pub fn load_from_fs(
+        facade: &impl Facade,
+        directory: impl AsRef<Path>,
+        extension: &str,
+    ) -> Result<Cubemap, RendererError> {
+        let layout = Self::open_faces(directory, extension)?;
+
+        Self::load_cubemap(facade, &layout)
+    }
+
+    /// Loads a cubemap saved by [`save_to_fs`](Self::save_to_fs), keeping its mip levels
+    ///
+    /// The main level is loaded from `directory` like [`load_from_fs`](Self::load_from_fs), every
+    /// following level from the `mip1`, `mip2`, ... directories inside it until one is missing.
+    pub fn load_from_fs_with_mipmaps(
+        facade: &impl Facade,
+        directory: impl AsRef<Path>,
+        extension: &str,
+    ) -> Result<Cubemap, RendererError> {
+        let directory = directory.as_ref();
+
+        let mut levels = vec![Self::open_faces(directory, extension)?];
+        loop {
+            let level_directory = directory.join(Self::mip_directory(levels.len()));
+            if !level_directory.is_dir() {
+                break;
+            }
+
+            levels.push(Self::open_faces(level_directory, extension)?);
+        }
+
+        Self::load_from_images_with_mipmaps(facade, levels)
+    }
+
+    /// Decodes the six faces in `directory`, checking that all of them exist first
+    fn open_faces(
+        directory: impl AsRef<Path>,
+        extension: &str,
+    ) -> Result<CubemapLayout, RendererError> {
+        let paths = Self::create_paths(directory, extension);
+        for (face, path) in Self::FACE_NAMES.iter().zip(&paths) {
+            if !path.is_file() {
+                return Err(RendererError::MissingCubemapFace {
+                    face,
+                    path: path.clone(),
+                });
+            }
+        }
+
+        let [x_pos, x_neg, y_pos, y_neg, z_pos, z_neg] = paths;
+        let open = |path: PathBuf| -> Result<DynamicImage, RendererError> {
+            Ok(ImageReader::open(path)?.decode()?)
+        };
+
+        Ok(CubemapLayout {
+            x_pos: open(x_pos)?,
+            x_neg: open(x_neg)?,
+            y_pos: open(y_pos)?,
+            y_neg: open(y_neg)?,
+            z_pos: open(z_pos)?,
+            z_neg: open(z_neg)?,
+        })
+    }
+
+    /// The directory [`save_to_fs`](Self::save_to_fs) puts mip `level` in
+    fn mip_directory(level: usize) -> String {
+        format!("mip{}", level)
+    }
+
+    /// Reads every mip level of every face of `cubemap` back from the gpu
+    ///
+    /// Faces are read as floats, so HDR values are kept. The first layout is the main level,
+    /// the same order [`load_from_images_with_mipmaps`](Self::load_from_images_with_mipmaps)
+    /// expects.
+    pub fn read_cubemap(cubemap: &Cubemap) -> Result<Vec<CubemapLayout>, RendererError> {
+        (0..cubemap.get_mipmap_levels())
+            .map(|level| Self::read_level(cubemap, level))
+            .collect()
+    }
+
+    /// Reads the six faces of mip `level` of `cubemap`
+    fn read_level(cubemap: &Cubemap, level: u32) -> Result<CubemapLayout, RendererError> {
+        let mipmap = cubemap
+            .mipmap(level)
+            .ok_or_else(|| RendererError::Glium(format!("cubemap has no mip level {}", level)))?;
+        let resolution = (cubemap.dimensions() >> level).max(1);
+        let rect = Rect {
+            left: 0,
+            bottom: 0,
+            width: resolution,
+            height: resolution,
+        };
+
+        let [x_pos, x_neg, y_pos, y_neg, z_pos, z_neg] = Self::LAYERS.map(|layer| {
+            let image: TextureAnyImage = mipmap.image(layer).into();
+            let data: RawImage2d<f32> = image.raw_read::<_, (f32, f32, f32)>(&rect);
+
+            image::Rgb32FImage::from_raw(resolution, resolution, data.data.into_owned())
+                .map(DynamicImage::ImageRgb32F)
+                .ok_or_else(|| RendererError::Glium("failed to read cubemap face".to_string()))
+        });
+
+        Ok(CubemapLayout {
+            x_pos: x_pos?,
+            x_neg: x_neg?,
+            y_pos: y_pos?,
+            y_neg: y_neg?,
+            z_pos: z_pos?,
+            z_neg: z_neg?,
+        })
+    }
+
+    /// Saves every mip level of `cubemap` as `exr` so it can be loaded again with
+    /// [`load_from_fs_with_mipmaps`](Self::load_from_fs_with_mipmaps)
+    ///
+    /// The main level is saved in `directory`, so [`load_from_fs`](Self::load_from_fs) can also
+    /// load it and regenerate the mipmaps. Every following level is saved in a `mip1`, `mip2`,
+    /// ... directory inside it. Useful for caching prefiltered environment maps, whose mip levels
+    /// can't be regenerated.
+    pub fn save_to_fs(cubemap: &Cubemap, directory: impl AsRef<Path>) -> Result<(), RendererError> {
+        let directory = directory.as_ref();
+
+        for (level, layout) in Self::read_cubemap(cubemap)?.iter().enumerate() {
+            match level {
+                0 => layout.save_to_fs(directory, "exr")?,
+                level => layout.save_to_fs(directory.join(Self::mip_directory(level)), "exr")?,
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Uploads faces that are already in memory (ex. generated or downloaded), the same as
+    /// [`load_cubemap`](Self::load_cubemap)
+    pub fn load_from_images(
+        facade: &impl Facade,
+        layout: CubemapLayout,
+    ) -> Result<Cubemap, RendererError> {
+        Self::load_cubemap(facade, &layout)
+    }
+
+    /// Uploads every mip level from memory instead of generating them
+    ///
+    /// The first layout is the main level, every following level has to be half the size of the
+    /// previous one (rounded down, at least 1). Useful for prefiltered environment maps that were
+    /// computed elsewhere.
+    pub fn load_from_images_with_mipmaps(
+        facade: &impl Facade,
+        levels: Vec<CubemapLayout>,
+    ) -> Result<Cubemap, RendererError> {
+        let resolution = levels.first().map_or(0, |layout| layout.x_pos.width());
+        let mipmaps = levels.len() as u32;
+
+        // A 1x1 level is the smallest, there can't be more levels after it
+        if mipmaps == 0 || resolution == 0 || mipmaps > 32 - resolution.leading_zeros() {
+            return Err(RendererError::InvalidResolution {
+                resolution,
+                mipmaps,
+            });
+        }
+
+        for (level, layout) in levels.iter().enumerate() {
+            Self::check_faces(layout, (resolution >> level).max(1))?;
+        }
+
+        let cubemap = Cubemap::empty_with_format(
+            facade,
+            UncompressedFloatFormat::F16F16F16,
+            MipmapsOption::EmptyMipmapsMax(mipmaps - 1),
+            resolution,
+        )?;
+
+        for (level, layout) in levels.iter().enumerate() {
+            Self::write_faces(facade, &cubemap, layout, level as u32);
+        }
+
+        Ok(cubemap)
+    }
+
+    /// Uploads the six faces of `layout` to a new cubemap
+    ///
+    /// Float images (ex. `DynamicImage::ImageRgb32F`) are uploaded as floats so HDR data doesn't
+    /// get clamped, every other image is uploaded as 8-bit. This uses raw OpenGL, so the `gl`
+    /// functions have to be loaded first (done in [`SystemLoop::new`]).
+    ///
+    /// Every face has to be square and the same size, otherwise an error naming the first face
+    /// that isn't is returned.
+    ///
+    /// [`SystemLoop::new`]: crate::system_loop::SystemLoop::new
+    pub fn load_cubemap(
+        facade: &impl Facade,
+        layout: &CubemapLayout,
+    ) -> Result<Cubemap, RendererError> {
+        let resolution = layout.x_pos.width();
+        Self::check_faces(layout, resolution)?;
+
+        let cubemap = Cubemap::empty_with_format(
+            facade,
+            UncompressedFloatFormat::F16F16F16,
+            MipmapsOption::AutoGeneratedMipmaps,
+            resolution,
+        )?;
+
+        Self::write_faces(facade, &cubemap, layout, 0);
+
+        unsafe {
+            cubemap.generate_mipmaps();
+        }
+
+        Ok(cubemap)
+    }
+
+    /// Checks that every face is `resolution` by `resolution`
+    fn check_faces(layout: &CubemapLayout, resolution: u32) -> Result<(), RendererError> {
+        for (layer, face) in Self::LAYERS.iter().zip(Self::FACE_NAMES) {
+            let image = layout.get_from_gl_enum(*layer);
+            let size = (image.width(), image.height());
+
+            if size != (resolution, resolution) {
+                return Err(RendererError::CubemapFaceSize {
+                    face,
+                    size,
+                    expected: (resolution, resolution),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes every face of `layout` to mip `level` of `cubemap`
+    fn write_faces(facade: &impl Facade, cubemap: &Cubemap, layout: &CubemapLayout, level: u32) {
+        for layer in Self::LAYERS {
+            let image = layout.get_from_gl_enum(layer);
+
+            match image {
+                DynamicImage::ImageRgb32F(_) | DynamicImage::ImageRgba32F(_) => {
+                    let data = image.to_rgb32f();
+                    Self::write_face(facade, cubemap, layer, level, gl::FLOAT, data.as_ptr() as _);
+                }
+                _ => {
+                    let data = image.to_rgb8();
+                    Self::write_face(
+                        facade,
+                        cubemap,
+                        layer,
+                        level,
+                        gl::UNSIGNED_BYTE,
+                        data.as_ptr() as _,
+                    );
+                }
+            }
+        }
+    }
+
+    /// Writes tightly packed rgb `data` of type `ty` to a face of `cubemap`
+    fn write_face(
+        facade: &impl Facade,
+        cubemap: &Cubemap,
+        layer: CubeLayer,
+        level: u32,
+        ty: gl::types::GLenum,
+        data: *const std::ffi::c_void,
+    ) {
+        let target = gl::TEXTURE_CUBE_MAP_POSITIVE_X
+            + Self::LAYERS.iter().position(|l| *l == layer).unwrap() as u32;
+        let resolution = (cubemap.dimensions() >> level).max(1) as i32;
+
+        // glium caches the OpenGL state, so everything that is changed has to be restored
+        unsafe {
+            facade.get_context().exec_in_context(|| {
+                let mut bound_texture = 0;
+                let mut unpack_buffer = 0;
+                let mut unpack_alignment = 0;
+                gl::GetIntegerv(gl::TEXTURE_BINDING_CUBE_MAP, &mut bound_texture);
+                gl::GetIntegerv(gl::PIXEL_UNPACK_BUFFER_BINDING, &mut unpack_buffer);
+                gl::GetIntegerv(gl::UNPACK_ALIGNMENT, &mut unpack_alignment);
+
+                gl::BindTexture(gl::TEXTURE_CUBE_MAP, cubemap.get_id());
+                gl::BindBuffer(gl::PIXEL_UNPACK_BUFFER, 0);
+                gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+
+                gl::TexSubImage2D(
+                    target,
+                    level as i32,
+                    0,
+                    0,
+                    resolution,
+                    resolution,
+                    gl::RGB,
+                    ty,
+                    data,
+                );
+
+                gl::PixelStorei(gl::UNPACK_ALIGNMENT, unpack_alignment);
+                gl::BindBuffer(gl::PIXEL_UNPACK_BUFFER, unpack_buffer as u32);
+                gl::BindTexture(gl::TEXTURE_CUBE_MAP, bound_texture as u32);
+            });
+        }
+    }
+}
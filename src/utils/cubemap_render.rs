@@ -0,0 +1,193 @@
+//! Rendering into the six faces of a cubemap
+
+use crate::renderer::Renderable;
+use crate::utils::camera::Camera;
+use crate::utils::texture_registry::TextureRegistry;
+use glium::backend::Facade;
+use glium::framebuffer::SimpleFrameBuffer;
+use glium::texture::{CubeLayer, Cubemap, DepthTexture2d};
+use glium::Surface;
+use nalgebra::{Matrix4, Vector3};
+use std::error::Error;
+use std::rc::Rc;
+
+/// The faces of a cubemap in the order of [`CAMERA_DIRECTIONS`]
+pub const CUBE_LAYERS: [CubeLayer; 6] = [
+    CubeLayer::PositiveX,
+    CubeLayer::NegativeX,
+    CubeLayer::PositiveY,
+    CubeLayer::NegativeY,
+    CubeLayer::PositiveZ,
+    CubeLayer::NegativeZ,
+];
+
+/// The direction and up vector of the camera for each face in [`CUBE_LAYERS`]
+///
+/// The up vectors follow the OpenGL cubemap convention, so a face rendered with these doesn't
+/// have to be flipped.
+pub const CAMERA_DIRECTIONS: [([f32; 3], [f32; 3]); 6] = [
+    ([1.0, 0.0, 0.0], [0.0, -1.0, 0.0]),
+    ([-1.0, 0.0, 0.0], [0.0, -1.0, 0.0]),
+    ([0.0, 1.0, 0.0], [0.0, 0.0, 1.0]),
+    ([0.0, -1.0, 0.0], [0.0, 0.0, -1.0]),
+    ([0.0, 0.0, 1.0], [0.0, -1.0, 0.0]),
+    ([0.0, 0.0, -1.0], [0.0, -1.0, 0.0]),
+];
+
+/// View matrices looking from the origin at each face in [`CUBE_LAYERS`]
+pub fn camera_directions() -> [Matrix4<f32>; 6] {
+    CAMERA_DIRECTIONS
+        .map(|(direction, up)| Matrix4::look_at_rh(&[0.0; 3].into(), &direction.into(), &up.into()))
+}
+
+/// A cubemap that is rendered from a point in the scene, for reflections of dynamic objects
+///
+/// Call [`update`](Self::update) every frame with a closure that renders the scene, it is called
+/// once per face with the camera of that face. The result can be used as the environment of a
+/// [`PBRSkybox`](crate::utils::pbr_skybox::PBRSkybox) or filtered with
+/// [`Prefilter`](crate::shaders::prefilter::Prefilter) first for rough materials.
+///
+/// The faces are stored as 16 bit floats, so the scene should be rendered without tone mapping
+/// to keep the bright parts bright.
+pub struct DynamicCubemap {
+    cubemap: Rc<Cubemap>,
+    depth: DepthTexture2d,
+    position: Vector3<f32>,
+    near: f32,
+    far: f32,
+    update_interval: u32,
+    frames_until_update: u32,
+}
+
+impl DynamicCubemap {
+    /// Creates an empty cubemap with faces of `resolution` by `resolution` pixels
+    pub fn new(facade: &impl Facade, resolution: u32) -> Result<Self, Box<dyn Error>> {
+        let cubemap = Cubemap::empty_with_format(
+            facade,
+            glium::texture::UncompressedFloatFormat::F16F16F16,
+            glium::texture::MipmapsOption::AutoGeneratedMipmaps,
+            resolution,
+        )?;
+        let depth = DepthTexture2d::empty(facade, resolution, resolution)?;
+
+        let cubemap = Rc::new(cubemap);
+        TextureRegistry::track_cubemap(&cubemap);
+
+        Ok(Self {
+            cubemap,
+            depth,
+            position: Vector3::zeros(),
+            near: 0.1,
+            far: 1000.0,
+            update_interval: 1,
+            frames_until_update: 0,
+        })
+    }
+
+    /// The rendered faces, only changes when the cubemap is updated
+    pub fn get_cubemap(&self) -> &Rc<Cubemap> {
+        &self.cubemap
+    }
+
+    pub fn get_resolution(&self) -> u32 {
+        self.cubemap.get_width()
+    }
+
+    /// The point in the world the faces are rendered from
+    pub fn get_position(&self) -> &Vector3<f32> {
+        &self.position
+    }
+    pub fn set_position(&mut self, position: Vector3<f32>) {
+        self.position = position;
+    }
+
+    pub fn get_near(&self) -> f32 {
+        self.near
+    }
+    /// The near clipping plane of each face, 0.1 by default
+    ///
+    /// Should be large enough to clip the object using the reflection when it is at
+    /// [`position`](Self::get_position), otherwise it reflects its own inside.
+    pub fn set_near(&mut self, near: f32) {
+        self.near = near;
+    }
+
+    pub fn get_far(&self) -> f32 {
+        self.far
+    }
+    /// The far clipping plane of each face, 1000 by default
+    pub fn set_far(&mut self, far: f32) {
+        self.far = far;
+    }
+
+    pub fn get_update_interval(&self) -> u32 {
+        self.update_interval
+    }
+    /// Only render the faces every `update_interval` calls to [`update`](Self::update), 1 by
+    /// default
+    ///
+    /// Rendering the scene six more times is expensive, and reflections rarely need to keep up
+    /// with every frame. Values below 1 are treated as 1.
+    pub fn set_update_interval(&mut self, update_interval: u32) {
+        self.update_interval = update_interval.max(1);
+        self.frames_until_update = self.frames_until_update.min(self.update_interval - 1);
+    }
+
+    /// Renders the faces if enough frames passed since the last time
+    ///
+    /// Returns true if the faces were rendered. The first call always renders.
+    pub fn update(
+        &mut self,
+        facade: &impl Facade,
+        render: impl FnMut(&mut Renderable, &Camera),
+    ) -> Result<bool, Box<dyn Error>> {
+        if self.frames_until_update > 0 {
+            self.frames_until_update -= 1;
+            return Ok(false);
+        }
+
+        self.render(facade, render)?;
+
+        Ok(true)
+    }
+
+    /// Renders the faces right away, ignoring the update interval
+    ///
+    /// `render` is called once per face with a surface already cleared to black and a 90 degree
+    /// camera at [`position`](Self::get_position) looking at that face. Its view and projection
+    /// matrices should be used for the scene, ex. by setting it as [`SceneData::camera`] and its
+    /// projection matrix as [`SceneData::projection`].
+    ///
+    /// [`SceneData::camera`]: crate::renderer::SceneData::camera
+    /// [`SceneData::projection`]: crate::renderer::SceneData::projection
+    pub fn render(
+        &mut self,
+        facade: &impl Facade,
+        mut render: impl FnMut(&mut Renderable, &Camera),
+    ) -> Result<(), Box<dyn Error>> {
+        for (layer, (direction, up)) in CUBE_LAYERS.into_iter().zip(CAMERA_DIRECTIONS) {
+            let image = self.cubemap.main_level().image(layer);
+            let mut fb = SimpleFrameBuffer::with_depth_buffer(facade, image, &self.depth)?;
+            fb.clear_color_and_depth((0.0, 0.0, 0.0, 1.0), 1.0);
+
+            let mut camera = Camera::look_at(
+                self.position,
+                self.position + Vector3::from(direction),
+                up.into(),
+            );
+            camera.set_fovy(90.0f32.to_radians());
+            camera.set_near(self.near);
+            camera.set_far(self.far);
+
+            render(&mut Renderable::from(&mut fb), &camera);
+        }
+
+        unsafe {
+            self.cubemap.generate_mipmaps();
+        }
+
+        self.frames_until_update = self.update_interval - 1;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,34 @@
+//! Helpers for lists that are edited by index, like the models of a scene
+
+/// Removes the items at every index in `indices` and returns them in their original order
+///
+/// Indices refer to positions before anything is removed, so the remaining items don't shift
+/// under the indices still to be removed. Duplicates and indices past the end are ignored. Useful
+/// when a UI collects the models to delete while iterating over them:
+///
+/// ```
+/// use opengl_renderer::utils::indices::remove_indices;
+///
+/// let mut models = vec!["sphere", "cube", "plane", "teapot"];
+/// let removed = remove_indices(&mut models, &[3, 0]);
+///
+/// assert_eq!(models, ["cube", "plane"]);
+/// assert_eq!(removed, ["sphere", "teapot"]);
+/// ```
+pub fn remove_indices<T>(items: &mut Vec<T>, indices: &[usize]) -> Vec<T> {
+    let mut remove = vec![false; items.len()];
+    for &index in indices {
+        if let Some(remove) = remove.get_mut(index) {
+            *remove = true;
+        }
+    }
+
+    let (removed, kept) = std::mem::take(items)
+        .into_iter()
+        .zip(remove)
+        .partition::<Vec<_>, _>(|(_, remove)| *remove);
+
+    *items = kept.into_iter().map(|(item, _)| item).collect();
+
+    removed.into_iter().map(|(item, _)| item).collect()
+}
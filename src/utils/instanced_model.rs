@@ -0,0 +1,116 @@
+use crate::renderer::RenderScene;
+use crate::shader::Shader;
+use crate::shaders::pbr::PBR;
+use crate::utils::model::Model;
+use crate::vertex::Instance;
+use crate::vertex::Vertex;
+use glium::backend::Context;
+use glium::backend::Facade;
+use glium::vertex::BufferCreationError;
+use glium::IndexBuffer;
+use glium::VertexBuffer;
+use std::rc::Rc;
+
+struct InstancedSubModel {
+    vertex_buffer: Rc<VertexBuffer<Vertex>>,
+    index_buffer: Rc<IndexBuffer<u32>>,
+    shader: PBR,
+}
+
+/// Draws the same geometry many times with one draw call per sub model
+///
+/// Every instance has its own model matrix and albedo tint (see [`Instance`]). Shadows, picking,
+/// and transparency sorting treat all of the instances as a single object.
+pub struct InstancedModel {
+    sub_models: Vec<InstancedSubModel>,
+    instances: Rc<VertexBuffer<Instance>>,
+    facade: Rc<Context>,
+}
+
+impl InstancedModel {
+    pub fn new(
+        facade: &impl Facade,
+        vb: VertexBuffer<Vertex>,
+        ib: IndexBuffer<u32>,
+        instances: &[Instance],
+    ) -> Result<Self, BufferCreationError> {
+        let mut model = Self {
+            sub_models: vec![InstancedSubModel {
+                vertex_buffer: Rc::new(vb),
+                index_buffer: Rc::new(ib),
+                shader: PBR::load_instanced(facade),
+            }],
+            instances: Rc::new(VertexBuffer::empty(facade, 0)?),
+            facade: facade.get_context().clone(),
+        };
+
+        model.set_instances(instances)?;
+
+        Ok(model)
+    }
+
+    /// Instances every sub model of `model`, sharing its buffers and textures
+    pub fn from_model(
+        facade: &impl Facade,
+        model: &Model<PBR>,
+        instances: &[Instance],
+    ) -> Result<Self, BufferCreationError> {
+        let sub_models = model
+            .get_sub_models()
+            .iter()
+            .map(|sub| {
+                let mut shader = PBR::load_instanced(facade);
+                shader.set_pbr_params(sub.get_shader().get_pbr_params().clone());
+                shader.set_cull_mode(sub.get_shader().get_cull_mode());
+                shader.set_alpha_mode(sub.get_shader().get_alpha_mode());
+                shader.set_model_mat(sub.get_shader().get_model_mat());
+
+                InstancedSubModel {
+                    vertex_buffer: sub.get_vertex_buffer().clone(),
+                    index_buffer: sub.get_index_buffer().clone(),
+                    shader,
+                }
+            })
+            .collect();
+
+        let mut model = Self {
+            sub_models,
+            instances: Rc::new(VertexBuffer::empty(facade, 0)?),
+            facade: facade.get_context().clone(),
+        };
+
+        model.set_instances(instances)?;
+
+        Ok(model)
+    }
+
+    /// Replaces every instance, uploading them to the gpu
+    pub fn set_instances(&mut self, instances: &[Instance]) -> Result<(), BufferCreationError> {
+        self.instances = Rc::new(VertexBuffer::dynamic(&self.facade, instances)?);
+
+        for sub in &mut self.sub_models {
+            sub.shader.set_instances(self.instances.clone());
+        }
+
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.instances.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.instances.len() == 0
+    }
+
+    /// The shader of each sub model
+    pub fn get_shaders_mut(&mut self) -> impl Iterator<Item = &mut PBR> {
+        self.sub_models.iter_mut().map(|sub| &mut sub.shader)
+    }
+
+    pub fn publish<'a>(&'a self, scene: &mut RenderScene<'a>) {
+        for sub in &self.sub_models {
+            scene.publish(&*sub.vertex_buffer, &*sub.index_buffer, &sub.shader);
+        }
+    }
+}
@@ -0,0 +1,167 @@
+use glium::uniforms::UniformValue;
+use glium::uniforms::Uniforms;
+
+/// The maximum amount of point lights that can be uploaded to a shader.
+///
+/// This has to match `MAX_LIGHTS` in the shaders. Any lights added past this limit are kept in
+/// [`Lights`] but ignored when rendering.
+pub const MAX_LIGHTS: usize = 16;
+
+/// A light that shines equally in every direction from a point
+///
+/// The light's color is divided by `constant + linear * d + quadratic * d * d` where `d` is the
+/// distance from the light.
+#[derive(Clone, Copy, Debug)]
+pub struct PointLight {
+    pub position: [f32; 3],
+    pub color: [f32; 3],
+    pub constant: f32,
+    pub linear: f32,
+    pub quadratic: f32,
+    /// Distance after which the light has no effect, 0 for no limit
+    ///
+    /// The light fades out smoothly before reaching the range so there is no visible edge.
+    pub range: f32,
+}
+
+impl PointLight {
+    /// Light with inverse square falloff and no range
+    pub fn new(position: [f32; 3], color: [f32; 3]) -> Self {
+        Self {
+            position,
+            color,
+            constant: 0.0,
+            linear: 0.0,
+            quadratic: 1.0,
+            range: 0.0,
+        }
+    }
+
+    /// Light that reaches `range` units, `color` is its brightness at the light's position
+    ///
+    /// The coefficients are picked so the light is at roughly 1% of its brightness at the range.
+    pub fn with_range(position: [f32; 3], color: [f32; 3], range: f32) -> Self {
+        Self {
+            position,
+            color,
+            constant: 1.0,
+            linear: 4.5 / range,
+            quadratic: 75.0 / (range * range),
+            range,
+        }
+    }
+
+    pub fn get_attenuation(&self) -> [f32; 3] {
+        [self.constant, self.linear, self.quadratic]
+    }
+    pub fn set_attenuation(&mut self, constant: f32, linear: f32, quadratic: f32) {
+        self.constant = constant;
+        self.linear = linear;
+        self.quadratic = quadratic;
+    }
+}
+
+/// A light that is infinitely far away, like the sun
+///
+/// `direction` is the direction the light travels in, so a sun straight above the scene is
+/// `[0.0, -1.0, 0.0]`.
+#[derive(Clone, Copy, Debug)]
+pub struct DirectionalLight {
+    pub direction: [f32; 3],
+    pub color: [f32; 3],
+}
+
+impl DirectionalLight {
+    pub fn new(direction: [f32; 3], color: [f32; 3]) -> Self {
+        Self { direction, color }
+    }
+}
+
+/// All the point lights in a scene
+///
+/// Stored on [`SceneData`](crate::renderer::SceneData) so every material rendered in the scene
+/// shares the same lights.
+#[derive(Clone, Default)]
+pub struct Lights {
+    lights: Vec<PointLight>,
+}
+
+impl Lights {
+    pub fn new() -> Self {
+        Self { lights: Vec::new() }
+    }
+
+    pub fn add_light(&mut self, position: [f32; 3], color: [f32; 3]) {
+        self.add_point_light(PointLight::new(position, color));
+    }
+
+    pub fn add_point_light(&mut self, light: PointLight) {
+        self.lights.push(light);
+    }
+
+    pub fn get_lights(&self) -> &Vec<PointLight> {
+        &self.lights
+    }
+
+    pub fn get_lights_mut(&mut self) -> &mut Vec<PointLight> {
+        &mut self.lights
+    }
+
+    pub fn clear(&mut self) {
+        self.lights.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.lights.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lights.is_empty()
+    }
+
+    /// Adds the lights to the given uniforms
+    ///
+    /// Uploads `lightPositions[]`, `lightColors[]`, `lightAttenuations[]` (constant, linear,
+    /// quadratic), `lightRanges[]`, and `numLights`. Only the first [`MAX_LIGHTS`] lights are
+    /// uploaded.
+    pub fn with_uniforms<U: Uniforms>(&self, uniforms: U) -> LightUniforms<'_, U> {
+        LightUniforms {
+            lights: self,
+            uniforms,
+        }
+    }
+}
+
+pub struct LightUniforms<'a, U: Uniforms> {
+    lights: &'a Lights,
+    uniforms: U,
+}
+
+impl<'a, U: Uniforms> Uniforms for LightUniforms<'a, U> {
+    fn visit_values<'b, F: FnMut(&str, UniformValue<'b>)>(&'b self, mut output: F) {
+        self.uniforms.visit_values(&mut output);
+
+        let lights = &self.lights.lights[..self.lights.len().min(MAX_LIGHTS)];
+
+        for (i, light) in lights.iter().enumerate() {
+            output(
+                &format!("lightPositions[{}]", i),
+                UniformValue::Vec3(light.position),
+            );
+            output(
+                &format!("lightColors[{}]", i),
+                UniformValue::Vec3(light.color),
+            );
+            output(
+                &format!("lightAttenuations[{}]", i),
+                UniformValue::Vec3(light.get_attenuation()),
+            );
+            output(
+                &format!("lightRanges[{}]", i),
+                UniformValue::Float(light.range),
+            );
+        }
+
+        output("numLights", UniformValue::SignedInt(lights.len() as i32));
+    }
+}
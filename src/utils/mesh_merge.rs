@@ -0,0 +1,60 @@
+//! Combining meshes that share a material, see [`Model::merge_sub_models`]
+//!
+//! [`Model::merge_sub_models`]: crate::utils::model::Model::merge_sub_models
+
+use crate::vertex::Vertex;
+use nalgebra::Matrix3;
+use nalgebra::Matrix4;
+use nalgebra::Vector3;
+
+/// Combines triangle lists into one, moving the vertices of each mesh by its transform
+///
+/// The indices of each mesh are offset by the number of vertices before it. Normals are
+/// transformed by the inverse transpose so they stay perpendicular to the surface under
+/// non-uniform scaling. Transforms that mirror the mesh flip the winding of its triangles and the
+/// handedness of its tangents, so it is still culled and normal mapped the same way.
+pub fn merge_meshes(meshes: &[(&[Vertex], &[u32], Matrix4<f32>)]) -> (Vec<Vertex>, Vec<u32>) {
+    let vertex_count = meshes.iter().map(|(vertices, _, _)| vertices.len()).sum();
+    let index_count = meshes.iter().map(|(_, indices, _)| indices.len()).sum();
+    let mut merged_vertices = Vec::with_capacity(vertex_count);
+    let mut merged_indices = Vec::with_capacity(index_count);
+
+    for (vertices, indices, transform) in meshes {
+        let linear: Matrix3<f32> = transform.fixed_view::<3, 3>(0, 0).into_owned();
+        let normal_matrix = linear.try_inverse().unwrap_or(linear).transpose();
+        let mirrored = linear.determinant() < 0.0;
+
+        let offset = merged_vertices.len() as u32;
+        merged_vertices.extend(vertices.iter().map(|vertex| {
+            let position = transform.transform_point(&vertex.position.into()).coords;
+            let normal = normal_matrix * Vector3::from(vertex.normal);
+            let tangent =
+                linear * Vector3::new(vertex.tangent[0], vertex.tangent[1], vertex.tangent[2]);
+            let handedness = match mirrored {
+                true => -vertex.tangent[3],
+                false => vertex.tangent[3],
+            };
+
+            Vertex {
+                position: position.into(),
+                normal: normal.try_normalize(f32::EPSILON).unwrap_or(normal).into(),
+                // A zero tangent means there is no tangent data, which has to stay that way
+                tangent: match tangent.try_normalize(f32::EPSILON) {
+                    Some(tangent) => [tangent.x, tangent.y, tangent.z, handedness],
+                    None => [0.0; 4],
+                },
+                ..*vertex
+            }
+        }));
+
+        for face in indices.chunks(3) {
+            let face = face.iter().map(|index| index + offset);
+            match mirrored {
+                true => merged_indices.extend(face.rev()),
+                false => merged_indices.extend(face),
+            }
+        }
+    }
+
+    (merged_vertices, merged_indices)
+}
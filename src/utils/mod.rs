@@ -1,7 +1,24 @@
+pub mod aabb;
+pub mod animation;
+pub mod billboard_text;
 pub mod camera;
 pub mod cubemap_loader;
+pub mod cubemap_render;
+pub mod indices;
+pub mod instanced_model;
+pub mod lights;
+pub mod mesh_merge;
 pub mod model;
+pub mod normals;
 pub mod pbr_skybox;
 pub mod positioning;
+pub mod render_surface;
+pub mod scene_description;
+pub mod shadow_map;
 pub mod shapes;
+pub mod texture_atlas;
+pub mod texture_load_queue;
 pub mod texture_loader;
+pub mod texture_registry;
+pub mod texture_saver;
+pub mod vertex_ao;
@@ -1,21 +1,45 @@
+use crate::error::RendererError;
+use crate::renderer::DEFAULT_LAYER;
+use crate::shaders::outline::Outline;
+use crate::shaders::pbr::AlphaMode;
+use crate::shaders::pbr::PBRMap;
 use crate::shaders::pbr::PBRSimple;
 use crate::shaders::pbr::PBRTextures;
+use crate::utils::aabb::Aabb;
+use crate::utils::mesh_merge::merge_meshes;
+use crate::utils::normals::generate_tangents;
+use crate::utils::normals::smooth_normals;
+use crate::utils::normals::DEFAULT_SMOOTHING_ANGLE;
 use crate::utils::positioning::Rotation;
+use crate::utils::texture_load_queue::TextureLoadQueue;
 use crate::utils::texture_loader::TextureLoader;
 use crate::{renderer::RenderScene, shaders::pbr::PBR};
+use glium::backend::Context;
 use glium::backend::Facade;
+use glium::Texture2d;
 use glium::{IndexBuffer, VertexBuffer};
+use image::DynamicImage;
+use image::RgbaImage;
 use nalgebra::Matrix4;
+use nalgebra::Vector2;
 use nalgebra::Vector3;
 use rayon::prelude::IntoParallelIterator;
 use rayon::prelude::IntoParallelRefIterator;
 use rayon::prelude::ParallelIterator;
+use russimp::material::Material;
+use russimp::material::PropertyTypeInfo;
+use russimp::mesh::Mesh;
+use russimp::node::Node;
 use russimp::scene::PostProcess;
 use russimp::scene::Scene;
+use russimp::texture::DataContent;
+use russimp::texture::Texture;
 use russimp::texture::TextureType;
 use std::error::Error;
+use std::ops::Range;
 use std::path::Path;
 use std::path::PathBuf;
+use std::rc::Rc;
 
 use crate::{shader::Shader, vertex::Vertex};
 
@@ -23,26 +47,58 @@ pub struct SubModel<S>
 where
     S: Shader,
 {
-    vertex_buffer: VertexBuffer<Vertex>,
-    index_buffer: IndexBuffer<u32>,
+    vertex_buffer: Rc<VertexBuffer<Vertex>>,
+    index_buffer: Rc<IndexBuffer<u32>>,
     shader: S,
     euler: Rotation,
     position: Vector3<f32>,
     parent_mat: Matrix4<f32>,
+    node_mat: Matrix4<f32>,
+    node_name: Option<String>,
+    bounds: Option<Aabb>,
+    visible: bool,
 }
 
 impl<S> SubModel<S>
 where
     S: Shader,
 {
+    /// Adds the sub model to the scene, unless it is [hidden](Self::set_visible)
     pub fn publish<'a>(&'a self, scene: &mut RenderScene<'a>) {
-        scene.publish(&self.vertex_buffer, &self.index_buffer, &self.shader);
+        self.publish_in_layer(scene, DEFAULT_LAYER);
+    }
+    /// Same as [`publish`](Self::publish) in the given layers, see
+    /// [`RenderScene::publish_in_layer`]
+    pub fn publish_in_layer<'a>(&'a self, scene: &mut RenderScene<'a>, layer: u32) {
+        if !self.visible {
+            return;
+        }
+
+        scene.publish_in_layer(
+            &*self.vertex_buffer,
+            &*self.index_buffer,
+            &self.shader,
+            layer,
+        );
     }
     pub fn set_vertex_buffer(&mut self, vb: VertexBuffer<Vertex>) {
-        self.vertex_buffer = vb;
+        self.bounds = Self::read_bounds(&vb);
+        self.vertex_buffer = Rc::new(vb);
     }
     pub fn set_index_buffer(&mut self, ib: IndexBuffer<u32>) {
-        self.index_buffer = ib;
+        self.index_buffer = Rc::new(ib);
+    }
+    pub fn get_vertex_buffer(&self) -> &Rc<VertexBuffer<Vertex>> {
+        &self.vertex_buffer
+    }
+    pub fn get_index_buffer(&self) -> &Rc<IndexBuffer<u32>> {
+        &self.index_buffer
+    }
+    pub fn get_shader(&self) -> &S {
+        &self.shader
+    }
+    pub fn get_shader_mut(&mut self) -> &mut S {
+        &mut self.shader
     }
     pub fn get_rotation(&self) -> &Rotation {
         &self.euler
@@ -68,10 +124,159 @@ where
         self.update_matrix();
     }
 
+    /// The transform of the node the mesh belongs to, relative to the model
+    ///
+    /// Only set by [`Model::load_from_fs_hierarchical`] and [`Model::center_on_origin`], the
+    /// identity otherwise. The [rotation](Self::set_rotation) and [position](Self::set_position)
+    /// are applied before it, in the space of the node.
+    pub fn get_node_mat(&self) -> &Matrix4<f32> {
+        &self.node_mat
+    }
+    pub fn set_node_mat(&mut self, mat: Matrix4<f32>) {
+        self.node_mat = mat;
+        self.update_matrix();
+    }
+
+    /// The name of the node the mesh belongs to, only set by
+    /// [`Model::load_from_fs_hierarchical`]
+    pub fn get_node_name(&self) -> Option<&str> {
+        self.node_name.as_deref()
+    }
+
+    /// The transform relative to the model, the node transform after the sub model's own
+    /// rotation and position
+    pub fn get_local_mat(&self) -> Matrix4<f32> {
+        self.node_mat * self.euler.get_matrix4().append_translation(&self.position)
+    }
+
+    /// Hidden sub models are skipped when publishing and picking, visible by default
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+    pub fn set_visible(&mut self, visible: bool) {
+        self.visible = visible;
+    }
+
+    /// The bounding box of the vertices, before any transformations
+    pub fn get_bounds(&self) -> Option<&Aabb> {
+        self.bounds.as_ref()
+    }
+
+    /// The bounding box in world space
+    pub fn get_world_bounds(&self) -> Option<Aabb> {
+        Some(self.bounds?.transform(&self.shader.get_model_mat()))
+    }
+
+    /// Copy of the sub model drawn with a different shader, the buffers are shared
+    pub fn with_shader<T: Shader>(&self, shader: T) -> SubModel<T> {
+        let mut sub_model = SubModel {
+            vertex_buffer: self.vertex_buffer.clone(),
+            index_buffer: self.index_buffer.clone(),
+            shader,
+            euler: self.euler,
+            position: self.position,
+            parent_mat: self.parent_mat,
+            node_mat: self.node_mat,
+            node_name: self.node_name.clone(),
+            bounds: self.bounds,
+            visible: self.visible,
+        };
+        sub_model.update_matrix();
+
+        sub_model
+    }
+
+    /// A sub model of a freshly loaded mesh, without any transformations
+    fn from_mesh(
+        shader: S,
+        vertex_buffer: VertexBuffer<Vertex>,
+        index_buffer: IndexBuffer<u32>,
+        vertices: &[Vertex],
+    ) -> Self {
+        SubModel {
+            shader,
+            vertex_buffer: Rc::new(vertex_buffer),
+            index_buffer: Rc::new(index_buffer),
+            bounds: Aabb::from_vertices(vertices),
+            visible: true,
+            euler: Rotation::from_euler_angles(0.0, 0.0, 0.0),
+            position: [0.0, 0.0, 0.0].into(),
+            parent_mat: Matrix4::new_translation(&[0.0; 3].into()),
+            node_mat: Matrix4::identity(),
+            node_name: None,
+        }
+    }
+
+    /// One sub model with the geometry of all of `sub_models`, drawn with the material of the
+    /// first one
+    ///
+    /// The local transforms are baked into the vertices, see [`merge_meshes`].
+    fn merge(facade: &impl Facade, sub_models: &[&SubModel<S>]) -> Result<Self, RendererError> {
+        let read_error = |err: glium::buffer::ReadError| RendererError::Glium(format!("{:?}", err));
+
+        let mut meshes = Vec::with_capacity(sub_models.len());
+        for sub_model in sub_models {
+            let vertices = sub_model.vertex_buffer.read().map_err(read_error)?;
+            let indices = sub_model.index_buffer.read().map_err(read_error)?;
+
+            meshes.push((vertices, indices, sub_model.get_local_mat()));
+        }
+
+        let meshes: Vec<_> = meshes
+            .iter()
+            .map(|(vertices, indices, local)| (&vertices[..], &indices[..], *local))
+            .collect();
+        let (vertices, indices) = merge_meshes(&meshes);
+
+        let first = sub_models[0];
+        let vb = VertexBuffer::new(facade, &vertices)?;
+        let ib = IndexBuffer::new(facade, first.index_buffer.get_primitives_type(), &indices)?;
+
+        let mut sub_model = SubModel {
+            bounds: Aabb::from_vertices(&vertices),
+            vertex_buffer: Rc::new(vb),
+            index_buffer: Rc::new(ib),
+            shader: first.shader.clone_sized(),
+            euler: Rotation::from_euler_angles(0.0, 0.0, 0.0),
+            position: [0.0; 3].into(),
+            parent_mat: first.parent_mat,
+            node_mat: Matrix4::identity(),
+            node_name: None,
+            visible: first.visible,
+        };
+        sub_model.update_matrix();
+
+        Ok(sub_model)
+    }
+
+    fn read_bounds(vb: &VertexBuffer<Vertex>) -> Option<Aabb> {
+        Aabb::from_vertices(&vb.read().ok()?)
+    }
+
     pub fn update_matrix(&mut self) {
-        self.shader.set_model_mat(
-            self.parent_mat * self.euler.get_matrix4().append_translation(&self.position),
-        );
+        self.shader
+            .set_model_mat(self.parent_mat * self.get_local_mat());
+    }
+}
+
+/// Clones share the vertex and index buffers, only the shader is copied
+impl<S> Clone for SubModel<S>
+where
+    S: Shader,
+{
+    fn clone(&self) -> Self {
+        Self {
+            vertex_buffer: self.vertex_buffer.clone(),
+            index_buffer: self.index_buffer.clone(),
+            shader: self.shader.clone_sized(),
+            euler: self.euler,
+            position: self.position,
+            parent_mat: self.parent_mat,
+            node_mat: self.node_mat,
+            node_name: self.node_name.clone(),
+            bounds: self.bounds,
+            visible: self.visible,
+        }
     }
 }
 
@@ -83,6 +288,10 @@ where
     shader: S,
     euler: Rotation,
     position: Vector3<f32>,
+    path: Option<PathBuf>,
+    bounds: Option<(Vector3<f32>, f32)>,
+    lod_levels: Vec<(f32, Model<S>)>,
+    layer: u32,
 }
 
 impl<S> Model<S>
@@ -92,27 +301,262 @@ where
     pub fn new(vb: VertexBuffer<Vertex>, ib: IndexBuffer<u32>, shader: S) -> Self {
         let sub_model = SubModel {
             shader: shader.clone_sized(),
-            index_buffer: ib,
-            vertex_buffer: vb,
+            bounds: SubModel::<S>::read_bounds(&vb),
+            visible: true,
+            index_buffer: Rc::new(ib),
+            vertex_buffer: Rc::new(vb),
             euler: Rotation::from_euler_angles(0.0, 0.0, 0.0),
             position: [0.0; 3].into(),
             parent_mat: Matrix4::new_translation(&[0.0; 3].into()),
+            node_mat: Matrix4::identity(),
+            node_name: None,
         };
 
+        let sub_models = vec![sub_model];
+
         Self {
-            sub_models: vec![sub_model],
+            bounds: Self::compute_bounds(&sub_models),
+            sub_models,
             shader,
             euler: Rotation::from_euler_angles(0.0, 0.0, 0.0),
             position: [0.0; 3].into(),
+            path: None,
+            lod_levels: Vec::new(),
+            layer: DEFAULT_LAYER,
         }
     }
 
+    /// Adds every visible sub model of the [level of detail](Self::set_lod_levels) for the current
+    /// camera to the scene
     pub fn publish<'a>(&'a self, scene: &mut RenderScene<'a>) {
-        for sub_model in &self.sub_models {
-            sub_model.publish(scene);
+        let model = self.select_lod(&scene.scene_data.camera.position);
+
+        for sub_model in &model.sub_models {
+            sub_model.publish_in_layer(scene, self.layer);
         }
     }
 
+    /// The layers the model is published to, [`DEFAULT_LAYER`] by default
+    ///
+    /// A bitmask, [`RenderScene::finish_layers`] only renders models in one of the requested
+    /// layers. The [levels of detail](Self::set_lod_levels) are published to the same layers.
+    pub fn set_layer(&mut self, layer: u32) {
+        self.layer = layer;
+    }
+    pub fn get_layer(&self) -> u32 {
+        self.layer
+    }
+
+    /// Publishes an outline around every visible sub model, ex. to highlight the selected model
+    ///
+    /// The model itself still has to be [published](Self::publish). Each sub model is outlined
+    /// with a copy of `outline` (which sets the color and width), so sub models that overlap on
+    /// screen are outlined separately. See [`Outline`] for what the surface needs.
+    pub fn render_outline<'a>(&'a self, scene: &mut RenderScene<'a>, outline: &Outline) {
+        let model = self.select_lod(&scene.scene_data.camera.position);
+
+        for sub_model in model.sub_models.iter().filter(|sub| sub.visible) {
+            let mut outline = outline.clone();
+            outline.set_model_mat(sub_model.shader.get_model_mat());
+
+            scene.publish_owned(
+                &*sub_model.vertex_buffer,
+                &*sub_model.index_buffer,
+                outline,
+                self.layer,
+            );
+        }
+    }
+
+    /// The bounding sphere as `(center, radius)`, before the model's transformations
+    ///
+    /// Computed when the model is created from the bounding boxes of its sub models, so it
+    /// contains every vertex but isn't always the smallest possible sphere. `None` if the model
+    /// has no vertices.
+    pub fn get_bounds(&self) -> Option<(Vector3<f32>, f32)> {
+        self.bounds
+    }
+
+    /// The bounding box of every sub model, before the model's transformations
+    ///
+    /// Hidden sub models are included. `None` if the model has no vertices.
+    pub fn get_local_aabb(&self) -> Option<Aabb> {
+        self.sub_models
+            .iter()
+            .filter_map(|sub| Some(sub.bounds?.transform(&sub.get_local_mat())))
+            .reduce(|a, b| a.union(&b))
+    }
+
+    /// The bounding box of every sub model in world space, ex. to frame the model with the camera
+    /// or put it on the ground
+    ///
+    /// The center and extents are [`Aabb::get_center`] and [`Aabb::get_extents`].
+    pub fn get_world_aabb(&self) -> Option<Aabb> {
+        self.sub_models
+            .iter()
+            .filter_map(SubModel::get_world_bounds)
+            .reduce(|a, b| a.union(&b))
+    }
+
+    /// Moves the geometry so the center of its [bounding box](Self::get_local_aabb) is at the
+    /// model's position, ex. for files that were exported far away from their origin
+    ///
+    /// The offset is added to the [node transform](SubModel::get_node_mat) of every sub model.
+    /// The [levels of detail](Self::set_lod_levels) are moved by the same offset so they stay
+    /// lined up with the model.
+    pub fn center_on_origin(&mut self) {
+        if let Some(aabb) = self.get_local_aabb() {
+            self.offset_geometry(&Matrix4::new_translation(&-aabb.get_center()));
+        }
+    }
+
+    fn offset_geometry(&mut self, offset: &Matrix4<f32>) {
+        for sub in &mut self.sub_models {
+            sub.set_node_mat(offset * sub.node_mat);
+        }
+
+        for (_, lod) in &mut self.lod_levels {
+            lod.offset_geometry(offset);
+        }
+
+        self.update_bounds();
+    }
+
+    /// How many sub models [`merge_sub_models`](Self::merge_sub_models) would remove, including
+    /// the levels of detail, without changing anything
+    pub fn count_mergeable(&self) -> usize {
+        let lods: usize = self
+            .lod_levels
+            .iter()
+            .map(|(_, lod)| lod.count_mergeable())
+            .sum();
+
+        self.sub_models.len() - self.mergeable_groups().len() + lods
+    }
+
+    /// Combines sub models with [equal materials](Shader::equal_shader) into one, so each
+    /// material takes a single draw call
+    ///
+    /// Meant for imported scenes that are split into many small meshes sharing a few materials.
+    /// Only sub models that are both visible or both hidden are combined. The local transform of
+    /// each one is baked into its vertices, so the merged sub model has no rotation, position, or
+    /// node transform, and no node name. The levels of detail are merged as well.
+    ///
+    /// Returns how many sub models were removed, the same as
+    /// [`count_mergeable`](Self::count_mergeable) before the call.
+    pub fn merge_sub_models(&mut self, facade: &impl Facade) -> Result<usize, RendererError> {
+        let groups = self.mergeable_groups();
+        let mut removed = self.sub_models.len() - groups.len();
+
+        if removed > 0 {
+            let mut sub_models = Vec::with_capacity(groups.len());
+            for group in &groups {
+                let group: Vec<_> = group.iter().map(|index| &self.sub_models[*index]).collect();
+
+                sub_models.push(match group[..] {
+                    [sub_model] => sub_model.clone(),
+                    _ => SubModel::merge(facade, &group)?,
+                });
+            }
+
+            self.sub_models = sub_models;
+            self.update_bounds();
+        }
+
+        for (_, lod) in &mut self.lod_levels {
+            removed += lod.merge_sub_models(facade)?;
+        }
+
+        Ok(removed)
+    }
+
+    /// Indices of the sub models that can be drawn together, in the order of their first member
+    fn mergeable_groups(&self) -> Vec<Vec<usize>> {
+        let mut groups: Vec<Vec<usize>> = Vec::new();
+
+        for (index, sub_model) in self.sub_models.iter().enumerate() {
+            let group = groups.iter_mut().find(|group| {
+                let first = &self.sub_models[group[0]];
+
+                first.visible == sub_model.visible
+                    && first.shader.equal_shader(sub_model.shader.as_any())
+            });
+
+            match group {
+                Some(group) => group.push(index),
+                None => groups.push(vec![index]),
+            }
+        }
+
+        groups
+    }
+
+    /// Sets lower detail replacements that are drawn when the camera is far away
+    ///
+    /// Each level is the distance from which it is used and the model to draw instead. The level
+    /// with the largest distance that the camera is past is picked, measured from the center of
+    /// the [bounding sphere](Self::get_bounds). Closer than every level this model itself is drawn,
+    /// which is always the case without levels (the default).
+    ///
+    /// The levels are moved and rotated along with this model, their own transformations are
+    /// replaced.
+    pub fn set_lod_levels(&mut self, mut lod_levels: Vec<(f32, Model<S>)>) {
+        lod_levels.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+        self.lod_levels = lod_levels;
+        self.update_matrix();
+    }
+    pub fn get_lod_levels(&self) -> &[(f32, Model<S>)] {
+        &self.lod_levels
+    }
+    /// Changing the distances here doesn't sort the levels again, use
+    /// [`set_lod_levels`](Self::set_lod_levels) for that
+    pub fn get_lod_levels_mut(&mut self) -> &mut [(f32, Model<S>)] {
+        &mut self.lod_levels
+    }
+
+    /// The model to draw when the camera is at `camera_position`, see
+    /// [`set_lod_levels`](Self::set_lod_levels)
+    pub fn select_lod(&self, camera_position: &Vector3<f32>) -> &Model<S> {
+        let center = self.bounds.map(|(center, _)| center).unwrap_or_default();
+        let center = self.shader.get_model_mat().transform_point(&center.into());
+        let distance = (center.coords - camera_position).norm();
+
+        self.lod_levels
+            .iter()
+            .rev()
+            .find(|(threshold, _)| distance >= *threshold)
+            .map(|(_, model)| model)
+            .unwrap_or(self)
+    }
+
+    /// Recomputes the bounds after the local transform of a sub model changed
+    pub(crate) fn update_bounds(&mut self) {
+        self.bounds = Self::compute_bounds(&self.sub_models);
+    }
+
+    /// Sphere around the local bounding boxes of every sub model
+    fn compute_bounds(sub_models: &[SubModel<S>]) -> Option<(Vector3<f32>, f32)> {
+        let corners: Vec<_> = sub_models
+            .iter()
+            .filter_map(|sub| Some(sub.bounds?.transform(&sub.get_local_mat()).get_corners()))
+            .flatten()
+            .collect();
+
+        let first = *corners.first()?;
+        let aabb = corners
+            .iter()
+            .fold(Aabb::new(first, first), |aabb, corner| {
+                Aabb::new(aabb.min.inf(corner), aabb.max.sup(corner))
+            });
+        let center = aabb.get_center();
+        let radius = corners
+            .iter()
+            .map(|corner| (corner - center).norm())
+            .fold(0.0, f32::max);
+
+        Some((center, radius))
+    }
+
     pub fn get_shader(&self) -> &S {
         &self.shader
     }
@@ -139,6 +583,11 @@ where
         self.update_matrix();
     }
 
+    /// The file the model was loaded from, `None` for models created with [`new`](Self::new)
+    pub fn get_path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
     pub fn get_sub_models(&self) -> &Vec<SubModel<S>> {
         &self.sub_models
     }
@@ -146,6 +595,95 @@ where
         &mut self.sub_models
     }
 
+    /// Replaces the shader of the model and of every sub model with a clone of `shader`
+    ///
+    /// Each sub model keeps its model matrix. The [levels of detail](Self::set_lod_levels) get
+    /// the shader too, so they keep looking the same as the model.
+    pub fn set_shader(&mut self, shader: S) {
+        for sub in &mut self.sub_models {
+            let model = sub.shader.get_model_mat();
+            sub.shader = shader.clone_sized();
+            sub.shader.set_model_mat(model);
+        }
+
+        for (_, lod) in &mut self.lod_levels {
+            lod.set_shader(shader.clone_sized());
+        }
+
+        let model = self.shader.get_model_mat();
+        self.shader = shader;
+        self.shader.set_model_mat(model);
+    }
+
+    /// Tests the ray against the world space bounding box of every visible sub model
+    ///
+    /// Returns the distance to the nearest hit, in multiples of `direction`. Only the bounding
+    /// boxes are tested, not the individual triangles.
+    pub fn intersects_ray(&self, origin: &Vector3<f32>, direction: &Vector3<f32>) -> Option<f32> {
+        self.sub_models
+            .iter()
+            .filter(|sub| sub.visible)
+            .filter_map(|sub| sub.get_world_bounds()?.intersects_ray(origin, direction))
+            .min_by(|a, b| a.total_cmp(b))
+    }
+
+    /// Calls [`Shader::reload_if_changed`] on every shader in the model
+    pub fn reload_if_changed(&mut self, facade: &Rc<Context>) -> bool {
+        let mut reloaded = false;
+        self.for_each_material(|shader| reloaded |= shader.reload_if_changed(facade));
+
+        reloaded
+    }
+
+    /// Calls `f` with the shader of the model, of every sub model, and of every [level of
+    /// detail](Self::set_lod_levels), ex. to change a value on the whole model at once
+    ///
+    /// Hidden sub models are included. Changing the model matrix has no lasting effect, it is
+    /// overwritten the next time the model or a sub model moves.
+    pub fn for_each_material(&mut self, mut f: impl FnMut(&mut S)) {
+        self.visit_materials(&mut f);
+    }
+
+    fn visit_materials(&mut self, f: &mut dyn FnMut(&mut S)) {
+        f(&mut self.shader);
+
+        for sub in &mut self.sub_models {
+            f(&mut sub.shader);
+        }
+
+        for (_, lod) in &mut self.lod_levels {
+            lod.visit_materials(f);
+        }
+    }
+
+    /// Copy of the model where every sub model is drawn with a clone of `shader`
+    ///
+    /// The buffers are shared, so this is cheap. Useful for drawing a model with a debug material
+    /// like [`NormalDebug`](crate::shaders::normal_debug::NormalDebug).
+    pub fn with_shader<T: Shader>(&self, shader: T) -> Model<T> {
+        let mut model = Model {
+            sub_models: self
+                .sub_models
+                .iter()
+                .map(|sub| sub.with_shader(shader.clone_sized()))
+                .collect(),
+            shader: shader.clone_sized(),
+            euler: self.euler,
+            position: self.position,
+            path: self.path.clone(),
+            bounds: self.bounds,
+            lod_levels: self
+                .lod_levels
+                .iter()
+                .map(|(distance, lod)| (*distance, lod.with_shader(shader.clone_sized())))
+                .collect(),
+            layer: self.layer,
+        };
+        model.update_matrix();
+
+        model
+    }
+
     pub fn update_matrix(&mut self) {
         let mat = self.euler.get_matrix4().append_translation(&self.position);
         self.shader.set_model_mat(mat);
@@ -153,70 +691,315 @@ where
         for sub in &mut self.sub_models {
             sub.set_parent_mat(mat);
         }
+
+        for (_, lod) in &mut self.lod_levels {
+            lod.euler = self.euler;
+            lod.position = self.position;
+            lod.update_matrix();
+        }
+    }
+}
+
+/// Same as [`Model::for_each_material`] for every model in `models`, ex. to bump a value on all
+/// the models of a scene or check them for [shader changes](Shader::reload_if_changed) in one call
+pub fn for_each_material<S: Shader>(models: &mut [Model<S>], mut f: impl FnMut(&mut S)) {
+    for model in models {
+        model.visit_materials(&mut f);
+    }
+}
+
+/// Clones share the vertex and index buffers of every sub model, so cloning is cheap
+impl<S> Clone for Model<S>
+where
+    S: Shader,
+{
+    fn clone(&self) -> Self {
+        Self {
+            sub_models: self.sub_models.clone(),
+            shader: self.shader.clone_sized(),
+            euler: self.euler,
+            position: self.position,
+            path: self.path.clone(),
+            bounds: self.bounds,
+            lod_levels: self.lod_levels.clone(),
+            layer: self.layer,
+        }
+    }
+}
+
+/// Which map of which sub models a texture queued by [`Model::load_from_fs_queued`] is for
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct QueuedTexture {
+    /// More than one when several nodes use the same mesh
+    pub sub_models: Vec<usize>,
+    pub map: PBRMap,
+    pub path: PathBuf,
+    /// Copied from the [`LoadOptions`] the model was loaded with, see
+    /// [`Model::set_queued_error`]
+    pub missing_texture_fallback: bool,
+}
+
+/// How [`Model::load_from_fs_with`] imports a file
+#[derive(Clone, Copy, Debug)]
+pub struct LoadOptions {
+    /// Keeps the transforms of the nodes in the file, see [`Model::load_from_fs_hierarchical`]
+    pub hierarchical: bool,
+    /// Faces meeting at a sharper angle (in degrees) keep a hard edge when normals have to be
+    /// generated, see [`smooth_normals`]
+    pub smoothing_angle: f32,
+    /// Textures the file references that can't be loaded are replaced with a
+    /// [checkerboard](TextureLoader::missing_texture) instead of being left out, so they are easy
+    /// to spot. Only enabled by default in debug builds, failures are printed either way.
+    pub missing_texture_fallback: bool,
+}
+
+impl Default for LoadOptions {
+    fn default() -> Self {
+        Self {
+            hierarchical: false,
+            smoothing_angle: DEFAULT_SMOOTHING_ANGLE,
+            missing_texture_fallback: cfg!(debug_assertions),
+        }
     }
 }
 
 pub trait ModelLoad {
-    fn load_from_fs<P>(facade: &impl Facade, path: P) -> Result<Self, Box<dyn Error>>
+    fn load_from_fs<P>(facade: &impl Facade, path: P) -> Result<Self, RendererError>
     where
         Self: Sized,
         P: AsRef<Path>;
 }
 
 impl ModelLoad for Model<PBR> {
-    fn load_from_fs<P>(facade: &impl Facade, path: P) -> Result<Model<PBR>, Box<dyn Error>>
+    /// Bakes the transforms of the nodes in the file into the vertices, see
+    /// [`Model::load_from_fs_hierarchical`] to keep them
+    fn load_from_fs<P>(facade: &impl Facade, path: P) -> Result<Model<PBR>, RendererError>
     where
         P: AsRef<Path>,
     {
-        let path = path.as_ref();
+        Self::load_from_fs_with(facade, path, LoadOptions::default())
+    }
+}
+
+impl Model<PBR> {
+    /// Loads a model, keeping the transforms of the nodes in the file
+    ///
+    /// Unlike [`load_from_fs`](ModelLoad::load_from_fs) the vertices are kept as they are in the
+    /// file and each sub model gets the transform of its node instead, see
+    /// [`SubModel::get_node_mat`]. This way the parts of the model can still be moved on their
+    /// own. A mesh used by several nodes gets a sub model for each, sharing the buffers.
+    pub fn load_from_fs_hierarchical(
+        facade: &impl Facade,
+        path: impl AsRef<Path>,
+    ) -> Result<Self, RendererError> {
+        let options = LoadOptions {
+            hierarchical: true,
+            ..Default::default()
+        };
+
+        Self::load_from_fs_with(facade, path, options)
+    }
+
+    /// Loads a model with the given options, ex. to change how normals are generated for meshes
+    /// stored without them
+    pub fn load_from_fs_with(
+        facade: &impl Facade,
+        path: impl AsRef<Path>,
+        options: LoadOptions,
+    ) -> Result<Self, RendererError> {
+        Ok(Self::load_scene(facade, path.as_ref(), options, None)?.0)
+    }
+
+    /// Same as [`load_from_fs_with`](Self::load_from_fs_with), but the texture files are decoded
+    /// in the background by `queue` instead of before returning
+    ///
+    /// The sub models use their default values until the textures arrive. Every texture the queue
+    /// returns comes with `key` and where it goes, pass them to
+    /// [`set_queued_texture`](Self::set_queued_texture) of the model with that key, or the error
+    /// to [`set_queued_error`](Self::set_queued_error) if it failed to load. Textures embedded in
+    /// the file are still loaded right away.
+    pub fn load_from_fs_queued<K: Clone + Send + 'static>(
+        facade: &impl Facade,
+        path: impl AsRef<Path>,
+        options: LoadOptions,
+        queue: &mut TextureLoadQueue<(K, QueuedTexture)>,
+        key: K,
+    ) -> Result<Self, RendererError> {
+        let mut deferred = Vec::new();
+        let (model, _, mesh_indices) =
+            Self::load_scene(facade, path.as_ref(), options, Some(&mut deferred))?;
+
+        for (meshes, map, path) in deferred {
+            let sub_models: Vec<usize> = mesh_indices
+                .iter()
+                .enumerate()
+                .filter(|(_, index)| meshes.contains(*index))
+                .map(|(sub, _)| sub)
+                .collect();
+            let texture_options = match sub_models.first() {
+                Some(sub) => model.sub_models[*sub]
+                    .shader
+                    .get_pbr_params()
+                    .get_texture_options(),
+                None => continue,
+            };
+
+            let target = QueuedTexture {
+                sub_models,
+                map,
+                path: path.clone(),
+                missing_texture_fallback: options.missing_texture_fallback,
+            };
+            queue.load((key.clone(), target), path, map.is_color(), texture_options);
+        }
+
+        Ok(model)
+    }
+
+    /// Puts a texture loaded by [`load_from_fs_queued`](Self::load_from_fs_queued) in place
+    pub fn set_queued_texture(&mut self, target: QueuedTexture, texture: Texture2d) {
+        let texture = Rc::new(texture);
+
+        for sub in target.sub_models {
+            if let Some(sub) = self.sub_models.get_mut(sub) {
+                sub.shader.get_pbr_params_mut().set_loaded_map(
+                    target.map,
+                    texture.clone(),
+                    target.path.clone(),
+                );
+            }
+        }
+    }
+
+    /// Reports a texture queued by [`load_from_fs_queued`](Self::load_from_fs_queued) that
+    /// couldn't be loaded
+    ///
+    /// Like the textures loaded right away, the error is printed and the
+    /// [checkerboard](TextureLoader::missing_texture) is put in place if
+    /// [`LoadOptions::missing_texture_fallback`] was set.
+    pub fn set_queued_error(
+        &mut self,
+        facade: &impl Facade,
+        target: QueuedTexture,
+        err: RendererError,
+    ) {
+        eprintln!("couldn't load texture {:?}: {}", target.path, err);
+
+        if !target.missing_texture_fallback {
+            return;
+        }
+
+        if let Ok(missing) = TextureLoader::missing_texture(facade) {
+            let missing = Rc::new(missing);
+
+            for sub in target.sub_models {
+                if let Some(sub) = self.sub_models.get_mut(sub) {
+                    sub.shader
+                        .get_pbr_params_mut()
+                        .set_map(target.map, missing.clone());
+                }
+            }
+        }
+    }
+
+    /// Also returns the imported scene and the index of the mesh each sub model was created from,
+    /// for reading what the model doesn't keep (ex. the bones and animations)
+    ///
+    /// With `deferred` the texture files aren't loaded, they are added to it along with the indices
+    /// of the meshes they are for.
+    ///
+    /// Meshes with the same material share one [`PBR`] and its textures, so they are
+    /// [equal](Shader::equal_shader) and can be merged.
+    pub(crate) fn load_scene(
+        facade: &impl Facade,
+        path: &Path,
+        options: LoadOptions,
+        mut deferred: Option<&mut Vec<(Vec<usize>, PBRMap, PathBuf)>>,
+    ) -> Result<(Self, Scene, Vec<usize>), RendererError> {
+        let hierarchical = options.hierarchical;
+        // Missing normals are generated by read_mesh instead of by assimp, which can't be given
+        // the smoothing angle through russimp. assimp then can't calculate the tangents of those
+        // meshes, so read_mesh generates them too.
+        let mut post_process = vec![
+            PostProcess::Triangulate,
+            PostProcess::CalculateTangentSpace,
+            // Skinned vertices have room for 4 bones
+            PostProcess::LimitBoneWeights,
+        ];
+        if !hierarchical {
+            post_process.push(PostProcess::PreTransformVertices);
+        }
+
         let scene = Scene::from_file(
-            path.to_str().ok_or(format!(
-                "Error loading file {:?}, invalid name",
-                path.file_name()
-            ))?,
-            vec![
-                // Quick fix, should change later
-                PostProcess::PreTransformVertices,
-                PostProcess::GenerateNormals,
-                PostProcess::Triangulate,
-            ],
+            path.to_str()
+                .ok_or_else(|| RendererError::InvalidPath(path.to_path_buf()))?,
+            post_process,
         )?;
 
-        let mut sub_models = Vec::new();
-        for mesh in scene.meshes {
-            let vertices = (0..mesh.vertices.len())
-                .into_par_iter()
-                .map(|index| {
-                    let vertex = mesh.vertices[index as usize];
-                    let position: [f32; 3] = [vertex.x, vertex.y, vertex.z];
-                    let normal_vec = mesh.normals[index as usize];
-                    let normal = [normal_vec.x, normal_vec.y, normal_vec.z];
-                    let tex_coords = match mesh.texture_coords[0].as_ref() {
-                        Some(texture_coords) => {
-                            let vec3 = texture_coords[index as usize];
-                            [vec3.x, vec3.y]
-                        }
-                        None => [0.0; 2],
-                    };
-
-                    return Vertex {
-                        position,
-                        normal,
-                        tex_coords,
-                        ..Default::default()
-                    };
-                })
-                .collect::<Vec<_>>();
+        let texture_types = [
+            TextureType::Diffuse,
+            TextureType::Height,
+            TextureType::AmbientOcclusion,
+            TextureType::Metalness,
+            TextureType::Roughness,
+            TextureType::Emissive,
+        ];
 
-            let indices = mesh
-                .faces
-                .par_iter()
-                .flat_map(|face| face.0.clone())
-                .collect::<Vec<_>>();
+        // Extracting the vertices doesn't need OpenGL, so every mesh is processed in parallel.
+        // Only the uploads below have to happen on the thread that owns the context.
+        let meshes = scene
+            .meshes
+            .par_iter()
+            .map(|mesh| {
+                let material_index = mesh.material_index as usize;
+                let scene_material = &scene.materials[material_index];
+                let has_textures = texture_types
+                    .iter()
+                    .any(|ty| scene_material.textures.contains_key(ty));
+
+                // The rest of the model is still worth showing, the textures are sampled at a
+                // single texel instead
+                if has_textures && mesh.texture_coords[0].is_none() {
+                    eprintln!(
+                        "mesh {:?} has textures but no texture coordinates, using (0, 0)",
+                        mesh.name
+                    );
+                }
+
+                let (vertices, indices) = read_mesh(mesh, options.smoothing_angle);
+
+                Ok((vertices, indices, material_index))
+            })
+            .collect::<Result<Vec<_>, RendererError>>()?;
+
+        // The material each material index was loaded as, and the range of its deferred textures
+        let mut materials: Vec<Option<(PBR, Range<usize>)>> = vec![None; scene.materials.len()];
+
+        let mut mesh_sub_models = Vec::new();
+        for (mesh_index, (vertices, indices, material_index)) in meshes.into_iter().enumerate() {
+            let scene_material = &scene.materials[material_index];
 
             let index_buffer =
                 IndexBuffer::new(facade, glium::index::PrimitiveType::TrianglesList, &indices)?;
             let vertex_buffer = VertexBuffer::new(facade, &vertices)?;
+
+            if let Some((pbr, deferred_range)) = &materials[material_index] {
+                if let Some(deferred) = deferred.as_deref_mut() {
+                    for (meshes, _, _) in &mut deferred[deferred_range.clone()] {
+                        meshes.push(mesh_index);
+                    }
+                }
+
+                mesh_sub_models.push(SubModel::from_mesh(
+                    pbr.clone(),
+                    vertex_buffer,
+                    index_buffer,
+                    &vertices,
+                ));
+                continue;
+            }
+
+            let deferred_start = deferred.as_ref().map_or(0, |deferred| deferred.len());
             let mut pbr = PBR::load_from_fs(facade);
             let pbr_tex = PBRTextures::from_simple(
                 facade,
@@ -226,58 +1009,175 @@ impl ModelLoad for Model<PBR> {
                 },
             );
             pbr.set_pbr_params(pbr_tex);
-            println!("{:#?}", scene.materials);
-            let scene_material = &scene.materials[mesh.material_index as usize];
-            let load_file = |name: TextureType| {
-                if let Some(texture) = scene_material.textures.get(&name) {
-                    println!("reading file {:?}", name);
-                    let mut path = PathBuf::from(path);
-                    path.set_file_name(&texture[0].path);
-
-                    return TextureLoader::from_fs(facade, &path).ok();
-                }
+            let texture_file = |name: &TextureType| {
+                scene_material.textures.get(name).map(|texture| {
+                    let mut file = PathBuf::from(path);
+                    file.set_file_name(&texture[0].path);
+                    file
+                })
+            };
+            let mut load_file = |pbr: &mut PBR, map: PBRMap, name: TextureType| {
+                let texture = match scene_material.textures.get(&name) {
+                    Some(texture) => &texture[0],
+                    None => return,
+                };
+                println!("reading file {:?}", name);
+
+                let result = match &texture.data {
+                    // Embedded textures (ex. in .glb files) have a path like "*0" instead of a file
+                    Some(data) => decode_embedded_texture(texture, data)
+                        .and_then(|image| pbr.get_pbr_params_mut().load_map_image(map, image)),
+                    None => match (texture_file(&name), deferred.as_deref_mut()) {
+                        (Some(file), Some(deferred)) => {
+                            deferred.push((vec![mesh_index], map, file));
+                            Ok(())
+                        }
+                        (Some(file), None) => pbr.get_pbr_params_mut().load_map(map, file),
+                        (None, _) => Ok(()),
+                    },
+                };
 
-                None
+                // A missing texture shouldn't stop the rest of the model from loading
+                if let Err(err) = result {
+                    eprintln!("couldn't load texture {}: {}", texture.path, err);
+
+                    if options.missing_texture_fallback {
+                        if let Ok(missing) = TextureLoader::missing_texture(facade) {
+                            pbr.get_pbr_params_mut().set_map(map, Rc::new(missing));
+                        }
+                    }
+                }
             };
 
-            if let Some(texture) = load_file(TextureType::Diffuse) {
-                pbr.get_pbr_params_mut().set_albedo(texture.into());
-            }
-            if let Some(texture) = load_file(TextureType::Height) {
-                pbr.get_pbr_params_mut().set_normal(texture.into());
-            }
-            if let Some(texture) = load_file(TextureType::AmbientOcclusion) {
-                pbr.get_pbr_params_mut().set_ao(texture.into());
-            }
-            if let Some(texture) = load_file(TextureType::Metalness) {
-                pbr.get_pbr_params_mut().set_metallic(texture.into());
+            load_file(&mut pbr, PBRMap::Albedo, TextureType::Diffuse);
+            load_file(&mut pbr, PBRMap::Normal, TextureType::Height);
+            // Height is already used for normal maps by obj files, so only displacement is read
+            load_file(&mut pbr, PBRMap::Height, TextureType::Displacement);
+
+            // glTF packs occlusion, roughness, and metallic into one texture, which assimp reports
+            // as the same file for each type. Load it once and use the packed path instead.
+            let orm_file = texture_file(&TextureType::Metalness).filter(|metallic| {
+                texture_file(&TextureType::Roughness).as_ref() == Some(metallic)
+            });
+
+            match orm_file {
+                Some(orm_file) => {
+                    load_file(&mut pbr, PBRMap::Orm, TextureType::Metalness);
+
+                    if texture_file(&TextureType::AmbientOcclusion) == Some(orm_file) {
+                        pbr.get_pbr_params_mut().set_orm_occlusion(true);
+                    } else {
+                        load_file(&mut pbr, PBRMap::Ao, TextureType::AmbientOcclusion);
+                    }
+                }
+                None => {
+                    load_file(&mut pbr, PBRMap::Ao, TextureType::AmbientOcclusion);
+                    load_file(&mut pbr, PBRMap::Metallic, TextureType::Metalness);
+                    load_file(&mut pbr, PBRMap::Roughness, TextureType::Roughness);
+                }
             }
-            if let Some(texture) = load_file(TextureType::Roughness) {
-                pbr.get_pbr_params_mut().set_roughness(texture.into());
+            load_file(&mut pbr, PBRMap::Emissive, TextureType::Emissive);
+
+            let ao_uv_index = scene_material
+                .textures
+                .get(&TextureType::AmbientOcclusion)
+                .map(|texture| texture[0].uv_index);
+            if ao_uv_index == Some(1) {
+                pbr.get_pbr_params_mut().set_ao_uv2(true);
             }
+            pbr.set_alpha_mode(gltf_alpha_mode(scene_material));
+            pbr.set_double_sided(is_two_sided(scene_material));
+
+            let deferred_end = deferred.as_ref().map_or(0, |deferred| deferred.len());
+            materials[material_index] = Some((pbr.clone(), deferred_start..deferred_end));
 
-            let sub_model = SubModel {
-                shader: pbr,
+            mesh_sub_models.push(SubModel::from_mesh(
+                pbr,
                 vertex_buffer,
                 index_buffer,
-                euler: Rotation::from_euler_angles(0.0, 0.0, 0.0),
-                position: [0.0, 0.0, 0.0].into(),
-                parent_mat: Matrix4::new_translation(&[0.0; 3].into()),
-            };
-
-            sub_models.push(sub_model);
+                &vertices,
+            ));
         }
 
-        Ok(Self {
+        let (sub_models, mesh_indices) = match (hierarchical, &scene.root) {
+            (true, Some(root)) => {
+                let mut sub_models = Vec::new();
+                let mut mesh_indices = Vec::new();
+                Self::collect_nodes(
+                    &root.borrow(),
+                    Matrix4::identity(),
+                    &mesh_sub_models,
+                    &mut sub_models,
+                    &mut mesh_indices,
+                );
+                (sub_models, mesh_indices)
+            }
+            _ => {
+                let mesh_indices = (0..mesh_sub_models.len()).collect();
+                (mesh_sub_models, mesh_indices)
+            }
+        };
+
+        let model = Self {
+            bounds: Self::compute_bounds(&sub_models),
             sub_models,
             shader: PBR::load_from_fs(facade),
             euler: Rotation::from_euler_angles(0.0, 0.0, 0.0),
             position: [0.0, 0.0, 0.0].into(),
-        })
+            path: Some(path.to_path_buf()),
+            lod_levels: Vec::new(),
+            layer: DEFAULT_LAYER,
+        };
+
+        Ok((model, scene, mesh_indices))
+    }
+
+    /// Adds a sub model for every mesh of `node` and its children, with the transform of the
+    /// node they are in
+    fn collect_nodes(
+        node: &Node,
+        parent: Matrix4<f32>,
+        meshes: &[SubModel<PBR>],
+        sub_models: &mut Vec<SubModel<PBR>>,
+        mesh_indices: &mut Vec<usize>,
+    ) {
+        let node_mat = parent * assimp_matrix(&node.transformation);
+
+        for mesh in &node.meshes {
+            if let Some(sub_model) = meshes.get(*mesh as usize) {
+                let mut sub_model = sub_model.clone();
+                sub_model.node_name = Some(node.name.clone());
+                sub_model.set_node_mat(node_mat);
+
+                sub_models.push(sub_model);
+                mesh_indices.push(*mesh as usize);
+            }
+        }
+
+        for child in &node.children {
+            Self::collect_nodes(&child.borrow(), node_mat, meshes, sub_models, mesh_indices);
+        }
     }
 }
 
 impl Model<PBR> {
+    /// Gives the model and every sub model the same textures and values, ex. to recolor the whole
+    /// model
+    ///
+    /// Unlike [`set_shader`](Self::set_shader) the rest of each material, like its scalar
+    /// overrides, is kept. The textures are shared, not copied.
+    pub fn set_pbr_params_all(&mut self, params: PBRTextures) {
+        for sub in &mut self.sub_models {
+            sub.shader.set_pbr_params(params.clone());
+        }
+
+        for (_, lod) in &mut self.lod_levels {
+            lod.set_pbr_params_all(params.clone());
+        }
+
+        self.shader.set_pbr_params(params);
+    }
+
     pub fn debug_ui(&mut self, ui: &mut egui::Ui) -> egui::InnerResponse<()> {
         let mut response = self.euler.debug_ui(ui).response;
 
@@ -303,6 +1203,8 @@ impl Model<PBR> {
             for (i, sub) in self.sub_models.iter_mut().enumerate() {
                 ui.push_id(i, |ui| {
                     ui.collapsing(format!("Sub Model {}", i), |ui| {
+                        ui.checkbox(&mut sub.visible, "visible");
+
                         let mut response = sub.euler.debug_ui(ui).response;
 
                         ui.horizontal(|ui| {
@@ -333,3 +1235,193 @@ impl Model<PBR> {
         egui::InnerResponse::new((), response)
     }
 }
+
+/// The `alphaMode` and `alphaCutoff` of a glTF material, other formats are always opaque
+fn gltf_alpha_mode(material: &Material) -> AlphaMode {
+    let property = |key: &str| {
+        material
+            .properties
+            .iter()
+            .find(|property| property.key == key)
+            .map(|property| &property.data)
+    };
+
+    match property("$mat.gltf.alphaMode") {
+        Some(PropertyTypeInfo::String(mode)) if mode == "MASK" => {
+            match property("$mat.gltf.alphaCutoff") {
+                Some(PropertyTypeInfo::FloatArray(cutoff)) if !cutoff.is_empty() => {
+                    AlphaMode::Mask(cutoff[0])
+                }
+                _ => AlphaMode::Mask(AlphaMode::DEFAULT_CUTOFF),
+            }
+        }
+        Some(PropertyTypeInfo::String(mode)) if mode == "BLEND" => AlphaMode::Blend,
+        _ => AlphaMode::Opaque,
+    }
+}
+
+/// Whether the material is marked double sided (`$mat.twosided`), ex. by glTF's `doubleSided`
+fn is_two_sided(material: &Material) -> bool {
+    let property = material
+        .properties
+        .iter()
+        .find(|property| property.key == "$mat.twosided")
+        .map(|property| &property.data);
+
+    // Importers store the flag either as an integer or as a single byte
+    match property {
+        Some(PropertyTypeInfo::IntegerArray(values)) => values.first().is_some_and(|v| *v != 0),
+        Some(PropertyTypeInfo::Buffer(bytes)) => bytes.first().is_some_and(|b| *b != 0),
+        _ => false,
+    }
+}
+
+/// Assimp matrices are stored row by row
+pub(crate) fn assimp_matrix(m: &russimp::Matrix4x4) -> Matrix4<f32> {
+    #[rustfmt::skip]
+    let matrix = Matrix4::new(
+        m.a1, m.a2, m.a3, m.a4,
+        m.b1, m.b2, m.b3, m.b4,
+        m.c1, m.c2, m.c3, m.c4,
+        m.d1, m.d2, m.d3, m.d4,
+    );
+
+    matrix
+}
+
+/// Decodes a texture that assimp extracted from the model file
+///
+/// Compressed textures are stored as the bytes of an image file (usually png or jpg), others as
+/// `width * height` BGRA texels.
+fn decode_embedded_texture(
+    texture: &Texture,
+    data: &DataContent,
+) -> Result<DynamicImage, Box<dyn Error>> {
+    match data {
+        DataContent::Bytes(bytes) => Ok(image::load_from_memory(bytes)?),
+        DataContent::Texel(texels) => {
+            let pixels = texels
+                .iter()
+                .flat_map(|texel| [texel.r, texel.g, texel.b, texel.a])
+                .collect();
+
+            RgbaImage::from_raw(texture.width, texture.height, pixels)
+                .map(DynamicImage::ImageRgba8)
+                .ok_or_else(|| "embedded texture is smaller than its size".into())
+        }
+    }
+}
+
+/// The vertices and indices of a mesh imported by assimp, the same as the loaders upload them
+///
+/// Normals the mesh is missing are generated with [`smooth_normals`] using `smoothing_angle`, and
+/// missing tangents with [`generate_tangents`] when it has texture coordinates.
+pub fn read_mesh(mesh: &Mesh, smoothing_angle: f32) -> (Vec<Vertex>, Vec<u32>) {
+    let indices = mesh
+        .faces
+        .par_iter()
+        .flat_map(|face| face.0.clone())
+        .collect::<Vec<_>>();
+
+    let normals: Vec<Vector3<f32>> = match mesh.normals.len() == mesh.vertices.len() {
+        true => mesh
+            .normals
+            .iter()
+            .map(|normal| Vector3::new(normal.x, normal.y, normal.z))
+            .collect(),
+        false => {
+            let positions = mesh
+                .vertices
+                .iter()
+                .map(|vertex| Vector3::new(vertex.x, vertex.y, vertex.z))
+                .collect::<Vec<_>>();
+
+            smooth_normals(&positions, &indices, smoothing_angle)
+        }
+    };
+
+    // assimp only calculates tangents for meshes that came with normals
+    let generated_tangents = match (
+        mesh.tangents.len() == mesh.vertices.len(),
+        mesh.texture_coords[0].as_ref(),
+    ) {
+        (false, Some(texture_coords)) => {
+            let positions = mesh
+                .vertices
+                .iter()
+                .map(|vertex| Vector3::new(vertex.x, vertex.y, vertex.z))
+                .collect::<Vec<_>>();
+            let tex_coords = texture_coords
+                .iter()
+                .map(|coords| Vector2::new(coords.x, coords.y))
+                .collect::<Vec<_>>();
+
+            Some(generate_tangents(
+                &positions,
+                &normals,
+                &tex_coords,
+                &indices,
+            ))
+        }
+        _ => None,
+    };
+
+    let vertices = (0..mesh.vertices.len())
+        .into_par_iter()
+        .map(|index| {
+            let vertex = mesh.vertices[index];
+            let position: [f32; 3] = [vertex.x, vertex.y, vertex.z];
+            let normal_vec = normals[index];
+            let normal = [normal_vec.x, normal_vec.y, normal_vec.z];
+            let tex_coords = match mesh.texture_coords[0].as_ref() {
+                Some(texture_coords) => {
+                    let vec3 = texture_coords[index];
+                    [vec3.x, vec3.y]
+                }
+                None => [0.0; 2],
+            };
+            let tex_coords2 = match mesh.texture_coords.get(1).and_then(|t| t.as_ref()) {
+                Some(texture_coords) => {
+                    let vec3 = texture_coords[index];
+                    [vec3.x, vec3.y]
+                }
+                None => tex_coords,
+            };
+            // Tangents can only be calculated when the mesh has texture coordinates
+            let tangent = match (mesh.tangents.get(index), mesh.bitangents.get(index)) {
+                (Some(tangent), Some(bitangent)) => {
+                    let n = normal_vec;
+                    let t = Vector3::new(tangent.x, tangent.y, tangent.z);
+                    let b = Vector3::new(bitangent.x, bitangent.y, bitangent.z);
+                    let handedness = if n.cross(&t).dot(&b) < 0.0 { -1.0 } else { 1.0 };
+
+                    [t.x, t.y, t.z, handedness]
+                }
+                _ => match &generated_tangents {
+                    Some(tangents) => tangents[index],
+                    None => [0.0; 4],
+                },
+            };
+
+            let color = match mesh.colors.first().and_then(|colors| colors.as_ref()) {
+                Some(colors) => {
+                    let color = colors[index];
+                    [color.r, color.g, color.b, color.a]
+                }
+                None => [1.0; 4],
+            };
+
+            Vertex {
+                position,
+                normal,
+                tex_coords,
+                tex_coords2,
+                tangent,
+                color,
+                ao: 1.0,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    (vertices, indices)
+}
@@ -0,0 +1,130 @@
+use nalgebra::Vector2;
+use nalgebra::Vector3;
+use std::collections::HashMap;
+
+/// Used by [`LoadOptions`](crate::utils::model::LoadOptions), the same as assimp's default
+pub const DEFAULT_SMOOTHING_ANGLE: f32 = 175.0;
+
+/// Generates a normal for every position of a triangle list, for meshes stored without them
+///
+/// Every vertex averages the normals of the faces around its position, skipping faces that meet
+/// its own face at more than `max_angle` degrees so sharp edges stay sharp. Positions are matched
+/// exactly, since most formats store a separate vertex for each corner of a face.
+pub fn smooth_normals(
+    positions: &[Vector3<f32>],
+    indices: &[u32],
+    max_angle: f32,
+) -> Vec<Vector3<f32>> {
+    let face_normals: Vec<Vector3<f32>> = indices
+        .chunks_exact(3)
+        .map(|face| {
+            let [a, b, c] = [0, 1, 2].map(|i| positions[face[i] as usize]);
+            (b - a)
+                .cross(&(c - a))
+                .try_normalize(0.0)
+                .unwrap_or_else(Vector3::zeros)
+        })
+        .collect();
+
+    // The faces using each vertex, and the faces touching each position
+    let mut vertex_faces = vec![Vec::new(); positions.len()];
+    let mut position_faces: HashMap<[u32; 3], Vec<usize>> = HashMap::new();
+    for (face_index, face) in indices.chunks_exact(3).enumerate() {
+        for index in face {
+            vertex_faces[*index as usize].push(face_index);
+
+            let position = positions[*index as usize].map(f32::to_bits);
+            position_faces
+                .entry(position.into())
+                .or_default()
+                .push(face_index);
+        }
+    }
+
+    let min_cos = max_angle.to_radians().cos();
+
+    positions
+        .iter()
+        .zip(&vertex_faces)
+        .map(|(position, faces)| {
+            let own: Vector3<f32> = faces.iter().map(|face| face_normals[*face]).sum();
+            let own = match own.try_normalize(0.0) {
+                Some(own) => own,
+                None => return Vector3::y(),
+            };
+
+            let neighbors = &position_faces[&Into::<[u32; 3]>::into(position.map(f32::to_bits))];
+            let mut seen = Vec::with_capacity(neighbors.len());
+            let mut normal = Vector3::zeros();
+            for face in neighbors {
+                // A face lists the same position once per corner at that position
+                if seen.contains(face) {
+                    continue;
+                }
+                seen.push(*face);
+
+                if face_normals[*face].dot(&own) >= min_cos {
+                    normal += face_normals[*face];
+                }
+            }
+
+            normal.try_normalize(0.0).unwrap_or(own)
+        })
+        .collect()
+}
+
+/// Generates a tangent for every vertex of a triangle list from its texture coordinates, for
+/// meshes stored without them
+///
+/// The tangent points along the u direction of the texture, made perpendicular to the normal, with
+/// the handedness of the bitangent (the v direction) in `w` like
+/// [`Vertex::tangent`](crate::vertex::Vertex::tangent). Vertices whose faces have no usable
+/// texture coordinates get a zero tangent, so shaders fall back to their own tangent space.
+pub fn generate_tangents(
+    positions: &[Vector3<f32>],
+    normals: &[Vector3<f32>],
+    tex_coords: &[Vector2<f32>],
+    indices: &[u32],
+) -> Vec<[f32; 4]> {
+    let mut tangents = vec![Vector3::zeros(); positions.len()];
+    let mut bitangents = vec![Vector3::zeros(); positions.len()];
+
+    for face in indices.chunks_exact(3) {
+        let [a, b, c] = [0, 1, 2].map(|i| face[i] as usize);
+        let edge1 = positions[b] - positions[a];
+        let edge2 = positions[c] - positions[a];
+        let uv1 = tex_coords[b] - tex_coords[a];
+        let uv2 = tex_coords[c] - tex_coords[a];
+
+        let determinant = uv1.x * uv2.y - uv2.x * uv1.y;
+        // The texture is collapsed to a line or a point on this face
+        if determinant.abs() < f32::EPSILON {
+            continue;
+        }
+
+        let tangent = (edge1 * uv2.y - edge2 * uv1.y) / determinant;
+        let bitangent = (edge2 * uv1.x - edge1 * uv2.x) / determinant;
+        for index in [a, b, c] {
+            tangents[index] += tangent;
+            bitangents[index] += bitangent;
+        }
+    }
+
+    normals
+        .iter()
+        .zip(tangents.iter().zip(&bitangents))
+        .map(|(normal, (tangent, bitangent))| {
+            let tangent = tangent - normal * normal.dot(tangent);
+            match tangent.try_normalize(f32::EPSILON) {
+                Some(t) => {
+                    let handedness = match normal.cross(&t).dot(bitangent) < 0.0 {
+                        true => -1.0,
+                        false => 1.0,
+                    };
+                    [t.x, t.y, t.z, handedness]
+                }
+                None => [0.0; 4],
+            }
+        })
+        .collect()
+}
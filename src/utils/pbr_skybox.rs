@@ -1,5 +1,18 @@
+use crate::error::RendererError;
+use crate::renderer::SceneData;
+use crate::shaders::brdf::BRDF;
+use crate::shaders::equi_rect_to_cubemap::EquiRectCubemap;
+use crate::shaders::irradiance_convolution::IrradianceConvolution;
+use crate::shaders::prefilter::Prefilter;
+use crate::utils::texture_registry::TextureRegistry;
+use glium::backend::Facade;
 use glium::texture::Cubemap;
 use glium::Texture2d;
+use nalgebra::Matrix3;
+use nalgebra::Rotation3;
+use nalgebra::Vector3;
+use std::error::Error;
+use std::path::Path;
 use std::rc::Rc;
 
 #[derive(Clone)]
@@ -8,6 +21,8 @@ pub struct PBRSkybox {
     irradiance: Rc<Cubemap>,
     prefilter: Rc<Cubemap>,
     brdf: Rc<Texture2d>,
+    rotation: f32,
+    tint: [f32; 3],
 }
 
 impl PBRSkybox {
@@ -17,14 +32,76 @@ impl PBRSkybox {
         prefilter: Rc<Cubemap>,
         brdf: Rc<Texture2d>,
     ) -> Self {
+        TextureRegistry::track_cubemap(&skybox);
+        TextureRegistry::track_cubemap(&irradiance);
+        TextureRegistry::track_cubemap(&prefilter);
+        TextureRegistry::track_texture(&brdf);
+
         Self {
             skybox,
             irradiance,
             prefilter,
             brdf,
+            rotation: 0.0,
+            tint: [1.0; 3],
         }
     }
+
+    /// The skybox set on the scene, or an error naming the `material` that needs it
+    ///
+    /// Meant for [`Shader::check_scene`](crate::shader::Shader::check_scene) of materials that
+    /// sample the skybox or its maps.
+    pub fn require<'a>(
+        scene_data: &'a SceneData,
+        material: &str,
+    ) -> Result<&'a PBRSkybox, RendererError> {
+        scene_data.get_scene_object::<PBRSkybox>().ok_or_else(|| {
+            RendererError::MissingSceneObject {
+                material: material.to_string(),
+                object: "PBRSkybox",
+            }
+        })
+    }
+
+    /// Computes the irradiance, prefilter, and brdf maps for the skybox
+    ///
+    /// Everything stays on the gpu, so the maps keep their float (HDR) formats.
+    pub fn generate_in_memory(
+        facade: &impl Facade,
+        skybox: Rc<Cubemap>,
+        irradiance_convolution: &IrradianceConvolution,
+        prefilter: &Prefilter,
+        brdf: &BRDF,
+    ) -> Self {
+        let irradiance = irradiance_convolution.calculate(facade, &skybox);
+        let prefilter = prefilter.compute(facade, &skybox);
+        let brdf = brdf.compute(facade);
+
+        Self::new(skybox, irradiance.into(), prefilter.into(), brdf.into())
+    }
+
+    /// Creates the skybox and all of its maps from an equirectangular HDR image
+    ///
+    /// `resolution` is the size of each face of the skybox cubemap.
+    pub fn from_equirect_hdr(
+        facade: &impl Facade,
+        path: impl AsRef<Path>,
+        resolution: u32,
+    ) -> Result<Self, Box<dyn Error>> {
+        let skybox =
+            EquiRectCubemap::load_from_fs(facade).compute_from_fs_hdr(facade, path, resolution)?;
+
+        Ok(Self::generate_in_memory(
+            facade,
+            skybox.into(),
+            &IrradianceConvolution::load_from_fs(facade),
+            &Prefilter::load_from_fs(facade),
+            &BRDF::load_from_fs(facade),
+        ))
+    }
+
     pub fn set_skybox(&mut self, skybox: Rc<Cubemap>) {
+        TextureRegistry::track_cubemap(&skybox);
         self.skybox = skybox;
     }
 
@@ -33,6 +110,7 @@ impl PBRSkybox {
     }
 
     pub fn set_irradiance(&mut self, irradiance: Rc<Cubemap>) {
+        TextureRegistry::track_cubemap(&irradiance);
         self.irradiance = irradiance;
     }
 
@@ -41,6 +119,7 @@ impl PBRSkybox {
     }
 
     pub fn set_prefilter(&mut self, prefilter: Rc<Cubemap>) {
+        TextureRegistry::track_cubemap(&prefilter);
         self.prefilter = prefilter;
     }
 
@@ -48,10 +127,45 @@ impl PBRSkybox {
         &self.prefilter
     }
     pub fn set_brdf(&mut self, brdf: Rc<Texture2d>) {
+        TextureRegistry::track_texture(&brdf);
         self.brdf = brdf;
     }
 
     pub fn get_brdf(&self) -> &Rc<Texture2d> {
         &self.brdf
     }
+
+    /// Rotates the environment around the up axis, ex. to line the sun up with the scene's lights
+    ///
+    /// Applies to both the visible skybox and the image based lighting of [`PBR`], so they stay
+    /// consistent.
+    ///
+    /// The rotation is applied when the cubemaps are sampled instead of being baked into them, so
+    /// the irradiance and prefilter maps never have to be regenerated and the rotation can be
+    /// animated. This costs a 3x3 matrix multiplication per lookup. Maps generated with
+    /// [`generate_in_memory`](Self::generate_in_memory) or loaded from disk are always in the
+    /// orientation of the original skybox.
+    ///
+    /// [`PBR`]: crate::shaders::pbr::PBR
+    pub fn set_rotation_rad(&mut self, rotation: f32) {
+        self.rotation = rotation;
+    }
+    pub fn get_rotation_rad(&self) -> f32 {
+        self.rotation
+    }
+
+    /// Turns a world space direction into the direction to sample the cubemaps with
+    pub fn get_environment_rotation(&self) -> Matrix3<f32> {
+        Rotation3::from_axis_angle(&Vector3::y_axis(), -self.rotation).into_inner()
+    }
+
+    /// Multiplied with the color of the visible skybox, white by default
+    ///
+    /// Only changes the background, the lighting still uses the original colors.
+    pub fn set_tint(&mut self, tint: [f32; 3]) {
+        self.tint = tint;
+    }
+    pub fn get_tint(&self) -> [f32; 3] {
+        self.tint
+    }
 }
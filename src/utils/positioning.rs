@@ -1,5 +1,6 @@
 use nalgebra::{Matrix4, Rotation3};
 
+#[derive(Clone, Copy, Debug)]
 pub struct Rotation {
     rotation: [f32; 3],
 }
@@ -0,0 +1,311 @@
+use crate::error::RendererError;
+use glium::backend::Facade;
+use glium::framebuffer::ColorAttachment;
+use glium::framebuffer::SimpleFrameBuffer;
+use glium::framebuffer::ToColorAttachment;
+use glium::texture::DepthStencilTexture2d;
+use glium::texture::DepthStencilTexture2dMultisample;
+use glium::texture::DepthTexture2d;
+use glium::texture::DepthTexture2dMultisample;
+use glium::texture::MipmapsOption;
+use glium::texture::SrgbFormat;
+use glium::texture::SrgbTexture2d;
+use glium::texture::SrgbTexture2dMultisample;
+use glium::texture::Texture2d;
+use glium::texture::Texture2dMultisample;
+use glium::texture::TextureCreationError;
+use glium::texture::UncompressedFloatFormat;
+use glium::uniforms::MagnifySamplerFilter;
+use glium::Surface;
+use std::rc::Rc;
+
+/// A color texture a [`RenderSurface`] can render into
+///
+/// [`SrgbTexture2d`] is for images that are displayed as they are, [`Texture2d`] with a float
+/// format for hdr images that are post-processed first (ex. bloom and tone mapping).
+pub trait SurfaceTexture: Sized {
+    type Format: Copy;
+    type Multisample;
+
+    fn empty(
+        facade: &impl Facade,
+        format: Self::Format,
+        width: u32,
+        height: u32,
+    ) -> Result<Self, TextureCreationError>;
+    fn empty_multisample(
+        facade: &impl Facade,
+        format: Self::Format,
+        width: u32,
+        height: u32,
+        samples: u32,
+    ) -> Result<Self::Multisample, TextureCreationError>;
+
+    fn attachment(&self) -> ColorAttachment<'_>;
+    fn multisample_attachment(multisample: &Self::Multisample) -> ColorAttachment<'_>;
+    fn size(&self) -> (u32, u32);
+}
+
+impl SurfaceTexture for SrgbTexture2d {
+    type Format = SrgbFormat;
+    type Multisample = SrgbTexture2dMultisample;
+
+    fn empty(
+        facade: &impl Facade,
+        format: SrgbFormat,
+        width: u32,
+        height: u32,
+    ) -> Result<Self, TextureCreationError> {
+        Self::empty_with_format(facade, format, MipmapsOption::NoMipmap, width, height)
+    }
+
+    fn empty_multisample(
+        facade: &impl Facade,
+        format: SrgbFormat,
+        width: u32,
+        height: u32,
+        samples: u32,
+    ) -> Result<SrgbTexture2dMultisample, TextureCreationError> {
+        SrgbTexture2dMultisample::empty_with_format(
+            facade,
+            format,
+            MipmapsOption::NoMipmap,
+            width,
+            height,
+            samples,
+        )
+    }
+
+    fn attachment(&self) -> ColorAttachment<'_> {
+        self.to_color_attachment()
+    }
+
+    fn multisample_attachment(multisample: &SrgbTexture2dMultisample) -> ColorAttachment<'_> {
+        multisample.to_color_attachment()
+    }
+
+    fn size(&self) -> (u32, u32) {
+        self.dimensions()
+    }
+}
+
+impl SurfaceTexture for Texture2d {
+    type Format = UncompressedFloatFormat;
+    type Multisample = Texture2dMultisample;
+
+    fn empty(
+        facade: &impl Facade,
+        format: UncompressedFloatFormat,
+        width: u32,
+        height: u32,
+    ) -> Result<Self, TextureCreationError> {
+        Self::empty_with_format(facade, format, MipmapsOption::NoMipmap, width, height)
+    }
+
+    fn empty_multisample(
+        facade: &impl Facade,
+        format: UncompressedFloatFormat,
+        width: u32,
+        height: u32,
+        samples: u32,
+    ) -> Result<Texture2dMultisample, TextureCreationError> {
+        Texture2dMultisample::empty_with_format(
+            facade,
+            format,
+            MipmapsOption::NoMipmap,
+            width,
+            height,
+            samples,
+        )
+    }
+
+    fn attachment(&self) -> ColorAttachment<'_> {
+        self.to_color_attachment()
+    }
+
+    fn multisample_attachment(multisample: &Texture2dMultisample) -> ColorAttachment<'_> {
+        multisample.to_color_attachment()
+    }
+
+    fn size(&self) -> (u32, u32) {
+        self.dimensions()
+    }
+}
+
+enum DepthBuffer {
+    Depth(DepthTexture2d),
+    DepthStencil(DepthStencilTexture2d),
+    DepthMultisample(DepthTexture2dMultisample),
+    DepthStencilMultisample(DepthStencilTexture2dMultisample),
+}
+
+/// An offscreen color and depth target to render into, ex. to show the scene in an egui image
+///
+/// The color texture is parameterized by its type and format, see [`SurfaceTexture`]. With more
+/// than one sample the scene is rendered into multisampled textures, call
+/// [`resolve`](Self::resolve) afterwards to get a texture that can be sampled.
+pub struct RenderSurface<T: SurfaceTexture = SrgbTexture2d> {
+    /// The rendered image, only up to date after [`resolve`](Self::resolve) when multisampled
+    pub texture: Rc<T>,
+    multisample: Option<T::Multisample>,
+    depth: DepthBuffer,
+    format: T::Format,
+    samples: u32,
+    stencil: bool,
+}
+
+impl RenderSurface<SrgbTexture2d> {
+    /// An 8 bit srgb surface with a stencil buffer and no multisampling, for displaying directly
+    pub fn new(facade: &impl Facade, width: u32, height: u32) -> Result<Self, RendererError> {
+        Self::with_format(facade, width, height, SrgbFormat::U8U8U8U8, 1, true)
+    }
+}
+
+impl RenderSurface<Texture2d> {
+    /// A half float surface with a stencil buffer and no multisampling, for hdr rendering
+    pub fn new_hdr(facade: &impl Facade, width: u32, height: u32) -> Result<Self, RendererError> {
+        Self::with_format(
+            facade,
+            width,
+            height,
+            UncompressedFloatFormat::F16F16F16F16,
+            1,
+            true,
+        )
+    }
+}
+
+impl<T: SurfaceTexture> RenderSurface<T> {
+    /// Multisampling is disabled with a `samples` of 1 (or 0)
+    pub fn with_format(
+        facade: &impl Facade,
+        width: u32,
+        height: u32,
+        format: T::Format,
+        samples: u32,
+        stencil: bool,
+    ) -> Result<Self, RendererError> {
+        let samples = samples.max(1);
+        let multisample = match samples > 1 {
+            true => Some(T::empty_multisample(
+                facade, format, width, height, samples,
+            )?),
+            false => None,
+        };
+
+        let depth = match (samples > 1, stencil) {
+            (false, false) => DepthBuffer::Depth(DepthTexture2d::empty(facade, width, height)?),
+            (false, true) => {
+                DepthBuffer::DepthStencil(DepthStencilTexture2d::empty(facade, width, height)?)
+            }
+            (true, false) => DepthBuffer::DepthMultisample(DepthTexture2dMultisample::empty(
+                facade, width, height, samples,
+            )?),
+            (true, true) => DepthBuffer::DepthStencilMultisample(
+                DepthStencilTexture2dMultisample::empty(facade, width, height, samples)?,
+            ),
+        };
+
+        Ok(Self {
+            texture: Rc::new(T::empty(facade, format, width, height)?),
+            multisample,
+            depth,
+            format,
+            samples,
+            stencil,
+        })
+    }
+
+    /// Recreates the textures at the new size with the same format, samples, and stencil
+    pub fn resize(
+        &mut self,
+        facade: &impl Facade,
+        width: u32,
+        height: u32,
+    ) -> Result<(), RendererError> {
+        *self = Self::with_format(
+            facade,
+            width,
+            height,
+            self.format,
+            self.samples,
+            self.stencil,
+        )?;
+
+        Ok(())
+    }
+
+    /// Renders into the multisampled textures, or straight into [`texture`](Self::texture)
+    pub fn frame_buffer(&self, facade: &impl Facade) -> Result<SimpleFrameBuffer, RendererError> {
+        let color = match &self.multisample {
+            Some(multisample) => T::multisample_attachment(multisample),
+            None => self.texture.attachment(),
+        };
+
+        let frame_buffer = match &self.depth {
+            DepthBuffer::Depth(depth) => SimpleFrameBuffer::with_depth_buffer(facade, color, depth),
+            DepthBuffer::DepthStencil(depth) => {
+                SimpleFrameBuffer::with_depth_stencil_buffer(facade, color, depth)
+            }
+            DepthBuffer::DepthMultisample(depth) => {
+                SimpleFrameBuffer::with_depth_buffer(facade, color, depth)
+            }
+            DepthBuffer::DepthStencilMultisample(depth) => {
+                SimpleFrameBuffer::with_depth_stencil_buffer(facade, color, depth)
+            }
+        }?;
+
+        Ok(frame_buffer)
+    }
+
+    /// Averages the samples of every pixel into [`texture`](Self::texture) and returns it,
+    /// nothing has to be done without multisampling
+    pub fn resolve(&self, facade: &impl Facade) -> Result<&Rc<T>, RendererError> {
+        if let Some(multisample) = &self.multisample {
+            let source = SimpleFrameBuffer::new(facade, T::multisample_attachment(multisample))?;
+            let target = SimpleFrameBuffer::new(facade, self.texture.attachment())?;
+
+            // Blitting between the same sizes resolves the samples, so the filter doesn't matter
+            source.fill(&target, MagnifySamplerFilter::Nearest);
+        }
+
+        Ok(&self.texture)
+    }
+
+    /// The depth of the last rendered scene, for post-processing (ex.
+    /// [`DepthOfField`](crate::shaders::depth_of_field::DepthOfField))
+    ///
+    /// Only surfaces without a stencil buffer or multisampling have a depth texture that can be
+    /// sampled, `None` otherwise.
+    pub fn get_depth_texture(&self) -> Option<&DepthTexture2d> {
+        match &self.depth {
+            DepthBuffer::Depth(depth) => Some(depth),
+            _ => None,
+        }
+    }
+
+    pub fn get_format(&self) -> T::Format {
+        self.format
+    }
+
+    /// 1 when the surface isn't multisampled
+    pub fn get_samples(&self) -> u32 {
+        self.samples
+    }
+
+    pub fn has_stencil(&self) -> bool {
+        self.stencil
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        self.texture.size()
+    }
+
+    pub fn width(&self) -> u32 {
+        self.size().0
+    }
+
+    pub fn height(&self) -> u32 {
+        self.size().1
+    }
+}
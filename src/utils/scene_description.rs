@@ -0,0 +1,183 @@
+//! Saving and loading the models of a scene
+//!
+//! Only what is needed to rebuild the scene is stored: the file each model came from, its
+//! transform, and the material of each of its sub models. Textures are stored as the path they
+//! were loaded from, so they have to still exist when the scene is loaded. The camera can be
+//! stored too, to come back to the same view.
+
+use crate::shaders::pbr::{PBRSimple, PBRTextureSources, PBRTextures, PBR};
+use crate::utils::camera::CameraDescription;
+use crate::utils::model::{Model, ModelLoad};
+use crate::utils::positioning::Rotation;
+use glium::backend::Facade;
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct MaterialDescription {
+    /// Used for every map that doesn't have a source
+    pub params: PBRSimple,
+    pub sources: PBRTextureSources,
+    pub orm_occlusion: bool,
+    #[serde(default)]
+    pub ao_uv2: bool,
+    /// Clamped to at least 1 when loading
+    #[serde(default)]
+    pub anisotropy: u16,
+    #[serde(default = "MaterialDescription::default_height_scale")]
+    pub height_scale: f32,
+}
+
+impl MaterialDescription {
+    pub fn from_textures(textures: &PBRTextures) -> Self {
+        Self {
+            params: textures.to_simple(),
+            sources: textures.get_sources().clone(),
+            orm_occlusion: textures.get_orm_occlusion(),
+            ao_uv2: textures.get_ao_uv2(),
+            anisotropy: textures.get_anisotropy(),
+            height_scale: textures.get_height_scale(),
+        }
+    }
+
+    fn default_height_scale() -> f32 {
+        0.05
+    }
+
+    pub fn to_textures(&self, facade: &impl Facade) -> Result<PBRTextures, Box<dyn Error>> {
+        let mut textures = PBRTextures::from_simple(facade, self.params.clone());
+        textures.load_sources(&self.sources)?;
+        textures.set_orm_occlusion(self.orm_occlusion);
+        textures.set_ao_uv2(self.ao_uv2);
+        textures.set_anisotropy(self.anisotropy);
+        textures.set_height_scale(self.height_scale);
+
+        Ok(textures)
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SubModelDescription {
+    pub position: [f32; 3],
+    /// Euler angles in radians
+    pub rotation: [f32; 3],
+    pub material: MaterialDescription,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ModelDescription {
+    pub path: PathBuf,
+    /// Loaded with [`Model::load_from_fs_hierarchical`] instead of flattening the nodes
+    #[serde(default)]
+    pub hierarchical: bool,
+    pub position: [f32; 3],
+    /// Euler angles in radians
+    pub rotation: [f32; 3],
+    pub sub_models: Vec<SubModelDescription>,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct SceneDescription {
+    pub models: Vec<ModelDescription>,
+    /// Where the scene was viewed from, not set by [`from_models`](Self::from_models)
+    #[serde(default)]
+    pub camera: Option<CameraDescription>,
+}
+
+impl SceneDescription {
+    /// Describes every model that was loaded from a file, the others are skipped
+    pub fn from_models<'a>(models: impl IntoIterator<Item = &'a Model<PBR>>) -> Self {
+        let models = models
+            .into_iter()
+            .filter_map(|model| {
+                let sub_models = model
+                    .get_sub_models()
+                    .iter()
+                    .map(|sub| SubModelDescription {
+                        position: (*sub.get_position()).into(),
+                        rotation: sub.get_rotation().get_euler_angles(),
+                        material: MaterialDescription::from_textures(
+                            sub.get_shader().get_pbr_params(),
+                        ),
+                    })
+                    .collect();
+
+                Some(ModelDescription {
+                    path: model.get_path()?.to_path_buf(),
+                    hierarchical: model
+                        .get_sub_models()
+                        .iter()
+                        .any(|sub| sub.get_node_name().is_some()),
+                    position: (*model.get_position()).into(),
+                    rotation: model.get_rotation().get_euler_angles(),
+                    sub_models,
+                })
+            })
+            .collect();
+
+        Self {
+            models,
+            camera: None,
+        }
+    }
+
+    /// Writes the description as json
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), Box<dyn Error>> {
+        let writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(writer, self)?;
+
+        Ok(())
+    }
+
+    /// Reads a description written by [`save`](Self::save)
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Box<dyn Error>> {
+        let reader = BufReader::new(File::open(path)?);
+
+        Ok(serde_json::from_reader(reader)?)
+    }
+
+    /// Reads a description and loads every model in it
+    pub fn load(
+        path: impl AsRef<Path>,
+        facade: &impl Facade,
+    ) -> Result<Vec<Model<PBR>>, Box<dyn Error>> {
+        Self::open(path)?.to_models(facade)
+    }
+
+    /// Loads every model from its file and applies the saved transforms and materials
+    ///
+    /// Sub models are matched by index, so changes to the model files since saving can put
+    /// materials on the wrong sub model.
+    pub fn to_models(&self, facade: &impl Facade) -> Result<Vec<Model<PBR>>, Box<dyn Error>> {
+        self.models
+            .iter()
+            .map(|description| {
+                let mut model = match description.hierarchical {
+                    true => Model::load_from_fs_hierarchical(facade, &description.path)?,
+                    false => Model::<PBR>::load_from_fs(facade, &description.path)?,
+                };
+
+                for (sub, sub_description) in model
+                    .get_sub_models_mut()
+                    .iter_mut()
+                    .zip(&description.sub_models)
+                {
+                    let [roll, pitch, yaw] = sub_description.rotation;
+                    sub.set_position(sub_description.position.into());
+                    sub.set_rotation(Rotation::from_euler_angles(roll, pitch, yaw));
+                    sub.get_shader_mut()
+                        .set_pbr_params(sub_description.material.to_textures(facade)?);
+                }
+
+                let [roll, pitch, yaw] = description.rotation;
+                model.set_position(description.position.into());
+                model.set_rotation(Rotation::from_euler_angles(roll, pitch, yaw));
+
+                Ok(model)
+            })
+            .collect()
+    }
+}
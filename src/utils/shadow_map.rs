@@ -0,0 +1,153 @@
+use crate::shaders::shadow_depth::ShadowDepth;
+use crate::utils::lights::DirectionalLight;
+use glium::backend::Context;
+use glium::backend::Facade;
+use glium::index::IndicesSource;
+use glium::texture::DepthTexture2d;
+use glium::uniforms::MagnifySamplerFilter;
+use glium::uniforms::MinifySamplerFilter;
+use glium::uniforms::SamplerBehavior;
+use glium::uniforms::SamplerWrapFunction;
+use glium::uniforms::UniformValue;
+use glium::uniforms::Uniforms;
+use glium::vertex::VerticesSource;
+use glium::Texture2d;
+use nalgebra::Matrix4;
+use nalgebra::Vector3;
+use std::rc::Rc;
+
+/// A depth texture rendered from the point of view of the directional light
+///
+/// Created by [`Renderer::enable_shadows`](crate::renderer::Renderer::enable_shadows), which
+/// renders it every frame and puts a copy on [`SceneData`](crate::renderer::SceneData) for the
+/// materials to sample.
+#[derive(Clone)]
+pub struct ShadowMap {
+    depth: Rc<DepthTexture2d>,
+    light_space_matrix: Matrix4<f32>,
+    extent: f32,
+    shadow_depth: ShadowDepth,
+    facade: Rc<Context>,
+}
+
+impl ShadowMap {
+    pub fn new(facade: &impl Facade, resolution: u32) -> Self {
+        Self {
+            depth: Rc::new(DepthTexture2d::empty(facade, resolution, resolution).unwrap()),
+            light_space_matrix: Matrix4::identity(),
+            extent: 20.0,
+            shadow_depth: ShadowDepth::load_from_fs(facade),
+            facade: facade.get_context().clone(),
+        }
+    }
+
+    pub fn get_depth(&self) -> &Rc<DepthTexture2d> {
+        &self.depth
+    }
+
+    /// Transforms world space positions into the light's clip space
+    pub fn get_light_space_matrix(&self) -> Matrix4<f32> {
+        self.light_space_matrix
+    }
+
+    /// Half the width of the area around the camera that casts shadows
+    pub fn get_extent(&self) -> f32 {
+        self.extent
+    }
+    pub fn set_extent(&mut self, extent: f32) {
+        self.extent = extent;
+    }
+
+    /// Renders the casters into the depth texture
+    ///
+    /// The light looks at `center` from far enough away that everything within
+    /// [`extent`](Self::get_extent) of it is in view.
+    pub fn render<'a>(
+        &mut self,
+        light: &DirectionalLight,
+        center: Vector3<f32>,
+        casters: impl IntoIterator<Item = (VerticesSource<'a>, IndicesSource<'a>, Matrix4<f32>)>,
+    ) {
+        let direction = Vector3::from(light.direction).normalize();
+
+        // look_at breaks when looking straight along the up vector
+        let up = if direction.y.abs() > 0.99 {
+            Vector3::z()
+        } else {
+            Vector3::y()
+        };
+
+        let eye = center - direction * self.extent * 2.0;
+        let view = Matrix4::look_at_rh(&eye.into(), &center.into(), &up);
+        let projection = Matrix4::new_orthographic(
+            -self.extent,
+            self.extent,
+            -self.extent,
+            self.extent,
+            0.1,
+            self.extent * 4.0,
+        );
+
+        self.light_space_matrix = projection * view;
+
+        self.shadow_depth
+            .compute(&self.facade, &self.depth, self.light_space_matrix, casters);
+    }
+
+    /// Adds `shadowMap`, `lightSpaceMatrix`, and `useShadows` to the given uniforms
+    ///
+    /// Every sampler needs a texture bound, so `fallback` is bound to `shadowMap` when there is no
+    /// shadow map. It is never read since `useShadows` is false.
+    pub fn with_uniforms<'a, U: Uniforms>(
+        shadow_map: Option<&'a ShadowMap>,
+        fallback: &'a Texture2d,
+        uniforms: U,
+    ) -> ShadowUniforms<'a, U> {
+        ShadowUniforms {
+            shadow_map,
+            fallback,
+            uniforms,
+        }
+    }
+}
+
+pub struct ShadowUniforms<'a, U: Uniforms> {
+    shadow_map: Option<&'a ShadowMap>,
+    fallback: &'a Texture2d,
+    uniforms: U,
+}
+
+impl<'a, U: Uniforms> Uniforms for ShadowUniforms<'a, U> {
+    fn visit_values<'b, F: FnMut(&str, UniformValue<'b>)>(&'b self, mut output: F) {
+        self.uniforms.visit_values(&mut output);
+
+        match self.shadow_map {
+            Some(shadow_map) => {
+                let behavior = SamplerBehavior {
+                    wrap_function: (
+                        SamplerWrapFunction::Clamp,
+                        SamplerWrapFunction::Clamp,
+                        SamplerWrapFunction::Clamp,
+                    ),
+                    minify_filter: MinifySamplerFilter::Nearest,
+                    magnify_filter: MagnifySamplerFilter::Nearest,
+                    ..Default::default()
+                };
+
+                output(
+                    "shadowMap",
+                    UniformValue::DepthTexture2d(&shadow_map.depth, Some(behavior)),
+                );
+                output(
+                    "lightSpaceMatrix",
+                    UniformValue::Mat4(shadow_map.light_space_matrix.into()),
+                );
+                output("useShadows", UniformValue::Bool(true));
+            }
+            None => {
+                output("shadowMap", UniformValue::Texture2d(self.fallback, None));
+                output("useShadows", UniformValue::Bool(false));
+            }
+        }
+    }
+}
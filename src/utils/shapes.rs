@@ -1,4 +1,8 @@
 use crate::vertex::Vertex;
+use glium::backend::Facade;
+use glium::index::PrimitiveType;
+use glium::IndexBuffer;
+use glium::VertexBuffer;
 
 pub fn get_cube() -> Vec<Vertex> {
     vec![
@@ -228,6 +232,12 @@ pub fn get_cube() -> Vec<Vertex> {
     ]
 }
 
+/// A quad covering the whole screen in normalized device coordinates, as a triangle strip
+///
+/// The vertices are top-left, bottom-left, top-right, bottom-right. Texture coordinates follow
+/// OpenGL's convention with `(0, 0)` in the bottom-left corner, so sampling a texture that was
+/// rendered to shows it the right way up. Prefer [`fullscreen_quad`], which doesn't depend on the
+/// vertex order.
 pub fn get_quad() -> [Vertex; 4] {
     [
         Vertex {
@@ -252,3 +262,15 @@ pub fn get_quad() -> [Vertex; 4] {
         },
     ]
 }
+
+/// Buffers for drawing [`get_quad`] as a triangle list
+///
+/// Both triangles are wound counter-clockwise when seen from the front (looking down -z), so the
+/// quad isn't culled with the default backface culling.
+pub fn fullscreen_quad(facade: &impl Facade) -> (VertexBuffer<Vertex>, IndexBuffer<u32>) {
+    let vertex_buffer = VertexBuffer::new(facade, &get_quad()).unwrap();
+    let index_buffer =
+        IndexBuffer::new(facade, PrimitiveType::TrianglesList, &[0, 1, 2, 2, 1, 3]).unwrap();
+
+    (vertex_buffer, index_buffer)
+}
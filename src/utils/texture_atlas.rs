@@ -0,0 +1,171 @@
+use crate::error::RendererError;
+use crate::utils::texture_registry::TextureRegistry;
+use glium::backend::Facade;
+use glium::texture::MipmapsOption;
+use glium::texture::RawImage2d;
+use glium::texture::UncompressedFloatFormat;
+use glium::Texture2d;
+use image::imageops;
+use image::DynamicImage;
+use image::RgbaImage;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::rc::Rc;
+
+/// Where an image was placed in a [`TextureAtlas`], in pixels from the top left corner like the
+/// image itself
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct AtlasRegion {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl AtlasRegion {
+    /// The texture coordinates of the region as `[u_min, v_min, u_max, v_max]`
+    ///
+    /// The atlas is flipped vertically when it is uploaded like every other texture, so `v`
+    /// starts at the bottom.
+    pub fn uv_rect(&self, atlas_width: u32, atlas_height: u32) -> [f32; 4] {
+        let (atlas_width, atlas_height) = (atlas_width as f32, atlas_height as f32);
+
+        [
+            self.x as f32 / atlas_width,
+            1.0 - (self.y + self.height) as f32 / atlas_height,
+            (self.x + self.width) as f32 / atlas_width,
+            1.0 - self.y as f32 / atlas_height,
+        ]
+    }
+}
+
+/// Packs rectangles of the given sizes into shelves, returning the size of the atlas and the top
+/// left corner of every rectangle in the same order as `sizes`
+///
+/// The tallest rectangles are placed first, left to right, and a new shelf is started below once
+/// a row is full. The width is the smallest power of two that roughly fits everything in a
+/// square and is at least as wide as the widest rectangle. `padding` pixels are left between
+/// rectangles so filtering doesn't bleed neighbours into each other.
+pub fn pack_shelves(sizes: &[(u32, u32)], padding: u32) -> ((u32, u32), Vec<(u32, u32)>) {
+    let area: u64 = sizes
+        .iter()
+        .map(|(width, height)| (width + padding) as u64 * (height + padding) as u64)
+        .sum();
+    let widest = sizes.iter().map(|(width, _)| *width).max().unwrap_or(0);
+    let atlas_width = ((area as f64).sqrt().ceil() as u32)
+        .max(widest)
+        .next_power_of_two();
+
+    let mut order: Vec<usize> = (0..sizes.len()).collect();
+    order.sort_by(|a, b| sizes[*b].1.cmp(&sizes[*a].1));
+
+    let mut positions = vec![(0, 0); sizes.len()];
+    let (mut x, mut shelf_y, mut shelf_height) = (0, 0, 0);
+    for index in order {
+        let (width, height) = sizes[index];
+
+        if x > 0 && x + width > atlas_width {
+            x = 0;
+            shelf_y += shelf_height + padding;
+            shelf_height = 0;
+        }
+
+        positions[index] = (x, shelf_y);
+        x += width + padding;
+        shelf_height = shelf_height.max(height);
+    }
+
+    ((atlas_width, (shelf_y + shelf_height).max(1)), positions)
+}
+
+/// Many small images combined into one texture, ex. for decals or icons
+///
+/// Models sampling different parts of the same texture can share a material, so they can be
+/// batched and use a single texture unit. The images are looked up by key to get the part of the
+/// atlas they are in, see [`get_uv_rect`](Self::get_uv_rect).
+///
+/// The atlas is stored as 8 bit rgba without mipmaps, since the smaller levels would blend
+/// neighbouring images together. Like [`TextureLoader::from_image`] the colors aren't converted
+/// from srgb.
+///
+/// [`TextureLoader::from_image`]: crate::utils::texture_loader::TextureLoader::from_image
+pub struct TextureAtlas<K = String> {
+    texture: Rc<Texture2d>,
+    regions: HashMap<K, AtlasRegion>,
+}
+
+impl<K: Eq + Hash> TextureAtlas<K> {
+    /// Packs `images` with [`pack_shelves`] and uploads the atlas
+    ///
+    /// If two images have the same key, the key looks up the last one.
+    pub fn new(
+        facade: &impl Facade,
+        images: impl IntoIterator<Item = (K, DynamicImage)>,
+        padding: u32,
+    ) -> Result<Self, RendererError> {
+        let (keys, images): (Vec<K>, Vec<RgbaImage>) = images
+            .into_iter()
+            .map(|(key, image)| (key, image.into_rgba8()))
+            .unzip();
+
+        let sizes: Vec<_> = images.iter().map(RgbaImage::dimensions).collect();
+        let ((width, height), positions) = pack_shelves(&sizes, padding);
+
+        let mut atlas = RgbaImage::new(width, height);
+        for (image, (x, y)) in images.iter().zip(&positions) {
+            imageops::replace(&mut atlas, image, *x as i64, *y as i64);
+        }
+
+        let regions = keys
+            .into_iter()
+            .zip(sizes.into_iter().zip(positions))
+            .map(|(key, ((width, height), (x, y)))| {
+                (
+                    key,
+                    AtlasRegion {
+                        x,
+                        y,
+                        width,
+                        height,
+                    },
+                )
+            })
+            .collect();
+
+        let texture = Texture2d::with_format(
+            facade,
+            RawImage2d::from_raw_rgba_reversed(&atlas.into_raw(), (width, height)),
+            UncompressedFloatFormat::U8U8U8U8,
+            MipmapsOption::NoMipmap,
+        )?;
+        let texture = Rc::new(texture);
+        TextureRegistry::track_texture(&texture);
+
+        Ok(Self { texture, regions })
+    }
+
+    pub fn get_texture(&self) -> &Rc<Texture2d> {
+        &self.texture
+    }
+
+    pub fn size(&self) -> (u32, u32) {
+        self.texture.dimensions()
+    }
+
+    /// Where the image was placed, in pixels
+    pub fn get_region(&self, key: &K) -> Option<&AtlasRegion> {
+        self.regions.get(key)
+    }
+
+    /// The texture coordinates of the image as `[u_min, v_min, u_max, v_max]`, see
+    /// [`AtlasRegion::uv_rect`]
+    pub fn get_uv_rect(&self, key: &K) -> Option<[f32; 4]> {
+        let (width, height) = self.size();
+
+        Some(self.regions.get(key)?.uv_rect(width, height))
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        self.regions.keys()
+    }
+}
@@ -0,0 +1,140 @@
+use crate::error::RendererError;
+use crate::utils::texture_loader::TextureLoader;
+use crate::utils::texture_loader::TextureOptions;
+use glium::backend::Facade;
+use glium::Texture2d;
+use image::io::Reader as ImageReader;
+use std::path::Path;
+use std::sync::mpsc;
+use std::sync::mpsc::Receiver;
+use std::sync::mpsc::Sender;
+
+/// An image that finished decoding and waits to be uploaded
+struct Decoded<K> {
+    key: K,
+    options: TextureOptions,
+    image: Result<(Vec<f32>, u32, u32), RendererError>,
+}
+
+/// Loads textures without blocking the render thread
+///
+/// Decoding an image is most of the time spent loading a texture, so it is done on the rayon
+/// thread pool. Only the upload needs the OpenGL context, which [`poll`](Self::poll) does on the
+/// calling thread for a few textures per frame. Each texture is identified by a key, ex. which
+/// material and map it belongs to, so it can be put in place once it is ready. Until then the
+/// material keeps whatever it had, see [`Model::load_from_fs_queued`].
+///
+/// [`Model::load_from_fs_queued`]: crate::utils::model::Model::load_from_fs_queued
+pub struct TextureLoadQueue<K> {
+    sender: Sender<Decoded<K>>,
+    receiver: Receiver<Decoded<K>>,
+    pending: usize,
+    uploads_per_poll: usize,
+}
+
+impl<K: Send + 'static> TextureLoadQueue<K> {
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::channel();
+
+        Self {
+            sender,
+            receiver,
+            pending: 0,
+            uploads_per_poll: 2,
+        }
+    }
+
+    /// The most textures uploaded by each [`poll`](Self::poll), 2 by default
+    ///
+    /// Uploading large textures (and generating their mipmaps) still takes a moment, so a lower
+    /// number keeps frames smoother while a higher one finishes loading sooner.
+    pub fn get_uploads_per_poll(&self) -> usize {
+        self.uploads_per_poll
+    }
+    pub fn set_uploads_per_poll(&mut self, uploads_per_poll: usize) {
+        self.uploads_per_poll = uploads_per_poll.max(1);
+    }
+
+    /// Starts decoding the image at `path` in the background
+    ///
+    /// Color maps (ex. albedo) should be loaded with `srgb` so they are converted to linear space,
+    /// like [`TextureLoader::from_fs_srgb`].
+    pub fn load(&mut self, key: K, path: impl AsRef<Path>, srgb: bool, options: TextureOptions) {
+        let path = path.as_ref().to_path_buf();
+        let sender = self.sender.clone();
+        self.pending += 1;
+
+        rayon::spawn(move || {
+            let image = Self::decode(&path, srgb);
+            // The queue was dropped or cleared, nobody wants the texture anymore
+            let _ = sender.send(Decoded {
+                key,
+                options,
+                image,
+            });
+        });
+    }
+
+    fn decode(path: &Path, srgb: bool) -> Result<(Vec<f32>, u32, u32), RendererError> {
+        let image = ImageReader::open(path)?.decode()?;
+
+        Ok(TextureLoader::decode(image, srgb))
+    }
+
+    /// Uploads up to [`get_uploads_per_poll`](Self::get_uploads_per_poll) of the textures that
+    /// finished decoding, call it once per frame
+    ///
+    /// Images that failed to load are returned with their error, so every key that was queued is
+    /// returned exactly once.
+    pub fn poll(&mut self, facade: &impl Facade) -> Vec<(K, Result<Texture2d, RendererError>)> {
+        let mut uploaded = Vec::new();
+
+        while uploaded.len() < self.uploads_per_poll {
+            let decoded = match self.receiver.try_recv() {
+                Ok(decoded) => decoded,
+                Err(_) => break,
+            };
+            self.pending -= 1;
+
+            let texture = decoded.image.and_then(|(data, width, height)| {
+                TextureLoader::from_memory_rgba_f32_with_options(
+                    facade,
+                    &data,
+                    width,
+                    height,
+                    decoded.options,
+                )
+                .map_err(|err| RendererError::Glium(err.to_string()))
+            });
+
+            uploaded.push((decoded.key, texture));
+        }
+
+        uploaded
+    }
+
+    /// How many textures were queued but not returned by [`poll`](Self::poll) yet
+    pub fn get_pending(&self) -> usize {
+        self.pending
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pending == 0
+    }
+
+    /// Forgets every queued texture, ex. when the models they were for are replaced
+    ///
+    /// Images that are still being decoded finish in the background but are never returned.
+    pub fn clear(&mut self) {
+        let (sender, receiver) = mpsc::channel();
+        self.sender = sender;
+        self.receiver = receiver;
+        self.pending = 0;
+    }
+}
+
+impl<K: Send + 'static> Default for TextureLoadQueue<K> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
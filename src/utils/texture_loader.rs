@@ -1,23 +1,120 @@
+use crate::error::RendererError;
+use glium::texture::CompressedFormat;
+use glium::texture::CompressedMipmapsOption;
+use glium::texture::CompressedTexture2d;
+use glium::texture::MipmapsOption;
 use glium::texture::Texture2dDataSink;
+use glium::uniforms::MagnifySamplerFilter;
+use glium::uniforms::MinifySamplerFilter;
+use glium::uniforms::Sampler;
+use glium::uniforms::SamplerBehavior;
+use glium::uniforms::SamplerWrapFunction;
+use glium::Rect;
 use glium::Texture2d;
 use glium::{backend::Facade, texture::RawImage2d};
 use image::codecs::hdr::HdrDecoder;
 use image::io::Reader as ImageReader;
+use image::DynamicImage;
 use rayon::prelude::IntoParallelRefIterator;
+use rayon::slice::ParallelSliceMut;
 use rayon::{prelude::ParallelIterator, slice::ParallelSlice};
 use std::fs::File;
 use std::io::BufReader;
+use std::io::Read;
 use std::path::Path;
 use std::{borrow::Cow, error::Error};
 
+/// How a loaded texture is stored and sampled, see
+/// [`TextureLoader::from_fs_with_options`]
+///
+/// Glium sets the wrapping and filtering when a texture is sampled instead of on the texture, so
+/// only `generate_mipmaps` is used while loading. The rest is applied by
+/// [`sampled`](Self::sampled), which materials call when binding the texture (ex.
+/// [`PBRTextures::set_texture_options`]).
+///
+/// The default repeats the texture so tiling maps line up, and generates mipmaps that are
+/// blended trilinearly.
+///
+/// [`PBRTextures::set_texture_options`]: crate::shaders::pbr::PBRTextures::set_texture_options
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TextureOptions {
+    /// Wrapping along the horizontal texture coordinate
+    pub wrap_s: SamplerWrapFunction,
+    /// Wrapping along the vertical texture coordinate
+    pub wrap_t: SamplerWrapFunction,
+    pub min_filter: MinifySamplerFilter,
+    pub mag_filter: MagnifySamplerFilter,
+    pub generate_mipmaps: bool,
+}
+
+impl Default for TextureOptions {
+    fn default() -> Self {
+        Self {
+            wrap_s: SamplerWrapFunction::Repeat,
+            wrap_t: SamplerWrapFunction::Repeat,
+            min_filter: MinifySamplerFilter::LinearMipmapLinear,
+            mag_filter: MagnifySamplerFilter::Linear,
+            generate_mipmaps: true,
+        }
+    }
+}
+
+impl TextureOptions {
+    /// Samples `texture` with the wrapping and filters of the options
+    ///
+    /// The mipmap filters fall back to the largest level for textures without mipmaps.
+    pub fn sampled<'t>(&self, texture: &'t Texture2d) -> Sampler<'t, Texture2d> {
+        Sampler(
+            texture,
+            SamplerBehavior {
+                wrap_function: (self.wrap_s, self.wrap_t, self.wrap_t),
+                minify_filter: self.min_filter,
+                magnify_filter: self.mag_filter,
+                ..Default::default()
+            },
+        )
+    }
+
+    fn mipmaps(&self) -> MipmapsOption {
+        match self.generate_mipmaps {
+            true => MipmapsOption::AutoGeneratedMipmaps,
+            false => MipmapsOption::NoMipmap,
+        }
+    }
+}
+
 pub struct TextureLoader {}
 
 impl TextureLoader {
+    /// Uploads rgb floats without mipmaps, ex. for 1x1 textures holding a single value where
+    /// mipmaps are pointless
     pub fn from_memory_f32(
         facade: &impl Facade,
         buffer: &[f32],
         width: u32,
         height: u32,
+    ) -> Result<Texture2d, Box<dyn Error>> {
+        Self::from_memory_f32_mipmaps(facade, buffer, width, height, MipmapsOption::NoMipmap)
+    }
+
+    /// Same as [`from_memory_f32`](Self::from_memory_f32), generating mipmaps if the options ask
+    /// for them
+    pub fn from_memory_f32_with_options(
+        facade: &impl Facade,
+        buffer: &[f32],
+        width: u32,
+        height: u32,
+        options: TextureOptions,
+    ) -> Result<Texture2d, Box<dyn Error>> {
+        Self::from_memory_f32_mipmaps(facade, buffer, width, height, options.mipmaps())
+    }
+
+    fn from_memory_f32_mipmaps(
+        facade: &impl Facade,
+        buffer: &[f32],
+        width: u32,
+        height: u32,
+        mipmaps: MipmapsOption,
     ) -> Result<Texture2d, Box<dyn Error>> {
         let buffer_grouped = buffer
             .par_chunks_exact(3)
@@ -28,19 +125,488 @@ impl TextureLoader {
             facade,
             RawImage2d::from_raw(Cow::from(buffer_grouped), width, height),
             glium::texture::UncompressedFloatFormat::F16F16F16,
-            glium::texture::MipmapsOption::NoMipmap,
+            mipmaps,
+        )?)
+    }
+
+    /// Same as [`from_memory_f32_with_options`](Self::from_memory_f32_with_options) for rgba
+    /// floats, ex. the ones returned by [`decode`](Self::decode)
+    pub fn from_memory_rgba_f32_with_options(
+        facade: &impl Facade,
+        buffer: &[f32],
+        width: u32,
+        height: u32,
+        options: TextureOptions,
+    ) -> Result<Texture2d, Box<dyn Error>> {
+        let buffer_grouped = buffer
+            .par_chunks_exact(4)
+            .map(|chunk| (chunk[0], chunk[1], chunk[2], chunk[3]))
+            .collect::<Vec<_>>();
+
+        Ok(Texture2d::with_format(
+            facade,
+            RawImage2d::from_raw(Cow::from(buffer_grouped), width, height),
+            glium::texture::UncompressedFloatFormat::F16F16F16F16,
+            options.mipmaps(),
         )?)
     }
 
+    /// A magenta and black checkerboard to show in place of a texture that couldn't be loaded,
+    /// so broken references stand out instead of silently using a default
+    pub fn missing_texture(facade: &impl Facade) -> Result<Texture2d, Box<dyn Error>> {
+        const SIZE: u32 = 64;
+        const SQUARE: u32 = 8;
+
+        let data = (0..SIZE * SIZE)
+            .flat_map(|index| {
+                let (x, y) = (index % SIZE / SQUARE, index / SIZE / SQUARE);
+                match (x + y) % 2 {
+                    0 => [1.0, 0.0, 1.0],
+                    _ => [0.0; 3],
+                }
+            })
+            .collect::<Vec<f32>>();
+
+        Self::from_memory_f32(facade, &data, SIZE, SIZE)
+    }
+
     pub fn from_fs(
         facade: &impl Facade,
         path: impl AsRef<Path>,
     ) -> Result<Texture2d, Box<dyn Error>> {
-        let img = ImageReader::open(path)?.decode()?.flipv().into_rgb32f();
+        Self::from_image(facade, ImageReader::open(path)?.decode()?)
+    }
+
+    /// Same as [`from_fs`](Self::from_fs) for an image that is already decoded
+    pub fn from_image(
+        facade: &impl Facade,
+        img: DynamicImage,
+    ) -> Result<Texture2d, Box<dyn Error>> {
+        let options = TextureOptions {
+            generate_mipmaps: false,
+            ..Default::default()
+        };
+
+        Self::from_image_with_options(facade, img, options)
+    }
+
+    /// Same as [`from_fs`](Self::from_fs), generating mipmaps if the options ask for them
+    ///
+    /// The wrapping and filters still have to be applied when sampling, with
+    /// [`TextureOptions::sampled`].
+    pub fn from_fs_with_options(
+        facade: &impl Facade,
+        path: impl AsRef<Path>,
+        options: TextureOptions,
+    ) -> Result<Texture2d, Box<dyn Error>> {
+        Self::from_image_with_options(facade, ImageReader::open(path)?.decode()?, options)
+    }
+
+    /// Same as [`from_fs_with_options`](Self::from_fs_with_options) for an image that is already
+    /// decoded
+    pub fn from_image_with_options(
+        facade: &impl Facade,
+        img: DynamicImage,
+        options: TextureOptions,
+    ) -> Result<Texture2d, Box<dyn Error>> {
+        let (img_data, width, height) = Self::decode(img, false);
+
+        Self::from_memory_rgba_f32_with_options(facade, &img_data, width, height, options)
+    }
+
+    /// Loads a color map (ex. albedo or emissive) that is stored in srgb
+    ///
+    /// The colors are converted to linear space so they can be used in lighting like the values
+    /// from [`from_fs`](Self::from_fs), which should be used for data maps (metallic, roughness,
+    /// normal, ...) instead.
+    pub fn from_fs_srgb(
+        facade: &impl Facade,
+        path: impl AsRef<Path>,
+    ) -> Result<Texture2d, Box<dyn Error>> {
+        Self::from_image_srgb(facade, ImageReader::open(path)?.decode()?)
+    }
+
+    /// Same as [`from_fs_srgb`](Self::from_fs_srgb) for an image that is already decoded
+    pub fn from_image_srgb(
+        facade: &impl Facade,
+        img: DynamicImage,
+    ) -> Result<Texture2d, Box<dyn Error>> {
+        let options = TextureOptions {
+            generate_mipmaps: false,
+            ..Default::default()
+        };
+
+        Self::from_image_srgb_with_options(facade, img, options)
+    }
+
+    /// Same as [`from_fs_srgb`](Self::from_fs_srgb), generating mipmaps if the options ask for
+    /// them
+    ///
+    /// The mipmaps are generated from the linear colors, so they are averaged correctly.
+    pub fn from_fs_srgb_with_options(
+        facade: &impl Facade,
+        path: impl AsRef<Path>,
+        options: TextureOptions,
+    ) -> Result<Texture2d, Box<dyn Error>> {
+        Self::from_image_srgb_with_options(facade, ImageReader::open(path)?.decode()?, options)
+    }
+
+    /// Same as [`from_fs_srgb_with_options`](Self::from_fs_srgb_with_options) for an image that is
+    /// already decoded
+    pub fn from_image_srgb_with_options(
+        facade: &impl Facade,
+        img: DynamicImage,
+        options: TextureOptions,
+    ) -> Result<Texture2d, Box<dyn Error>> {
+        let (img_data, width, height) = Self::decode(img, true);
+
+        Self::from_memory_rgba_f32_with_options(facade, &img_data, width, height, options)
+    }
+
+    /// The part of loading an image that doesn't need OpenGL, so it can run on another thread
+    ///
+    /// Returns the rgba floats that
+    /// [`from_memory_rgba_f32_with_options`](Self::from_memory_rgba_f32_with_options) uploads,
+    /// flipped vertically and converted from srgb to linear when `srgb` is true. The alpha is kept
+    /// (1 for images without one) so the albedo map's alpha can mask or blend, it is never
+    /// converted.
+    pub fn decode(img: DynamicImage, srgb: bool) -> (Vec<f32>, u32, u32) {
+        let img = img.flipv().into_rgba32f();
         let (width, height) = img.dimensions();
-        let img_data = img.into_raw();
+        let mut img_data = img.into_raw();
+
+        if srgb {
+            img_data.par_chunks_exact_mut(4).for_each(|texel| {
+                for value in &mut texel[..3] {
+                    *value = match *value <= 0.04045 {
+                        true => *value / 12.92,
+                        false => ((*value + 0.055) / 1.055).powf(2.4),
+                    }
+                }
+            });
+        }
+
+        (img_data, width, height)
+    }
+
+    /// Loads a block compressed dds file, keeping the mipmaps stored in it
+    ///
+    /// Supports BC1 (`DXT1`), BC3 (`DXT5`), and BC5 (`ATI2`/`BC5U`), with either a legacy FourCC
+    /// or a DX10 header. The data is uploaded as is, so it is much faster than decoding an image
+    /// and stays compressed on the GPU. Like the other loaders the image is flipped vertically.
+    /// The texture isn't converted from srgb, so color maps saved as srgb still need converting
+    /// when sampled.
+    pub fn from_dds(
+        facade: &impl Facade,
+        path: impl AsRef<Path>,
+    ) -> Result<CompressedTexture2d, Box<dyn Error>> {
+        let mut data = Vec::new();
+        File::open(path)?.read_to_end(&mut data)?;
+
+        let read_u32 = |offset: usize| {
+            data.get(offset..offset + 4)
+                .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+                .ok_or(RendererError::InvalidDds("truncated header"))
+        };
+
+        if data.get(0..4) != Some(b"DDS ") || read_u32(4)? != 124 {
+            return Err(RendererError::InvalidDds("missing dds header").into());
+        }
+
+        // DDPF_FOURCC, uncompressed files only describe their channel masks
+        const DDPF_FOURCC: u32 = 0x4;
+        if read_u32(80)? & DDPF_FOURCC == 0 {
+            return Err(RendererError::UnsupportedFormat("uncompressed dds".to_string()).into());
+        }
+
+        let height = read_u32(12)?;
+        let width = read_u32(16)?;
+        let mipmaps = read_u32(28)?.max(1);
+        if width == 0 || height == 0 || mipmaps > 32 - width.max(height).leading_zeros() {
+            return Err(RendererError::InvalidDds("invalid size or mipmap count").into());
+        }
+        let four_cc = data
+            .get(84..88)
+            .ok_or(RendererError::InvalidDds("truncated header"))?;
+
+        let (format, offset) = match four_cc {
+            b"DXT1" => (CompressedFormat::S3tcDxt1Alpha, 128),
+            b"DXT5" => (CompressedFormat::S3tcDxt5Alpha, 128),
+            b"ATI2" | b"BC5U" => (CompressedFormat::RgtcFormatUU, 128),
+            b"DX10" => {
+                let format = match read_u32(128)? {
+                    71 => CompressedFormat::S3tcDxt1Alpha,
+                    77 => CompressedFormat::S3tcDxt5Alpha,
+                    83 => CompressedFormat::RgtcFormatUU,
+                    dxgi => {
+                        return Err(RendererError::UnsupportedFormat(format!(
+                            "dds DXGI format {}",
+                            dxgi
+                        ))
+                        .into())
+                    }
+                };
+
+                (format, 148)
+            }
+            _ => {
+                return Err(RendererError::UnsupportedFormat(format!(
+                    "dds FourCC {:?}",
+                    String::from_utf8_lossy(four_cc)
+                ))
+                .into())
+            }
+        };
+
+        if !format.is_supported(&**facade.get_context()) {
+            return Err(RendererError::UnsupportedFormat(format!(
+                "{:?} isn't supported by the current context",
+                format
+            ))
+            .into());
+        }
+
+        let block_size = Self::block_size(format);
+
+        let mut levels = Vec::with_capacity(mipmaps as usize);
+        let mut offset = offset;
+        for level in 0..mipmaps {
+            let (width, height) = ((width >> level).max(1), (height >> level).max(1));
+            let size = width.div_ceil(4) as usize * height.div_ceil(4) as usize * block_size;
+
+            let mut level_data = offset
+                .checked_add(size)
+                .and_then(|end| data.get(offset..end))
+                .ok_or(RendererError::InvalidDds("truncated image data"))?
+                .to_vec();
+            Self::flip_blocks(&mut level_data, format, width, height);
+
+            levels.push((level_data, width, height));
+            offset += size;
+        }
+
+        Self::upload_levels(facade, &levels, format)
+    }
+
+    /// Loads a block compressed ktx2 file, keeping the mipmaps stored in it
+    ///
+    /// Supports BC1, BC3, BC5, and BC7 without supercompression, which is what `toktx` or
+    /// `basisu` write when transcoding ahead of time. Basis Universal (ETC1S or UASTC) and zstd
+    /// supercompressed files need a transcoder, so they return
+    /// [`UnsupportedFormat`](RendererError::UnsupportedFormat) instead, as do formats the current
+    /// context can't sample. BC1, BC3, and BC5 are flipped vertically like the other loaders, BC7
+    /// blocks can't be flipped in place so those files should be saved bottom to top. Like
+    /// [`from_dds`](Self::from_dds) srgb formats are uploaded without converting them.
+    pub fn from_ktx2(
+        facade: &impl Facade,
+        path: impl AsRef<Path>,
+    ) -> Result<CompressedTexture2d, Box<dyn Error>> {
+        const IDENTIFIER: [u8; 12] = [
+            0xAB, 0x4B, 0x54, 0x58, 0x20, 0x32, 0x30, 0xBB, 0x0D, 0x0A, 0x1A, 0x0A,
+        ];
+
+        let mut data = Vec::new();
+        File::open(path)?.read_to_end(&mut data)?;
+
+        let read_u32 = |offset: usize| {
+            data.get(offset..offset + 4)
+                .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+                .ok_or(RendererError::InvalidKtx2("truncated header"))
+        };
+        let read_u64 = |offset: usize| {
+            data.get(offset..offset + 8)
+                .map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()) as usize)
+                .ok_or(RendererError::InvalidKtx2("truncated level index"))
+        };
+
+        if data.get(0..12) != Some(&IDENTIFIER[..]) {
+            return Err(RendererError::InvalidKtx2("missing ktx2 identifier").into());
+        }
+
+        let vk_format = read_u32(12)?;
+        let width = read_u32(20)?;
+        let height = read_u32(24)?.max(1);
+        let mipmaps = read_u32(40)?.max(1);
+        if width == 0 || mipmaps > 32 - width.max(height).leading_zeros() {
+            return Err(RendererError::InvalidKtx2("invalid size or level count").into());
+        }
+
+        if read_u32(28)? > 1 || read_u32(32)? > 1 || read_u32(36)? > 1 {
+            return Err(RendererError::UnsupportedFormat(
+                "ktx2 3d, array, or cubemap texture".to_string(),
+            )
+            .into());
+        }
+
+        match read_u32(44)? {
+            0 => (),
+            1 => {
+                return Err(RendererError::UnsupportedFormat(
+                    "ktx2 BasisLZ (ETC1S) supercompression, transcode it to BC7 or BC3 first"
+                        .to_string(),
+                )
+                .into())
+            }
+            2 => {
+                return Err(RendererError::UnsupportedFormat(
+                    "ktx2 zstd supercompression".to_string(),
+                )
+                .into())
+            }
+            scheme => {
+                return Err(RendererError::UnsupportedFormat(format!(
+                    "ktx2 supercompression scheme {}",
+                    scheme
+                ))
+                .into())
+            }
+        }
+
+        // VkFormat values, the srgb variants share the layout of the unorm ones
+        let format = match vk_format {
+            0 => {
+                return Err(RendererError::UnsupportedFormat(
+                    "ktx2 UASTC texture, transcode it to BC7 or BC3 first".to_string(),
+                )
+                .into())
+            }
+            131..=134 => CompressedFormat::S3tcDxt1Alpha,
+            137 | 138 => CompressedFormat::S3tcDxt5Alpha,
+            143 => CompressedFormat::RgtcFormatUU,
+            145 | 146 => CompressedFormat::BptcUnorm4,
+            vk_format => {
+                return Err(RendererError::UnsupportedFormat(format!(
+                    "ktx2 VkFormat {}",
+                    vk_format
+                ))
+                .into())
+            }
+        };
+
+        if !format.is_supported(&**facade.get_context()) {
+            return Err(RendererError::UnsupportedFormat(format!(
+                "{:?} isn't supported by the current context",
+                format
+            ))
+            .into());
+        }
+
+        let block_size = Self::block_size(format);
+
+        let mut levels = Vec::with_capacity(mipmaps as usize);
+        for level in 0..mipmaps {
+            let (width, height) = ((width >> level).max(1), (height >> level).max(1));
+            let size = width.div_ceil(4) as usize * height.div_ceil(4) as usize * block_size;
+
+            // Each entry of the level index is an offset, a length, and an uncompressed length
+            let offset = read_u64(80 + level as usize * 24)?;
+            if read_u64(88 + level as usize * 24)? != size {
+                return Err(RendererError::InvalidKtx2("level has the wrong size").into());
+            }
+
+            let mut level_data = offset
+                .checked_add(size)
+                .and_then(|end| data.get(offset..end))
+                .ok_or(RendererError::InvalidKtx2("truncated image data"))?
+                .to_vec();
+            if format != CompressedFormat::BptcUnorm4 {
+                Self::flip_blocks(&mut level_data, format, width, height);
+            }
+
+            levels.push((level_data, width, height));
+        }
+
+        Self::upload_levels(facade, &levels, format)
+    }
+
+    /// Creates a compressed texture from the largest level and writes the rest as its mipmaps
+    fn upload_levels(
+        facade: &impl Facade,
+        levels: &[(Vec<u8>, u32, u32)],
+        format: CompressedFormat,
+    ) -> Result<CompressedTexture2d, Box<dyn Error>> {
+        let (main, width, height) = &levels[0];
+        let texture = CompressedTexture2d::with_compressed_data(
+            facade,
+            main,
+            *width,
+            *height,
+            format,
+            CompressedMipmapsOption::EmptyMipmapsMax(levels.len() as u32 - 1),
+        )?;
+
+        for (level, (level_data, width, height)) in levels.iter().enumerate().skip(1) {
+            let rect = Rect {
+                left: 0,
+                bottom: 0,
+                width: *width,
+                height: *height,
+            };
+
+            texture
+                .mipmap(level as u32)
+                .ok_or(RendererError::Glium("too many mipmaps".to_string()))?
+                .write_compressed_data(rect, level_data, *width, *height, format)
+                .map_err(|_| RendererError::Glium("failed to upload mipmap".to_string()))?;
+        }
+
+        Ok(texture)
+    }
+
+    /// Flips block compressed data vertically, by reversing the rows of blocks and the rows of
+    /// pixels in each block
+    fn flip_blocks(data: &mut [u8], format: CompressedFormat, width: u32, height: u32) {
+        let block_size = Self::block_size(format);
+        let row_size = width.div_ceil(4) as usize * block_size;
+        // Small mipmaps only use the first rows of each block
+        let rows = height.min(4) as usize;
+
+        let block_rows = data.chunks_exact(row_size).rev().flatten().copied();
+        let flipped = block_rows.collect::<Vec<_>>();
+        data.copy_from_slice(&flipped);
+
+        for block in data.chunks_exact_mut(block_size) {
+            match format {
+                CompressedFormat::S3tcDxt1Alpha => Self::flip_color_block(block, rows),
+                CompressedFormat::S3tcDxt5Alpha => {
+                    Self::flip_alpha_block(&mut block[0..8], rows);
+                    Self::flip_color_block(&mut block[8..16], rows);
+                }
+                _ => {
+                    Self::flip_alpha_block(&mut block[0..8], rows);
+                    Self::flip_alpha_block(&mut block[8..16], rows);
+                }
+            }
+        }
+    }
+
+    /// Bytes per 4x4 block of pixels
+    fn block_size(format: CompressedFormat) -> usize {
+        match format {
+            CompressedFormat::S3tcDxt1Alpha => 8,
+            _ => 16,
+        }
+    }
+
+    /// Two colors and a byte of 2 bit indices per row
+    fn flip_color_block(block: &mut [u8], rows: usize) {
+        block[4..4 + rows].reverse();
+    }
+
+    /// Two values and 48 bits of 3 bit indices, 12 bits per row
+    fn flip_alpha_block(block: &mut [u8], rows: usize) {
+        let mut bits = [0; 8];
+        bits[..6].copy_from_slice(&block[2..8]);
+        let indices = u64::from_le_bytes(bits);
+
+        let mut flipped = indices;
+        for row in 0..rows {
+            let source = (indices >> (12 * row)) & 0xFFF;
+            let target = 12 * (rows - 1 - row);
+            flipped = (flipped & !(0xFFF << target)) | (source << target);
+        }
 
-        Self::from_memory_f32(facade, &img_data, width, height)
+        block[2..8].copy_from_slice(&flipped.to_le_bytes()[..6]);
     }
 
     pub fn from_fs_hdr(
@@ -0,0 +1,121 @@
+//! Counts the textures used by materials and skyboxes, for noticing leaks
+//!
+//! Every texture handed to a [`PBRTextures`](crate::shaders::pbr::PBRTextures),
+//! [`PBRSkybox`](crate::utils::pbr_skybox::PBRSkybox), or other shared texture owner is tracked
+//! until its last [`Rc`] is dropped. Editing materials in a debug UI should keep the numbers
+//! steady, numbers that keep growing mean something is holding on to old textures.
+//!
+//! Only textures that reached one of those owners are counted, render targets of passes like
+//! bloom aren't. For the memory of the whole GPU see [`Context::get_free_video_memory`].
+//!
+//! [`Context::get_free_video_memory`]: glium::backend::Context::get_free_video_memory
+
+use glium::texture::Cubemap;
+use glium::Texture2d;
+use std::cell::RefCell;
+use std::rc::{Rc, Weak};
+
+/// The textures that are still alive, see [`TextureRegistry::get_stats`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TextureStats {
+    pub textures: usize,
+    /// Estimated from the size, format, and mipmaps of each texture
+    pub texture_bytes: usize,
+    pub cubemaps: usize,
+    /// Estimated the same way as `texture_bytes`, for all six faces
+    pub cubemap_bytes: usize,
+}
+
+impl TextureStats {
+    pub fn total_bytes(&self) -> usize {
+        self.texture_bytes + self.cubemap_bytes
+    }
+}
+
+/// Weak references to every tracked texture along with its estimated size
+///
+/// There is one registry per thread, like the OpenGL context itself, used through the associated
+/// functions.
+#[derive(Default)]
+pub struct TextureRegistry {
+    textures: Vec<(Weak<Texture2d>, usize)>,
+    cubemaps: Vec<(Weak<Cubemap>, usize)>,
+}
+
+thread_local! {
+    static REGISTRY: RefCell<TextureRegistry> = RefCell::new(TextureRegistry::default());
+}
+
+impl TextureRegistry {
+    /// Counts `texture` until it is dropped, tracking the same texture again does nothing
+    pub fn track_texture(texture: &Rc<Texture2d>) {
+        REGISTRY.with(|registry| {
+            Self::track(&mut registry.borrow_mut().textures, texture, || {
+                let bits = Self::bits_per_pixel(texture.get_internal_format().ok());
+                let levels = texture.get_mipmap_levels();
+                Self::estimate_bytes(texture.width(), texture.height(), bits, levels)
+            })
+        });
+    }
+
+    /// Same as [`track_texture`](Self::track_texture) for cubemaps
+    pub fn track_cubemap(cubemap: &Rc<Cubemap>) {
+        REGISTRY.with(|registry| {
+            Self::track(&mut registry.borrow_mut().cubemaps, cubemap, || {
+                let bits = Self::bits_per_pixel(cubemap.get_internal_format().ok());
+                let size = cubemap.get_width();
+                6 * Self::estimate_bytes(size, size, bits, cubemap.get_mipmap_levels())
+            })
+        });
+    }
+
+    /// Counts the tracked textures that are still alive on this thread
+    pub fn get_stats() -> TextureStats {
+        REGISTRY.with(|registry| {
+            let mut registry = registry.borrow_mut();
+            Self::prune(&mut registry.textures);
+            Self::prune(&mut registry.cubemaps);
+
+            TextureStats {
+                textures: registry.textures.len(),
+                texture_bytes: registry.textures.iter().map(|(_, bytes)| bytes).sum(),
+                cubemaps: registry.cubemaps.len(),
+                cubemap_bytes: registry.cubemaps.iter().map(|(_, bytes)| bytes).sum(),
+            }
+        })
+    }
+
+    /// Only asks for the size of new textures, since that has to query OpenGL
+    fn track<T>(
+        entries: &mut Vec<(Weak<T>, usize)>,
+        texture: &Rc<T>,
+        bytes: impl FnOnce() -> usize,
+    ) {
+        Self::prune(entries);
+
+        let weak = Rc::downgrade(texture);
+        if !entries.iter().any(|(entry, _)| entry.ptr_eq(&weak)) {
+            entries.push((weak, bytes()));
+        }
+    }
+
+    fn prune<T>(entries: &mut Vec<(Weak<T>, usize)>) {
+        entries.retain(|(entry, _)| entry.strong_count() > 0);
+    }
+
+    /// Assumes rgba8 when the format can't be queried
+    fn bits_per_pixel(format: Option<glium::texture::InternalFormat>) -> usize {
+        format.map_or(32, |format| format.get_total_bits())
+    }
+
+    /// Bytes of a single layer including every mipmap
+    fn estimate_bytes(width: u32, height: u32, bits: usize, levels: u32) -> usize {
+        (0..levels)
+            .map(|level| {
+                let width = (width >> level).max(1) as usize;
+                let height = (height >> level).max(1) as usize;
+                width * height * bits / 8
+            })
+            .sum()
+    }
+}
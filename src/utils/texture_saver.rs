@@ -0,0 +1,157 @@
+use crate::error::RendererError;
+use glium::backend::Facade;
+use glium::texture::InternalFormat;
+use glium::texture::InternalFormatType;
+use glium::texture::RawImage2d;
+use glium::texture::SrgbTexture2d;
+use glium::Texture2d;
+use image::DynamicImage;
+use std::error::Error;
+use std::path::Path;
+use std::path::PathBuf;
+use std::thread::JoinHandle;
+
+pub struct ImageSaver {}
+
+impl ImageSaver {
+    /// Reads a texture back from the gpu into an image
+    ///
+    /// The image is stored bottom row first like OpenGL does. Float (HDR) textures are clamped to
+    /// `0.0..=1.0` and encoded as 8-bit.
+    pub fn from_texture(texture: &Texture2d) -> Result<DynamicImage, Box<dyn Error>> {
+        let (width, height) = texture.dimensions();
+
+        let data = if Self::is_float(texture) {
+            // Safe since every float format can be read back as rgba floats
+            let raw: RawImage2d<f32> =
+                unsafe { texture.unchecked_read::<RawImage2d<f32>, (f32, f32, f32, f32)>() };
+
+            raw.data
+                .iter()
+                .map(|value| (value.clamp(0.0, 1.0) * 255.0).round() as u8)
+                .collect::<Vec<_>>()
+        } else {
+            let raw: RawImage2d<u8> = texture.read_to_pixel_buffer().read_as_texture_2d()?;
+
+            raw.data.into_owned()
+        };
+
+        let image =
+            image::ImageBuffer::from_raw(width, height, data).ok_or("failed to create image")?;
+
+        Ok(DynamicImage::ImageRgba8(image))
+    }
+
+    /// Reads an srgb texture back from the gpu into an image
+    ///
+    /// The pixels are returned as stored, so they are already gamma corrected. The image is stored
+    /// bottom row first like OpenGL does.
+    pub fn from_srgb_texture(texture: &SrgbTexture2d) -> Result<DynamicImage, Box<dyn Error>> {
+        let (width, height) = texture.dimensions();
+        let raw: RawImage2d<u8> = texture.read_to_pixel_buffer().read_as_texture_2d()?;
+
+        let image = image::ImageBuffer::from_raw(width, height, raw.data.into_owned())
+            .ok_or("failed to create image")?;
+
+        Ok(DynamicImage::ImageRgba8(image))
+    }
+
+    /// Reads the last frame that was presented to the window
+    ///
+    /// The pixels are returned as stored, so with an srgb framebuffer they are already gamma
+    /// corrected like what is shown on screen. The image is stored bottom row first like OpenGL
+    /// does.
+    pub fn from_front_buffer(facade: &impl Facade) -> Result<DynamicImage, Box<dyn Error>> {
+        let raw: RawImage2d<u8> = facade.get_context().read_front_buffer()?;
+        let image = image::ImageBuffer::from_raw(raw.width, raw.height, raw.data.into_owned())
+            .ok_or("failed to create image")?;
+
+        Ok(DynamicImage::ImageRgba8(image))
+    }
+
+    fn is_float(texture: &Texture2d) -> bool {
+        let ty = match texture.get_internal_format() {
+            Ok(InternalFormat::OneComponent { ty1, .. }) => ty1,
+            Ok(InternalFormat::TwoComponents { ty1, .. }) => ty1,
+            Ok(InternalFormat::ThreeComponents { ty1, .. }) => ty1,
+            Ok(InternalFormat::FourComponents { ty1, .. }) => ty1,
+            Err(_) => return false,
+        };
+
+        ty == InternalFormatType::Float
+    }
+}
+
+pub struct TextureSaver {}
+
+impl TextureSaver {
+    /// Saves a texture to the filesystem
+    ///
+    /// The file format is inferred from the extension of `path`. Since OpenGL stores textures
+    /// bottom row first, `flip_v` should usually be true to get an upright image.
+    pub fn to_fs(
+        texture: &Texture2d,
+        path: impl AsRef<Path>,
+        flip_v: bool,
+    ) -> Result<(), Box<dyn Error>> {
+        let image = ImageSaver::from_texture(texture)?;
+
+        Ok(Self::save_image(image, path.as_ref(), flip_v)?)
+    }
+
+    /// Saves an srgb texture (ex. the one a scene was rendered into) to the filesystem in the
+    /// background
+    ///
+    /// The texture is read back right away, only encoding and writing the file happens on another
+    /// thread. Join the returned handle to wait for the file to be written, ex. in tests.
+    pub fn srgb_to_fs_in_background(
+        texture: &SrgbTexture2d,
+        path: impl Into<PathBuf>,
+        flip_v: bool,
+    ) -> Result<JoinHandle<Result<(), RendererError>>, Box<dyn Error>> {
+        let image = ImageSaver::from_srgb_texture(texture)?;
+
+        Ok(Self::image_to_fs_in_background(image, path, flip_v))
+    }
+
+    /// Saves a screenshot of the last frame presented to the window in the background
+    ///
+    /// See [`ImageSaver::from_front_buffer`] for how srgb framebuffers are handled and
+    /// [`srgb_to_fs_in_background`](Self::srgb_to_fs_in_background) for the returned handle. The
+    /// image is saved upright.
+    pub fn screenshot_in_background(
+        facade: &impl Facade,
+        path: impl Into<PathBuf>,
+    ) -> Result<JoinHandle<Result<(), RendererError>>, Box<dyn Error>> {
+        let image = ImageSaver::from_front_buffer(facade)?;
+
+        Ok(Self::image_to_fs_in_background(image, path, true))
+    }
+
+    /// Encodes and writes an image that was already read back on another thread
+    pub fn image_to_fs_in_background(
+        image: DynamicImage,
+        path: impl Into<PathBuf>,
+        flip_v: bool,
+    ) -> JoinHandle<Result<(), RendererError>> {
+        let path = path.into();
+
+        std::thread::spawn(move || Self::save_image(image, &path, flip_v))
+    }
+
+    fn save_image(mut image: DynamicImage, path: &Path, flip_v: bool) -> Result<(), RendererError> {
+        if flip_v {
+            image = image.flipv();
+        }
+
+        // Most formats (ex. jpeg) don't support an alpha channel
+        let image = match path.extension().and_then(|ext| ext.to_str()) {
+            Some("png") | Some("tiff") | Some("tif") | Some("webp") => image,
+            _ => DynamicImage::ImageRgb8(image.into_rgb8()),
+        };
+
+        image.save(path)?;
+
+        Ok(())
+    }
+}
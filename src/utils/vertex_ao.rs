@@ -0,0 +1,205 @@
+//! Ambient occlusion baked into the vertices of static models
+
+use crate::error::RendererError;
+use crate::shader::Shader;
+use crate::utils::aabb::Aabb;
+use crate::utils::model::Model;
+use glium::backend::Facade;
+use glium::VertexBuffer;
+use nalgebra::Vector3;
+use rayon::prelude::IntoParallelRefIterator;
+use rayon::prelude::ParallelIterator;
+
+/// Bakes how occluded every vertex of the model is by the model itself into its
+/// [`ao`](crate::vertex::Vertex::ao)
+///
+/// For each vertex `samples` rays are cast over the hemisphere around its normal against the
+/// triangles of every sub model, counting the ones that hit within the radius of the model's
+/// [bounding sphere](Model::get_bounds). The PBR material multiplies the result into its ambient
+/// lighting, so static props get contact shadows without the runtime cost of ssao. The levels of
+/// detail are baked against their own triangles.
+///
+/// Every ray is tested against every triangle on the cpu, so this is meant to run once after
+/// loading rather than every frame. The baked vertices are uploaded into new vertex buffers, so
+/// other models and sub models sharing the old ones (ex. the same mesh used by several nodes, or
+/// clones from [`Model::with_shader`]) keep their own occlusion.
+pub fn bake_vertex_ao<S: Shader>(
+    facade: &impl Facade,
+    model: &mut Model<S>,
+    samples: usize,
+) -> Result<(), RendererError> {
+    let radius = match model.get_bounds() {
+        Some((_, radius)) => radius,
+        None => return Ok(()),
+    };
+
+    let mut meshes = Vec::new();
+    for sub_model in model.get_sub_models() {
+        let local = sub_model.get_local_mat();
+        let normal_matrix = local.fixed_view::<3, 3>(0, 0).into_owned();
+
+        let vertices = read_buffer(sub_model.get_vertex_buffer().read())?;
+        let indices = read_buffer(sub_model.get_index_buffer().read())?;
+
+        let points = vertices
+            .iter()
+            .map(|vertex| {
+                let position = local.transform_point(&vertex.position.into()).coords;
+                let normal = normal_matrix * Vector3::from(vertex.normal);
+
+                (position, normal)
+            })
+            .collect::<Vec<_>>();
+        let corner = |index: u32| {
+            points
+                .get(index as usize)
+                .map(|(position, _)| *position)
+                .ok_or(RendererError::IndexOutOfRange {
+                    index,
+                    vertices: points.len(),
+                })
+        };
+        let triangles = indices
+            .chunks_exact(3)
+            .map(|face| Ok([corner(face[0])?, corner(face[1])?, corner(face[2])?]))
+            .collect::<Result<Vec<_>, RendererError>>()?;
+
+        meshes.push((vertices, points, triangles));
+    }
+
+    let triangles = meshes
+        .iter()
+        .flat_map(|(_, _, triangles)| triangles.iter().copied())
+        .collect::<Vec<_>>();
+
+    for (sub_model, (mut vertices, points, _)) in model.get_sub_models_mut().iter_mut().zip(meshes)
+    {
+        let occlusion = compute_occlusion(&points, &triangles, samples, radius);
+
+        for (vertex, occlusion) in vertices.iter_mut().zip(occlusion) {
+            vertex.ao = occlusion;
+        }
+
+        sub_model.set_vertex_buffer(VertexBuffer::new(facade, &vertices)?);
+    }
+
+    for (_, lod) in model.get_lod_levels_mut() {
+        bake_vertex_ao(facade, lod, samples)?;
+    }
+
+    Ok(())
+}
+
+/// How much of the hemisphere above each `(position, normal)` isn't blocked by `triangles`
+///
+/// 1 is fully open and 0 fully occluded. Only hits closer than `max_distance` count. The
+/// directions are spread evenly and weighted by the cosine to the normal, so the result doesn't
+/// change between runs. With 0 samples nothing is occluded.
+pub fn compute_occlusion(
+    points: &[(Vector3<f32>, Vector3<f32>)],
+    triangles: &[[Vector3<f32>; 3]],
+    samples: usize,
+    max_distance: f32,
+) -> Vec<f32> {
+    let bounds = triangles
+        .iter()
+        .flatten()
+        .fold(None, |aabb: Option<Aabb>, corner| match aabb {
+            Some(aabb) => Some(Aabb::new(aabb.min.inf(corner), aabb.max.sup(corner))),
+            None => Some(Aabb::new(*corner, *corner)),
+        });
+    let (bounds, samples) = match bounds {
+        Some(bounds) if samples > 0 => (bounds, samples),
+        _ => return vec![1.0; points.len()],
+    };
+
+    // Keeps rays from hitting the triangles their vertex belongs to
+    let bias = max_distance * 1e-4;
+
+    points
+        .par_iter()
+        .map(|(position, normal)| {
+            let normal = match normal.try_normalize(f32::EPSILON) {
+                Some(normal) => normal,
+                None => return 1.0,
+            };
+            let (tangent, bitangent) = orthonormal_basis(&normal);
+            let origin = position + normal * bias;
+
+            let hits = (0..samples)
+                .filter(|&sample| {
+                    let (x, y, z) = hemisphere_direction(sample, samples);
+                    let direction = tangent * x + bitangent * y + normal * z;
+
+                    let enters = bounds.intersects_ray(&origin, &direction);
+                    enters.is_some_and(|distance| distance <= max_distance)
+                        && triangles.iter().any(|triangle| {
+                            intersect_triangle(&origin, &direction, triangle)
+                                .is_some_and(|distance| distance <= max_distance)
+                        })
+                })
+                .count();
+
+            1.0 - hits as f32 / samples as f32
+        })
+        .collect()
+}
+
+fn read_buffer<T>(result: Result<T, glium::buffer::ReadError>) -> Result<T, RendererError> {
+    result.map_err(|err| RendererError::Glium(format!("{:?}", err)))
+}
+
+/// Two directions perpendicular to `normal` and each other
+fn orthonormal_basis(normal: &Vector3<f32>) -> (Vector3<f32>, Vector3<f32>) {
+    let up = match normal.y.abs() < 0.99 {
+        true => Vector3::y(),
+        false => Vector3::x(),
+    };
+    let tangent = up.cross(normal).normalize();
+
+    (tangent, normal.cross(&tangent))
+}
+
+/// Cosine weighted direction from the Hammersley sequence, with z pointing along the normal
+fn hemisphere_direction(sample: usize, samples: usize) -> (f32, f32, f32) {
+    let u = (sample as f32 + 0.5) / samples as f32;
+    let v = (sample as u32).reverse_bits() as f32 / 2f32.powi(32);
+
+    let radius = u.sqrt();
+    let angle = 2.0 * std::f32::consts::PI * v;
+
+    (radius * angle.cos(), radius * angle.sin(), (1.0 - u).sqrt())
+}
+
+/// Möller-Trumbore, the distance along the ray to where it hits either side of the triangle
+fn intersect_triangle(
+    origin: &Vector3<f32>,
+    direction: &Vector3<f32>,
+    [a, b, c]: &[Vector3<f32>; 3],
+) -> Option<f32> {
+    let edge1 = b - a;
+    let edge2 = c - a;
+    let p = direction.cross(&edge2);
+    let determinant = edge1.dot(&p);
+
+    // The ray is parallel to the triangle
+    if determinant.abs() < f32::EPSILON {
+        return None;
+    }
+
+    let inverse = 1.0 / determinant;
+    let to_origin = origin - a;
+    let u = to_origin.dot(&p) * inverse;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+
+    let q = to_origin.cross(&edge1);
+    let v = direction.dot(&q) * inverse;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+
+    let distance = edge2.dot(&q) * inverse;
+    (distance > 0.0).then_some(distance)
+}
@@ -8,6 +8,19 @@ pub struct Vertex {
     pub position: [f32; 3],
     pub normal: [f32; 3],
     pub tex_coords: [f32; 2],
+    /// Second set of texture coordinates, used by ambient occlusion maps that need their own
+    /// (ex. baked lightmaps). Loaders copy `tex_coords` when there is no second set.
+    pub tex_coords2: [f32; 2],
+    /// Tangent with the handedness of the bitangent stored in `w`
+    ///
+    /// A zero tangent means there is no tangent data, shaders should fall back to calculating
+    /// the tangent space themselves.
+    pub tangent: [f32; 4],
+    /// Multiplied with the albedo, white by default so it doesn't change anything
+    pub color: [f32; 4],
+    /// Baked ambient occlusion multiplied into the ambient lighting of the PBR material, 1 (not
+    /// occluded) by default, see [`bake_vertex_ao`](crate::utils::vertex_ao::bake_vertex_ao)
+    pub ao: f32,
 }
 
 impl Default for Vertex {
@@ -16,8 +29,63 @@ impl Default for Vertex {
             position: [0.0, 0.0, 1.0],
             normal: [0.0, 0.0, -1.0],
             tex_coords: [0.0; 2],
+            tex_coords2: [0.0; 2],
+            tangent: [0.0; 4],
+            color: [1.0; 4],
+            ao: 1.0,
         }
     }
 }
 
-implement_vertex!(Vertex, position, normal, tex_coords);
+implement_vertex!(
+    Vertex,
+    position,
+    normal,
+    tex_coords,
+    tex_coords2,
+    tangent,
+    color,
+    ao
+);
+
+/// Per-instance data for instanced rendering
+///
+/// Attribute names are prefixed with `instance_` so they don't clash with [`Vertex`].
+#[derive(Clone, Copy, Debug)]
+pub struct Instance {
+    /// Applied before the model matrix of the material
+    pub instance_model: [[f32; 4]; 4],
+    /// Multiplied with the albedo of the material
+    pub instance_albedo: [f32; 3],
+}
+
+impl Instance {
+    pub fn new(model: nalgebra::Matrix4<f32>) -> Self {
+        Self {
+            instance_model: model.into(),
+            instance_albedo: [1.0; 3],
+        }
+    }
+
+    pub fn with_albedo(model: nalgebra::Matrix4<f32>, albedo: [f32; 3]) -> Self {
+        Self {
+            instance_model: model.into(),
+            instance_albedo: albedo,
+        }
+    }
+}
+
+implement_vertex!(Instance, instance_model, instance_albedo);
+
+/// Per-vertex bones for skinned rendering, in a buffer next to the [`Vertex`] buffer
+///
+/// Up to 4 bones move each vertex. Unused slots have a weight of 0, see
+/// [`AnimatedModel`](crate::utils::animation::AnimatedModel).
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SkinWeights {
+    /// Indices into the bone matrices of the [`PBR`](crate::shaders::pbr::PBR) material
+    pub bone_ids: [i32; 4],
+    pub bone_weights: [f32; 4],
+}
+
+implement_vertex!(SkinWeights, bone_ids, bone_weights);
@@ -0,0 +1,57 @@
+use image::DynamicImage;
+use image::Rgba;
+use image::RgbaImage;
+use opengl_renderer::shaders::pbr::AlphaMode;
+use opengl_renderer::utils::texture_loader::TextureLoader;
+
+/// A 2x1 albedo map with an opaque texel on the left and a cut out one on the right
+fn cutout_albedo() -> DynamicImage {
+    let mut image = RgbaImage::new(2, 1);
+    image.put_pixel(0, 0, Rgba([200, 100, 50, 255]));
+    image.put_pixel(1, 0, Rgba([200, 100, 50, 0]));
+
+    DynamicImage::ImageRgba8(image)
+}
+
+fn texel_alpha(data: &[f32], index: usize) -> f32 {
+    data[index * 4 + 3]
+}
+
+#[test]
+fn albedo_alpha_is_kept() {
+    let (data, width, height) = TextureLoader::decode(cutout_albedo(), true);
+
+    assert_eq!((width, height), (2, 1));
+    assert_eq!(data.len(), 8);
+    assert_eq!(texel_alpha(&data, 0), 1.0);
+    assert_eq!(texel_alpha(&data, 1), 0.0);
+}
+
+#[test]
+fn srgb_conversion_leaves_alpha_linear() {
+    let mut image = RgbaImage::new(1, 1);
+    image.put_pixel(0, 0, Rgba([128, 128, 128, 128]));
+    let (data, _, _) = TextureLoader::decode(DynamicImage::ImageRgba8(image), true);
+
+    assert!(data[0] < 0.25);
+    assert!((texel_alpha(&data, 0) - 128.0 / 255.0).abs() < 1e-5);
+}
+
+#[test]
+fn mask_discards_transparent_texel() {
+    let (data, _, _) = TextureLoader::decode(cutout_albedo(), true);
+    let mask = AlphaMode::Mask(AlphaMode::DEFAULT_CUTOFF);
+    let material_alpha = 1.0;
+
+    assert!(!mask.discards(texel_alpha(&data, 0) * material_alpha));
+    assert!(mask.discards(texel_alpha(&data, 1) * material_alpha));
+    assert!(!AlphaMode::Opaque.discards(texel_alpha(&data, 1) * material_alpha));
+}
+
+#[test]
+fn images_without_alpha_are_opaque() {
+    let image = DynamicImage::ImageRgb8(image::RgbImage::new(1, 1));
+    let (data, _, _) = TextureLoader::decode(image, false);
+
+    assert_eq!(data, vec![0.0, 0.0, 0.0, 1.0]);
+}
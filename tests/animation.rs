@@ -0,0 +1,56 @@
+use nalgebra::UnitQuaternion;
+use nalgebra::Vector3;
+use opengl_renderer::utils::animation::AnimationClip;
+use opengl_renderer::utils::animation::Channel;
+use std::collections::HashMap;
+use std::time::Duration;
+
+fn translation(channel: &Channel, ticks: f64) -> Vector3<f32> {
+    channel.sample(ticks).fixed_view::<3, 1>(0, 3).into_owned()
+}
+
+fn moving_channel() -> Channel {
+    Channel {
+        positions: vec![(0.0, Vector3::zeros()), (10.0, Vector3::new(2.0, 0.0, 0.0))],
+        ..Default::default()
+    }
+}
+
+#[test]
+fn positions_are_interpolated() {
+    let channel = moving_channel();
+
+    assert!((translation(&channel, 5.0) - Vector3::new(1.0, 0.0, 0.0)).norm() < 1e-5);
+}
+
+#[test]
+fn first_and_last_keys_are_held() {
+    let channel = moving_channel();
+
+    assert_eq!(translation(&channel, -1.0), Vector3::zeros());
+    assert_eq!(translation(&channel, 20.0), Vector3::new(2.0, 0.0, 0.0));
+}
+
+#[test]
+fn rotations_are_slerped() {
+    let quarter = UnitQuaternion::from_axis_angle(&Vector3::y_axis(), std::f32::consts::FRAC_PI_2);
+    let channel = Channel {
+        rotations: vec![(0.0, UnitQuaternion::identity()), (2.0, quarter)],
+        ..Default::default()
+    };
+
+    let x = channel.sample(1.0).transform_vector(&Vector3::x());
+    let expected = UnitQuaternion::from_axis_angle(&Vector3::y_axis(), std::f32::consts::FRAC_PI_4)
+        * Vector3::x();
+
+    assert!((x - expected).norm() < 1e-5);
+}
+
+#[test]
+fn looping_wraps_around() {
+    let clip = AnimationClip::new("walk", 50.0, 25.0, HashMap::new());
+
+    assert_eq!(clip.get_duration(), Duration::from_secs(2));
+    assert!((clip.ticks_at(Duration::from_secs(3), true) - 25.0).abs() < 1e-9);
+    assert_eq!(clip.ticks_at(Duration::from_secs(3), false), 50.0);
+}
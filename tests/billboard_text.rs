@@ -0,0 +1,37 @@
+use opengl_renderer::utils::billboard_text::rasterize_text;
+
+#[test]
+fn text_is_drawn_in_its_color() {
+    let image = rasterize_text("Label", 32.0, [1.0, 0.0, 0.0]);
+    let (width, height) = image.dimensions();
+    assert!(
+        width > height,
+        "a single line should be wider than it is tall"
+    );
+
+    let covered: Vec<_> = image.pixels().filter(|pixel| pixel[3] > 0).collect();
+    assert!(!covered.is_empty(), "no glyphs were drawn");
+    assert!(covered
+        .iter()
+        .all(|pixel| pixel[0] == 255 && pixel[1] == 0 && pixel[2] == 0));
+
+    // Gaps between the letters stay transparent
+    assert!(covered.len() < (width * height) as usize);
+}
+
+#[test]
+fn larger_fonts_give_larger_images() {
+    let small = rasterize_text("Label", 16.0, [1.0; 3]);
+    let large = rasterize_text("Label", 64.0, [1.0; 3]);
+
+    assert!(large.width() > small.width());
+    assert!(large.height() > small.height());
+}
+
+#[test]
+fn newlines_add_rows() {
+    let one = rasterize_text("a", 32.0, [1.0; 3]);
+    let two = rasterize_text("a\na", 32.0, [1.0; 3]);
+
+    assert!(two.height() > one.height());
+}
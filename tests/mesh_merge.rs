@@ -0,0 +1,88 @@
+use nalgebra::Matrix4;
+use nalgebra::Vector3;
+use opengl_renderer::utils::mesh_merge::merge_meshes;
+use opengl_renderer::vertex::Vertex;
+
+fn triangle() -> Vec<Vertex> {
+    [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]]
+        .into_iter()
+        .map(|position| Vertex {
+            position,
+            normal: [0.0, 0.0, 1.0],
+            tangent: [1.0, 0.0, 0.0, 1.0],
+            ..Default::default()
+        })
+        .collect()
+}
+
+#[test]
+fn indices_are_offset_by_earlier_meshes() {
+    let vertices = triangle();
+    let indices = [0, 1, 2];
+    let identity = Matrix4::identity();
+
+    let (merged_vertices, merged_indices) = merge_meshes(&[
+        (&vertices, &indices, identity),
+        (&vertices, &indices, identity),
+    ]);
+
+    assert_eq!(merged_vertices.len(), 6);
+    assert_eq!(merged_indices, [0, 1, 2, 3, 4, 5]);
+}
+
+#[test]
+fn vertices_are_transformed() {
+    let vertices = triangle();
+    let transform = Matrix4::new_translation(&Vector3::new(0.0, 0.0, 5.0))
+        * Matrix4::from_euler_angles(0.0, std::f32::consts::FRAC_PI_2, 0.0);
+
+    let (merged, _) = merge_meshes(&[(&vertices, &[0, 1, 2], transform)]);
+
+    let close = |a: [f32; 3], b: [f32; 3]| (Vector3::from(a) - Vector3::from(b)).norm() < 1e-5;
+    assert!(close(merged[1].position, [0.0, 0.0, 4.0]));
+    assert!(close(merged[0].normal, [1.0, 0.0, 0.0]));
+    assert!(close(
+        [
+            merged[0].tangent[0],
+            merged[0].tangent[1],
+            merged[0].tangent[2]
+        ],
+        [0.0, 0.0, -1.0]
+    ));
+}
+
+#[test]
+fn normals_stay_unit_length_when_scaled() {
+    let vertices = triangle();
+    let transform = Matrix4::new_nonuniform_scaling(&Vector3::new(2.0, 3.0, 4.0));
+
+    let (merged, _) = merge_meshes(&[(&vertices, &[0, 1, 2], transform)]);
+
+    assert!((Vector3::from(merged[0].normal).norm() - 1.0).abs() < 1e-5);
+}
+
+#[test]
+fn mirroring_flips_winding_and_handedness() {
+    let vertices = triangle();
+    let transform = Matrix4::new_nonuniform_scaling(&Vector3::new(-1.0, 1.0, 1.0));
+
+    let (merged_vertices, merged_indices) = merge_meshes(&[(&vertices, &[0, 1, 2], transform)]);
+
+    assert_eq!(merged_indices, [2, 1, 0]);
+    assert_eq!(merged_vertices[0].tangent[3], -1.0);
+}
+
+#[test]
+fn missing_tangents_stay_zero() {
+    let vertices: Vec<_> = triangle()
+        .into_iter()
+        .map(|vertex| Vertex {
+            tangent: [0.0; 4],
+            ..vertex
+        })
+        .collect();
+
+    let (merged, _) = merge_meshes(&[(&vertices, &[0, 1, 2], Matrix4::identity())]);
+
+    assert!(merged.iter().all(|vertex| vertex.tangent == [0.0; 4]));
+}
@@ -0,0 +1,131 @@
+use nalgebra::Vector2;
+use nalgebra::Vector3;
+use opengl_renderer::utils::model::read_mesh;
+use opengl_renderer::utils::normals::generate_tangents;
+use opengl_renderer::utils::normals::smooth_normals;
+use russimp::scene::PostProcess;
+use russimp::scene::Scene;
+
+/// Two triangles folded 90 degrees along the z axis, with a separate vertex for every corner
+fn folded() -> (Vec<Vector3<f32>>, Vec<u32>) {
+    let positions = vec![
+        // Floor facing up
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(0.0, 0.0, 1.0),
+        Vector3::new(1.0, 0.0, 0.0),
+        // Wall facing +x
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(0.0, 1.0, 0.0),
+        Vector3::new(0.0, 0.0, 1.0),
+    ];
+
+    (positions, (0..6).collect())
+}
+
+#[test]
+fn sharp_edges_stay_sharp() {
+    let (positions, indices) = folded();
+
+    let normals = smooth_normals(&positions, &indices, 80.0);
+
+    assert!((normals[0] - Vector3::y()).norm() < 1e-5);
+    assert!((normals[3] - Vector3::x()).norm() < 1e-5);
+}
+
+#[test]
+fn shallow_edges_are_smoothed() {
+    let (positions, indices) = folded();
+
+    let normals = smooth_normals(&positions, &indices, 175.0);
+    let diagonal = Vector3::new(1.0, 1.0, 0.0).normalize();
+
+    assert!((normals[0] - diagonal).norm() < 1e-5);
+    assert!((normals[3] - diagonal).norm() < 1e-5);
+    // The corners that aren't shared keep the normal of their face
+    assert!((normals[2] - Vector3::y()).norm() < 1e-5);
+}
+
+#[test]
+fn obj_without_normals_gets_normals() {
+    let obj = "v 0 0 0\nv 1 0 0\nv 0 0 -1\nf 1 2 3\n";
+    let scene = Scene::from_buffer(obj.as_bytes(), vec![PostProcess::Triangulate], "obj").unwrap();
+    let mesh = &scene.meshes[0];
+    assert!(mesh.normals.is_empty());
+
+    let positions: Vec<_> = mesh
+        .vertices
+        .iter()
+        .map(|v| Vector3::new(v.x, v.y, v.z))
+        .collect();
+    let indices: Vec<_> = mesh.faces.iter().flat_map(|face| face.0.clone()).collect();
+
+    let normals = smooth_normals(&positions, &indices, 175.0);
+
+    assert_eq!(normals.len(), positions.len());
+    for normal in normals {
+        assert!((normal - Vector3::y()).norm() < 1e-5);
+    }
+}
+
+#[test]
+fn obj_without_normals_gets_tangents() {
+    let obj = "v 0 0 0\nv 1 0 0\nv 0 0 -1\nvt 0 0\nvt 1 0\nvt 0 1\nf 1/1 2/2 3/3\n";
+    let scene = Scene::from_buffer(obj.as_bytes(), vec![PostProcess::Triangulate], "obj").unwrap();
+    let mesh = &scene.meshes[0];
+    assert!(mesh.normals.is_empty());
+
+    let positions: Vec<_> = mesh
+        .vertices
+        .iter()
+        .map(|v| Vector3::new(v.x, v.y, v.z))
+        .collect();
+    let tex_coords: Vec<_> = mesh.texture_coords[0]
+        .as_ref()
+        .unwrap()
+        .iter()
+        .map(|uv| Vector2::new(uv.x, uv.y))
+        .collect();
+    let indices: Vec<_> = mesh.faces.iter().flat_map(|face| face.0.clone()).collect();
+
+    let normals = smooth_normals(&positions, &indices, 175.0);
+    let tangents = generate_tangents(&positions, &normals, &tex_coords, &indices);
+
+    assert_eq!(tangents.len(), positions.len());
+    for tangent in tangents {
+        // u runs along +x and v along -z, which is the bitangent of a +y normal
+        assert!((Vector3::new(tangent[0], tangent[1], tangent[2]) - Vector3::x()).norm() < 1e-5);
+        assert_eq!(tangent[3], 1.0);
+    }
+}
+
+#[test]
+fn collapsed_tex_coords_have_no_tangent() {
+    let (positions, indices) = folded();
+    let normals = smooth_normals(&positions, &indices, 80.0);
+    let tex_coords = vec![Vector2::zeros(); positions.len()];
+
+    let tangents = generate_tangents(&positions, &normals, &tex_coords, &indices);
+
+    assert!(tangents.iter().all(|tangent| *tangent == [0.0; 4]));
+}
+
+#[test]
+fn loader_generates_missing_normals_and_tangents() {
+    let obj = "v 0 0 0\nv 1 0 0\nv 0 0 -1\nvt 0 0\nvt 1 0\nvt 0 1\nf 1/1 2/2 3/3\n";
+    // The same post processing the loader uses
+    let post_process = vec![PostProcess::Triangulate, PostProcess::CalculateTangentSpace];
+    let scene = Scene::from_buffer(obj.as_bytes(), post_process, "obj").unwrap();
+    assert!(scene.meshes[0].normals.is_empty());
+
+    let (vertices, indices) = read_mesh(&scene.meshes[0], 175.0);
+
+    assert_eq!(vertices.len(), 3);
+    assert_eq!(indices, vec![0, 1, 2]);
+    for vertex in vertices {
+        let [x, y, z, handedness] = vertex.tangent;
+
+        assert!((Vector3::from(vertex.normal) - Vector3::y()).norm() < 1e-5);
+        assert!((Vector3::new(x, y, z) - Vector3::x()).norm() < 1e-5);
+        assert_eq!(handedness, 1.0);
+    }
+}
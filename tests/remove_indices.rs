@@ -0,0 +1,58 @@
+use opengl_renderer::utils::indices::remove_indices;
+
+#[test]
+fn removes_first() {
+    let mut items = vec![0, 1, 2, 3];
+    let removed = remove_indices(&mut items, &[0]);
+
+    assert_eq!(items, [1, 2, 3]);
+    assert_eq!(removed, [0]);
+}
+
+#[test]
+fn removes_last() {
+    let mut items = vec![0, 1, 2, 3];
+    let removed = remove_indices(&mut items, &[3]);
+
+    assert_eq!(items, [0, 1, 2]);
+    assert_eq!(removed, [3]);
+}
+
+#[test]
+fn removes_multiple_in_any_order() {
+    let mut items = vec![0, 1, 2, 3, 4, 5];
+    let removed = remove_indices(&mut items, &[4, 1, 5]);
+
+    assert_eq!(items, [0, 2, 3]);
+    assert_eq!(removed, [1, 4, 5]);
+}
+
+#[test]
+fn removes_everything() {
+    let mut items = vec![0, 1, 2];
+    let removed = remove_indices(&mut items, &[0, 1, 2]);
+
+    assert!(items.is_empty());
+    assert_eq!(removed, [0, 1, 2]);
+}
+
+#[test]
+fn nothing_to_remove() {
+    let mut items = vec![0, 1, 2];
+    let removed = remove_indices(&mut items, &[]);
+
+    assert_eq!(items, [0, 1, 2]);
+    assert!(removed.is_empty());
+
+    let mut empty: Vec<i32> = Vec::new();
+    assert!(remove_indices(&mut empty, &[]).is_empty());
+}
+
+#[test]
+fn ignores_duplicates_and_out_of_range() {
+    let mut items = vec![0, 1, 2];
+    let removed = remove_indices(&mut items, &[1, 1, 7]);
+
+    assert_eq!(items, [0, 2]);
+    assert_eq!(removed, [1]);
+}
@@ -0,0 +1,57 @@
+use opengl_renderer::utils::texture_atlas::pack_shelves;
+use opengl_renderer::utils::texture_atlas::AtlasRegion;
+
+fn overlaps(a: (u32, u32, u32, u32), b: (u32, u32, u32, u32)) -> bool {
+    a.0 < b.0 + b.2 && b.0 < a.0 + a.2 && a.1 < b.1 + b.3 && b.1 < a.1 + a.3
+}
+
+#[test]
+fn packed_rects_fit_without_overlapping() {
+    let sizes = [(16, 16), (1, 1), (32, 8), (8, 32), (5, 7), (1, 1), (20, 3)];
+    let padding = 2;
+
+    let ((width, height), positions) = pack_shelves(&sizes, padding);
+    assert_eq!(positions.len(), sizes.len());
+    assert!(width.is_power_of_two());
+
+    // Including the padding so neighbours are far enough apart
+    let rects: Vec<_> = sizes
+        .iter()
+        .zip(&positions)
+        .map(|((w, h), (x, y))| (*x, *y, w + padding, h + padding))
+        .collect();
+
+    for (i, ((w, h), (x, y))) in sizes.iter().zip(&positions).enumerate() {
+        assert!(
+            x + w <= width && y + h <= height,
+            "rect {} is outside the atlas",
+            i
+        );
+
+        for (j, other) in rects.iter().enumerate().skip(i + 1) {
+            assert!(!overlaps(rects[i], *other), "rects {} and {} overlap", i, j);
+        }
+    }
+}
+
+#[test]
+fn atlas_is_at_least_as_wide_as_the_widest_rect() {
+    let ((width, height), positions) = pack_shelves(&[(300, 2), (4, 4)], 0);
+
+    assert_eq!(width, 512);
+    assert_eq!(positions[0], (0, 0));
+    assert!(height >= 4);
+}
+
+#[test]
+fn uv_rect_starts_at_the_bottom() {
+    let region = AtlasRegion {
+        x: 0,
+        y: 0,
+        width: 32,
+        height: 16,
+    };
+
+    // The top left of the image ends up at the top of the texture after flipping
+    assert_eq!(region.uv_rect(64, 64), [0.0, 0.75, 0.5, 1.0]);
+}
@@ -0,0 +1,54 @@
+use nalgebra::Vector3;
+use opengl_renderer::utils::vertex_ao::compute_occlusion;
+
+/// Two triangles covering `-size..size` on x and z at height `y`
+fn quad(y: f32, size: f32) -> Vec<[Vector3<f32>; 3]> {
+    let corner = |x: f32, z: f32| Vector3::new(x * size, y, z * size);
+
+    vec![
+        [corner(-1.0, -1.0), corner(1.0, -1.0), corner(1.0, 1.0)],
+        [corner(-1.0, -1.0), corner(1.0, 1.0), corner(-1.0, 1.0)],
+    ]
+}
+
+fn point_up() -> (Vector3<f32>, Vector3<f32>) {
+    (Vector3::zeros(), Vector3::y())
+}
+
+#[test]
+fn open_point_is_unoccluded() {
+    let occlusion = compute_occlusion(&[point_up()], &quad(0.0, 1.0), 64, 10.0);
+
+    assert_eq!(occlusion, [1.0]);
+}
+
+#[test]
+fn covered_point_is_occluded() {
+    let mut triangles = quad(0.0, 100.0);
+    triangles.extend(quad(0.1, 100.0));
+
+    let occlusion = compute_occlusion(&[point_up()], &triangles, 64, 10.0);
+
+    assert!(occlusion[0] < 0.05, "{}", occlusion[0]);
+}
+
+#[test]
+fn hits_past_the_distance_are_ignored() {
+    let occlusion = compute_occlusion(&[point_up()], &quad(5.0, 100.0), 64, 1.0);
+
+    assert_eq!(occlusion, [1.0]);
+}
+
+#[test]
+fn small_roof_partially_occludes() {
+    let occlusion = compute_occlusion(&[point_up()], &quad(1.0, 1.0), 256, 10.0);
+
+    assert!(occlusion[0] > 0.1 && occlusion[0] < 0.9, "{}", occlusion[0]);
+}
+
+#[test]
+fn no_samples_is_unoccluded() {
+    let occlusion = compute_occlusion(&[point_up(), point_up()], &quad(0.1, 1.0), 0, 10.0);
+
+    assert_eq!(occlusion, [1.0, 1.0]);
+}